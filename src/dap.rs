@@ -0,0 +1,884 @@
+// dap.rs — Debug Adapter Protocol server (`raven dap`)
+//
+// Editors (VS Code, nvim-dap, Helix, …) start `raven dap` and exchange
+// `Content-Length`-framed JSON over stdin/stdout. Programs are loaded exactly
+// like `raven run` does, so `.fas` sources and the files they `.include` get
+// breakpoints and stepping by source line through `line_addrs`. ELF images built with `-g` get the
+// same from their DWARF line table; other ELF images are stepped by instruction.
+// Requests are read on a helper thread so `pause` still works while the program
+// runs.
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use crate::falcon::instruction::Instruction;
+use crate::falcon::program::{function_at, ProgramArgs};
+use crate::falcon::{self, Bus};
+use crate::headless::{ConsoleSink, Loaded, load_program};
+use crate::json::{self, Json};
+use crate::ui::Console;
+use crate::ui::console::ConsoleColor;
+use crate::ui::view::disasm::{disasm_word, freg_name, reg_name};
+
+const THREAD_ID: i64 = 1;
+
+// variablesReference values for the scopes of every frame
+const REGS_REF: i64 = 1;
+const FREGS_REF: i64 = 2;
+const DATA_REF: i64 = 3;
+const STACK_REF: i64 = 4;
+
+/// Words listed in the Data and Stack scopes.
+const MEM_SCOPE_WORDS: u32 = 64;
+
+/// Instructions executed between checks for new requests while running.
+const SLICE_STEPS: u32 = 20_000;
+
+/// How a resumed program decides to stop again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Continue,
+    /// `stepIn`: the next source line (or instruction without line info).
+    StepIn,
+    /// `next`: the next source line at the same call depth or shallower.
+    Over { depth: usize },
+    /// `stepOut`: the first source line after the current function returns.
+    Out { depth: usize },
+}
+
+/// Shadow call-stack entry recorded on `jal`/`jalr` with a link register.
+struct Call {
+    /// Entry address of the callee.
+    func: u32,
+    /// Address of the call instruction.
+    site: u32,
+    ret: u32,
+}
+
+enum StepResult {
+    Ok,
+    /// Waiting for console input; the ecall is retried once input arrives.
+    Blocked,
+    Ebreak,
+    Exited(u32),
+    Fault,
+}
+
+struct Session {
+    loaded: Loaded,
+    /// Canonical paths of a `.fas` program and the files it includes, in the
+    /// order `line_starts` numbers them; empty for binaries.
+    sources: Vec<PathBuf>,
+    console: Console,
+    sink: ConsoleSink,
+    /// Breakpoint addresses per source path, as the client named it.
+    breakpoints: HashMap<PathBuf, Vec<u32>>,
+    /// First address of each source line → (index into `sources`, 0-based line).
+    line_starts: BTreeMap<u32, (usize, usize)>,
+    calls: Vec<Call>,
+    stop_on_entry: bool,
+    /// Execution request in progress (None = stopped).
+    running: Option<Mode>,
+    /// Request to resume once the user types console input.
+    waiting_input: Option<Mode>,
+}
+
+impl Session {
+    fn new(loaded: Loaded, path: &Path, stop_on_entry: bool) -> Session {
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        let mut sources = Vec::new();
+        let mut line_starts = BTreeMap::new();
+        if let Some(main) = &loaded.line_addrs {
+            let files = std::iter::once((path, main)).chain(loaded.include_line_addrs.iter().map(|(p, m)| (p.as_path(), m)));
+            for (file, (p, lines)) in files.enumerate() {
+                sources.push(canonical(p));
+                line_starts.extend(lines.iter().map(|(&line, &addr)| (addr, (file, line))));
+            }
+        }
+        Session {
+            loaded,
+            sources,
+            console: Console::default(),
+            sink: ConsoleSink::default(),
+            breakpoints: HashMap::new(),
+            line_starts,
+            calls: Vec::new(),
+            stop_on_entry,
+            running: None,
+            waiting_input: None,
+        }
+    }
+
+    /// Source file (index into `sources`) and 0-based line holding `addr`.
+    fn line_of(&self, addr: u32) -> Option<(usize, usize)> {
+        self.line_starts.range(..=addr).next_back().map(|(_, &l)| l)
+    }
+
+    fn at_line_start(&self) -> bool {
+        let pc = self.loaded.cpu.pc;
+        if let Some(debug) = &self.loaded.debug {
+            return debug.is_stmt(pc);
+        }
+        self.line_starts.is_empty() || self.line_starts.contains_key(&pc)
+    }
+
+    /// Whether a source line starts at `addr`.
+    fn is_line_start(&self, addr: u32) -> bool {
+        match &self.loaded.debug {
+            Some(debug) => debug.is_stmt(addr),
+            None => self.line_starts.contains_key(&addr),
+        }
+    }
+
+    /// Source file and 1-based line of `addr`: the `.fas` source or a file it
+    /// includes, or the DWARF line table of an ELF.
+    fn source_line(&self, addr: u32) -> Option<(PathBuf, usize)> {
+        if let Some((file, line)) = self.line_of(addr) {
+            return Some((self.sources[file].clone(), line + 1));
+        }
+        let debug = self.loaded.debug.as_ref()?;
+        let loc = debug.location(addr)?;
+        // Prefer the path the client set breakpoints through, which may sit under
+        // a different root than the one the program was compiled in.
+        let path = self.breakpoints.keys().find(|p| debug.find_file(p) == Some(loc.file));
+        Some((path.unwrap_or(&debug.files[loc.file]).clone(), loc.line as usize))
+    }
+
+    /// Addresses for a breakpoint on `line` (1-based) of `path`, with the line it
+    /// lands on: a line without code moves to the next line that has some.
+    fn breakpoint_addrs(&self, path: &Path, line: i64) -> Option<(usize, Vec<u32>)> {
+        if line < 1 {
+            return None;
+        }
+        if !self.sources.is_empty() {
+            let path = path.canonicalize().ok()?;
+            let file = self.sources.iter().position(|src| *src == path)?;
+            let (&addr, &(_, l)) = self
+                .line_starts
+                .iter()
+                .filter(|&(_, &(f, l))| f == file && l as i64 >= line - 1)
+                .min_by_key(|&(_, &(_, l))| l)?;
+            return Some((l + 1, vec![addr]));
+        }
+        let debug = self.loaded.debug.as_ref()?;
+        let file = debug.find_file(path).or_else(|| debug.find_file(&path.canonicalize().ok()?))?;
+        let (l, addrs) = debug.line_addrs(file, line as u32)?;
+        Some((l as usize, addrs))
+    }
+
+    fn name_at(&self, addr: u32) -> String {
+        self.loaded
+            .labels
+            .get(&addr)
+            .and_then(|names| names.first())
+            .cloned()
+            .or_else(|| Some(self.loaded.debug.as_ref()?.function_at(addr)?.name.clone()))
+            .unwrap_or_else(|| format!("0x{addr:08x}"))
+    }
+
+    fn step(&mut self) -> StepResult {
+        let l = &mut self.loaded;
+        let pc = l.cpu.pc;
+        if !l.in_code(pc) {
+            self.console.push_error(format!("Execution reached 0x{pc:08X}, outside the loaded program."));
+            return StepResult::Fault;
+        }
+        let word = l.mem.peek32(pc).unwrap_or(0);
+        l.cpu.ebreak_hit = false;
+        match falcon::exec::step(&mut l.cpu, &mut l.mem, &mut self.console) {
+            Ok(true) => {
+                self.track_call(pc, word);
+                StepResult::Ok
+            }
+            Ok(false) if self.console.reading => StepResult::Blocked,
+            Ok(false) => match l.cpu.exit_code {
+                Some(code) => StepResult::Exited(code),
+                None if l.cpu.ebreak_hit => StepResult::Ebreak,
+                None => StepResult::Fault,
+            },
+            Err(e) => {
+                self.console.push_error(e.to_string());
+                StepResult::Fault
+            }
+        }
+    }
+
+    fn track_call(&mut self, pc: u32, word: u32) {
+        let next = self.loaded.cpu.pc;
+        match falcon::decoder::decode(word) {
+            Ok(Instruction::Jal { rd: 1 | 5, .. } | Instruction::Jalr { rd: 1 | 5, .. }) => {
+                self.calls.push(Call { func: next, site: pc, ret: pc.wrapping_add(4) });
+            }
+            Ok(Instruction::Jalr { rd: 0, rs1: 1 | 5, .. }) => {
+                if let Some(i) = self.calls.iter().rposition(|c| c.ret == next) {
+                    self.calls.truncate(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.values().flatten().any(|&a| a == self.loaded.cpu.pc)
+    }
+
+    /// Set the program going once configuration is done, or return why it stops
+    /// first: on entry when asked to, or at a breakpoint on the first instruction,
+    /// which `run_slice` would step past since it checks after each instruction.
+    fn start(&mut self) -> Option<&'static str> {
+        if self.stop_on_entry {
+            return Some("entry");
+        }
+        if self.at_breakpoint() {
+            return Some("breakpoint");
+        }
+        self.running = Some(Mode::Continue);
+        None
+    }
+
+    /// Whether a program resumed with `mode` should stop at the current PC.
+    fn stop_reason(&self, mode: Mode) -> Option<&'static str> {
+        if self.at_breakpoint() {
+            return Some("breakpoint");
+        }
+        let depth = self.calls.len();
+        let stop = match mode {
+            Mode::Continue => false,
+            Mode::StepIn => self.at_line_start(),
+            Mode::Over { depth: d } => depth <= d && self.at_line_start(),
+            Mode::Out { depth: d } => depth < d && self.at_line_start(),
+        };
+        stop.then_some("step")
+    }
+}
+
+struct Server {
+    out: io::Stdout,
+    seq: i64,
+    session: Option<Session>,
+}
+
+/// Serve DAP on stdin/stdout until the client disconnects.
+pub fn run(mem_size: Option<usize>) -> i32 {
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut r = stdin.lock();
+        while let Ok(Some(msg)) = json::read_message(&mut r) {
+            if tx.send(msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut server = Server { out: io::stdout(), seq: 0, session: None };
+    loop {
+        let msg = if server.is_running() {
+            match rx.try_recv() {
+                Ok(m) => Some(m),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match rx.recv() {
+                Ok(m) => Some(m),
+                Err(_) => break,
+            }
+        };
+        if let Some(msg) = msg
+            && !server.handle(&msg, mem_size)
+        {
+            break;
+        }
+        if server.is_running() {
+            server.run_slice();
+        }
+    }
+    0
+}
+
+impl Server {
+    fn is_running(&self) -> bool {
+        self.session.as_ref().is_some_and(|s| s.running.is_some())
+    }
+
+    fn send(&mut self, mut msg: Json) {
+        self.seq += 1;
+        if let Json::Obj(m) = &mut msg {
+            m.insert(0, ("seq".into(), Json::from(self.seq)));
+        }
+        let _ = json::write_message(&mut self.out.lock(), &msg);
+    }
+
+    fn respond(&mut self, req: &Json, body: Json) {
+        let mut msg = vec![
+            ("type", Json::from("response")),
+            ("request_seq", req.get("seq").clone()),
+            ("success", Json::from(true)),
+            ("command", req.get("command").clone()),
+        ];
+        if body != Json::Null {
+            msg.push(("body", body));
+        }
+        self.send(Json::obj(msg));
+    }
+
+    fn fail(&mut self, req: &Json, message: impl Into<String>) {
+        self.send(Json::obj([
+            ("type", Json::from("response")),
+            ("request_seq", req.get("seq").clone()),
+            ("success", Json::from(false)),
+            ("command", req.get("command").clone()),
+            ("message", Json::from(message.into())),
+        ]));
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        let mut msg = vec![("type", Json::from("event")), ("event", Json::from(event))];
+        if body != Json::Null {
+            msg.push(("body", body));
+        }
+        self.send(Json::obj(msg));
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) {
+        let mut body = vec![
+            ("reason", Json::from(reason)),
+            ("threadId", Json::from(THREAD_ID)),
+            ("allThreadsStopped", Json::from(true)),
+        ];
+        if let Some(t) = text {
+            body.push(("text", Json::from(t)));
+        }
+        self.event("stopped", Json::obj(body));
+    }
+
+    /// Forward guest console output as `output` events.
+    fn flush_output(&mut self) {
+        let Some(s) = self.session.as_mut() else { return };
+        let mut chunks: Vec<(String, &'static str)> = Vec::new();
+        s.sink.drain(&s.console, |text, color, ends_line| {
+            let category = if color == ConsoleColor::Normal { "stdout" } else { "stderr" };
+            let mut t = text.to_string();
+            if ends_line {
+                t.push('\n');
+            }
+            match chunks.last_mut() {
+                Some((prev, cat)) if *cat == category => prev.push_str(&t),
+                _ => chunks.push((t, category)),
+            }
+        });
+        for (text, category) in chunks {
+            self.event("output", Json::obj([("category", Json::from(category)), ("output", Json::from(text))]));
+        }
+    }
+
+    /// Handle one request; returns false when the session should end.
+    fn handle(&mut self, req: &Json, mem_size: Option<usize>) -> bool {
+        if req.get("type").as_str() != Some("request") {
+            return true;
+        }
+        let args = req.get("arguments");
+        match req.get("command").as_str().unwrap_or("") {
+            "initialize" => {
+                self.respond(
+                    req,
+                    Json::obj([
+                        ("supportsConfigurationDoneRequest", Json::from(true)),
+                        ("supportsReadMemoryRequest", Json::from(true)),
+                        ("supportsDisassembleRequest", Json::from(true)),
+                        ("supportsTerminateRequest", Json::from(true)),
+                        ("supportsEvaluateForHovers", Json::from(true)),
+                    ]),
+                );
+            }
+            "launch" => {
+                let Some(program) = args.get("program").as_str() else {
+                    self.fail(req, "launch: missing \"program\"");
+                    return true;
+                };
+                let path = PathBuf::from(program);
+                // `args` is a list of strings, `env` an object of NAME: value
+                let program_args = ProgramArgs {
+                    args: args.get("args").as_array().iter().filter_map(|a| a.as_str().map(str::to_string)).collect(),
+                    env: match args.get("env") {
+                        Json::Obj(vars) => vars.iter().filter_map(|(k, v)| Some(format!("{k}={}", v.as_str()?))).collect(),
+                        _ => Vec::new(),
+                    },
+                };
+                // `clock` is a frequency like "10mhz"; `hostTime` reads the host's clock instead
+                let mut clock = crate::falcon::clock::GuestClock::default();
+                if let Some(freq) = args.get("clock").as_str() {
+                    match crate::falcon::clock::parse_hz(freq) {
+                        Ok(hz) => clock.hz = hz,
+                        Err(e) => {
+                            self.fail(req, format!("launch: {e}"));
+                            return true;
+                        }
+                    }
+                }
+                clock.host = args.get("hostTime").as_bool().unwrap_or(false);
+                match load_program(&path, mem_size, &[], &program_args) {
+                    Ok(mut loaded) => {
+                        loaded.cpu.clock = clock;
+                        for w in &loaded.warnings {
+                            self.event("output", Json::obj([("category", Json::from("console")), ("output", Json::from(format!("{w}\n")))]));
+                        }
+                        let stop_on_entry = args.get("stopOnEntry").as_bool().unwrap_or(false);
+                        self.session = Some(Session::new(loaded, &path, stop_on_entry));
+                        self.respond(req, Json::Null);
+                        self.event("initialized", Json::Null);
+                    }
+                    Err(e) => self.fail(req, e),
+                }
+            }
+            "setBreakpoints" => self.set_breakpoints(req),
+            "setExceptionBreakpoints" => self.respond(req, Json::obj([("breakpoints", Json::Arr(vec![]))])),
+            "configurationDone" => {
+                self.respond(req, Json::Null);
+                if let Some(reason) = self.session.as_mut().and_then(Session::start) {
+                    self.stopped(reason, None);
+                }
+            }
+            "threads" => {
+                let thread = Json::obj([("id", Json::from(THREAD_ID)), ("name", Json::from("hart 0"))]);
+                self.respond(req, Json::obj([("threads", Json::Arr(vec![thread]))]));
+            }
+            "stackTrace" => self.stack_trace(req),
+            "scopes" => {
+                let scope = |name: &str, r: i64, expensive: bool| {
+                    Json::obj([
+                        ("name", Json::from(name)),
+                        ("variablesReference", Json::from(r)),
+                        ("expensive", Json::from(expensive)),
+                    ])
+                };
+                let scopes = vec![
+                    scope("Registers", REGS_REF, false),
+                    scope("Float Registers", FREGS_REF, false),
+                    scope("Data", DATA_REF, true),
+                    scope("Stack", STACK_REF, true),
+                ];
+                self.respond(req, Json::obj([("scopes", Json::Arr(scopes))]));
+            }
+            "variables" => {
+                let vars = self.variables(args.get("variablesReference").as_i64().unwrap_or(0));
+                self.respond(req, Json::obj([("variables", Json::Arr(vars))]));
+            }
+            cmd @ ("continue" | "next" | "stepIn" | "stepOut") => {
+                let Some(s) = self.session.as_mut() else {
+                    self.fail(req, "no program loaded");
+                    return true;
+                };
+                let depth = s.calls.len();
+                let mode = match cmd {
+                    "continue" => Mode::Continue,
+                    "next" => Mode::Over { depth },
+                    "stepIn" => Mode::StepIn,
+                    _ => Mode::Out { depth },
+                };
+                if s.console.reading {
+                    s.waiting_input = Some(mode);
+                } else {
+                    s.running = Some(mode);
+                }
+                let body = if cmd == "continue" { Json::obj([("allThreadsContinued", Json::from(true))]) } else { Json::Null };
+                self.respond(req, body);
+            }
+            "pause" => {
+                self.respond(req, Json::Null);
+                if let Some(s) = self.session.as_mut()
+                    && s.running.take().is_some()
+                {
+                    self.stopped("pause", None);
+                }
+            }
+            "evaluate" => self.evaluate(req),
+            "readMemory" => self.read_memory(req),
+            "disassemble" => self.disassemble(req),
+            "disconnect" | "terminate" => {
+                self.respond(req, Json::Null);
+                self.event("terminated", Json::Null);
+                return false;
+            }
+            other => self.fail(req, format!("unsupported request '{other}'")),
+        }
+        true
+    }
+
+    fn set_breakpoints(&mut self, req: &Json) {
+        let args = req.get("arguments");
+        let Some(s) = self.session.as_mut() else {
+            self.fail(req, "launch a program before setting breakpoints");
+            return;
+        };
+        let requested: Vec<i64> = args.get("breakpoints").as_array().iter().filter_map(|b| b.get("line").as_i64()).collect();
+        let path = PathBuf::from(args.get("source").get("path").as_str().unwrap_or(""));
+        let mut all = Vec::new();
+        let mut out = Vec::new();
+        for line in requested {
+            match s.breakpoint_addrs(&path, line) {
+                Some((l, addrs)) => {
+                    all.extend(&addrs);
+                    out.push(Json::obj([
+                        ("verified", Json::from(true)),
+                        ("line", Json::from(l)),
+                        ("instructionReference", Json::from(format!("0x{:08x}", addrs[0]))),
+                    ]));
+                }
+                None => out.push(Json::obj([
+                    ("verified", Json::from(false)),
+                    ("line", Json::from(line)),
+                    ("message", Json::from("no code at or after this line in the launched program")),
+                ])),
+            }
+        }
+        s.breakpoints.insert(path, all);
+        self.respond(req, Json::obj([("breakpoints", Json::Arr(out))]));
+    }
+
+    fn stack_trace(&mut self, req: &Json) {
+        let Some(s) = self.session.as_ref() else {
+            self.respond(req, Json::obj([("stackFrames", Json::Arr(vec![])), ("totalFrames", Json::from(0))]));
+            return;
+        };
+        let entry_name = s.name_at(s.loaded.text_base);
+        // Innermost first: the current PC, then each call site.
+        let mut pcs = vec![s.loaded.cpu.pc];
+        pcs.extend(s.calls.iter().rev().map(|c| c.site));
+        let mut frames = Vec::new();
+        for (i, &pc) in pcs.iter().enumerate() {
+            // Compiled code names the frame after the function holding its PC.
+            let func = match s.loaded.debug.as_ref().and_then(|d| d.function_at(pc)) {
+                Some(f) => Some(f.name.clone()),
+                None => function_at(&s.loaded.symbols, pc).map(|f| f.name.clone()),
+            };
+            let name = func.unwrap_or_else(|| {
+                s.calls.len().checked_sub(i + 1).map_or(entry_name.clone(), |c| s.name_at(s.calls[c].func))
+            });
+            let mut frame = vec![
+                ("id", Json::from(i)),
+                ("name", Json::from(name)),
+                ("line", Json::from(0u32)),
+                ("column", Json::from(0u32)),
+                ("instructionPointerReference", Json::from(format!("0x{pc:08x}"))),
+            ];
+            if let Some((src, line)) = s.source_line(pc) {
+                frame[2].1 = Json::from(line);
+                frame[3].1 = Json::from(1u32);
+                let name = src.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                frame.push((
+                    "source",
+                    Json::obj([("name", Json::from(name)), ("path", Json::from(src.to_string_lossy().into_owned()))]),
+                ));
+            }
+            frames.push(Json::obj(frame));
+        }
+        let total = frames.len();
+        self.respond(req, Json::obj([("stackFrames", Json::Arr(frames)), ("totalFrames", Json::from(total))]));
+    }
+
+    fn variables(&self, reference: i64) -> Vec<Json> {
+        let Some(s) = self.session.as_ref() else { return Vec::new() };
+        let cpu = &s.loaded.cpu;
+        let var = |name: String, value: String, mem: Option<u32>| {
+            let mut v = vec![
+                ("name", Json::from(name)),
+                ("value", Json::from(value)),
+                ("variablesReference", Json::from(0u32)),
+            ];
+            if let Some(a) = mem {
+                v.push(("memoryReference", Json::from(format!("0x{a:08x}"))));
+            }
+            Json::obj(v)
+        };
+        match reference {
+            REGS_REF => {
+                let mut vars = vec![var("pc".into(), format!("0x{:08x}", cpu.pc), Some(cpu.pc))];
+                for i in 0..32u8 {
+                    let v = cpu.read(i);
+                    vars.push(var(format!("{} (x{i})", reg_name(i)), format!("0x{v:08x} ({})", v as i32), None));
+                }
+                vars
+            }
+            FREGS_REF => {
+                let mut vars: Vec<Json> = (0..32u8)
+                    .map(|i| {
+                        let bits = cpu.fread_bits(i);
+                        var(format!("{} (f{i})", freg_name(i)), format!("{} (0x{bits:08x})", f32::from_bits(bits)), None)
+                    })
+                    .collect();
+                vars.push(var("fcsr".into(), format!("0x{:02x}", cpu.fcsr), None));
+                vars
+            }
+            DATA_REF | STACK_REF => {
+                let base = if reference == DATA_REF { s.loaded.data_base } else { cpu.read(2) & !3 };
+                (0..MEM_SCOPE_WORDS)
+                    .map_while(|i| {
+                        let addr = base.wrapping_add(i * 4);
+                        let w = s.loaded.mem.load32(addr).ok()?;
+                        let name = match s.loaded.labels.get(&addr).and_then(|n| n.first()) {
+                            Some(label) => format!("0x{addr:08x} <{label}>"),
+                            None => format!("0x{addr:08x}"),
+                        };
+                        Some(var(name, format!("0x{w:08x} ({})", w as i32), Some(addr)))
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn evaluate(&mut self, req: &Json) {
+        let args = req.get("arguments");
+        let expr = args.get("expression").as_str().unwrap_or("").trim().to_string();
+        let Some(s) = self.session.as_mut() else {
+            self.fail(req, "no program loaded");
+            return;
+        };
+        // While the program waits on a read syscall, the debug console feeds its input.
+        if s.console.reading && args.get("context").as_str() == Some("repl") {
+            s.sink.skip_input(&s.console, &expr);
+            s.console.push_input(expr);
+            s.console.reading = false;
+            s.running = s.waiting_input.take();
+            self.respond(req, Json::obj([("result", Json::from("")), ("variablesReference", Json::from(0u32))]));
+            return;
+        }
+        let cpu = &s.loaded.cpu;
+        let value = if expr == "pc" {
+            Some(cpu.pc)
+        } else if let Some(i) = (0..32u8).find(|&i| reg_name(i) == expr || format!("x{i}") == expr || (i == 8 && expr == "fp")) {
+            Some(cpu.read(i))
+        } else {
+            s.loaded.labels.iter().find(|(_, names)| names.contains(&expr)).map(|(&a, _)| a)
+        };
+        match value {
+            Some(v) => self.respond(
+                req,
+                Json::obj([
+                    ("result", Json::from(format!("0x{v:08x} ({})", v as i32))),
+                    ("variablesReference", Json::from(0u32)),
+                    ("memoryReference", Json::from(format!("0x{v:08x}"))),
+                ]),
+            ),
+            None => self.fail(req, format!("cannot evaluate '{expr}' (registers and labels are supported)")),
+        }
+    }
+
+    fn read_memory(&mut self, req: &Json) {
+        let args = req.get("arguments");
+        let Some(s) = self.session.as_ref() else {
+            self.fail(req, "no program loaded");
+            return;
+        };
+        let Some(base) = parse_addr(args.get("memoryReference").as_str().unwrap_or("")) else {
+            self.fail(req, "invalid memoryReference");
+            return;
+        };
+        let addr = base.wrapping_add(args.get("offset").as_i64().unwrap_or(0) as u32);
+        let count = args.get("count").as_i64().unwrap_or(0).clamp(0, 1 << 20) as u32;
+        let bytes: Vec<u8> = (0..count).map_while(|i| s.loaded.mem.load8(addr.wrapping_add(i)).ok()).collect();
+        let unreadable = count - bytes.len() as u32;
+        self.respond(
+            req,
+            Json::obj([
+                ("address", Json::from(format!("0x{addr:08x}"))),
+                ("data", Json::from(base64(&bytes))),
+                ("unreadableBytes", Json::from(unreadable)),
+            ]),
+        );
+    }
+
+    fn disassemble(&mut self, req: &Json) {
+        let args = req.get("arguments");
+        let Some(s) = self.session.as_ref() else {
+            self.fail(req, "no program loaded");
+            return;
+        };
+        let Some(base) = parse_addr(args.get("memoryReference").as_str().unwrap_or("")) else {
+            self.fail(req, "invalid memoryReference");
+            return;
+        };
+        let offset = args.get("offset").as_i64().unwrap_or(0) + args.get("instructionOffset").as_i64().unwrap_or(0) * 4;
+        let start = (base as i64 + offset) as u32 & !3;
+        let count = args.get("instructionCount").as_i64().unwrap_or(0).clamp(0, 4096) as u32;
+        let instrs: Vec<Json> = (0..count)
+            .map(|i| {
+                let addr = start.wrapping_add(i * 4);
+                let mut v = vec![("address", Json::from(format!("0x{addr:08x}")))];
+                match s.loaded.mem.load32(addr) {
+                    Ok(w) => {
+                        v.push(("instructionBytes", Json::from(format!("{w:08x}"))));
+                        v.push(("instruction", Json::from(disasm_word(w))));
+                        if let Some(names) = s.loaded.labels.get(&addr) {
+                            v.push(("symbol", Json::from(names.join(", "))));
+                        }
+                        if s.is_line_start(addr)
+                            && let Some((src, line)) = s.source_line(addr)
+                        {
+                            v.push(("location", Json::obj([("path", Json::from(src.to_string_lossy().into_owned()))])));
+                            v.push(("line", Json::from(line)));
+                        }
+                    }
+                    Err(_) => v.push(("instruction", Json::from("??"))),
+                }
+                Json::obj(v)
+            })
+            .collect();
+        self.respond(req, Json::obj([("instructions", Json::Arr(instrs))]));
+    }
+
+    /// Execute a slice of the running request, reporting the stop
+    /// (breakpoint, step end, exit, fault) when it happens. Stop conditions are
+    /// checked after each instruction, so resuming from a breakpoint moves on.
+    fn run_slice(&mut self) {
+        for _ in 0..SLICE_STEPS {
+            let Some(s) = self.session.as_mut() else { return };
+            let Some(mode) = s.running else { break };
+            match s.step() {
+                StepResult::Ok => {
+                    if let Some(reason) = s.stop_reason(mode) {
+                        s.running = None;
+                        self.flush_output();
+                        self.stopped(reason, None);
+                        return;
+                    }
+                }
+                StepResult::Blocked => {
+                    s.running = None;
+                    s.waiting_input = Some(mode);
+                    self.flush_output();
+                    self.event(
+                        "output",
+                        Json::obj([
+                            ("category", Json::from("console")),
+                            ("output", Json::from("[program is waiting for input — type it in the debug console]\n")),
+                        ]),
+                    );
+                    return;
+                }
+                StepResult::Ebreak => {
+                    s.running = None;
+                    self.flush_output();
+                    self.stopped("breakpoint", Some("ebreak".into()));
+                    return;
+                }
+                StepResult::Exited(code) => {
+                    s.running = None;
+                    self.flush_output();
+                    self.event("exited", Json::obj([("exitCode", Json::from(code))]));
+                    self.event("terminated", Json::Null);
+                    return;
+                }
+                StepResult::Fault => {
+                    s.running = None;
+                    let msg = s.console.lines.iter().rev().find(|l| l.color == ConsoleColor::Error).map(|l| l.text.clone());
+                    self.flush_output();
+                    self.stopped("exception", msg);
+                    return;
+                }
+            }
+        }
+        self.flush_output();
+    }
+}
+
+fn parse_addr(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_matches_rfc4648_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn step_over_skips_calls() {
+        let src = ".text\nmain:\njal ra, f\nli a0, 1\nli a7, 93\necall\nf:\nli t0, 5\nret\n";
+        let dir = std::env::temp_dir().join(format!("raven_dap_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("prog.fas");
+        std::fs::write(&path, src).unwrap();
+        let loaded = load_program(&path, None, &[], &ProgramArgs::default()).unwrap();
+        let mut s = Session::new(loaded, &path, true);
+
+        // `next` over `jal ra, f` lands on the following line in main.
+        let mode = Mode::Over { depth: 0 };
+        assert!(matches!(s.step(), StepResult::Ok));
+        assert_eq!(s.calls.len(), 1);
+        while s.stop_reason(mode).is_none() {
+            assert!(matches!(s.step(), StepResult::Ok));
+        }
+        assert_eq!(s.line_of(s.loaded.cpu.pc), Some((0, 3)));
+        assert!(s.calls.is_empty());
+
+        // Without stopOnEntry, a breakpoint on main's first line still stops there.
+        s.loaded.cpu.pc = s.loaded.text_base;
+        s.stop_on_entry = false;
+        assert_eq!(s.start(), None);
+        assert_eq!(s.running, Some(Mode::Continue));
+        s.running = None;
+        let (line, addrs) = s.breakpoint_addrs(&path, 2).unwrap();
+        assert_eq!((line, addrs.as_slice()), (3, &[s.loaded.text_base][..]));
+        s.breakpoints.insert(path, addrs);
+        assert_eq!(s.start(), Some("breakpoint"));
+        assert_eq!(s.running, None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn steps_and_breakpoints_reach_included_files() {
+        let dir = std::env::temp_dir().join(format!("raven_dap_inc_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (main, lib) = (dir.join("main.fas"), dir.join("lib.fas"));
+        std::fs::write(&main, ".text\nmain:\njal ra, f\nli a7, 93\necall\n.include \"lib.fas\"\n").unwrap();
+        std::fs::write(&lib, "f:\nli t0, 5\nret\n").unwrap();
+        let loaded = load_program(&main, None, &[], &ProgramArgs::default()).unwrap();
+        let mut s = Session::new(loaded, &main, true);
+
+        // `stepIn` on the call stops on the first line of `f`, in lib.fas.
+        assert!(matches!(s.step(), StepResult::Ok));
+        assert_eq!(s.stop_reason(Mode::StepIn), Some("step"));
+        assert_eq!(s.source_line(s.loaded.cpu.pc), Some((lib.canonicalize().unwrap(), 2)));
+
+        // Breakpoints resolve per file: line 3 of lib.fas is `ret`, not main's `ecall`.
+        let (line, addrs) = s.breakpoint_addrs(&lib, 3).unwrap();
+        assert_eq!(line, 3);
+        assert_eq!(s.source_line(addrs[0]), Some((lib.canonicalize().unwrap(), 3)));
+        let (_, main_addrs) = s.breakpoint_addrs(&main, 3).unwrap();
+        assert_eq!(main_addrs, [s.loaded.text_base]);
+        s.breakpoints.insert(lib, addrs);
+        assert!(matches!(s.step(), StepResult::Ok));
+        assert_eq!(s.stop_reason(Mode::Continue), Some("breakpoint"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,622 @@
+// disasm.rs — `raven disasm`: a standalone disassembler
+//
+// Reads an ELF, FALC container or flat binary and prints it objdump-style, or with
+// `--fas` as source that `raven` assembles back to the same words and bytes.
+// Branch and jump targets become labels (ELF symbols where there are any,
+// `L_<addr>` otherwise) and data sections come out as directives.
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::falcon::asm::{assemble_diagnostics, data_base_after, Severity};
+use crate::falcon::decoder::decode;
+use crate::falcon::instruction::Instruction;
+use crate::falcon::program::{inspect_elf, parse_falc, ElfSymbol, Falc, SymbolBinding};
+use crate::ui::view::disasm::{disasm_word, reg_name};
+
+pub fn usage() -> &'static str {
+    "usage: raven disasm <file.elf|file.bin> [--fas] [-t] [-o <out>]"
+}
+
+/// What a binary holds once its container is stripped away.
+struct Image {
+    entry: u32,
+    text_base: u32,
+    text: Vec<u32>,
+    /// Executable segments besides `text` (`.init`, code a linker script placed
+    /// elsewhere), in address order.
+    more_code: Vec<(u32, Vec<u32>)>,
+    data_base: u32,
+    data: Vec<u8>,
+    bss_size: u32,
+    symbols: BTreeMap<u32, Vec<String>>,
+    /// ELF symbols with their sizes and bindings.
+    symbol_table: Vec<ElfSymbol>,
+}
+
+impl Image {
+    fn parse(bytes: &[u8]) -> Result<Image, String> {
+        if bytes.len() >= 4 && &bytes[0..4] == b"\x7fELF" {
+            return Image::from_elf(bytes);
+        }
+        // FALC container or flat binary: text at 0, data on the first page after it
+        let Falc { text, data, bss_size } = parse_falc(bytes)?;
+        Ok(Image {
+            entry: 0,
+            text_base: 0,
+            text: words(text),
+            more_code: Vec::new(),
+            data_base: data_base_after(0, text.len() as u32).ok_or("binary too large")?,
+            data: data.to_vec(),
+            bss_size,
+            symbols: BTreeMap::new(),
+            symbol_table: Vec::new(),
+        })
+    }
+
+    fn from_elf(bytes: &[u8]) -> Result<Image, String> {
+        let info = inspect_elf(bytes).map_err(|e| e.to_string())?;
+        // Data sections are laid end to end from the first one, gaps zero-filled;
+        // NOBITS sections (no file bytes) past the data become .bss.
+        let (loaded, nobits): (Vec<_>, Vec<_>) = info.sections.iter().partition(|s| !s.bytes.is_empty());
+        let data_base = loaded.first().or(nobits.first()).map_or(info.data_base, |s| s.addr);
+        let mut data = Vec::new();
+        for s in &loaded {
+            let at = s.addr.wrapping_sub(data_base) as usize;
+            if data.len() < at {
+                data.resize(at, 0);
+            }
+            data.truncate(at);
+            data.extend_from_slice(&s.bytes);
+        }
+        let data_end = data_base.wrapping_add(data.len() as u32);
+        let bss_end = nobits.iter().map(|s| s.addr.wrapping_add(s.size)).max().unwrap_or(data_end).max(data_end);
+        let more_code =
+            info.code_segments.iter().filter(|(vaddr, _)| *vaddr != info.text_base).map(|(vaddr, seg)| (*vaddr, words(seg))).collect();
+        Ok(Image {
+            entry: info.entry,
+            text_base: info.text_base,
+            text: words(&info.text_bytes),
+            more_code,
+            data_base,
+            data,
+            bss_size: bss_end - data_end,
+            symbols: info.symbols.into_iter().collect(),
+            symbol_table: info.symbol_table,
+        })
+    }
+
+    /// Every code segment, `text` included, as (base, words) in address order.
+    fn code(&self) -> Vec<(u32, &[u32])> {
+        let mut code: Vec<(u32, &[u32])> = self.more_code.iter().map(|(base, w)| (*base, w.as_slice())).collect();
+        code.push((self.text_base, &self.text));
+        code.sort_by_key(|&(base, _)| base);
+        code
+    }
+
+    fn in_code(&self, addr: u32) -> bool {
+        self.code().iter().any(|&(base, w)| addr.wrapping_sub(base) < w.len() as u32 * 4)
+    }
+
+    /// Whether `addr` lies inside a sized data object (a literal pool or table
+    /// the linker put among the code), which is shown as words.
+    fn in_object(&self, addr: u32) -> bool {
+        self.symbol_table.iter().any(|s| !s.func && s.size > 0 && addr.wrapping_sub(s.addr) < s.size)
+    }
+
+    fn data_end(&self) -> u32 {
+        self.data_base.wrapping_add(self.data.len() as u32)
+    }
+}
+
+/// Little-endian words, the last one zero-padded.
+fn words(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks(4)
+        .map(|c| {
+            let mut w = [0u8; 4];
+            w[..c.len()].copy_from_slice(c);
+            u32::from_le_bytes(w)
+        })
+        .collect()
+}
+
+/// Whether `name` can be written as a label and read back as one.
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+/// Address a branch or `jal` at `pc` transfers to.
+fn target(pc: u32, word: u32) -> Option<u32> {
+    use Instruction::*;
+    match decode(word).ok()? {
+        Beq { imm, .. } | Bne { imm, .. } | Blt { imm, .. } | Bge { imm, .. } | Bltu { imm, .. } | Bgeu { imm, .. }
+        | Jal { imm, .. } => Some(pc.wrapping_add(imm as u32)),
+        _ => None,
+    }
+}
+
+/// Labels by address: every symbol that is a valid label (each name once), then
+/// `L_<addr>` for branch targets inside the code that have none.
+fn labels(img: &Image) -> BTreeMap<u32, Vec<String>> {
+    let mut used = HashSet::new();
+    let mut labels: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    for (&addr, names) in &img.symbols {
+        for name in names {
+            if is_label(name) && used.insert(name.clone()) {
+                labels.entry(addr).or_default().push(name.clone());
+            }
+        }
+    }
+    for (base, words) in img.code() {
+        for (i, &w) in words.iter().enumerate() {
+            let pc = base.wrapping_add(i as u32 * 4);
+            if let Some(t) = target(pc, w)
+                && img.in_code(t)
+                && t.is_multiple_of(4)
+                && !labels.contains_key(&t)
+            {
+                let mut name = format!("L_{t:08x}");
+                while !used.insert(name.clone()) {
+                    name.push('_');
+                }
+                labels.insert(t, vec![name]);
+            }
+        }
+    }
+    labels
+}
+
+/// `%hi`/`%lo` forms of the `lui` pairs (with an `addi`, load or store) that build
+/// a symbol's address, by text index, so the code follows its data to wherever the
+/// assembler places it. Uses are looked for after each `lui` until its register is
+/// overwritten, control leaves, or a label starts another block.
+fn symbol_refs(img: &Image, labels: &BTreeMap<u32, Vec<String>>) -> HashMap<usize, String> {
+    use Instruction::*;
+    let bss_end = img.data_end().wrapping_add(img.bss_size);
+    // A label, or an offset into the data or bss object that holds the address.
+    let name_of = |addr: u32| -> Option<String> {
+        if let Some(name) = labels.get(&addr).and_then(|n| n.first()) {
+            return Some(name.clone());
+        }
+        if !(img.data_base..bss_end).contains(&addr) {
+            return None;
+        }
+        let (&at, names) = labels.range(img.data_base..=addr).next_back()?;
+        Some(format!("{}+{}", names[0], addr - at))
+    };
+    let pc_of = |i: usize| img.text_base.wrapping_add(i as u32 * 4);
+    let mut refs = HashMap::new();
+    for (i, &w) in img.text.iter().enumerate() {
+        let Ok(Lui { rd, imm: hi }) = decode(w) else { continue };
+        if rd == 0 || img.in_object(pc_of(i)) {
+            continue;
+        }
+        let mut hi_name = None;
+        for (j, &u) in img.text.iter().enumerate().skip(i + 1).take(8) {
+            if labels.contains_key(&pc_of(j)) || img.in_object(pc_of(j)) {
+                break;
+            }
+            let ins = disasm_word(u);
+            let (rs1, lo, dest) = match decode(u) {
+                Ok(Addi { rd, rs1, imm } | Lb { rd, rs1, imm } | Lh { rd, rs1, imm } | Lw { rd, rs1, imm }
+                | Lbu { rd, rs1, imm } | Lhu { rd, rs1, imm }) => (rs1, imm, Some(rd)),
+                Ok(Sb { rs1, imm, .. } | Sh { rs1, imm, .. } | Sw { rs1, imm, .. } | Flw { rs1, imm, .. }
+                | Fsw { rs1, imm, .. }) => (rs1, imm, None),
+                Ok(Jal { .. } | Jalr { .. } | Ecall | Ebreak | Halt) | Err(_) => break,
+                Ok(_) if target(pc_of(j), u).is_some() => break,
+                // Anything else ends the search once it writes the register.
+                Ok(_) => match ins.split_whitespace().nth(1) {
+                    Some(first) if first.trim_end_matches(',') == reg_name(rd) => break,
+                    _ => continue,
+                },
+            };
+            if rs1 == rd
+                && let Some(name) = name_of(hi.wrapping_add(lo) as u32)
+                && let Some((head, _)) = ins.rsplit_once(", ")
+            {
+                let lo = match decode(u) {
+                    Ok(Addi { .. }) => format!("{head}, %lo({name})"),
+                    _ => format!("{head}, %lo({name})({})", reg_name(rd)),
+                };
+                refs.insert(j, lo);
+                hi_name.get_or_insert(name);
+            }
+            if dest == Some(rd) {
+                break;
+            }
+        }
+        if let Some(name) = hi_name {
+            refs.insert(i, format!("lui  {}, %hi({name})", reg_name(rd)));
+        }
+    }
+    refs
+}
+
+/// How `raven disasm` lays its output out.
+#[derive(Clone, Copy, PartialEq)]
+enum Style {
+    /// Address and word columns next to each line, like `objdump -d`.
+    Dump,
+    /// Plain source that assembles back to the same image.
+    Fas,
+}
+
+/// Renders `img`. Words in `raw` are written as `.word` even if they decode.
+/// Also returns, for each output line, the index of the text word on it.
+fn render(img: &Image, style: Style, raw: &BTreeSet<usize>) -> (String, Vec<Option<usize>>) {
+    let labels = labels(img);
+    let mut out = String::new();
+    let mut line_words = Vec::new();
+    let mut line = |out: &mut String, text: String, word: Option<usize>| {
+        out.push_str(&text);
+        out.push('\n');
+        line_words.push(word);
+    };
+    let label_lines = |out: &mut String, line: &mut dyn FnMut(&mut String, String, Option<usize>), addr: u32| {
+        for name in labels.get(&addr).into_iter().flatten() {
+            match style {
+                Style::Dump => line(out, format!("\n{addr:08x} <{name}>:"), None),
+                Style::Fas => line(out, format!("{name}:"), None),
+            }
+        }
+    };
+
+    let instruction = |pc: u32, w: u32| -> String {
+        if img.in_object(pc) {
+            return format!(".word 0x{w:08x}");
+        }
+        let ins = disasm_word(w);
+        if let Some(t) = target(pc, w)
+            && let Some(name) = labels.get(&t).and_then(|n| n.first())
+            && let Some((head, _)) = ins.rsplit_once(", ")
+        {
+            return format!("{head}, {name}");
+        }
+        ins
+    };
+
+    match style {
+        Style::Dump => {
+            line(&mut out, format!("entry {:08x}", img.entry), None);
+            for (base, words) in img.code() {
+                let end = base.wrapping_add(words.len() as u32 * 4);
+                let name = if base == img.text_base { ".text" } else { "code" };
+                line(&mut out, String::new(), None);
+                line(&mut out, format!("Disassembly of {name} ({base:08x}..{end:08x}):"), None);
+                for (i, &w) in words.iter().enumerate() {
+                    let pc = base.wrapping_add(i as u32 * 4);
+                    label_lines(&mut out, &mut line, pc);
+                    line(&mut out, format!("{pc:08x}:  {w:08x}  {}", instruction(pc, w)), None);
+                }
+            }
+        }
+        Style::Fas => {
+            line(&mut out, format!("# assemble at base 0x{:08x}", img.text_base), None);
+            if img.entry != img.text_base {
+                line(&mut out, format!("# entry point: 0x{:08x}", img.entry), None);
+            }
+            let placed = data_base_after(img.text_base, img.text.len() as u32 * 4);
+            if (!img.data.is_empty() || img.bss_size > 0) && placed != Some(img.data_base) {
+                line(
+                    &mut out,
+                    format!("# note: .data was at 0x{:08x}; code still addresses it there", img.data_base),
+                    None,
+                );
+            }
+            for (base, words) in &img.more_code {
+                let end = base.wrapping_add(words.len() as u32 * 4);
+                line(&mut out, format!("# note: code at 0x{base:08x}..0x{end:08x} is in another segment and left out"), None);
+            }
+            line(&mut out, ".text".into(), None);
+            let refs = symbol_refs(img, &labels);
+            for (i, &w) in img.text.iter().enumerate() {
+                let pc = img.text_base.wrapping_add(i as u32 * 4);
+                label_lines(&mut out, &mut line, pc);
+                let ins = refs.get(&i).cloned().unwrap_or_else(|| instruction(pc, w));
+                let text = if raw.contains(&i) && !ins.starts_with(".word") {
+                    format!("    .word 0x{w:08x}  # {ins}")
+                } else {
+                    format!("    {ins}")
+                };
+                line(&mut out, text, Some(i));
+            }
+        }
+    }
+
+    if !img.data.is_empty() {
+        match style {
+            Style::Dump => {
+                line(&mut out, String::new(), None);
+                line(&mut out, format!("Contents of .data ({:08x}..{:08x}):", img.data_base, img.data_end()), None);
+            }
+            Style::Fas => line(&mut out, "\n.data".into(), None),
+        }
+        // Directives never straddle a label
+        let mut cuts: Vec<u32> = labels.range(img.data_base + 1..img.data_end()).map(|(&a, _)| a).collect();
+        cuts.push(img.data_end());
+        let mut addr = img.data_base;
+        for cut in cuts {
+            label_lines(&mut out, &mut line, addr);
+            let start = addr.wrapping_sub(img.data_base) as usize;
+            let end = cut.wrapping_sub(img.data_base) as usize;
+            for (offset, directive) in data_directives(&img.data[start..end], addr) {
+                let text = match style {
+                    Style::Dump => format!("{:08x}:  {directive}", addr.wrapping_add(offset as u32)),
+                    Style::Fas => format!("    {directive}"),
+                };
+                line(&mut out, text, None);
+            }
+            addr = cut;
+        }
+    }
+
+    if img.bss_size > 0 {
+        let bss_end = img.data_end().wrapping_add(img.bss_size);
+        match style {
+            Style::Dump => {
+                line(&mut out, String::new(), None);
+                line(&mut out, format!(".bss ({:08x}..{bss_end:08x})", img.data_end()), None);
+            }
+            Style::Fas => line(&mut out, "\n.bss".into(), None),
+        }
+        let mut cuts: Vec<u32> = labels.range(img.data_end() + 1..bss_end).map(|(&a, _)| a).collect();
+        cuts.push(bss_end);
+        let mut addr = img.data_end();
+        for cut in cuts {
+            label_lines(&mut out, &mut line, addr);
+            if cut > addr {
+                let text = match style {
+                    Style::Dump => format!("{addr:08x}:  .space {}", cut - addr),
+                    Style::Fas => format!("    .space {}", cut - addr),
+                };
+                line(&mut out, text, None);
+            }
+            addr = cut;
+        }
+    }
+    (out, line_words)
+}
+
+/// The ELF symbol table, objdump `-t` style: address, binding (`l`ocal,
+/// `g`lobal, `w`eak), `F`unction or `O`bject, size and name.
+fn symbol_table(img: &Image) -> String {
+    let mut out = String::from("SYMBOL TABLE:\n");
+    for sym in &img.symbol_table {
+        let binding = match sym.binding {
+            SymbolBinding::Local => 'l',
+            SymbolBinding::Global => 'g',
+            SymbolBinding::Weak => 'w',
+        };
+        let kind = if sym.func { 'F' } else { 'O' };
+        out.push_str(&format!("{:08x} {binding} {kind} {:08x} {}\n", sym.addr, sym.size, sym.name));
+    }
+    out.push('\n');
+    out
+}
+
+/// Bytes that can sit inside an `.asciz` literal: the assembler does not process
+/// escapes and cuts comments at `#` and `;`.
+fn printable(b: u8) -> bool {
+    (0x20..0x7f).contains(&b) && !b"\"\\#;".contains(&b)
+}
+
+/// Directives reproducing `bytes` (found at `addr`), each with its offset:
+/// NUL-terminated printable runs as `.asciz`, aligned words as `.word`, the rest as `.byte`.
+fn data_directives(bytes: &[u8], addr: u32) -> Vec<(usize, String)> {
+    let mut out = Vec::new();
+    let mut pending: Vec<(usize, u8)> = Vec::new();
+    let flush = |out: &mut Vec<(usize, String)>, pending: &mut Vec<(usize, u8)>| {
+        for chunk in pending.chunks(8) {
+            let list: Vec<String> = chunk.iter().map(|(_, b)| b.to_string()).collect();
+            out.push((chunk[0].0, format!(".byte {}", list.join(", "))));
+        }
+        pending.clear();
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..].iter().take_while(|&&b| printable(b)).count();
+        if run >= 2 && bytes.get(i + run) == Some(&0) {
+            flush(&mut out, &mut pending);
+            out.push((i, format!(".asciz \"{}\"", String::from_utf8_lossy(&bytes[i..i + run]))));
+            i += run + 1;
+        } else if addr.wrapping_add(i as u32).is_multiple_of(4) && i + 4 <= bytes.len() {
+            flush(&mut out, &mut pending);
+            let w = u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+            out.push((i, format!(".word 0x{w:08x}")));
+            i += 4;
+        } else {
+            pending.push((i, bytes[i]));
+            i += 1;
+        }
+    }
+    flush(&mut out, &mut pending);
+    out
+}
+
+/// `--fas` source for `img`. Words the assembler would not encode back the same
+/// (or reject) are found by assembling the result and fall back to `.word`, every
+/// one a pass turns up at once.
+fn to_fas(img: &Image) -> String {
+    let mut raw = BTreeSet::new();
+    loop {
+        let (src, line_words) = render(img, Style::Fas, &raw);
+        let bad: Vec<usize> = match assemble_diagnostics(&src, img.text_base) {
+            (Some(prog), _) => img.text.iter().zip(&prog.text).enumerate().filter(|(_, (a, b))| a != b).map(|(i, _)| i).collect(),
+            (None, diags) => diags
+                .iter()
+                .filter(|d| d.severity == Severity::Error)
+                .filter_map(|d| line_words.get(d.line).copied().flatten())
+                .collect(),
+        };
+        let before = raw.len();
+        raw.extend(bad);
+        if raw.len() == before {
+            return src;
+        }
+    }
+}
+
+/// `raven disasm`: returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let run = || -> Result<(), String> {
+        let mut input = None;
+        let mut output: Option<PathBuf> = None;
+        let mut style = Style::Dump;
+        let mut with_symbols = false;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--fas" => style = Style::Fas,
+                "-t" => with_symbols = true,
+                "-o" => {
+                    output = Some(PathBuf::from(args.get(i + 1).ok_or("-o requires an output path")?));
+                    i += 1;
+                }
+                "--mem" => i += 1,
+                a if a.starts_with('-') => return Err(format!("unknown option '{a}'")),
+                a if input.is_none() => input = Some(PathBuf::from(a)),
+                _ => return Err("raven disasm takes exactly one input file".into()),
+            }
+            i += 1;
+        }
+        let input = input.ok_or("no input file")?;
+        if with_symbols && style == Style::Fas {
+            return Err("-t prints the symbol table with the dump, not with --fas".into());
+        }
+        let bytes = std::fs::read(&input).map_err(|e| format!("{}: {e}", input.display()))?;
+        let img = Image::parse(&bytes).map_err(|e| format!("{}: {e}", input.display()))?;
+        let text = match style {
+            Style::Dump if with_symbols => symbol_table(&img) + &render(&img, Style::Dump, &BTreeSet::new()).0,
+            Style::Dump => render(&img, Style::Dump, &BTreeSet::new()).0,
+            Style::Fas => to_fas(&img),
+        };
+        match output {
+            Some(path) => std::fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display())),
+            None => {
+                print!("{text}");
+                Ok(())
+            }
+        }
+    };
+    match run() {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::asm::assemble;
+    use crate::falcon::program::write_elf;
+
+    const SRC: &str = "\
+.text
+main:
+    la a0, msg
+    li t0, 3
+loop:
+    addi t0, t0, -1
+    bne t0, zero, loop
+    jal ra, helper
+    lui a1, 0x12345
+    auipc a2, 0
+    .word 0x1005a6af
+    fadd.s ft0, ft1, ft2
+    fcvt.w.s t1, ft0
+    ecall
+helper:
+    jalr zero, ra, 0
+    .word 0xffffffff
+.data
+msg: .asciz \"Hello, world!\"
+.align 4
+nums: .word 1, 0xdeadbeef, -2
+odd: .byte 1, 2, 3
+.bss
+buf: .space 64
+";
+
+    /// Assemble `fas` at the image's base and check it rebuilds the image.
+    fn assert_round_trips(img: &Image, fas: &str) {
+        let prog = match assemble(fas, img.text_base) {
+            Ok(p) => p,
+            Err(e) => panic!("line {}: {}\n{fas}", e.line, e.msg),
+        };
+        assert_eq!(prog.text, img.text, "{fas}");
+        assert_eq!(prog.data, img.data, "{fas}");
+        assert_eq!(prog.bss_size, img.bss_size, "{fas}");
+    }
+
+    #[test]
+    fn falc_images_round_trip_with_generated_labels() {
+        let prog = assemble(SRC, 0).unwrap();
+        let mut falc = b"FALC".to_vec();
+        let text: Vec<u8> = prog.text.iter().flat_map(|w| w.to_le_bytes()).collect();
+        for n in [text.len() as u32, prog.data.len() as u32, prog.bss_size] {
+            falc.extend_from_slice(&n.to_le_bytes());
+        }
+        falc.extend_from_slice(&text);
+        falc.extend_from_slice(&prog.data);
+        let img = Image::parse(&falc).unwrap();
+        let fas = to_fas(&img);
+        assert!(fas.contains("    bne  t0, zero, L_0000000c\n"), "{fas}");
+        // the assembler has no atomics, so lr.w stays a word
+        assert!(fas.contains("    .word 0x1005a6af  # lr.w      a3, (a1)\n"), "{fas}");
+        assert!(fas.contains("    .asciz \"Hello, world!\"\n"), "{fas}");
+        assert!(fas.contains("    .space 64\n"), "{fas}");
+        assert_round_trips(&img, &fas);
+    }
+
+    #[test]
+    fn every_word_that_does_not_round_trip_falls_back_in_one_pass() {
+        // lr.w and nop alternating: thousands of words the assembler can't rebuild
+        let flat: Vec<u8> = [0x1005_a6afu32, 0x0000_0013].repeat(2048).iter().flat_map(|w| w.to_le_bytes()).collect();
+        let img = Image::parse(&flat).unwrap();
+        let fas = to_fas(&img);
+        assert_eq!(fas.matches(".word 0x1005a6af").count(), 2048);
+        assert_round_trips(&img, &fas);
+    }
+
+    #[test]
+    fn fas_from_an_elf_runs_when_assembled_at_zero() {
+        use crate::falcon::program::{load_bytes, load_words};
+        use crate::falcon::{exec, registers::Cpu, Ram};
+        let src = "main:\n  li a0, 1\n  la a1, msg\n  li a2, 2\n  li a7, 64\n  ecall\n  lui t0, %hi(nums)\n  li a7, 93\n  lw a0, %lo(nums+4)(t0)\n  ecall\n.data\nmsg: .ascii \"hi\"\n.align 4\nnums: .word 1, 42\n";
+        let prog = assemble(src, 0x1_0000).unwrap();
+        let img = Image::parse(&write_elf(&prog, 0x1_0000, 0x1_0000)).unwrap();
+        let fas = to_fas(&img);
+        assert!(fas.contains("    lui  a1, %hi(msg)\n    addi a1, a1, %lo(msg)\n"), "{fas}");
+        assert!(fas.contains("    lui  t0, %hi(nums+4)\n"), "{fas}");
+        assert!(fas.contains("    lw   a0, %lo(nums+4)(t0)\n"), "{fas}");
+        assert_round_trips(&img, &fas);
+
+        let moved = assemble(&fas, 0).unwrap();
+        let mut mem = Ram::new(0x4000);
+        load_words(&mut mem, 0, &moved.text).unwrap();
+        load_bytes(&mut mem, moved.data_base, &moved.data).unwrap();
+        let mut cpu = Cpu::default();
+        exec::run(&mut cpu, &mut mem, &mut Default::default(), 100).unwrap();
+        assert_eq!((cpu.stdout.as_slice(), cpu.exit_code), (&b"hi"[..], Some(42)));
+    }
+
+    #[test]
+    fn elf_symbols_name_targets_and_data() {
+        let prog = assemble(SRC, 0x1_0000).unwrap();
+        let img = Image::parse(&write_elf(&prog, 0x1_0000, 0x1_0000)).unwrap();
+        let fas = to_fas(&img);
+        assert!(fas.contains("    jal  ra, helper\n"), "{fas}");
+        assert!(fas.contains("nums:\n    .word 0x00000001\n    .word 0xdeadbeef\n"), "{fas}");
+        assert!(fas.contains("buf:\n    .space 64\n"), "{fas}");
+        assert_round_trips(&img, &fas);
+
+        let dump = render(&img, Style::Dump, &BTreeSet::new()).0;
+        assert!(dump.contains("\n00010000 <main>:\n"), "{dump}");
+        assert!(dump.contains(&format!("00010010:  {:08x}  bne  t0, zero, loop\n", prog.text[4])), "{dump}");
+    }
+}
@@ -1,10 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::falcon::encoder::encode;
 use crate::falcon::instruction::Instruction;
+use crate::falcon::link::{self, Object, Reloc, RelocKind, Symbol};
 
-use super::errors::AsmError;
-use super::program::Program;
+use super::errors::{AsmError, Diagnostic};
+use super::include::{IncludePaths, Sources};
+use super::lint;
+use super::locals;
+use super::macros;
+use super::program::{data_base_after, Program};
 use super::pseudo::{
     parse_la, parse_li, parse_pop, parse_print, parse_print_str, parse_print_strln, parse_push,
     parse_random, parse_random_bytes, parse_read, parse_read_byte, parse_read_half,
@@ -182,12 +187,145 @@ fn eval_expr(
     Ok(acc)
 }
 
+/// Split a symbol assignment (`.equ name, expr`, `.set name expr` or `name = expr`)
+/// into name and expression. Either may be empty for `.equ`/`.set`, which the caller
+/// reports; `name = expr` only matches with both present.
+pub(super) fn parse_equate(line: &str) -> Option<(String, String)> {
+    if let Some(rest) = line.strip_prefix(".equ").or_else(|| line.strip_prefix(".set")) {
+        let rest = rest.trim();
+        return Some(if let Some((n, e)) = rest.split_once(',') {
+            (n.trim().to_string(), e.trim().to_string())
+        } else {
+            let mut it = rest.split_whitespace();
+            let n = it.next().unwrap_or("").trim().to_string();
+            let e = it.collect::<Vec<_>>().join(" ");
+            (n, e.trim().to_string())
+        });
+    }
+    if line.starts_with('.') {
+        return None;
+    }
+    let (lhs, rhs) = line.split_once('=')?;
+    let (name, expr) = (lhs.trim(), rhs.trim());
+    (!name.is_empty() && !expr.is_empty()).then(|| (name.to_string(), expr.to_string()))
+}
+
+/// Evaluate `expr` before any address is known (`.if`, `.rept`): only numbers and
+/// equates defined earlier in the source can appear.
+pub(super) fn eval_const(expr: &str, consts: &HashMap<String, i64>) -> Result<i64, String> {
+    let invalid = |e| match e {
+        EquateEvalError::UnknownSymbol(sym) => format!("symbol not defined before use: {sym}"),
+        EquateEvalError::InvalidExpr(msg) => msg,
+    };
+    if tokenize_expr(expr).map_err(invalid)?.iter().any(|t| matches!(t, ExprTok::Dot)) {
+        return Err("location counter `.` has no value here".into());
+    }
+    eval_expr(expr, 0, &HashMap::new(), consts).map_err(invalid)
+}
+
 // ---------- API ----------
+#[allow(dead_code)] // the front ends all pass include paths
 pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
-    let line_comments = extract_visible_comments(text);
-    let raw_block_comments = extract_block_comments(text);
-    let lines = preprocess(text);
-    let data_base = base_pc + 0x1000; // data region after code
+    assemble_with(text, base_pc, &IncludePaths::default())
+}
+
+/// `assemble`, resolving `.include` against `paths`.
+pub fn assemble_with(text: &str, base_pc: u32, paths: &IncludePaths) -> Result<Program, AsmError> {
+    let sources = Sources::load(text, paths);
+    match assemble_all(&sources, base_pc, None) {
+        Ok((mut prog, _)) => {
+            sources.place_program(&mut prog);
+            Ok(prog)
+        }
+        Err(mut errors) => Err(sources.place_error(errors.swap_remove(0))),
+    }
+}
+
+/// Assemble `text`, reporting every problem found instead of only the first.
+/// The program is `None` when any diagnostic is an error. Lint warnings are only
+/// produced for programs that assemble; the list is sorted by line.
+pub fn assemble_diagnostics(text: &str, base_pc: u32) -> (Option<Program>, Vec<Diagnostic>) {
+    assemble_diagnostics_with(text, base_pc, &IncludePaths::default())
+}
+
+/// `assemble_diagnostics`, resolving `.include` against `paths`. Problems in
+/// included files carry the file's path and sort after the main source's.
+pub fn assemble_diagnostics_with(text: &str, base_pc: u32, paths: &IncludePaths) -> (Option<Program>, Vec<Diagnostic>) {
+    let sources = Sources::load(text, paths);
+    let (prog, diags) = match assemble_all(&sources, base_pc, None) {
+        Ok((mut prog, mut warnings)) => {
+            warnings.extend(lint::check(&sources, &prog, base_pc));
+            warnings.retain(|d| !d.suppressed_by(sources.line_text(d.line)));
+            sources.place_program(&mut prog);
+            (Some(prog), warnings)
+        }
+        Err(errors) => {
+            let diags = errors.into_iter().map(|e| {
+                let line = sources.line_text(e.line);
+                Diagnostic::error(e, line)
+            });
+            (None, diags.collect())
+        }
+    };
+    let mut diags: Vec<Diagnostic> = diags.into_iter().map(|d| sources.place_diagnostic(d)).collect();
+    diags.sort_by_key(|d| (d.file.clone(), d.line, d.cols.start, d.severity));
+    diags.dedup();
+    (prog, diags)
+}
+
+/// Assemble one module of a multi-file program into a relocatable object for
+/// `link::link`. Text and data start at offset 0; references to labels the module
+/// does not define, and every address that depends on where the linker puts a
+/// section, become relocations. Only `.globl` labels are visible to other objects.
+pub fn assemble_object(text: &str, paths: &IncludePaths) -> Result<Object, Vec<AsmError>> {
+    let sources = Sources::load(text, paths);
+    let mut parts = ObjectParts::default();
+    match assemble_all(&sources, 0, Some(&mut parts)) {
+        Ok((prog, _)) => Ok(Object {
+            text: prog.text,
+            data: prog.data,
+            bss_size: prog.bss_size,
+            symbols: parts.symbols,
+            relocs: parts.relocs,
+        }),
+        Err(errors) => Err(errors.into_iter().map(|e| sources.place_error(e)).collect()),
+    }
+}
+
+/// What object mode collects on top of the program.
+#[derive(Default)]
+struct ObjectParts {
+    symbols: Vec<Symbol>,
+    relocs: Vec<Reloc>,
+}
+
+/// Both passes, collecting errors per line; errors come back in the order found.
+/// On success, also returns warnings only the passes themselves can see. Lines are
+/// global across `sources` until the caller places them back into files. With
+/// `object`, assembles for `assemble_object` instead of for running in place.
+fn assemble_all(
+    sources: &Sources,
+    base_pc: u32,
+    mut object: Option<&mut ObjectParts>,
+) -> Result<(Program, Vec<Diagnostic>), Vec<AsmError>> {
+    let line_comments = sources.scan(extract_visible_comments);
+    let raw_block_comments = sources.scan(extract_block_comments);
+    let describe = |line: usize| sources.describe(line);
+    let expanded = macros::expand(sources.lines.clone(), &describe);
+    let (mut lines, origins) = (expanded.lines, expanded.origins);
+    // An `.include` that failed only matters if conditional assembly kept it.
+    let kept: HashSet<usize> = lines
+        .iter()
+        .zip(&origins)
+        .filter(|((_, text), _)| text.starts_with(".include"))
+        .map(|((line, _), origin)| origin.last().map_or(*line, |f| f.body_line))
+        .collect();
+    let mut errors: Vec<AsmError> = sources.errors.iter().filter(|e| kept.contains(&e.line)).cloned().collect();
+    errors.extend(expanded.errors);
+    for (stmt, e) in locals::rename(&mut lines) {
+        errors.push(macros::in_macro(e, &origins[stmt], &describe));
+    }
+    let mut warnings: Vec<Diagnostic> = Vec::new();
 
     // 1st pass: symbol table
     #[derive(Clone, Copy)]
@@ -209,26 +347,28 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
     let mut pc_bss = 0u32;  // offset/size within .bss
     let mut items: Vec<(u32, LineKind, usize)> = Vec::new(); // (pc, LineKind, line number)
     let mut data_bytes = Vec::<u8>::new();
+    let mut data_line_offsets: HashMap<usize, u32> = HashMap::new(); // line → first data byte it emitted
     // Collect label defs by section and offset; resolve absolute addresses after first pass
     let mut label_defs = HashMap::<String, (Section, u32)>::new();
     let mut label_source_lines = HashMap::<String, usize>::new(); // label → source line (0-based)
     let mut equates = Vec::<EquateDef>::new();
     // fixups for `.word label` — resolved after labels map is built
     let mut word_label_fixups: Vec<(usize, String, usize)> = Vec::new(); // (byte_offset, label_name, line_no)
+    let mut globals = HashSet::<String>::new();
 
-    // Iterate over lines and collect labels and instructions
-    for (line_no, raw) in &lines {
+    // Each line is handled on its own so one bad line doesn't hide the rest.
+    let mut first_pass = |line_no: &usize, raw: &String, items: &mut Vec<(u32, LineKind, usize)>| -> Result<(), AsmError> {
         if raw == ".text" {
             section = Section::Text;
-            continue;
+            return Ok(());
         }
         if raw == ".data" {
             section = Section::Data;
-            continue;
+            return Ok(());
         }
         if raw == ".bss" {
             section = Section::Bss;
-            continue;
+            return Ok(());
         }
         if let Some(rest) = raw.strip_prefix(".section") {
             let rest = rest.trim();
@@ -241,21 +381,21 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 ".data" | "data" | ".rodata" | "rodata" | ".sdata" | "sdata" | ".srodata"
                 | "srodata" => section = Section::Data,
                 ".bss" | "bss" | ".sbss" | "sbss" => section = Section::Bss,
-                ".note.GNU-stack" => continue, // no-op for this simulator
+                ".note.GNU-stack" => return Ok(()), // no-op for this simulator
+                // Compilers split sections per function/object (`.text.startup`, `.rodata.str1.4`).
+                _ if name.starts_with(".text.") => section = Section::Text,
+                _ if [".data.", ".rodata.", ".sdata.", ".srodata."].iter().any(|p| name.starts_with(p)) => {
+                    section = Section::Data
+                }
+                _ if name.starts_with(".bss.") || name.starts_with(".sbss.") => section = Section::Bss,
                 "" => {
-                    return Err(AsmError {
-                        line: *line_no,
-                        msg: "missing section name".into(),
-                    })
+                    return Err(AsmError::new(*line_no, "missing section name"))
                 }
                 _ => {
-                    return Err(AsmError {
-                        line: *line_no,
-                        msg: format!("unknown section: {name}"),
-                    })
+                    return Err(AsmError::new(*line_no, format!("unknown section: {name}")))
                 }
             }
-            continue;
+            return Ok(());
         }
 
         let mut line = raw.as_str();
@@ -272,16 +412,16 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
             line = rest[1..].trim();
             if line.is_empty() {
                 // instruction label only
-                continue;
+                return Ok(());
             }
         }
 
         let ltrim = line.trim_start();
         if ltrim.is_empty() {
-            continue;
+            return Ok(());
         }
 
-        // Common GAS directives that are irrelevant for this simulator (accepted as no-ops).
+        // Only objects export symbols; a program assembled in place ignores `.globl`.
         if ltrim.starts_with(".globl") || ltrim.starts_with(".global") {
             let rest = ltrim
                 .strip_prefix(".globl")
@@ -289,14 +429,20 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 .unwrap_or("")
                 .trim();
             if rest.is_empty() {
-                return Err(AsmError {
-                    line: *line_no,
-                    msg: "missing symbol name in .globl/.global".into(),
-                });
+                return Err(AsmError::new(*line_no, "missing symbol name in .globl/.global"));
             }
-            continue;
+            for name in rest.split(',').map(str::trim) {
+                if name.is_empty() {
+                    return Err(AsmError::new(*line_no, "missing symbol name in .globl/.global"));
+                }
+                globals.insert(name.to_string());
+            }
+            return Ok(());
         }
-        if ltrim.starts_with(".type")
+
+        // Common GAS directives that are irrelevant for this simulator (accepted as no-ops).
+        if ltrim.starts_with(".include")
+            || ltrim.starts_with(".type")
             || ltrim.starts_with(".size")
             || ltrim.starts_with(".file")
             || ltrim.starts_with(".ident")
@@ -304,7 +450,7 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
             || ltrim.starts_with(".attribute")
             || ltrim.starts_with(".cfi_")
         {
-            continue;
+            return Ok(());
         }
 
         // Equates / symbol assignments (e.g. `len = . - msg` or `.equ len, . - msg`)
@@ -313,24 +459,9 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
             Section::Data => (Section::Data, pc_data),
             Section::Bss => (Section::Bss, pc_bss),
         };
-        if let Some(rest) = ltrim
-            .strip_prefix(".equ")
-            .or_else(|| ltrim.strip_prefix(".set"))
-        {
-            let rest = rest.trim();
-            let (name, expr) = if let Some((n, e)) = rest.split_once(',') {
-                (n.trim().to_string(), e.trim().to_string())
-            } else {
-                let mut it = rest.split_whitespace();
-                let n = it.next().unwrap_or("").trim().to_string();
-                let e = it.collect::<Vec<_>>().join(" ");
-                (n, e.trim().to_string())
-            };
+        if let Some((name, expr)) = parse_equate(ltrim) {
             if name.is_empty() || expr.is_empty() {
-                return Err(AsmError {
-                    line: *line_no,
-                    msg: "expected '.equ name, expr'".into(),
-                });
+                return Err(AsmError::new(*line_no, "expected '.equ name, expr'"));
             }
             equates.push(EquateDef {
                 name,
@@ -339,28 +470,29 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 off,
                 line_no: *line_no,
             });
-            continue;
-        }
-        if !ltrim.starts_with('.') {
-            if let Some((lhs, rhs)) = ltrim.split_once('=') {
-                let name = lhs.trim();
-                let expr = rhs.trim();
-                if !name.is_empty() && !expr.is_empty() {
-                    equates.push(EquateDef {
-                        name: name.to_string(),
-                        expr: expr.to_string(),
-                        sec,
-                        off,
-                        line_no: *line_no,
-                    });
-                    continue;
-                }
-            }
+            return Ok(());
         }
 
         match section {
             Section::Text => {
-                if ltrim.starts_with("li ") {
+                if let Some(rest) = ltrim.strip_prefix(".p2align").or_else(|| ltrim.strip_prefix(".align")) {
+                    // As in GAS for RISC-V, the operand is a power of two; pad with `nop`s.
+                    let n = parse_imm(rest.split(',').next().unwrap_or(""))
+                        .filter(|n| (0..=12).contains(n))
+                        .ok_or_else(|| AsmError::new(*line_no, format!("invalid .align: {rest}")))?;
+                    while !pc_text.is_multiple_of(1 << n) {
+                        items.push((pc_text, LineKind::Instr("nop".into()), *line_no));
+                        pc_text = pc_text.wrapping_add(4);
+                    }
+                } else if let Some(rest) = ltrim.strip_prefix(".word") {
+                    // Raw words in the code: constants, or encodings the assembler has no syntax for.
+                    for w in rest.split(',') {
+                        let v = parse_word(w)
+                            .ok_or_else(|| AsmError::new(*line_no, format!("invalid .word in .text (numbers only): {}", w.trim())))?;
+                        items.push((pc_text, LineKind::Word(v), *line_no));
+                        pc_text = pc_text.wrapping_add(4);
+                    }
+                } else if ltrim.starts_with("li ") {
                     let words = li_word_count(ltrim);
                     items.push((pc_text, LineKind::Li(ltrim.to_string()), *line_no));
                     pc_text = pc_text.wrapping_add(words * 4);
@@ -417,16 +549,11 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 }
             }
             Section::Data => {
+                let start = pc_data;
                 if let Some(rest) = line.strip_prefix(".align") {
-                    let n = parse_imm(rest).ok_or_else(|| AsmError {
-                        line: *line_no,
-                        msg: format!("invalid .align: {rest}"),
-                    })?;
+                    let n = parse_imm(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid .align: {rest}")))?;
                     if n <= 0 {
-                        return Err(AsmError {
-                            line: *line_no,
-                            msg: format!("alignment must be positive: {n}"),
-                        });
+                        return Err(AsmError::new(*line_no, format!("alignment must be positive: {n}")));
                     }
                     let n = n as u32;
                     let mask = n - 1;
@@ -442,56 +569,50 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                     }
                 } else if let Some(rest) = line.strip_prefix(".byte") {
                     for b in rest.split(',') {
-                        let v = parse_imm(b).ok_or_else(|| AsmError {
-                            line: *line_no,
-                            msg: format!("invalid .byte: {b}"),
-                        })?;
+                        let v = parse_imm(b).ok_or_else(|| AsmError::new(*line_no, format!("invalid .byte: {b}")))?;
                         if !(0..=255).contains(&v) {
-                            return Err(AsmError {
-                                line: *line_no,
-                                msg: format!(".byte outside 0..255: {v}"),
-                            });
+                            return Err(AsmError::new(*line_no, format!(".byte outside 0..255: {v}")));
                         }
                         data_bytes.push(v as u8);
                         pc_data += 1;
                     }
                 } else if let Some(rest) = line.strip_prefix(".half") {
                     for h in rest.split(',') {
-                        let v = parse_imm(h).ok_or_else(|| AsmError {
-                            line: *line_no,
-                            msg: format!("invalid .half: {h}"),
-                        })?;
+                        let v = parse_imm(h).ok_or_else(|| AsmError::new(*line_no, format!("invalid .half: {h}")))?;
                         if !(0..=65535).contains(&v) {
-                            return Err(AsmError {
-                                line: *line_no,
-                                msg: format!(".half outside 0..65535: {v}"),
-                            });
+                            return Err(AsmError::new(*line_no, format!(".half outside 0..65535: {v}")));
                         }
                         let bytes = (v as u16).to_le_bytes();
                         data_bytes.extend_from_slice(&bytes);
                         pc_data += 2;
                     }
                 } else if let Some(rest) = line.strip_prefix(".word") {
+                    if !pc_data.is_multiple_of(4) {
+                        warnings.push(Diagnostic::warning(
+                            *line_no,
+                            sources.line_text(*line_no),
+                            "unaligned-word",
+                            format!("`.word` at data offset {pc_data:#x} is not 4-byte aligned; add `.align 2` before it"),
+                            Some(".word"),
+                        ));
+                    }
                     for w in rest.split(',') {
                         let w = w.trim();
-                        if let Some(v) = parse_imm(w) {
-                            let bytes = (v as u32).to_le_bytes();
+                        if let Some(v) = parse_word(w) {
+                            let bytes = v.to_le_bytes();
                             data_bytes.extend_from_slice(&bytes);
-                        } else if w.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '.') && w.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                        } else if w.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '.') && w.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$') {
                             // label reference — emit placeholder, resolve after labels are built
                             word_label_fixups.push((data_bytes.len(), w.to_string(), *line_no));
                             data_bytes.extend_from_slice(&[0u8; 4]);
                         } else {
-                            return Err(AsmError { line: *line_no, msg: format!("invalid .word: {w}") });
+                            return Err(AsmError::new(*line_no, format!("invalid .word: {w}")));
                         }
                         pc_data += 4;
                     }
                 } else if let Some(rest) = line.strip_prefix(".dword") {
                     for d in rest.split(',') {
-                        let v = parse_imm64(d).ok_or_else(|| AsmError {
-                            line: *line_no,
-                            msg: format!("invalid .dword: {d}"),
-                        })?;
+                        let v = parse_imm64(d).ok_or_else(|| AsmError::new(*line_no, format!("invalid .dword: {d}")))?;
                         let bytes = (v as i64 as u64).to_le_bytes();
                         data_bytes.extend_from_slice(&bytes);
                         pc_data += 8;
@@ -499,29 +620,20 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 } else if let Some(rest) = line.strip_prefix(".float") {
                     for f in rest.split(',') {
                         let f = f.trim();
-                        let v: f32 = f.parse().map_err(|_| AsmError {
-                            line: *line_no,
-                            msg: format!("invalid .float: {f}"),
-                        })?;
+                        let v: f32 = f.parse().map_err(|_| AsmError::new(*line_no, format!("invalid .float: {f}")))?;
                         let bytes = v.to_le_bytes();
                         data_bytes.extend_from_slice(&bytes);
                         pc_data += 4;
                     }
                 } else if let Some(rest) = line.strip_prefix(".ascii") {
-                    let s = parse_str_lit(rest).ok_or_else(|| AsmError {
-                        line: *line_no,
-                        msg: format!("invalid .ascii: {rest}"),
-                    })?;
+                    let s = parse_str_lit(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid .ascii: {rest}")))?;
                     data_bytes.extend_from_slice(s.as_bytes());
                     pc_data += s.len() as u32;
                 } else if let Some(rest) = line
                     .strip_prefix(".asciz")
                     .or_else(|| line.strip_prefix(".string"))
                 {
-                    let s = parse_str_lit(rest).ok_or_else(|| AsmError {
-                        line: *line_no,
-                        msg: format!("invalid string: {rest}"),
-                    })?;
+                    let s = parse_str_lit(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid string: {rest}")))?;
                     data_bytes.extend_from_slice(s.as_bytes());
                     data_bytes.push(0);
                     pc_data += (s.len() + 1) as u32;
@@ -529,24 +641,18 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                     .strip_prefix(".space")
                     .or_else(|| line.strip_prefix(".zero"))
                 {
-                    let n = parse_imm(rest).ok_or_else(|| AsmError {
-                        line: *line_no,
-                        msg: format!("invalid size: {rest}"),
-                    })?;
+                    let n = parse_imm(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid size: {rest}")))?;
                     if n < 0 {
-                        return Err(AsmError {
-                            line: *line_no,
-                            msg: format!("size must be positive: {n}"),
-                        });
+                        return Err(AsmError::new(*line_no, format!("size must be positive: {n}")));
                     }
                     let n = n as usize;
                     data_bytes.extend(std::iter::repeat(0).take(n));
                     pc_data += n as u32;
                 } else {
-                    return Err(AsmError {
-                        line: *line_no,
-                        msg: format!("unknown data directive: {line}"),
-                    });
+                    return Err(AsmError::new(*line_no, format!("unknown data directive: {line}")));
+                }
+                if pc_data != start {
+                    data_line_offsets.entry(*line_no).or_insert(start);
                 }
             }
             Section::Bss => {
@@ -556,17 +662,14 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                     .or_else(|| line.strip_prefix(".zero"))
                     .or_else(|| line.strip_prefix(".skip"))
                 {
-                    let n = parse_imm(rest).ok_or_else(|| AsmError {
-                        line: *line_no,
-                        msg: format!("invalid size: {rest}"),
-                    })?;
+                    let n = parse_imm(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid size: {rest}")))?;
                     if n < 0 {
-                        return Err(AsmError { line: *line_no, msg: format!("size must be positive: {n}") });
+                        return Err(AsmError::new(*line_no, format!("size must be positive: {n}")));
                     }
                     pc_bss = pc_bss.wrapping_add(n as u32);
                 } else if let Some(rest) = line.strip_prefix(".align") {
-                    let n = parse_imm(rest).ok_or_else(|| AsmError { line: *line_no, msg: format!("invalid align: {rest}") })?;
-                    if n <= 0 { return Err(AsmError { line: *line_no, msg: format!("alignment must be positive: {n}") }); }
+                    let n = parse_imm(rest).ok_or_else(|| AsmError::new(*line_no, format!("invalid align: {rest}")))?;
+                    if n <= 0 { return Err(AsmError::new(*line_no, format!("alignment must be positive: {n}"))); }
                     let n = n as u32;
                     let mask = n - 1;
                     let aligned = if (pc_bss & mask) == 0 { pc_bss } else { (pc_bss + mask) & !mask };
@@ -580,32 +683,80 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                     || line.starts_with(".asciz")
                     || line.starts_with(".string")
                 {
-                    return Err(AsmError { line: *line_no, msg: ".bss does not store explicit data; use .space/.zero/.skip/.align".into() });
+                    return Err(AsmError::new(*line_no, ".bss does not store explicit data; use .space/.zero/.skip/.align"));
                 } else {
-                    return Err(AsmError { line: *line_no, msg: format!("unknown .bss directive: {line}") });
+                    return Err(AsmError::new(*line_no, format!("unknown .bss directive: {line}")));
                 }
             }
         }
+        Ok(())
+    };
+    // Statement index behind each item, to name the macro an error came from.
+    let mut item_stmts: Vec<usize> = Vec::new();
+    for (stmt, (line_no, raw)) in lines.iter().enumerate() {
+        if let Err(e) = first_pass(line_no, raw, &mut items) {
+            errors.push(macros::in_macro(e, &origins[stmt], &describe));
+        }
+        item_stmts.resize(items.len(), stmt);
     }
 
+    // Data goes on the first page after the text, however long the text turned out.
+    let data_size = pc_data;
+    let data_base = match data_base_after(base_pc, pc_text.wrapping_sub(base_pc)) {
+        Some(base) if base as u64 + data_size as u64 + pc_bss as u64 <= 1 << 32 => base,
+        _ if data_size == 0 && pc_bss == 0 => base_pc,
+        _ => {
+            // Blame the first data or bss label, the nearest thing to a culprit.
+            let line = label_defs
+                .iter()
+                .filter(|(_, (sec, _))| !matches!(sec, Section::Text))
+                .filter_map(|(name, _)| label_source_lines.get(name).copied())
+                .min()
+                .unwrap_or(0);
+            errors.push(AsmError::new(
+                line,
+                format!(
+                    ".data and .bss ({} bytes) do not fit between the end of .text at {pc_text:#x} and the end of the address space",
+                    data_size as u64 + pc_bss as u64
+                ),
+            ));
+            base_pc
+        }
+    };
+
     // Build final labels map with absolute addresses
     let mut labels = HashMap::<String, u32>::new();
-    let data_size = pc_data;
-    for (name, (sec, off)) in label_defs.into_iter() {
+    for (name, &(sec, off)) in &label_defs {
         let addr = match sec {
             Section::Text => off, // pc_text was absolute when captured
-            Section::Data => data_base + off,
-            Section::Bss => data_base + data_size + off,
+            Section::Data => data_base.wrapping_add(off),
+            Section::Bss => data_base.wrapping_add(data_size).wrapping_add(off),
         };
-        labels.insert(name, addr);
+        labels.insert(name.clone(), addr);
     }
 
+    // Symbols an object uses without defining; the linker supplies their addresses.
+    let mut externs = HashSet::<String>::new();
+
     // Resolve .word label fixups now that all label addresses are known
     for (offset, name, line_no) in &word_label_fixups {
-        let addr = labels.get(name).ok_or_else(|| AsmError {
-            line: *line_no,
-            msg: format!("undefined label in .word: {name}"),
-        })?;
+        if let Some(obj) = object.as_deref_mut() {
+            obj.relocs.push(Reloc {
+                section: link::Section::Data,
+                offset: *offset as u32,
+                kind: RelocKind::Abs32,
+                symbol: name.clone(),
+                addend: 0,
+            });
+            if !labels.contains_key(name) {
+                externs.insert(name.clone());
+                continue;
+            }
+        }
+        let Some(addr) = labels.get(name) else {
+            errors.push(AsmError::new(*line_no, format!("undefined label in .word: {name}")));
+            continue;
+        };
         let bytes = addr.to_le_bytes();
         data_bytes[*offset..*offset + 4].copy_from_slice(&bytes);
     }
@@ -614,8 +765,8 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
     let abs_of = |sec: Section, off: u32| -> i64 {
         match sec {
             Section::Text => off as i64,
-            Section::Data => (data_base + off) as i64,
-            Section::Bss => (data_base + data_size + off) as i64,
+            Section::Data => data_base.wrapping_add(off) as i64,
+            Section::Bss => data_base.wrapping_add(data_size).wrapping_add(off) as i64,
         }
     };
     let mut consts = HashMap::<String, i64>::new();
@@ -630,16 +781,12 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
             let mut progress = false;
             for def in pending.into_iter() {
                 if labels.contains_key(&def.name) {
-                    return Err(AsmError {
-                        line: def.line_no,
-                        msg: format!("equate redefines existing label: {}", def.name),
-                    });
+                    errors.push(AsmError::new(def.line_no, format!("equate redefines existing label: {}", def.name)));
+                    continue;
                 }
                 if consts.contains_key(&def.name) {
-                    return Err(AsmError {
-                        line: def.line_no,
-                        msg: format!("duplicate equate: {}", def.name),
-                    });
+                    errors.push(AsmError::new(def.line_no, format!("duplicate equate: {}", def.name)));
+                    continue;
                 }
 
                 let dot = abs_of(def.sec, def.off);
@@ -650,7 +797,7 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                     }
                     Err(EquateEvalError::UnknownSymbol(_)) => next.push(def),
                     Err(EquateEvalError::InvalidExpr(e)) => {
-                        return Err(AsmError { line: def.line_no, msg: e })
+                        errors.push(AsmError::new(def.line_no, e))
                     }
                 }
             }
@@ -659,17 +806,19 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 break;
             }
             if !progress {
-                // Produce a deterministic error for the first unresolved equate.
-                let def = &next[0];
-                let dot = abs_of(def.sec, def.off);
-                let msg = match eval_expr(&def.expr, dot, &labels, &consts) {
-                    Err(EquateEvalError::UnknownSymbol(sym)) => {
-                        format!("unknown symbol in equate '{}': {}", def.name, sym)
-                    }
-                    Err(EquateEvalError::InvalidExpr(e)) => e,
-                    Ok(_) => "failed to resolve equate".into(),
-                };
-                return Err(AsmError { line: def.line_no, msg });
+                // Report every equate that can't be resolved, in source order.
+                for def in &next {
+                    let dot = abs_of(def.sec, def.off);
+                    let msg = match eval_expr(&def.expr, dot, &labels, &consts) {
+                        Err(EquateEvalError::UnknownSymbol(sym)) => {
+                            format!("unknown symbol in equate '{}': {}", def.name, sym)
+                        }
+                        Err(EquateEvalError::InvalidExpr(e)) => e,
+                        Ok(_) => "failed to resolve equate".into(),
+                    };
+                    errors.push(AsmError::new(def.line_no, msg));
+                }
+                break;
             }
             pending = next;
         }
@@ -682,109 +831,102 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
     }
     for v in addr_to_labels.values_mut() { v.sort(); }
 
+    let label_sections: HashMap<(u32, String), link::Section> = label_defs
+        .iter()
+        .filter_map(|(name, (sec, _))| {
+            let sec = match sec {
+                Section::Text => link::Section::Text,
+                Section::Data => link::Section::Data,
+                Section::Bss => link::Section::Bss,
+            };
+            Some(((*labels.get(name)?, name.clone()), sec))
+        })
+        .collect();
+
+    let exported: HashMap<String, u32> = globals
+        .iter()
+        .filter(|g| label_defs.contains_key(*g))
+        .filter_map(|g| labels.get(g).map(|&addr| (g.clone(), addr)))
+        .collect();
+
     let label_to_line: HashMap<String, usize> = labels.iter()
         .filter_map(|(name, _addr)| {
             label_source_lines.get(name).map(|&ln| (name.clone(), ln))
         })
         .collect();
 
+    if object.is_some() {
+        for (_, kind, _) in &items {
+            for sym in symbol_refs(kind) {
+                if !labels.contains_key(&sym) && !consts.contains_key(&sym) {
+                    externs.insert(sym);
+                }
+            }
+        }
+        externs.extend(globals.iter().filter(|g| !labels.contains_key(*g) && !consts.contains_key(*g)).cloned());
+        // Placeholder address; every use of it gets a relocation.
+        for name in &externs {
+            labels.insert(name.clone(), 0);
+        }
+    }
+    let in_text = |name: &str| matches!(label_defs.get(name), Some((Section::Text, _)));
+
+    // `auipc` addresses carrying `%pcrel_hi(expr)`, for the `%pcrel_lo(label)` that pairs with them
+    let pcrel_his: HashMap<u32, String> = items
+        .iter()
+        .filter_map(|(pc, kind, _)| match kind {
+            LineKind::Instr(s) => pcrel_hi_arg(s).map(|expr| (*pc, expr)),
+            _ => None,
+        })
+        .collect();
+
     // 2nd pass: assemble
     let mut words = Vec::with_capacity(items.len());
     let mut comments: HashMap<u32, String> = HashMap::new();
     let mut block_comments: HashMap<u32, String> = HashMap::new();
     let mut line_addrs: HashMap<usize, u32> = HashMap::new();
     let mut prev_item_line: usize = 0;
-    for (pc, kind, line_no) in items {
-        line_addrs.entry(line_no).or_insert(pc);
-        if let Some(c) = line_comments.get(&line_no) {
-            comments.insert(pc, c.clone());
-        }
-        // Find the nearest ##! block comment for lines in range (prev_item_line+1)..=line_no
-        let search_start = prev_item_line + 1;
-        for search_line in search_start..=line_no {
-            if let Some(bc) = raw_block_comments.get(&search_line) {
-                block_comments.insert(pc, bc.clone());
-                break;
-            }
-        }
-        prev_item_line = line_no;
+    let second_pass = |pc: u32, kind: LineKind, line_no: usize, words: &mut Vec<u32>| -> Result<(), AsmError> {
         match kind {
+            LineKind::Word(w) => words.push(w),
             LineKind::Instr(s) => {
-                let inst = parse_instr(&s, pc, &labels, &consts).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
-                let word = encode(inst).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
+                let s = resolve_relocs(&s, pc, &labels, &consts, &pcrel_his).map_err(|e| AsmError::new(line_no, e))?;
+                let inst = parse_instr(&s, pc, &labels, &consts).map_err(|e| AsmError::new(line_no, e))?;
+                let word = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                 words.push(word);
             }
             LineKind::Li(s) => {
-                let insts = parse_li(&s, &consts).map_err(|e| AsmError { line: line_no, msg: e })?;
+                let insts = parse_li(&s, &consts).map_err(|e| AsmError::new(line_no, e))?;
                 for inst in insts {
-                    let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?;
+                    let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                     words.push(w);
                 }
             }
             LineKind::La(s) => {
-                let (i1, i2) = parse_la(&s, &labels).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
-                let w1 = encode(i1).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
-                let w2 = encode(i2).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
+                let (i1, i2) = parse_la(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
+                let w1 = encode(i1).map_err(|e| AsmError::new(line_no, e.to_string()))?;
+                let w2 = encode(i2).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                 words.push(w1);
                 words.push(w2);
             }
             LineKind::Push(s) => {
-                let (i1, i2) = parse_push(&s).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
-                let w1 = encode(i1).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
-                let w2 = encode(i2).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
+                let (i1, i2) = parse_push(&s).map_err(|e| AsmError::new(line_no, e))?;
+                let w1 = encode(i1).map_err(|e| AsmError::new(line_no, e.to_string()))?;
+                let w2 = encode(i2).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                 words.push(w1);
                 words.push(w2);
             }
             LineKind::Pop(s) => {
-                let (i1, i2) = parse_pop(&s).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
-                let w1 = encode(i1).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
-                let w2 = encode(i2).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e.to_string(),
-                })?;
+                let (i1, i2) = parse_pop(&s).map_err(|e| AsmError::new(line_no, e))?;
+                let w1 = encode(i1).map_err(|e| AsmError::new(line_no, e.to_string()))?;
+                let w2 = encode(i2).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                 words.push(w1);
                 words.push(w2);
             }
             LineKind::Print(s) => {
-                let insts = parse_print(&s).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
+                let insts = parse_print(&s).map_err(|e| AsmError::new(line_no, e))?;
                 for inst in insts {
-                    let w = encode(inst).map_err(|e| AsmError {
-                        line: line_no,
-                        msg: e.to_string(),
-                    })?;
+                    let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                     words.push(w);
                 }
             }
@@ -795,15 +937,9 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 } else if s.starts_with("printStr") {
                     s.replacen("printStr", "print_str", 1)
                 } else { s.clone() };
-                let insts = parse_print_str(&s_norm, &labels).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
+                let insts = parse_print_str(&s_norm, &labels).map_err(|e| AsmError::new(line_no, e))?;
                 for inst in insts {
-                    let w = encode(inst).map_err(|e| AsmError {
-                        line: line_no,
-                        msg: e.to_string(),
-                    })?;
+                    let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                     words.push(w);
                 }
             }
@@ -811,55 +947,90 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
                 let s_norm = if s.starts_with("printStrLn") {
                     s.replacen("printStrLn", "print_str_ln", 1)
                 } else { s.clone() };
-                let insts = parse_print_strln(&s_norm, &labels).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
+                let insts = parse_print_strln(&s_norm, &labels).map_err(|e| AsmError::new(line_no, e))?;
                 for inst in insts {
-                    let w = encode(inst).map_err(|e| AsmError {
-                        line: line_no,
-                        msg: e.to_string(),
-                    })?;
+                    let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                     words.push(w);
                 }
             }
             LineKind::Read(s) => {
-                let insts = parse_read(&s, &labels).map_err(|e| AsmError {
-                    line: line_no,
-                    msg: e,
-                })?;
+                let insts = parse_read(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
                 for inst in insts {
-                    let w = encode(inst).map_err(|e| AsmError {
-                        line: line_no,
-                        msg: e.to_string(),
-                    })?;
+                    let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?;
                     words.push(w);
                 }
             }
             LineKind::ReadByte(s) => {
-                let insts = parse_read_byte(&s, &labels).map_err(|e| AsmError { line: line_no, msg: e })?;
-                for inst in insts { let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?; words.push(w); }
+                let insts = parse_read_byte(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
+                for inst in insts { let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?; words.push(w); }
             }
             LineKind::ReadHalf(s) => {
-                let insts = parse_read_half(&s, &labels).map_err(|e| AsmError { line: line_no, msg: e })?;
-                for inst in insts { let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?; words.push(w); }
+                let insts = parse_read_half(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
+                for inst in insts { let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?; words.push(w); }
             }
             LineKind::ReadWord(s) => {
-                let insts = parse_read_word(&s, &labels).map_err(|e| AsmError { line: line_no, msg: e })?;
-                for inst in insts { let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?; words.push(w); }
+                let insts = parse_read_word(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
+                for inst in insts { let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?; words.push(w); }
             }
             LineKind::RandomByte(s) => {
-                let insts = parse_random(&s).map_err(|e| AsmError { line: line_no, msg: e })?;
-                for inst in insts { let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?; words.push(w); }
+                let insts = parse_random(&s).map_err(|e| AsmError::new(line_no, e))?;
+                for inst in insts { let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?; words.push(w); }
             }
             LineKind::RandomBytes(s) => {
-                let insts = parse_random_bytes(&s, &labels).map_err(|e| AsmError { line: line_no, msg: e })?;
-                for inst in insts { let w = encode(inst).map_err(|e| AsmError { line: line_no, msg: e.to_string() })?; words.push(w); }
+                let insts = parse_random_bytes(&s, &labels).map_err(|e| AsmError::new(line_no, e))?;
+                for inst in insts { let w = encode(inst).map_err(|e| AsmError::new(line_no, e.to_string()))?; words.push(w); }
             }
         }
+        Ok(())
+    };
+    for ((pc, kind, line_no), stmt) in items.into_iter().zip(item_stmts) {
+        line_addrs.entry(line_no).or_insert(pc);
+        if let Some(c) = line_comments.get(&line_no) {
+            comments.insert(pc, c.clone());
+        }
+        // Find the nearest ##! block comment for lines in range (prev_item_line+1)..=line_no
+        let search_start = prev_item_line + 1;
+        for search_line in search_start..=line_no {
+            if let Some(bc) = raw_block_comments.get(&search_line) {
+                block_comments.insert(pc, bc.clone());
+                break;
+            }
+        }
+        prev_item_line = line_no;
+        let first = words.len();
+        let result = match object.as_deref_mut() {
+            None => second_pass(pc, kind, line_no, &mut words),
+            Some(obj) => second_pass(pc, detach_jump(&kind, &in_text), line_no, &mut words).and_then(|()| {
+                let relocs = object_relocs(&kind, pc, &words[first..], &labels, &consts, &in_text);
+                obj.relocs.extend(relocs.map_err(|e| AsmError::new(line_no, e))?);
+                Ok(())
+            }),
+        };
+        if let Err(e) = result {
+            errors.push(macros::in_macro(e, &origins[stmt], &describe));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    if let Some(obj) = object {
+        for (name, &(sec, off)) in &label_defs {
+            let (section, value) = match sec {
+                Section::Text => (link::Section::Text, off - base_pc),
+                Section::Data => (link::Section::Data, off),
+                Section::Bss => (link::Section::Bss, off),
+            };
+            obj.symbols.push(Symbol { name: name.clone(), section: Some(section), value, global: globals.contains(name) });
+        }
+        for name in &externs {
+            obj.symbols.push(Symbol { name: name.clone(), section: None, value: 0, global: true });
+        }
+        obj.symbols.sort_by(|a, b| a.name.cmp(&b.name));
     }
 
-    Ok(Program {
+    let prog = Program {
         text: words,
         data: data_bytes,
         data_base,
@@ -867,15 +1038,24 @@ pub fn assemble(text: &str, base_pc: u32) -> Result<Program, AsmError> {
         comments,
         block_comments,
         labels: addr_to_labels,
+        label_sections,
+        globals: exported,
         line_addrs,
+        data_line_offsets,
         label_to_line,
-    })
+        includes: Vec::new(),
+        include_line_addrs: HashMap::new(),
+        include_data_line_offsets: HashMap::new(),
+        include_label_lines: HashMap::new(),
+    };
+    Ok((prog, warnings))
 }
 
 // ---------- Internals ----------
 #[derive(Debug, Clone)]
 enum LineKind {
     Instr(String),
+    Word(u32),
     Li(String),
     La(String),
     Push(String),
@@ -891,6 +1071,228 @@ enum LineKind {
     RandomBytes(String),
 }
 
+/// Expression inside an instruction's `%pcrel_hi(...)`, if it has one.
+fn pcrel_hi_arg(s: &str) -> Option<String> {
+    let (_, rest) = s.split_once("%pcrel_hi(")?;
+    Some(rest.split_once(')')?.0.trim().to_string())
+}
+
+/// Upper 20 bits of `v`, rounded so that adding the sign-extended `lo12(v)` gives `v` back.
+fn hi20(v: i64) -> i64 {
+    ((v + 0x800) >> 12) & 0xFFFFF
+}
+
+/// Low 12 bits of `v`, sign-extended.
+fn lo12(v: i64) -> i64 {
+    ((v & 0xFFF) ^ 0x800) - 0x800
+}
+
+/// Replace the relocation operators `%hi(expr)`, `%lo(expr)`, `%pcrel_hi(expr)` and
+/// `%pcrel_lo(label)` in an instruction with the numbers they stand for, so
+/// `lui a5, %hi(msg)` and `lw a0, %lo(msg)(a5)` parse like plain immediates.
+/// `%pcrel_lo` takes the label of the `auipc` whose `%pcrel_hi` it completes.
+fn resolve_relocs(
+    s: &str,
+    pc: u32,
+    labels: &HashMap<String, u32>,
+    consts: &HashMap<String, i64>,
+    pcrel_his: &HashMap<u32, String>,
+) -> Result<String, String> {
+    let value = |expr: &str, dot: u32| {
+        eval_expr(expr, dot as i64, labels, consts).map_err(|e| match e {
+            EquateEvalError::UnknownSymbol(sym) => format!("label not found: {sym}"),
+            EquateEvalError::InvalidExpr(msg) => msg,
+        })
+    };
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('%') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (op, arg, tail) = after
+            .split_once('(')
+            .and_then(|(op, args)| args.split_once(')').map(|(arg, tail)| (op.trim(), arg.trim(), tail)))
+            .ok_or_else(|| format!("expected '%op(symbol)': %{after}"))?;
+        let v = match op {
+            "hi" => hi20(value(arg, pc)?),
+            "lo" => lo12(value(arg, pc)?),
+            "pcrel_hi" => hi20(value(arg, pc)? - pc as i64),
+            "pcrel_lo" => {
+                let at = *labels.get(arg).ok_or_else(|| format!("label not found: {arg}"))?;
+                let target = pcrel_his
+                    .get(&at)
+                    .ok_or_else(|| format!("%pcrel_lo({arg}): `{arg}` does not label an auipc with %pcrel_hi"))?;
+                lo12(value(target, at)? - at as i64)
+            }
+            _ => return Err(format!("unknown relocation operator: %{op}")),
+        };
+        out.push_str(&v.to_string());
+        rest = tail;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Mnemonics whose last operand is a PC-relative branch or jump target.
+const JUMPS: &[&str] = &[
+    "j", "jal", "call", "tail", "beq", "bne", "blt", "bge", "bltu", "bgeu", "bgt", "ble", "bgtu", "bleu", "beqz",
+    "bnez", "bltz", "bgez", "blez", "bgtz",
+];
+
+/// Whether an operand names a symbol rather than a register or number.
+fn is_symbol(op: &str) -> bool {
+    op.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && op.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'))
+        && parse_reg(op).is_none()
+        && parse_freg(op).is_none()
+}
+
+/// Label a branch or jump instruction targets, if it names one.
+fn jump_target(s: &str) -> Option<String> {
+    let (mnemonic, rest) = s.split_once(char::is_whitespace)?;
+    if !JUMPS.contains(&mnemonic.to_lowercase().as_str()) {
+        return None;
+    }
+    split_operands(rest.trim()).pop().filter(|op| is_symbol(op))
+}
+
+/// Label a `la`-style pseudo-instruction loads the address of.
+fn address_operand(kind: &LineKind) -> Option<String> {
+    let s = match kind {
+        LineKind::La(s)
+        | LineKind::PrintStr(s)
+        | LineKind::PrintStrLn(s)
+        | LineKind::Read(s)
+        | LineKind::ReadByte(s)
+        | LineKind::ReadHalf(s)
+        | LineKind::ReadWord(s)
+        | LineKind::RandomBytes(s) => s,
+        _ => return None,
+    };
+    let (_, rest) = s.split_once(char::is_whitespace)?;
+    split_operands(rest.trim()).into_iter().find(|op| is_symbol(op))
+}
+
+/// `(operator, expression)` of each `%op(expr)` in an instruction.
+fn reloc_operators(s: &str) -> Vec<(&str, &str)> {
+    s.split('%')
+        .skip(1)
+        .filter_map(|part| {
+            let (op, args) = part.split_once('(')?;
+            Some((op.trim(), args.split_once(')')?.0.trim()))
+        })
+        .collect()
+}
+
+/// Symbols named in an expression.
+fn expr_symbols(expr: &str) -> Vec<String> {
+    tokenize_expr(expr)
+        .map(|toks| toks.into_iter().filter_map(|t| if let ExprTok::Ident(name) = t { Some(name) } else { None }).collect())
+        .unwrap_or_default()
+}
+
+/// Every symbol an item uses as an address: jump targets, `la` operands and the
+/// arguments of `%hi`/`%lo`/`%pcrel_hi`.
+fn symbol_refs(kind: &LineKind) -> Vec<String> {
+    match kind {
+        LineKind::Instr(s) => {
+            let mut refs: Vec<String> = jump_target(s).into_iter().collect();
+            for (op, expr) in reloc_operators(s) {
+                if op != "pcrel_lo" {
+                    refs.extend(expr_symbols(expr));
+                }
+            }
+            refs
+        }
+        _ => address_operand(kind).into_iter().collect(),
+    }
+}
+
+/// In an object, a jump to anything outside the module's own text is assembled with
+/// offset 0 and left to a relocation.
+fn detach_jump(kind: &LineKind, in_text: &impl Fn(&str) -> bool) -> LineKind {
+    if let LineKind::Instr(s) = kind
+        && let Some(target) = jump_target(s)
+        && !in_text(&target)
+        && let Some(at) = s.rfind(&target)
+    {
+        return LineKind::Instr(format!("{}0{}", &s[..at], &s[at + target.len()..]));
+    }
+    kind.clone()
+}
+
+/// Relocations for the words an item assembled to at `pc` in an object.
+/// PC-relative references within the module's own text need none.
+fn object_relocs(
+    kind: &LineKind,
+    pc: u32,
+    words: &[u32],
+    labels: &HashMap<String, u32>,
+    consts: &HashMap<String, i64>,
+    in_text: &impl Fn(&str) -> bool,
+) -> Result<Vec<Reloc>, String> {
+    let reloc = |offset: u32, kind, symbol: &str, addend| Reloc {
+        section: link::Section::Text,
+        offset,
+        kind,
+        symbol: symbol.to_string(),
+        addend,
+    };
+    let opcode = |i: usize| words.get(i).map_or(0, |w| w & 0x7f);
+    let mut out = Vec::new();
+    match kind {
+        LineKind::Instr(s) => {
+            if let Some(target) = jump_target(s).filter(|t| !in_text(t)) {
+                let kind = if opcode(0) == 0x6f { RelocKind::Jal } else { RelocKind::Branch };
+                out.push(reloc(pc, kind, &target, 0));
+            }
+            for (op, expr) in reloc_operators(s) {
+                if op == "pcrel_lo" {
+                    continue;
+                }
+                let syms: Vec<String> = expr_symbols(expr).into_iter().filter(|n| labels.contains_key(n)).collect();
+                let [sym] = syms.as_slice() else {
+                    if syms.len() > 1 {
+                        return Err(format!("%{op}({expr}): an object can only relocate against one symbol"));
+                    }
+                    continue;
+                };
+                if op == "pcrel_hi" {
+                    if in_text(sym) {
+                        continue;
+                    }
+                    return Err(format!(
+                        "%pcrel_hi({expr}): `{sym}` is not in this module's .text; use %hi/%lo or la"
+                    ));
+                }
+                let value = eval_expr(expr, pc as i64, labels, consts).map_err(|_| format!("invalid expression: {expr}"))?;
+                let addend = (value - labels[sym] as i64) as i32;
+                let kind = match op {
+                    "hi" => RelocKind::Hi20,
+                    _ if matches!(opcode(0), 0x23 | 0x27) => RelocKind::Lo12S,
+                    _ => RelocKind::Lo12I,
+                };
+                out.push(reloc(pc, kind, sym, addend));
+            }
+        }
+        _ => {
+            if let Some(sym) = address_operand(kind)
+                && let Some(i) = (0..words.len()).find(|&i| opcode(i) == 0x37)
+            {
+                let at = pc + 4 * i as u32;
+                out.push(reloc(at, RelocKind::Hi20, &sym, 0));
+                out.push(reloc(at + 4, RelocKind::Lo12I, &sym, 0));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// A `.word` value: anything from `i32::MIN` up to `0xFFFFFFFF`, as its 32 bits.
+fn parse_word(s: &str) -> Option<u32> {
+    parse_imm64(s).filter(|v| (i32::MIN as i64..=u32::MAX as i64).contains(v)).map(|v| v as u32)
+}
+
 /// Returns the number of words `li rd, imm` will emit (1 for 12-bit literals, 2 otherwise).
 /// Called during the first pass before equates are resolved; equate names conservatively → 2 words.
 fn li_word_count(s: &str) -> u32 {
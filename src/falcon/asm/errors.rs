@@ -1,14 +1,107 @@
 use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct AsmError {
+    /// Included file the error is in; `None` for the main source.
+    pub file: Option<PathBuf>,
     pub line: usize,
     pub msg: String,
 }
 
+impl AsmError {
+    pub(super) fn new(line: usize, msg: impl Into<String>) -> Self {
+        AsmError { file: None, line, msg: msg.into() }
+    }
+}
+
 impl fmt::Display for AsmError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
         write!(f, "line {}: {}", self.line + 1, self.msg)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem reported by `assemble_diagnostics`, for the editor gutter and problems panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Included file the problem is in; `None` for the main source.
+    pub file: Option<PathBuf>,
+    /// 0-based source line.
+    pub line: usize,
+    /// Character columns `[start, end)` on that line.
+    pub cols: Range<usize>,
+    pub severity: Severity,
+    pub msg: String,
+    /// Lint name for warnings, as used in `# nolint(name)`.
+    pub code: Option<&'static str>,
+}
+
+impl Diagnostic {
+    /// Error diagnostic for `e`. The span covers the statement (comments excluded), narrowed
+    /// to the offending token when the message ends in one, as in `invalid .byte: 300`.
+    pub(super) fn error(e: AsmError, src_line: &str) -> Self {
+        // Errors inside a macro expansion point at the call, so drop the macro trace.
+        let msg = e.msg.split(" (in macro `").next().unwrap_or(&e.msg);
+        let focus = msg.rsplit_once(": ").map(|(_, tok)| tok.trim().trim_matches(|c| c == '\'' || c == '"'));
+        let cols = span_of(src_line, focus);
+        Diagnostic { file: e.file, line: e.line, cols, severity: Severity::Error, msg: e.msg, code: None }
+    }
+
+    /// Lint warning on `line`, underlining `focus` when it appears in the statement.
+    pub(super) fn warning(line: usize, src_line: &str, code: &'static str, msg: String, focus: Option<&str>) -> Self {
+        Diagnostic { file: None, line, cols: span_of(src_line, focus), severity: Severity::Warning, msg, code: Some(code) }
+    }
+
+    /// Whether a `nolint` comment on the diagnostic's line silences it: bare `nolint`
+    /// silences every warning, `nolint(a, b)` only the named ones. Errors can't be silenced.
+    pub(super) fn suppressed_by(&self, src_line: &str) -> bool {
+        let Some(code) = self.code else { return false };
+        let Some(at) = src_line.find(['#', ';']) else { return false };
+        let comment = src_line[at..].to_ascii_lowercase();
+        let Some(pos) = comment.find("nolint") else { return false };
+        let rest = comment[pos + "nolint".len()..].trim_start();
+        match rest.strip_prefix('(').and_then(|r| r.split_once(')')) {
+            Some((names, _)) => names.split(',').any(|n| n.trim() == code),
+            None => true,
+        }
+    }
+}
+
+/// Character span of the statement on `src_line` (comments excluded), or of `focus` within it.
+fn span_of(src_line: &str, focus: Option<&str>) -> Range<usize> {
+    let code = src_line.find(['#', ';']).map_or(src_line, |i| &src_line[..i]);
+    let start = code.len() - code.trim_start().len();
+    let mut span = start..code.trim_end().len();
+    if let Some(tok) = focus
+        && !tok.is_empty()
+        && let Some(at) = code[start..].find(tok)
+    {
+        span = start + at..start + at + tok.len();
+    }
+    let col = |byte: usize| src_line[..byte].chars().count();
+    col(span.start)..col(span.end)
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}:{}: {kind}: {}", self.line + 1, self.cols.start + 1, self.msg)?;
+        if let Some(code) = self.code {
+            write!(f, " [{code}]")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+// src/falcon/asm/include.rs
+//
+// `.include "file"` splices another source file into the statement list before
+// macro expansion. Each file owns a block of "global" line numbers (the main
+// source first, from 0), so the passes keep using plain `usize` lines and
+// `Sources::locate` turns one back into a file and a line at the end.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::errors::{AsmError, Diagnostic};
+use super::program::Program;
+use super::utils::preprocess;
+
+/// Where `.include` looks for files.
+#[derive(Clone, Debug, Default)]
+pub struct IncludePaths {
+    /// Path of the main source. Includes resolve against its directory first, or the
+    /// working directory when `None` (an unsaved editor buffer).
+    pub main: Option<PathBuf>,
+    /// Further directories searched in order, like `-I` for `as`.
+    pub dirs: Vec<PathBuf>,
+    /// Unsaved editor text of included files, by canonical path; read instead of the disk.
+    pub open: HashMap<PathBuf, String>,
+}
+
+struct File {
+    path: Option<PathBuf>,
+    text: String,
+    /// Global line number of the file's first line.
+    start: usize,
+    /// Byte offset of each line in `text`, so `line_text` doesn't rescan the file.
+    line_offsets: Vec<usize>,
+}
+
+impl File {
+    fn new(path: Option<PathBuf>, text: String, start: usize) -> Self {
+        let line_offsets = std::iter::once(0).chain(text.match_indices('\n').map(|(at, _)| at + 1)).collect();
+        File { path, text, start, line_offsets }
+    }
+}
+
+/// The main source plus every file it includes.
+pub(super) struct Sources {
+    files: Vec<File>,
+    /// Statements in assembly order, as `(global line, text)` like `preprocess` returns.
+    pub lines: Vec<(usize, String)>,
+    /// Problems with `.include` lines. Those lines stay in `lines`, so an include in
+    /// a block conditional assembly drops is not an error.
+    pub errors: Vec<AsmError>,
+}
+
+impl Sources {
+    pub(super) fn load(text: &str, paths: &IncludePaths) -> Self {
+        let main = File::new(paths.main.clone(), text.to_string(), 0);
+        let mut sources = Sources { files: vec![main], lines: Vec::new(), errors: Vec::new() };
+        let mut stack = vec![0];
+        sources.splice(0, paths, &mut stack);
+        sources
+    }
+
+    /// Append the statements of file `idx`, recursing into its `.include`s. `stack`
+    /// holds the files currently being spliced, to catch cycles.
+    fn splice(&mut self, idx: usize, paths: &IncludePaths, stack: &mut Vec<usize>) {
+        let start = self.files[idx].start;
+        for (line, code) in preprocess(&self.files[idx].text) {
+            let line = start + line;
+            let Some(arg) = code.strip_prefix(".include").filter(|a| a.is_empty() || a.starts_with([' ', '\t', '"'])) else {
+                self.lines.push((line, code));
+                continue;
+            };
+            let Some(name) = arg.trim().strip_prefix('"').and_then(|a| a.strip_suffix('"')) else {
+                self.unspliced(line, code.clone(), "expected .include \"file\"".into());
+                continue;
+            };
+            let Some(path) = self.resolve(idx, name, paths) else {
+                self.unspliced(line, code.clone(), format!("include file not found: {name}"));
+                continue;
+            };
+            let known = self.files.iter().position(|f| f.path.as_deref().and_then(|p| p.canonicalize().ok()).as_ref() == Some(&path));
+            if let Some(at) = known.and_then(|k| stack.iter().position(|&s| s == k)) {
+                let chain: Vec<String> = stack[at..].iter().chain([&stack[at]]).map(|&f| self.name(f)).collect();
+                self.unspliced(line, code.clone(), format!("include cycle: {}", chain.join(" -> ")));
+                continue;
+            }
+            let next = match known {
+                Some(k) => k,
+                None => {
+                    let read = match paths.open.get(&path) {
+                        Some(text) => Ok(text.clone()),
+                        None => std::fs::read_to_string(&path),
+                    };
+                    let text = match read {
+                        Ok(text) => text,
+                        Err(e) => {
+                            self.unspliced(line, code.clone(), format!("cannot read {}: {e}", path.display()));
+                            continue;
+                        }
+                    };
+                    let last = self.files.last().unwrap();
+                    let start = last.start + last.text.lines().count() + 1;
+                    self.files.push(File::new(Some(path), text, start));
+                    self.files.len() - 1
+                }
+            };
+            stack.push(next);
+            self.splice(next, paths, stack);
+            stack.pop();
+        }
+    }
+
+    /// Keep an `.include` that could not be spliced as a statement, with its error.
+    fn unspliced(&mut self, line: usize, code: String, msg: String) {
+        self.lines.push((line, code));
+        self.errors.push(AsmError::new(line, msg));
+    }
+
+    /// Look for `name` next to the including file, then in the include directories.
+    fn resolve(&self, from: usize, name: &str, paths: &IncludePaths) -> Option<PathBuf> {
+        let here = match &self.files[from].path {
+            Some(p) => p.parent().map(Path::to_path_buf).unwrap_or_default(),
+            None => PathBuf::new(),
+        };
+        std::iter::once(here)
+            .chain(paths.dirs.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|p| p.is_file())
+            .and_then(|p| p.canonicalize().ok())
+    }
+
+    fn name(&self, file: usize) -> String {
+        match &self.files[file].path {
+            Some(p) => p.file_name().map_or_else(|| p.display().to_string(), |n| n.to_string_lossy().into_owned()),
+            None => "<main>".to_string(),
+        }
+    }
+
+    /// File index and 0-based line within it for a global line.
+    pub(super) fn locate(&self, line: usize) -> (usize, usize) {
+        let file = self.files.partition_point(|f| f.start <= line).saturating_sub(1);
+        (file, line - self.files[file].start)
+    }
+
+    /// "line N" in the main source, "file.fas:N" in an included one.
+    pub(super) fn describe(&self, line: usize) -> String {
+        match self.locate(line) {
+            (0, l) => format!("line {}", l + 1),
+            (f, l) => format!("{}:{}", self.name(f), l + 1),
+        }
+    }
+
+    /// Raw source text of a global line.
+    pub(super) fn line_text(&self, line: usize) -> &str {
+        let (file, l) = self.locate(line);
+        let file = &self.files[file];
+        let Some(&from) = file.line_offsets.get(l) else { return "" };
+        let to = file.line_offsets.get(l + 1).map_or(file.text.len(), |next| next - 1);
+        let text = &file.text[from..to];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    /// Apply a per-file line scan (comment extraction) to every file, keyed by global line.
+    pub(super) fn scan(&self, f: fn(&str) -> HashMap<usize, String>) -> HashMap<usize, String> {
+        self.files.iter().flat_map(|file| f(&file.text).into_iter().map(|(l, s)| (file.start + l, s))).collect()
+    }
+
+    fn path_of(&self, file: usize) -> Option<PathBuf> {
+        if file == 0 { None } else { self.files[file].path.clone() }
+    }
+
+    /// Turn an error's global line into a file and a line within it.
+    pub(super) fn place_error(&self, mut e: AsmError) -> AsmError {
+        let (file, line) = self.locate(e.line);
+        (e.file, e.line) = (self.path_of(file), line);
+        e
+    }
+
+    pub(super) fn place_diagnostic(&self, mut d: Diagnostic) -> Diagnostic {
+        let (file, line) = self.locate(d.line);
+        (d.file, d.line) = (self.path_of(file), line);
+        d
+    }
+
+    /// Split the program's global line tables into the main file's and the includes'.
+    pub(super) fn place_program(&self, prog: &mut Program) {
+        prog.includes = self.files[1..].iter().filter_map(|f| f.path.clone()).collect();
+        for (line, addr) in std::mem::take(&mut prog.line_addrs) {
+            match self.locate(line) {
+                (0, l) => prog.line_addrs.insert(l, addr),
+                (f, l) => prog.include_line_addrs.insert((f - 1, l), addr),
+            };
+        }
+        for (line, off) in std::mem::take(&mut prog.data_line_offsets) {
+            match self.locate(line) {
+                (0, l) => prog.data_line_offsets.insert(l, off),
+                (f, l) => prog.include_data_line_offsets.insert((f - 1, l), off),
+            };
+        }
+        for (label, line) in std::mem::take(&mut prog.label_to_line) {
+            match self.locate(line) {
+                (0, l) => {
+                    prog.label_to_line.insert(label, l);
+                }
+                (f, l) => {
+                    prog.include_label_lines.insert(label, (f - 1, l));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,357 @@
+// src/falcon/asm/lint.rs
+//
+// Static checks over a program that assembled. Each finding is a warning with a
+// lint name (`unused-label`, `write-x0`, ...) that a `# nolint(name)` comment on
+// its line silences; `unaligned-word` is raised by the assembler's first pass.
+use std::collections::HashSet;
+
+use crate::falcon::decoder::decode;
+use crate::falcon::instruction::Instruction;
+use crate::falcon::semihost;
+use crate::falcon::syscall::{RARS_EXIT, SYS_EXIT, SYS_EXIT_GROUP};
+
+use super::errors::Diagnostic;
+use super::include::Sources;
+use super::locals;
+use super::macros;
+use super::program::Program;
+
+const OP_JAL: u32 = 0x6f;
+const OP_JALR: u32 = 0x67;
+const WORD_NOP: u32 = 0x0000_0013;
+const WORD_ECALL: u32 = 0x0000_0073;
+const WORD_EBREAK: u32 = 0x0010_0073; // also `halt`
+
+/// Labels that are entry points, so never reported as unused.
+const ENTRY_LABELS: &[&str] = &["main", "_start"];
+
+/// One text word with the source line it came from.
+struct Word {
+    addr: u32,
+    word: u32,
+    line: usize,
+}
+
+struct Lints<'a> {
+    src: &'a Sources,
+    out: Vec<Diagnostic>,
+}
+
+impl Lints<'_> {
+    fn warn(&mut self, line: usize, code: &'static str, msg: String, focus: Option<&str>) {
+        let src_line = self.src.line_text(line);
+        self.out.push(Diagnostic::warning(line, src_line, code, msg, focus));
+    }
+}
+
+/// Lines are global across `sources`, as in the program before it is placed into files.
+pub(super) fn check(sources: &Sources, prog: &Program, base_pc: u32) -> Vec<Diagnostic> {
+    let words = text_words(prog, base_pc);
+    let mut stmts = macros::expand(sources.lines.clone(), &|line| sources.describe(line)).lines;
+    locals::rename(&mut stmts);
+    let mut lints = Lints { src: sources, out: Vec::new() };
+    unused_labels(&mut lints, &stmts, prog);
+    writes_to_x0(&mut lints, &words);
+    branches_to_data(&mut lints, &words, prog);
+    missing_exit(&mut lints, &words);
+    fall_through(&mut lints, &stmts, &words, prog);
+    t_regs_across_calls(&mut lints, &words, prog);
+    lints.out
+}
+
+/// The text section as words tagged with their source line.
+fn text_words(prog: &Program, base_pc: u32) -> Vec<Word> {
+    let mut starts: Vec<(u32, usize)> = prog.line_addrs.iter().map(|(&line, &addr)| (addr, line)).collect();
+    starts.sort();
+    prog.text
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &word)| {
+            let addr = base_pc.wrapping_add(i as u32 * 4);
+            let at = starts.partition_point(|&(a, _)| a <= addr).checked_sub(1)?;
+            Some(Word { addr, word, line: starts[at].1 })
+        })
+        .collect()
+}
+
+/// Integer register written and integer registers read by `word`, from its encoding.
+fn int_regs(word: u32) -> (Option<u8>, [Option<u8>; 2]) {
+    let rd = ((word >> 7) & 31) as u8;
+    let rs1 = ((word >> 15) & 31) as u8;
+    let rs2 = ((word >> 20) & 31) as u8;
+    match word & 0x7f {
+        0x33 | 0x2f => (Some(rd), [Some(rs1), Some(rs2)]), // OP, AMO
+        0x13 | 0x03 | OP_JALR => (Some(rd), [Some(rs1), None]), // OP-IMM, LOAD, JALR
+        0x23 | 0x63 => (None, [Some(rs1), Some(rs2)]),     // STORE, BRANCH
+        0x37 | 0x17 | OP_JAL => (Some(rd), [None, None]),  // LUI, AUIPC, JAL
+        0x07 | 0x27 => (None, [Some(rs1), None]),          // FLW, FSW (base address)
+        0x53 => match word >> 25 {
+            0x50 | 0x60 | 0x70 => (Some(rd), [None, None]), // compares, fcvt.w[u].s, fmv.x.w/fclass
+            0x68 | 0x78 => (None, [Some(rs1), None]),       // fcvt.s.w[u], fmv.w.x
+            _ => (None, [None, None]),
+        },
+        _ => (None, [None, None]),
+    }
+}
+
+fn is_call(word: u32) -> bool {
+    matches!(word & 0x7f, OP_JAL | OP_JALR) && (word >> 7) & 31 == 1
+}
+
+/// `j`, `jr` and `ret`: control never continues to the next word.
+fn is_jump(word: u32) -> bool {
+    matches!(word & 0x7f, OP_JAL | OP_JALR) && (word >> 7) & 31 == 0
+}
+
+/// Whether execution can't run past `words[i]`: an unconditional jump, `ebreak`/`halt`,
+/// an `ecall` whose `a7` is an exit syscall (Linux's or RARS's), or a semihosting
+/// call whose `a0` is an exit operation. A number that can't be worked out counts
+/// as an exit.
+fn ends_flow(words: &[Word], i: usize) -> bool {
+    let w = words[i].word;
+    if w == WORD_EBREAK {
+        return !is_semihost_call(words, i);
+    }
+    if w == semihost::EXIT_NOP && i > 0 && is_semihost_call(words, i - 1) {
+        return selects(words, i - 1, 10, &[semihost::SYS_EXIT, semihost::SYS_EXIT_EXTENDED]);
+    }
+    if is_jump(w) {
+        return true;
+    }
+    w == WORD_ECALL && selects(words, i, 17, &[SYS_EXIT, SYS_EXIT_GROUP, RARS_EXIT])
+}
+
+/// Whether the `li reg, N` shortly before `words[i]` loads one of `values`;
+/// true when there is no such `li` to read.
+fn selects(words: &[Word], i: usize, reg: u8, values: &[u32]) -> bool {
+    for prev in words[..i].iter().rev().take(8) {
+        if int_regs(prev.word).0 == Some(reg) {
+            return match decode(prev.word) {
+                Ok(Instruction::Addi { rs1: 0, imm, .. }) => values.contains(&(imm as u32)),
+                _ => true,
+            };
+        }
+    }
+    true
+}
+
+/// `slli x0, x0, 0x1f; ebreak; srai x0, x0, 7` around `words[i]`.
+fn is_semihost_call(words: &[Word], i: usize) -> bool {
+    i > 0
+        && words[i - 1].word == semihost::ENTRY_NOP
+        && words.get(i + 1).is_some_and(|w| w.word == semihost::EXIT_NOP)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+/// Only labels in the main source count: a library is expected to have routines a
+/// given program doesn't call.
+fn unused_labels(lints: &mut Lints, stmts: &[(usize, String)], prog: &Program) {
+    let mut used = HashSet::new();
+    for (_, code) in stmts {
+        // The assembler treats everything before the first ':' as the label.
+        let stmt = code.split_once(':').map_or(code.as_str(), |(_, rest)| rest);
+        used.extend(stmt.split(|c| !is_ident_char(c)).filter(|t| !t.is_empty()).map(str::to_string));
+    }
+    let mut unused: Vec<(&String, usize)> = prog
+        .label_to_line
+        .iter()
+        .filter(|(name, _)| !used.contains(*name) && !ENTRY_LABELS.contains(&name.as_str()) && !locals::is_local(name))
+        .filter(|&(_, &line)| lints.src.locate(line).0 == 0)
+        .map(|(name, &line)| (name, line))
+        .collect();
+    unused.sort_by_key(|&(_, line)| line);
+    for (name, line) in unused {
+        lints.warn(line, "unused-label", format!("label `{name}` is never referenced"), Some(name));
+    }
+}
+
+fn writes_to_x0(lints: &mut Lints, words: &[Word]) {
+    let mut reported = HashSet::new();
+    for w in words {
+        // The semihosting markers are x0 writes on purpose
+        let marker = matches!(w.word, semihost::ENTRY_NOP | semihost::EXIT_NOP);
+        if int_regs(w.word).0 != Some(0) || is_jump(w.word) || w.word == WORD_NOP || marker || !reported.insert(w.line) {
+            continue;
+        }
+        let src = lints.src.line_text(w.line);
+        let focus = ["zero", "x0"].into_iter().find(|r| src.contains(r));
+        lints.warn(w.line, "write-x0", "write to `x0` is discarded; `x0` always reads as zero".into(), focus);
+    }
+}
+
+fn branches_to_data(lints: &mut Lints, words: &[Word], prog: &Program) {
+    let data_end = prog.data_base.wrapping_add(prog.data.len() as u32).wrapping_add(prog.bss_size);
+    for w in words {
+        use Instruction::*;
+        let imm = match decode(w.word) {
+            Ok(Beq { imm, .. } | Bne { imm, .. } | Blt { imm, .. } | Bge { imm, .. } | Bltu { imm, .. } | Bgeu { imm, .. } | Jal { imm, .. }) => imm,
+            _ => continue,
+        };
+        let target = w.addr.wrapping_add(imm as u32);
+        if (prog.data_base..data_end).contains(&target) {
+            let name = prog.labels.get(&target).and_then(|n| n.first());
+            let what = name.map_or(format!("{target:#010x}"), |n| format!("`{n}`"));
+            lints.warn(w.line, "branch-to-data", format!("jump target {what} is in the data section"), name.map(String::as_str));
+        }
+    }
+}
+
+fn missing_exit(lints: &mut Lints, words: &[Word]) {
+    let Some(last) = words.len().checked_sub(1) else { return };
+    if !ends_flow(words, last) {
+        lints.warn(
+            words[last].line,
+            "missing-exit",
+            "execution runs past the end of `.text`; finish with an exit `ecall` or `halt`".into(),
+            None,
+        );
+    }
+}
+
+/// Code right before a `.data`/`.bss` switch that doesn't jump or exit runs straight into
+/// the next `.text` block, which is rarely what the data section in between suggests.
+fn fall_through(lints: &mut Lints, stmts: &[(usize, String)], words: &[Word], prog: &Program) {
+    let mut in_text = true;
+    let mut emitted = false;
+    for &(line, ref code) in stmts {
+        let switch = match code.strip_prefix(".section").map(str::trim).unwrap_or(code) {
+            s if s.starts_with(".text") || s.starts_with("text") => Some(true),
+            ".data" | ".bss" | ".rodata" | ".sdata" | ".sbss" | "data" | "bss" | "rodata" => Some(false),
+            _ => None,
+        };
+        match switch {
+            Some(true) => in_text = true,
+            Some(false) if in_text && emitted => {
+                in_text = false;
+                emitted = false;
+                // First word of the next text block, if there is one.
+                let next = prog.line_addrs.iter().filter(|&(&l, _)| l > line).min_by_key(|&(&l, _)| l);
+                let Some((&next_line, &next_addr)) = next else { continue };
+                let Some(i) = words.iter().position(|w| w.addr == next_addr) else { continue };
+                if i > 0 && !ends_flow(words, i - 1) {
+                    lints.warn(
+                        words[i - 1].line,
+                        "fall-through",
+                        format!(
+                            "execution falls through past `{code}` (line {}) into the code at line {}",
+                            line + 1,
+                            next_line + 1
+                        ),
+                        None,
+                    );
+                }
+            }
+            Some(false) => in_text = false,
+            None => emitted |= in_text && prog.line_addrs.contains_key(&line),
+        }
+    }
+}
+
+fn t_reg_name(r: u8) -> String {
+    format!("t{}", if r < 8 { r - 5 } else { r - 25 })
+}
+
+/// A `t*` register set before a call and read after it: the callee may have clobbered it.
+/// Tracking restarts at every label and jump, so only straight-line code is checked.
+fn t_regs_across_calls(lints: &mut Lints, words: &[Word], prog: &Program) {
+    let is_t = |r: u8| matches!(r, 5..=7 | 28..=31);
+    let mut written: Vec<u8> = Vec::new();
+    let mut clobbered: Vec<(u8, usize)> = Vec::new(); // (register, line of the call)
+    for (i, w) in words.iter().enumerate() {
+        if i > 0 && prog.labels.contains_key(&w.addr) {
+            written.clear();
+            clobbered.clear();
+        }
+        let (rd, reads) = int_regs(w.word);
+        for r in reads.into_iter().flatten() {
+            if let Some(pos) = clobbered.iter().position(|&(c, _)| c == r) {
+                let (_, call_line) = clobbered.remove(pos);
+                let name = t_reg_name(r);
+                lints.warn(
+                    w.line,
+                    "t-reg-across-call",
+                    format!(
+                        "`{name}` is read after the call on line {} but t-registers are caller-saved; keep it in an s-register or save it around the call",
+                        call_line + 1
+                    ),
+                    Some(&name),
+                );
+            }
+        }
+        if is_call(w.word) {
+            clobbered = written.drain(..).map(|r| (r, w.line)).collect();
+            continue;
+        }
+        if let Some(r) = rd {
+            clobbered.retain(|&(c, _)| c != r);
+            if is_t(r) && !written.contains(&r) {
+                written.push(r);
+            }
+        }
+        if is_jump(w.word) {
+            written.clear();
+            clobbered.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{assemble_diagnostics, Severity};
+
+    fn warnings(src: &str) -> Vec<(usize, &'static str)> {
+        let (prog, diags) = assemble_diagnostics(src, 0);
+        assert!(prog.is_some(), "should assemble: {diags:?}");
+        assert!(diags.iter().all(|d| d.severity == Severity::Warning));
+        diags.iter().map(|d| (d.line, d.code.unwrap())).collect()
+    }
+
+    const EXIT: &str = "li a7, 93\necall\n";
+
+    #[test]
+    fn clean_program_has_no_warnings() {
+        let src = format!(".data\nn: .word 3\n.text\nmain: la t0, n\nlw a0, 0(t0)\njal ra, f\n{EXIT}f: addi a0, a0, 1\nret\n");
+        assert_eq!(warnings(&src), vec![]);
+    }
+
+    #[test]
+    fn each_lint_fires() {
+        let src = ".data\nd: .word 1\nb: .byte 1\n.word 2\n.text\nmain: addi zero, t0, 1\nli t1, 5\ncall f\nadd a0, a0, t1\nbeqz a0, f\nj d\nunused: nop\n.data\nz: .byte 0\n.text\nf: ret\n";
+        let got = warnings(src);
+        for expected in [
+            (2, "unused-label"), // b
+            (3, "unaligned-word"),
+            (5, "write-x0"),
+            (8, "t-reg-across-call"),
+            (10, "branch-to-data"),
+            (11, "unused-label"),
+            (11, "fall-through"),
+        ] {
+            assert!(got.contains(&expected), "missing {expected:?} in {got:?}");
+        }
+        // `f: ret` ends the text section, so no missing-exit
+        assert!(!got.iter().any(|&(_, c)| c == "missing-exit"));
+        assert_eq!(warnings(".text\nmain: li a0, 1\n"), vec![(1, "missing-exit")]);
+        assert_eq!(warnings(".text\nmain: li a7, 64\necall\n"), vec![(2, "missing-exit")]);
+        assert!(warnings(".text\nmain: li a7, 10\necall\n").is_empty());
+    }
+
+    #[test]
+    fn semihosting_call_is_not_an_exit() {
+        let call = "slli x0, x0, 0x1f\nebreak\nsrai x0, x0, 7\n";
+        assert_eq!(warnings(&format!(".text\nmain: li a0, 4\n{call}")), vec![(4, "missing-exit")]);
+        assert!(warnings(&format!(".text\nmain: li a0, 4\n{call}{EXIT}")).is_empty());
+        assert!(warnings(&format!(".text\nmain: li a0, 0x18\n{call}")).is_empty());
+    }
+
+    #[test]
+    fn nolint_comments_suppress() {
+        let src = ".text\nmain: addi x0, x0, 1 # nolint\nspare: nop ; nolint(write-x0)\nhalt\n";
+        assert_eq!(warnings(src), vec![(2, "unused-label")]);
+        let src = ".text\nmain: nop\nspare: halt # NOLINT(write-x0, unused-label)\n";
+        assert_eq!(warnings(src), vec![]);
+    }
+}
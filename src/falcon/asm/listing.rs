@@ -0,0 +1,168 @@
+// src/falcon/asm/listing.rs
+//
+// Assembly listings (`.lst`): every source line next to the address and machine
+// words it became, followed by a symbol table. Lines that expand to several words
+// (pseudo-instructions, macro calls, `.align` padding) list each word with its
+// disassembly underneath, so hand encodings can be checked word by word. Data
+// lines show their bytes in memory order, four to a row.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use super::program::Program;
+
+/// Rows of bytes listed under one data line; `.space 4096` needn't fill a page.
+const MAX_DATA_ROWS: usize = 8;
+
+/// Render the listing of `prog`, assembled from `text` (the file `name`) at
+/// `base_pc`. Files pulled in with `.include` are re-read from disk and listed
+/// after the main source under their own heading. `disasm` formats one word.
+pub fn listing(name: &str, text: &str, prog: &Program, base_pc: u32, disasm: impl Fn(u32) -> String) -> String {
+    let text_end = base_pc.wrapping_add(prog.text.len() as u32 * 4);
+    // Every line's words run up to the next line's first address.
+    let starts: BTreeSet<u32> =
+        prog.line_addrs.values().chain(prog.include_line_addrs.values()).copied().chain([text_end]).collect();
+    let words_from = |addr: u32| -> Vec<(u32, u32)> {
+        let end = starts.range(addr + 1..).next().copied().unwrap_or(text_end);
+        (addr..end)
+            .step_by(4)
+            .filter_map(|a| prog.text.get((a.wrapping_sub(base_pc) / 4) as usize).map(|&w| (a, w)))
+            .collect()
+    };
+    // Likewise for data: a line's bytes run up to the next data line's first byte.
+    let data_starts: BTreeSet<u32> = prog
+        .data_line_offsets
+        .values()
+        .chain(prog.include_data_line_offsets.values())
+        .copied()
+        .chain([prog.data.len() as u32])
+        .collect();
+    let bytes_from = |off: u32| -> Vec<(u32, String)> {
+        let end = data_starts.range(off + 1..).next().copied().unwrap_or(prog.data.len() as u32);
+        let bytes = prog.data.get(off as usize..end as usize).unwrap_or_default();
+        bytes
+            .chunks(4)
+            .zip((off..).step_by(4))
+            .map(|(chunk, o)| (prog.data_base.wrapping_add(o), chunk.iter().map(|b| format!("{b:02x}")).collect()))
+            .collect()
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "RAVEN listing of {name}");
+    let bss_base = prog.data_base.wrapping_add(prog.data.len() as u32);
+    let _ = writeln!(
+        out,
+        ".text {base_pc:08x}..{text_end:08x}  .data {:08x}..{bss_base:08x}  .bss {bss_base:08x}..{:08x}",
+        prog.data_base,
+        bss_base.wrapping_add(prog.bss_size)
+    );
+
+    let file = |out: &mut String, heading: &str, text: &str, addrs: &HashMap<usize, u32>, data: &HashMap<usize, u32>| {
+        let _ = writeln!(out, "\n{heading}\n line  address   word      source");
+        for (i, src) in text.lines().enumerate() {
+            if let Some(&off) = data.get(&i) {
+                let rows = bytes_from(off);
+                match rows.as_slice() {
+                    [(addr, hex)] => {
+                        let _ = writeln!(out, "{:>5}  {addr:08x}  {hex:<8}  {src}", i + 1);
+                    }
+                    _ => {
+                        let _ = writeln!(out, "{}", format!("{:>5}  {:18}  {src}", i + 1, "").trim_end());
+                        for (addr, hex) in rows.iter().take(MAX_DATA_ROWS) {
+                            let _ = writeln!(out, "       {addr:08x}  {hex}");
+                        }
+                        if rows.len() > MAX_DATA_ROWS {
+                            let _ = writeln!(out, "       ...       ({} bytes in all)", rows.iter().map(|(_, hex)| hex.len() / 2).sum::<usize>());
+                        }
+                    }
+                }
+                continue;
+            }
+            let words = addrs.get(&i).map(|&a| words_from(a)).unwrap_or_default();
+            match words.as_slice() {
+                [(addr, word)] => {
+                    let _ = writeln!(out, "{:>5}  {addr:08x}  {word:08x}  {src}", i + 1);
+                }
+                _ => {
+                    let _ = writeln!(out, "{}", format!("{:>5}  {:18}  {src}", i + 1, "").trim_end());
+                    for (addr, word) in &words {
+                        let _ = writeln!(out, "       {addr:08x}  {word:08x}      {}", disasm(*word));
+                    }
+                }
+            }
+        }
+    };
+    file(&mut out, name, text, &prog.line_addrs, &prog.data_line_offsets);
+    for (idx, path) in prog.includes.iter().enumerate() {
+        let addrs: HashMap<usize, u32> = prog
+            .include_line_addrs
+            .iter()
+            .filter(|&(&(f, _), _)| f == idx)
+            .map(|(&(_, line), &addr)| (line, addr))
+            .collect();
+        let data: HashMap<usize, u32> = prog
+            .include_data_line_offsets
+            .iter()
+            .filter(|&(&(f, _), _)| f == idx)
+            .map(|(&(_, line), &off)| (line, off))
+            .collect();
+        let text = std::fs::read_to_string(path).unwrap_or_else(|e| format!("(cannot read: {e})"));
+        file(&mut out, &path.display().to_string(), &text, &addrs, &data);
+    }
+
+    let mut symbols: Vec<(u32, &str)> =
+        prog.labels.iter().flat_map(|(&addr, names)| names.iter().map(move |n| (addr, n.as_str()))).collect();
+    symbols.sort();
+    let _ = writeln!(out, "\nSymbols\n address   section  name");
+    for (addr, sym) in symbols {
+        let section = if (base_pc..text_end).contains(&addr) {
+            ".text"
+        } else if (prog.data_base..bss_base).contains(&addr) {
+            ".data"
+        } else {
+            ".bss"
+        };
+        let _ = writeln!(out, " {addr:08x}  {section:<7}  {sym}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assemble;
+    use super::*;
+
+    #[test]
+    fn lines_show_their_words_and_pseudos_expand() {
+        let src = "main: li a0, 5\n  la a1, msg\n\n.data\nmsg: .asciz \"hi\"\n";
+        let prog = assemble(src, 0).unwrap();
+        let lst = listing("t.fas", src, &prog, 0, |w| format!("<{w:08x}>"));
+        let lines: Vec<&str> = lst.lines().collect();
+        assert_eq!(lines[1], ".text 00000000..0000000c  .data 00001000..00001003  .bss 00001003..00001003");
+        assert_eq!(lines[4], " line  address   word      source");
+        assert_eq!(lines[5], format!("    1  00000000  {:08x}  main: li a0, 5", prog.text[0]));
+        assert_eq!(lines[6], "    2                        la a1, msg");
+        assert_eq!(lines[7], format!("       00000004  {0:08x}      <{0:08x}>", prog.text[1]));
+        assert_eq!(lines[8], format!("       00000008  {0:08x}      <{0:08x}>", prog.text[2]));
+        assert_eq!(lines[9], "    3");
+        assert_eq!(lines[11], "    5  00001000  686900    msg: .asciz \"hi\"");
+        assert!(lst.ends_with("Symbols\n address   section  name\n 00000000  .text    main\n 00001000  .data    msg\n"));
+    }
+
+    #[test]
+    fn data_lines_show_their_bytes() {
+        let src = ".data\nw: .word 0x11223344, 5\n.byte 1, 2\n.align 4\nbuf: .space 64\n";
+        let prog = assemble(src, 0).unwrap();
+        let lst = listing("t.fas", src, &prog, 0, |w| format!("<{w:08x}>"));
+        let lines: Vec<&str> = lst.lines().collect();
+        assert_eq!(lines[5], "    1                      .data");
+        assert_eq!(lines[6], "    2                      w: .word 0x11223344, 5");
+        assert_eq!(lines[7], "       00001000  44332211");
+        assert_eq!(lines[8], "       00001004  05000000");
+        assert_eq!(lines[9], "    3  00001008  0102      .byte 1, 2");
+        assert_eq!(lines[10], "    4  0000100a  0000      .align 4");
+        assert_eq!(lines[11], "    5                      buf: .space 64");
+        assert_eq!(lines[12], "       0000100c  00000000");
+        assert_eq!(lines[19], "       00001028  00000000");
+        assert_eq!(lines[20], "       ...       (64 bytes in all)");
+    }
+}
@@ -0,0 +1,154 @@
+// src/falcon/asm/locals.rs
+//
+// GAS numeric local labels: `1:` may be defined any number of times, and `1b` / `1f`
+// refer to the nearest definition before / after the referencing statement. Runs on
+// the expanded statements, giving every definition a unique `.L1$N` name so the
+// passes only ever see ordinary labels.
+use std::collections::HashMap;
+
+use super::errors::AsmError;
+
+/// Unique name of the `k`th definition of local label `n`.
+fn local_name(n: &str, k: usize) -> String {
+    format!(".L{n}${k}")
+}
+
+/// Whether `name` is a renamed numeric label.
+pub(super) fn is_local(name: &str) -> bool {
+    name.strip_prefix(".L").and_then(|n| n.split_once('$')).is_some_and(|(n, k)| {
+        !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) && k.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// The `N` of a `N:` definition at the start of a statement.
+fn definition(text: &str) -> Option<&str> {
+    let (label, _) = text.split_once(':')?;
+    let label = label.trim();
+    (!label.is_empty() && label.chars().all(|c| c.is_ascii_digit())).then_some(label)
+}
+
+/// Rename definitions and `Nb`/`Nf` references in place. Errors come with the index
+/// of the statement they belong to.
+pub(super) fn rename(lines: &mut [(usize, String)]) -> Vec<(usize, AsmError)> {
+    // Statement index of every definition, per label, in order.
+    let mut defs: HashMap<String, Vec<usize>> = HashMap::new();
+    for (stmt, (_, text)) in lines.iter().enumerate() {
+        if let Some(n) = definition(text) {
+            defs.entry(n.to_string()).or_default().push(stmt);
+        }
+    }
+
+    let mut errors = Vec::new();
+    for (stmt, (line, text)) in lines.iter_mut().enumerate() {
+        let (head, body) = match definition(text) {
+            Some(n) => {
+                let k = defs[n].iter().position(|&s| s == stmt).unwrap();
+                let rest = text.split_once(':').unwrap().1;
+                (format!("{}:", local_name(n, k)), rest.to_string())
+            }
+            None => (String::new(), text.clone()),
+        };
+        let body = replace_refs(&body, |n, forward| {
+            let found = defs.get(n).and_then(|at| {
+                if forward { at.iter().position(|&s| s > stmt) } else { at.iter().rposition(|&s| s <= stmt) }
+            });
+            match (found, forward) {
+                (Some(k), _) => Ok(local_name(n, k)),
+                (None, true) => Err(format!("no local label `{n}:` after `{n}f`")),
+                (None, false) => Err(format!("no local label `{n}:` before `{n}b`")),
+            }
+        });
+        match body {
+            Ok(body) => *text = format!("{head}{body}"),
+            Err(msg) => errors.push((stmt, AsmError::new(*line, msg))),
+        }
+    }
+    errors
+}
+
+/// Replace each `Nb`/`Nf` token outside string and character literals.
+fn replace_refs(text: &str, mut resolve: impl FnMut(&str, bool) -> Result<String, String>) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut chars = text.char_indices().peekable();
+    let mut prev_ident = false;
+    while let Some((i, c)) = chars.next() {
+        if let Some(q) = quote {
+            out.push(c);
+            if c == '\\' {
+                if let Some((_, esc)) = chars.next() {
+                    out.push(esc);
+                }
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if matches!(c, '"' | '\'') {
+            quote = Some(c);
+            out.push(c);
+            prev_ident = false;
+            continue;
+        }
+        if c.is_ascii_digit() && !prev_ident {
+            let mut end = i + 1;
+            while let Some(&(j, d)) = chars.peek() {
+                if !(d.is_ascii_alphanumeric() || matches!(d, '_' | '.' | '$')) {
+                    break;
+                }
+                end = j + d.len_utf8();
+                chars.next();
+            }
+            let token = &text[i..end];
+            let digits = &token[..token.len() - 1];
+            match token.chars().last() {
+                Some(dir @ ('b' | 'f')) if !digits.is_empty() && digits.chars().all(|d| d.is_ascii_digit()) => {
+                    out.push_str(&resolve(digits, dir == 'f')?);
+                }
+                _ => out.push_str(token),
+            }
+            prev_ident = true;
+            continue;
+        }
+        prev_ident = c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$');
+        out.push(c);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renamed(src: &[&str]) -> Vec<String> {
+        let mut lines: Vec<_> = src.iter().enumerate().map(|(i, s)| (i, s.to_string())).collect();
+        let errors = rename(&mut lines);
+        assert!(errors.is_empty(), "{errors:?}");
+        lines.into_iter().map(|(_, t)| t).collect()
+    }
+
+    #[test]
+    fn references_pick_nearest_definition() {
+        let got = renamed(&["1: addi t0, t0, -1", "bnez t0, 1b", "j 1f", "1: beqz t1, 1b", "li a0, 0x1b", "la a1, str1b"]);
+        assert_eq!(
+            got,
+            [
+                ".L1$0: addi t0, t0, -1",
+                "bnez t0, .L1$0",
+                "j .L1$1",
+                ".L1$1: beqz t1, .L1$1",
+                "li a0, 0x1b",
+                "la a1, str1b",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_definitions_are_errors() {
+        let mut lines = vec![(0, "j 2f".to_string()), (1, "2: j 3b".to_string())];
+        let errors = rename(&mut lines);
+        let msgs: Vec<_> = errors.iter().map(|(s, e)| (*s, e.msg.as_str())).collect();
+        assert_eq!(msgs, [(1, "no local label `3:` before `3b`")]);
+        assert_eq!(lines[0].1, "j .L2$0");
+    }
+}
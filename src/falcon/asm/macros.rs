@@ -0,0 +1,570 @@
+// src/falcon/asm/macros.rs
+//
+// GAS-style `.macro name params` / `.endm`, conditional assembly (`.if`, `.ifdef`,
+// `.else`, `.endif`) and repetition (`.rept`, `.irp`), expanded on preprocessed
+// lines before the first pass. Expanded statements keep the line number of the
+// outermost call site, so errors, breakpoints and `line_addrs` land on the line the
+// user wrote; `Frame`s record which macro body line each statement came from.
+//
+// Conditions and repeat counts are evaluated here, so they can only use numbers
+// and `.equ` symbols assigned earlier in the source; labels count for `.ifdef`.
+use std::collections::{HashMap, HashSet};
+
+use super::assembler::{eval_const, parse_equate};
+use super::errors::AsmError;
+
+/// Guards against a macro that (indirectly) calls itself.
+const MAX_DEPTH: usize = 64;
+/// Statements one source may expand to in total, so a macro that calls itself
+/// twice per level can't run for hours while staying under `MAX_DEPTH`.
+const MAX_STATEMENTS: usize = 1_000_000;
+/// Largest `.rept` count, so a typo can't expand into millions of lines; nested
+/// ones are held to `MAX_STATEMENTS` together.
+const MAX_REPT: i64 = 65536;
+
+/// One level of macro expansion a statement came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Frame {
+    pub name: String,
+    /// Source line of the statement inside the macro body.
+    pub body_line: usize,
+}
+
+struct Macro {
+    line: usize,
+    params: Vec<(String, Option<String>)>,
+    body: Vec<(usize, String)>,
+}
+
+#[derive(Default)]
+pub(super) struct Expanded {
+    /// Statements after expansion, as `(source line, text)` like `preprocess` returns.
+    pub lines: Vec<(usize, String)>,
+    /// Macro frames per statement (outermost first); empty outside macros.
+    pub origins: Vec<Vec<Frame>>,
+    pub errors: Vec<AsmError>,
+}
+
+/// Names a source line in messages ("line 6", or "lib.fas:6" inside an include).
+pub(super) type Describe<'a> = &'a dyn Fn(usize) -> String;
+
+/// Add "in macro `m` at line N" to an error raised while assembling an expanded statement.
+pub(super) fn in_macro(mut e: AsmError, origin: &[Frame], describe: Describe) -> AsmError {
+    let Some((inner, outer)) = origin.split_last() else { return e };
+    e.msg = format!("{} (in macro `{}` at {}", e.msg, inner.name, describe(inner.body_line));
+    for f in outer.iter().rev() {
+        e.msg.push_str(&format!(", called from `{}` at {}", f.name, describe(f.body_line)));
+    }
+    e.msg.push(')');
+    e
+}
+
+pub(super) fn expand(lines: Vec<(usize, String)>, describe: Describe) -> Expanded {
+    expand_limited(lines, describe, MAX_STATEMENTS)
+}
+
+/// `expand`, giving up after `budget` statements.
+fn expand_limited(lines: Vec<(usize, String)>, describe: Describe, budget: usize) -> Expanded {
+    let mut ex = Expander {
+        macros: HashMap::new(),
+        consts: HashMap::new(),
+        symbols: HashSet::new(),
+        count: 0,
+        budget,
+        limit: budget,
+        exhausted: false,
+        nesting: 0,
+        top_line: 0,
+        describe,
+        out: Expanded::default(),
+    };
+    ex.run(&lines, None, &[]);
+    ex.out
+}
+
+struct Expander<'a> {
+    macros: HashMap<String, Macro>,
+    /// Equates with a value known so far, for `.if` and `.rept`.
+    consts: HashMap<String, i64>,
+    /// Every label and equate seen so far, for `.ifdef`.
+    symbols: HashSet<String>,
+    /// Expansions so far; the value of `\@`.
+    count: usize,
+    /// Statements left to expand before giving up, out of `limit`; see `MAX_STATEMENTS`.
+    budget: usize,
+    limit: usize,
+    exhausted: bool,
+    /// How many `run`s are active, to tell the source's own lines apart.
+    nesting: usize,
+    /// Source line of the top-level statement being expanded.
+    top_line: usize,
+    describe: Describe<'a>,
+    out: Expanded,
+}
+
+fn first_word(s: &str) -> &str {
+    s.split(|c: char| c.is_whitespace() || c == ',').next().unwrap_or("")
+}
+
+fn opens_if(word: &str) -> bool {
+    matches!(word, ".if" | ".ifdef" | ".ifndef" | ".ifeq" | ".ifne" | ".ifgt" | ".ifge" | ".iflt" | ".ifle")
+}
+
+fn opens_loop(word: &str) -> bool {
+    matches!(word, ".rept" | ".irp")
+}
+
+/// Index of the line closing a block whose body starts at `from`, skipping nested
+/// blocks of the same kind; `None` when the input ends first.
+fn block_end(lines: &[(usize, String)], from: usize, opens: fn(&str) -> bool, close: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, (_, text)) in lines.iter().enumerate().skip(from) {
+        let word = first_word(text);
+        if opens(word) {
+            depth += 1;
+        } else if word == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+impl Expander<'_> {
+    fn error(&mut self, line: usize, trace: &[Frame], msg: String) {
+        self.out.errors.push(in_macro(AsmError::new(line, msg), trace, self.describe));
+    }
+
+    /// Expand `lines`; `site` is the outermost call line when inside a macro.
+    fn run(&mut self, lines: &[(usize, String)], site: Option<usize>, trace: &[Frame]) {
+        self.nesting += 1;
+        self.expand_lines(lines, site, trace);
+        self.nesting -= 1;
+    }
+
+    fn expand_lines(&mut self, lines: &[(usize, String)], site: Option<usize>, trace: &[Frame]) {
+        let mut trace = trace.to_vec();
+        let mut i = 0;
+        while i < lines.len() {
+            let (line_no, ref text) = lines[i];
+            let at = site.unwrap_or(line_no);
+            if self.nesting == 1 {
+                self.top_line = line_no;
+            }
+            if self.budget == 0 {
+                // Reported once, where the runaway expansion started.
+                if !self.exhausted {
+                    self.exhausted = true;
+                    let msg = format!("expansion exceeds {} statements (runaway .rept or recursive macro?)", self.limit);
+                    self.out.errors.push(AsmError::new(self.top_line, msg));
+                }
+                return;
+            }
+            self.budget -= 1;
+            if site.is_some()
+                && let Some(frame) = trace.last_mut()
+            {
+                frame.body_line = line_no;
+            }
+            i += 1;
+
+            let word = first_word(text);
+            let args = text[word.len()..].trim();
+            match word {
+                ".macro" => {
+                    // Nested definitions stay in the body and are defined when the
+                    // outer macro expands.
+                    let Some(end) = block_end(lines, i, |w| w == ".macro", ".endm") else {
+                        self.error(at, &trace, "missing .endm for .macro".into());
+                        return;
+                    };
+                    let body = lines[i..end].to_vec();
+                    i = end + 1;
+                    self.define(at, line_no, args, body, &trace);
+                    continue;
+                }
+                w if opens_if(w) => {
+                    let Some(end) = block_end(lines, i, opens_if, ".endif") else {
+                        self.error(at, &trace, format!("missing .endif for {w}"));
+                        return;
+                    };
+                    let taken = self.branch(&lines[i - 1..end], site, &trace);
+                    i = end + 1;
+                    if let Some(body) = taken {
+                        self.run(&body, site, &trace);
+                    }
+                    continue;
+                }
+                w if opens_loop(w) => {
+                    let Some(end) = block_end(lines, i, opens_loop, ".endr") else {
+                        self.error(at, &trace, format!("missing .endr for {w}"));
+                        return;
+                    };
+                    let body = lines[i..end].to_vec();
+                    i = end + 1;
+                    if w == ".rept" {
+                        match eval_const(args, &self.consts) {
+                            Ok(n) if (0..=MAX_REPT).contains(&n) => {
+                                for _ in 0..n {
+                                    if self.exhausted {
+                                        break;
+                                    }
+                                    self.run(&body, site, &trace);
+                                }
+                            }
+                            Ok(n) => self.error(at, &trace, format!(".rept count {n} out of range (0..={MAX_REPT})")),
+                            Err(e) => self.error(at, &trace, format!("invalid .rept count: {e}")),
+                        }
+                    } else {
+                        self.irp(args, &body, at, site, &trace);
+                    }
+                    continue;
+                }
+                ".endm" => {
+                    self.error(at, &trace, ".endm without .macro".into());
+                    continue;
+                }
+                ".else" | ".elseif" | ".endif" => {
+                    self.error(at, &trace, format!("{word} without .if"));
+                    continue;
+                }
+                ".endr" => {
+                    self.error(at, &trace, ".endr without .rept or .irp".into());
+                    continue;
+                }
+                _ => {}
+            }
+
+            // A label may precede a macro call on the same line.
+            let (label, stmt) = match text.split_once(':') {
+                Some((l, rest)) if !l.trim().is_empty() && l.trim().chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')) => {
+                    (Some(l.trim()), rest.trim())
+                }
+                _ => (None, text.as_str()),
+            };
+            if let Some(label) = label {
+                self.symbols.insert(label.to_string());
+            }
+            let name = first_word(stmt);
+            if !self.macros.contains_key(name) {
+                self.note_equate(stmt);
+                self.out.lines.push((at, text.clone()));
+                self.out.origins.push(trace.clone());
+                continue;
+            }
+            if let Some(label) = label {
+                self.out.lines.push((at, format!("{label}:")));
+                self.out.origins.push(trace.clone());
+            }
+            if trace.len() >= MAX_DEPTH {
+                self.error(at, &trace, format!("macro `{name}` nested too deeply (does it call itself?)"));
+                continue;
+            }
+            let args = stmt[name.len()..].trim();
+            let Some(body) = self.instantiate(name, args, at, &trace) else { continue };
+            let mut inner = trace.clone();
+            inner.push(Frame { name: name.to_string(), body_line: 0 });
+            self.run(&body, Some(at), &inner);
+        }
+    }
+
+    /// Remember an equate so later `.if`/`.rept` lines can use it.
+    fn note_equate(&mut self, stmt: &str) {
+        let Some((name, expr)) = parse_equate(stmt) else { return };
+        if name.is_empty() {
+            return;
+        }
+        match eval_const(&expr, &self.consts) {
+            Ok(v) => self.consts.insert(name.clone(), v),
+            Err(_) => self.consts.remove(&name),
+        };
+        self.symbols.insert(name);
+    }
+
+    /// Pick the branch of an `.if` block (opening line through the line before
+    /// `.endif`) whose condition holds, if any.
+    fn branch(&mut self, block: &[(usize, String)], site: Option<usize>, trace: &[Frame]) -> Option<Vec<(usize, String)>> {
+        // Split at `.elseif`/`.else` lines that belong to this `.if`, not a nested one.
+        let mut arms: Vec<(usize, Vec<(usize, String)>)> = vec![(0, Vec::new())];
+        let mut depth = 0;
+        for (idx, (line, text)) in block.iter().enumerate().skip(1) {
+            let word = first_word(text);
+            if opens_if(word) {
+                depth += 1;
+            } else if word == ".endif" {
+                depth -= 1;
+            } else if depth == 0 && matches!(word, ".else" | ".elseif") {
+                if block[arms.last().unwrap().0].1.trim() == ".else" {
+                    self.error(site.unwrap_or(*line), trace, format!("{word} after .else"));
+                    return None;
+                }
+                arms.push((idx, Vec::new()));
+                continue;
+            }
+            arms.last_mut().unwrap().1.push((*line, text.clone()));
+        }
+        for (head, body) in arms {
+            let text = block[head].1.trim();
+            let word = first_word(text);
+            let arg = text[word.len()..].trim();
+            match self.condition(word, arg) {
+                Ok(true) => return Some(body),
+                Ok(false) => {}
+                Err(e) => {
+                    self.error(site.unwrap_or(block[head].0), trace, format!("invalid {word} condition: {e}"));
+                    return None;
+                }
+            }
+        }
+        None
+    }
+
+    fn condition(&self, word: &str, arg: &str) -> Result<bool, String> {
+        let defined = |sym: &str| {
+            if sym.is_empty() {
+                return Err(format!("expected a symbol after {word}"));
+            }
+            Ok(self.symbols.contains(sym) || self.consts.contains_key(sym))
+        };
+        let value = || eval_const(arg, &self.consts);
+        Ok(match word {
+            ".else" => true,
+            ".ifdef" => defined(arg)?,
+            ".ifndef" => !defined(arg)?,
+            ".ifeq" => value()? == 0,
+            ".ifgt" => value()? > 0,
+            ".ifge" => value()? >= 0,
+            ".iflt" => value()? < 0,
+            ".ifle" => value()? <= 0,
+            _ => value()? != 0, // .if, .ifne, .elseif
+        })
+    }
+
+    /// `.irp sym, a, b, ...`: expand the body once per value with `\sym` replaced.
+    fn irp(&mut self, args: &str, body: &[(usize, String)], at: usize, site: Option<usize>, trace: &[Frame]) {
+        let sym = first_word(args);
+        if sym.is_empty() {
+            self.error(at, trace, "expected '.irp symbol, values...'".into());
+            return;
+        }
+        let values = args[sym.len()..].trim_start().trim_start_matches(',');
+        for value in split_args(values) {
+            if self.exhausted {
+                break;
+            }
+            let bound = HashMap::from([(sym, value)]);
+            let lines: Vec<_> = body.iter().map(|(l, text)| (*l, substitute(text, &bound, None))).collect();
+            self.run(&lines, site, trace);
+        }
+    }
+
+    fn define(&mut self, at: usize, line: usize, header: &str, body: Vec<(usize, String)>, trace: &[Frame]) {
+        let name = first_word(header);
+        if name.is_empty() {
+            self.error(at, trace, "missing macro name after .macro".into());
+            return;
+        }
+        if let Some(prev) = self.macros.get(name) {
+            let msg = format!("macro `{name}` already defined at {}", (self.describe)(prev.line));
+            self.error(at, trace, msg);
+            return;
+        }
+        let mut params = Vec::new();
+        for p in header[name.len()..].split([',', ' ', '\t']).map(str::trim).filter(|p| !p.is_empty()) {
+            let (p, default) = match p.split_once('=') {
+                Some((p, d)) => (p.trim(), Some(d.trim().to_string())),
+                None => (p.trim_end_matches(":req"), None),
+            };
+            params.push((p.to_string(), default));
+        }
+        self.macros.insert(name.to_string(), Macro { line, params, body });
+    }
+
+    /// Bind `args` to the macro's parameters and substitute them into its body.
+    fn instantiate(&mut self, name: &str, args: &str, at: usize, trace: &[Frame]) -> Option<Vec<(usize, String)>> {
+        let mac = &self.macros[name];
+        let def_at = (self.describe)(mac.line);
+        let mut values: Vec<Option<String>> = mac.params.iter().map(|(_, d)| d.clone()).collect();
+        let mut positional = 0;
+        for arg in split_args(args) {
+            let named = arg.split_once('=').and_then(|(k, v)| {
+                let k = k.trim();
+                mac.params.iter().position(|(p, _)| p == k).map(|idx| (idx, v.trim()))
+            });
+            let (idx, value) = match named {
+                Some(nv) => nv,
+                None => {
+                    positional += 1;
+                    (positional - 1, arg.as_str())
+                }
+            };
+            if idx >= values.len() {
+                let msg = format!(
+                    "macro `{name}` (defined at {def_at}) takes {} argument(s), got more",
+                    mac.params.len()
+                );
+                self.error(at, trace, msg);
+                return None;
+            }
+            values[idx] = Some(value.to_string());
+        }
+        let mut bound = HashMap::new();
+        for ((param, _), value) in mac.params.iter().zip(values) {
+            let Some(value) = value else {
+                let msg = format!("missing argument `{param}` for macro `{name}` (defined at {def_at})");
+                self.error(at, trace, msg);
+                return None;
+            };
+            bound.insert(param.as_str(), value);
+        }
+        let unique = self.count;
+        self.count += 1;
+        let body = mac.body.iter().map(|(l, text)| (*l, substitute(text, &bound, Some(unique)))).collect();
+        Some(body)
+    }
+}
+
+/// Split call arguments on commas outside quotes and parentheses.
+fn split_args(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let (mut depth, mut quote) = (0i32, None);
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                out.push(cur.trim().to_string());
+                cur.clear();
+                continue;
+            }
+            _ => {}
+        }
+        cur.push(c);
+    }
+    if !cur.trim().is_empty() || !out.is_empty() {
+        out.push(cur.trim().to_string());
+    }
+    out
+}
+
+/// Replace `\param` with its value, `\@` with the expansion counter (outside a
+/// macro it stays as written) and drop `\()`.
+fn substitute(text: &str, bound: &HashMap<&str, String>, unique: Option<usize>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('\\') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let (Some(r), Some(unique)) = (after.strip_prefix('@'), unique) {
+            out.push_str(&unique.to_string());
+            rest = r;
+        } else if let Some(r) = after.strip_prefix("()") {
+            rest = r;
+        } else {
+            let len = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+            match bound.get(&after[..len]) {
+                Some(v) if len > 0 => out.push_str(v),
+                _ => {
+                    // Not a parameter (e.g. `\n` inside a string): keep as written.
+                    out.push('\\');
+                    out.push_str(&after[..len]);
+                }
+            }
+            rest = &after[len..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::utils::preprocess;
+    use super::*;
+
+    fn line(l: usize) -> String {
+        format!("line {}", l + 1)
+    }
+
+    fn texts(src: &str) -> Vec<(usize, String)> {
+        let ex = expand(preprocess(src), &line);
+        assert!(ex.errors.is_empty(), "{:?}", ex.errors);
+        ex.lines
+    }
+
+    #[test]
+    fn substitutes_params_defaults_and_unique_labels() {
+        let src = ".macro inc reg, by=1\n  addi \\reg, \\reg, \\by\n.endm\n.macro spin n\nl\\@: addi \\n, \\n, -1\n  bnez \\n, l\\@\n.endm\ninc t0\ninc t1, by=4\nspin a0\nspin a1\n";
+        let got = texts(src);
+        let want = [
+            (7, "addi t0, t0, 1"),
+            (8, "addi t1, t1, 4"),
+            (9, "l2: addi a0, a0, -1"),
+            (9, "bnez a0, l2"),
+            (10, "l3: addi a1, a1, -1"),
+            (10, "bnez a1, l3"),
+        ];
+        assert_eq!(got, want.map(|(l, t)| (l, t.to_string())));
+    }
+
+    #[test]
+    fn nested_calls_keep_outer_call_site_and_trace() {
+        let src = ".macro two r\n  mv a0, \\r\n  three \\r\n.endm\n.macro three r\n  bogus \\r\n.endm\nstart: two s1\n";
+        let ex = expand(preprocess(src), &line);
+        assert_eq!(ex.lines[0], (7, "start:".to_string()));
+        assert_eq!(ex.lines[2], (7, "bogus s1".to_string()));
+        let e = in_macro(AsmError::new(7, "unsupported mnemonic: bogus"), &ex.origins[2], &line);
+        assert_eq!(e.msg, "unsupported mnemonic: bogus (in macro `three` at line 6, called from `two` at line 3)");
+    }
+
+    #[test]
+    fn reports_definition_and_call_errors() {
+        let ex = expand(preprocess(".macro m a\n nop\n.endm\nm\nm 1, 2\n.macro open\nnop\n"), &line);
+        let msgs: Vec<_> = ex.errors.iter().map(|e| (e.line, e.msg.as_str())).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                (3, "missing argument `a` for macro `m` (defined at line 1)"),
+                (4, "macro `m` (defined at line 1) takes 1 argument(s), got more"),
+                (5, "missing .endm for .macro"),
+            ]
+        );
+        let ex = expand(preprocess(".macro r\n r\n.endm\nr\n"), &line);
+        assert!(ex.errors[0].msg.contains("nested too deeply"));
+
+        // Two calls per level stays under MAX_DEPTH but would expand 2^40 times.
+        let src = ".macro boom n\n.if \\n\nboom \\n-1\nboom \\n-1\n.endif\n.endm\nnop\nboom 40\nnop\n";
+        let ex = expand_limited(preprocess(src), &line, 10_000);
+        let msgs: Vec<_> = ex.errors.iter().map(|e| (e.line, e.msg.as_str())).collect();
+        assert_eq!(msgs, [(7, "expansion exceeds 10000 statements (runaway .rept or recursive macro?)")]);
+    }
+
+    #[test]
+    fn conditionals_nest_and_see_earlier_equates() {
+        let src = ".equ N, 2\n.if N - 2\n  a\n.elseif N\n  .ifndef N\n    b\n  .else\n    c\n  .endif\n.else\n  d\n.endif\n";
+        assert_eq!(texts(src), [(0, ".equ N, 2".to_string()), (7, "c".to_string())]);
+    }
+
+    #[test]
+    fn repetition_works_inside_macros() {
+        let src = ".macro fill n, v\n.rept \\n\n  .byte \\v\n.endr\n.endm\nfill 2, 9\n.irp r, t0, t1\n  mv \\r, zero\n.endr\n";
+        let want = [(5, ".byte 9"), (5, ".byte 9"), (7, "mv t0, zero"), (7, "mv t1, zero")];
+        assert_eq!(texts(src), want.map(|(l, t)| (l, t.to_string())));
+
+        let ex = expand(preprocess(".rept 2\nnop\n.if 1\n"), &line);
+        assert_eq!(ex.errors[0].msg, "missing .endr for .rept");
+
+        // Each count is in range, but together they'd make 2^32 statements.
+        let src = "nop\n.rept 65536\n.rept 65536\nnop\n.endr\n.endr\n";
+        let ex = expand_limited(preprocess(src), &line, 10_000);
+        let msgs: Vec<_> = ex.errors.iter().map(|e| (e.line, e.msg.as_str())).collect();
+        assert_eq!(msgs, [(1, "expansion exceeds 10000 statements (runaway .rept or recursive macro?)")]);
+        assert!(ex.lines.len() < 10_000);
+    }
+}
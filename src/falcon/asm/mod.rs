@@ -1,15 +1,24 @@
 // src/falcon/asm/mod.rs
 mod assembler;
 mod errors;
+mod include;
+mod lint;
+mod listing;
+mod locals;
+mod macros;
 mod program;
 mod pseudo;
 pub(crate) mod utils;
 
+pub use assembler::{assemble_diagnostics, assemble_diagnostics_with, assemble_object, assemble_with};
+#[allow(unused_imports)]
 pub use assembler::assemble;
+pub use include::IncludePaths;
+pub use listing::listing;
 #[allow(unused_imports)]
-pub use errors::AsmError;
+pub use errors::{AsmError, Diagnostic, Severity};
 #[allow(unused_imports)]
-pub use program::Program;
+pub use program::{data_base_after, Program};
 
 #[cfg(test)]
 mod tests;
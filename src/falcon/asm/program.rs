@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::falcon::link::Section;
 
 // Structure returned with code and data
 pub struct Program {
@@ -14,10 +17,35 @@ pub struct Program {
     pub comments: HashMap<u32, String>,
     /// All label names at each instruction address (may be multiple labels on same addr).
     pub labels: HashMap<u32, Vec<String>>,
-    /// Maps 0-based source line → first instruction address emitted from that line.
+    /// Section each label was defined in, by address and name; a label just past the
+    /// end of one section shares its address with the start of the next.
+    pub label_sections: HashMap<(u32, String), Section>,
+    /// Labels exported with `.globl`, with the address each is defined at.
+    pub globals: HashMap<String, u32>,
+    /// Maps 0-based line of the main source → first instruction address emitted from that line.
     pub line_addrs: HashMap<usize, u32>,
-    /// Maps label name → 0-based source line where it is defined.
+    /// Maps 0-based line of the main source → offset in `data` of the first byte emitted from that line.
+    pub data_line_offsets: HashMap<usize, u32>,
+    /// Maps label name → 0-based line of the main source where it is defined.
     pub label_to_line: HashMap<String, usize>,
+    /// Files pulled in with `.include`, in the order first included.
+    pub includes: Vec<PathBuf>,
+    /// Like `line_addrs`, for included files: `(index into includes, line)` → address.
+    pub include_line_addrs: HashMap<(usize, usize), u32>,
+    /// Like `data_line_offsets`, for included files.
+    pub include_data_line_offsets: HashMap<(usize, usize), u32>,
+    /// Like `label_to_line`, for labels defined in included files.
+    pub include_label_lines: HashMap<String, (usize, usize)>,
     /// Block comments (`##! text`) shown above an instruction, keyed by instruction address.
     pub block_comments: HashMap<u32, String>,
 }
+
+/// Where `.data` starts when the text occupies `text_size` bytes from `base_pc`:
+/// the first page boundary after the text, but never below `base_pc + 0x1000`, so
+/// small programs keep the layout they always had. `None` when that is past the
+/// end of the address space.
+pub fn data_base_after(base_pc: u32, text_size: u32) -> Option<u32> {
+    const PAGE: u64 = 0x1000;
+    let after_text = (base_pc as u64 + text_size as u64).next_multiple_of(PAGE);
+    u32::try_from(after_text.max(base_pc as u64 + PAGE)).ok()
+}
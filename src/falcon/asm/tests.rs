@@ -274,3 +274,178 @@ fn char_literal_non_ascii_error() {
     let err = assemble(".text\nli a0, '\u{00e9}'", 0).err().expect("expected error");
     assert!(err.msg.contains("ASCII"), "error should mention ASCII: {}", err.msg);
 }
+
+#[test]
+fn diagnostics_collect_errors_from_both_passes() {
+    let src = ".data\nx: .byte 300\n.equ A, missing\n.text\nfoo t0\naddi t0, t0, 1 # fine\nbeq t0, t1, nowhere\n";
+    let (prog, diags) = assemble_diagnostics(src, 0);
+    assert!(prog.is_none());
+    let lines: Vec<usize> = diags.iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![1, 2, 4, 6]);
+    assert!(diags.iter().all(|d| d.severity == Severity::Error));
+    // `.byte 300` narrows to the bad value
+    assert_eq!(diags[0].cols, 9..12);
+    // The single-error API still reports the first problem found
+    assert_eq!(assemble(src, 0).err().unwrap().line, 1);
+}
+
+#[test]
+fn macros_expand_into_code_and_errors_name_the_macro() {
+    let src = ".macro countdown reg\nl\\@: addi \\reg, \\reg, -1\n  bnez \\reg, l\\@\n.endm\nli t0, 3\ncountdown t0\ncountdown t1\nhalt\n";
+    let prog = assemble(src, 0).expect("assemble");
+    // li + two expansions of two instructions each + halt
+    assert_eq!(prog.text.len(), 6);
+    assert_eq!(prog.line_addrs.get(&5), Some(&4));
+    assert_eq!(prog.line_addrs.get(&6), Some(&12));
+
+    let bad = ".macro load r\n  lw \\r, 0(nowhere)\n.endm\nload t0\n";
+    let (prog, diags) = assemble_diagnostics(bad, 0);
+    assert!(prog.is_none());
+    assert_eq!(diags[0].line, 3);
+    assert!(diags[0].msg.contains("(in macro `load` at line 2)"), "{}", diags[0].msg);
+}
+
+#[test]
+fn conditionals_pick_a_variant_and_rept_builds_tables() {
+    let src = ".equ DEBUG, 1\n.if DEBUG\n  li a0, 1\n  li a0, 2\n.else\n  li a0, 3\n.endif\n.ifdef RELEASE\n  nop\n.endif\nhalt\n.data\n.rept 3\n  .byte 0xAA\n.endr\n.irp v, 7, 8\n  .byte \\v\n.endr\n";
+    let prog = assemble(src, 0).expect("assemble");
+    assert_eq!(prog.text.len(), 3);
+    assert_eq!(&prog.data[..5], &[0xAA, 0xAA, 0xAA, 7, 8]);
+
+    let release = src.replace(".equ DEBUG, 1", ".equ DEBUG, 0");
+    assert_eq!(assemble(&release, 0).expect("assemble").text.len(), 2);
+
+    let (_, diags) = assemble_diagnostics(".if later\nnop\n.endif\n.equ later, 1\n.endr\n", 0);
+    let msgs: Vec<_> = diags.iter().map(|d| (d.line, d.msg.as_str())).collect();
+    assert_eq!(msgs, [
+        (0, "invalid .if condition: symbol not defined before use: later"),
+        (4, ".endr without .rept or .irp"),
+    ]);
+}
+
+#[test]
+fn compiler_output_with_relocations_and_local_labels() {
+    // Trimmed `riscv64-unknown-elf-gcc -march=rv32im -S` output.
+    let src = "\t.option nopic\n\t.section .text.startup,\"ax\",@progbits\n\t.align 2\n\t.globl main\n\t.type main, @function\nmain:\n\tlui a5,%hi(.LC0)\n\taddi a0,a5,%lo(.LC0)\n\tlw a1,%lo(val)(a5)\n.Lpcrel_1:\n\tauipc a2,%pcrel_hi(val+4)\n\tlw a2,%pcrel_lo(.Lpcrel_1)(a2)\n\tli t0,3\n1:\taddi t0,t0,-1\n\tbnez t0,1b\n\tj 1f\n\tnop\n1:\thalt\n\t.size main, .-main\n\t.section .rodata.str1.4,\"aMS\",@progbits,1\n.LC0:\n\t.string \"hi\"\n\t.data\n\t.align 4\nval:\t.word 42, 7\n";
+    let prog = assemble(src, 0).expect("assemble");
+    let (lc0, val) = (0x1000, 0x1004);
+    let enc = |i| encode(i).unwrap();
+    assert_eq!(prog.text[0], enc(Instruction::Lui { rd: 15, imm: 0x1000 }));
+    assert_eq!(prog.text[1], enc(Instruction::Addi { rd: 10, rs1: 15, imm: lc0 & 0xfff }));
+    assert_eq!(prog.text[2], enc(Instruction::Lw { rd: 11, rs1: 15, imm: val & 0xfff }));
+    // auipc at 12: target val+4 = 0x1008, so hi = 0x1000 and lo = 0x1008 - 12 - 0x1000
+    assert_eq!(prog.text[3], enc(Instruction::Auipc { rd: 12, imm: 0x1000 }));
+    assert_eq!(prog.text[4], enc(Instruction::Lw { rd: 12, rs1: 12, imm: 0x1008 - 12 - 0x1000 }));
+    // `1:` at 24; bnez at 28 jumps back 4, j at 32 skips the nop to `1: halt` at 40
+    assert_eq!(prog.text[7], enc(Instruction::Bne { rs1: 5, rs2: 0, imm: -4 }));
+    assert_eq!(prog.text[8], enc(Instruction::Jal { rd: 0, imm: 8 }));
+
+    let err = assemble("j 1f\n", 0).err().unwrap();
+    assert_eq!(err.msg, "no local label `1:` after `1f`");
+    let err = assemble("x: lw a0, %pcrel_lo(x)(a0)\n", 0).err().unwrap();
+    assert!(err.msg.contains("does not label an auipc"), "{}", err.msg);
+}
+
+/// Writes `files` into a fresh temp directory and returns its path.
+fn include_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("raven_inc_{name}_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    for (file, text) in files {
+        std::fs::write(dir.join(file), text).unwrap();
+    }
+    dir.canonicalize().unwrap()
+}
+
+#[test]
+fn include_splices_files_and_keeps_their_lines() {
+    let dir = include_dir("ok", &[
+        ("main.fas", ".include \"util.fas\"\nmain:\n  call double\n  halt\n"),
+        ("lib/util.fas", "# helpers\ndouble:\n  add a0, a0, a0\n  ret\n"),
+    ]);
+    let main = std::fs::read_to_string(dir.join("main.fas")).unwrap();
+    // Not next to main.fas, so only found through the include directories.
+    let paths = IncludePaths { main: Some(dir.join("main.fas")), dirs: vec![dir.join("lib")], ..Default::default() };
+    let prog = assemble_with(&main, 0, &paths).expect("assemble");
+    assert_eq!(prog.includes, vec![dir.join("lib/util.fas")]);
+    assert_eq!(prog.include_label_lines.get("double"), Some(&(0, 1)));
+    assert_eq!(prog.include_line_addrs.get(&(0, 2)), Some(&0));
+    assert_eq!(prog.label_to_line.get("main"), Some(&1));
+    assert_eq!(prog.line_addrs.get(&2), Some(&8));
+
+    let err = assemble_with(&main, 0, &IncludePaths { main: paths.main.clone(), ..Default::default() }).err().unwrap();
+    assert_eq!((err.file, err.line, err.msg.as_str()), (None, 0, "include file not found: util.fas"));
+}
+
+#[test]
+fn include_errors_name_the_file_and_cycles_are_caught() {
+    let dir = include_dir("err", &[
+        ("a.fas", ".include \"b.fas\"\nnop\n"),
+        ("b.fas", "nop\n.include \"a.fas\"\nbogus t0\n"),
+    ]);
+    let main = std::fs::read_to_string(dir.join("a.fas")).unwrap();
+    let paths = IncludePaths { main: Some(dir.join("a.fas")), ..Default::default() };
+    let (prog, diags) = assemble_diagnostics_with(&main, 0, &paths);
+    assert!(prog.is_none());
+    let got: Vec<_> = diags.iter().map(|d| (d.file.clone(), d.line, d.msg.as_str())).collect();
+    assert_eq!(got, vec![
+        (Some(dir.join("b.fas")), 1, "include cycle: a.fas -> b.fas -> a.fas"),
+        (Some(dir.join("b.fas")), 2, "unsupported mnemonic: bogus"),
+    ]);
+}
+
+#[test]
+fn unsaved_include_text_is_read_before_the_disk() {
+    let dir = include_dir("open", &[("main.fas", ".include \"util.fas\"\nhalt\n"), ("util.fas", "nop\n")]);
+    let main = std::fs::read_to_string(dir.join("main.fas")).unwrap();
+    let mut paths = IncludePaths { main: Some(dir.join("main.fas")), ..Default::default() };
+    paths.open.insert(dir.join("util.fas"), "nop\nbogus t0\n".into());
+    let (prog, diags) = assemble_diagnostics_with(&main, 0, &paths);
+    assert!(prog.is_none());
+    assert_eq!((diags[0].file.clone(), diags[0].line), (Some(dir.join("util.fas")), 1));
+}
+
+#[test]
+fn include_in_a_false_conditional_is_not_read() {
+    let dir = include_dir("cond", &[("debug.fas", "li a0, 1\n")]);
+    let src = ".ifdef DEBUG\n.include \"missing.fas\"\n.else\n.include \"debug.fas\"\n.endif\nhalt\n";
+    let paths = IncludePaths { main: Some(dir.join("main.fas")), ..Default::default() };
+    let prog = assemble_with(src, 0, &paths).expect("assemble");
+    assert_eq!(prog.text.len(), 2);
+
+    let err = assemble_with(&format!(".equ DEBUG, 1\n{src}"), 0, &paths).err().unwrap();
+    assert_eq!((err.line, err.msg.as_str()), (2, "include file not found: missing.fas"));
+}
+
+#[test]
+fn word_takes_any_32_bit_value_in_text_and_data() {
+    let prog = assemble("nop\n.word 0xdeadbeef, -1\n.data\n.word 0x80000000\n", 0).expect("assemble");
+    assert_eq!(&prog.text[1..], &[0xdead_beef, 0xffff_ffff]);
+    assert_eq!(prog.data, 0x8000_0000u32.to_le_bytes());
+
+    let err = assemble(".word main\nmain: nop\n", 0).err().unwrap();
+    assert_eq!((err.line, err.msg.as_str()), (0, "invalid .word in .text (numbers only): main"));
+    assert!(assemble(".data\n.word 0x100000000\n", 0).is_err());
+}
+
+#[test]
+fn data_moves_past_text_longer_than_a_page() {
+    // 1025 words of text end at 0x1004, so data starts on the next page.
+    let asm = ".data\nvar: .word 7\n.bss\nbuf: .space 8\n.text\nla t0, var\n.rept 1023\nnop\n.endr\n";
+    let prog = assemble(asm, 0).expect("assemble");
+    assert_eq!(prog.text.len(), 1025);
+    assert_eq!(prog.data_base, 0x2000);
+    assert_eq!(prog.labels[&0x2000], ["var"]);
+    assert_eq!(prog.labels[&0x2004], ["buf"]);
+    assert_eq!(prog.text[0], encode(Instruction::Lui { rd: 5, imm: 0x2000 }).unwrap());
+
+    // Short programs keep data at base_pc + 0x1000.
+    assert_eq!(assemble(".data\nx: .word 1\n.text\nnop", 0x400).unwrap().data_base, 0x1400);
+}
+
+#[test]
+fn data_that_wraps_the_address_space_is_an_error() {
+    let asm = ".text\nnop\n.data\nbig: .word 1\n.bss\nrest: .space 0x1000\n";
+    let err = assemble(asm, 0xFFFF_E000).err().expect("should not fit");
+    assert_eq!(err.line, 3);
+    assert!(err.msg.contains("do not fit"), "{}", err.msg);
+}
@@ -1281,6 +1281,10 @@ impl Bus for CacheController {
             line_data[offset + 3],
         ]))
     }
+
+    fn cycles(&self) -> u64 {
+        self.total_program_cycles()
+    }
 }
 
 impl CacheController {
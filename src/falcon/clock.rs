@@ -0,0 +1,114 @@
+// falcon/clock.rs
+//
+// The time a guest program sees. By default it is virtual: the cycles the run
+// has spent (`CacheController::total_program_cycles`) at a configurable clock
+// frequency, starting at the Unix epoch, plus whatever the program slept. Two
+// runs of the same program see the same times, whatever the host is doing.
+// `--host-time` switches to the host's wall clock instead.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Frequency the virtual clock assumes when none is given.
+pub const DEFAULT_HZ: u64 = 10_000_000;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GuestClock {
+    /// Cycles per second of the simulated core.
+    pub hz: u64,
+    /// Read the host's wall clock instead of counting cycles.
+    pub host: bool,
+    /// Time spent in `nanosleep`, added on top of either source.
+    pub slept_ns: u64,
+}
+
+impl Default for GuestClock {
+    fn default() -> Self {
+        Self { hz: DEFAULT_HZ, host: false, slept_ns: 0 }
+    }
+}
+
+impl GuestClock {
+    /// Nanoseconds since the epoch after `cycles` cycles.
+    pub fn now_ns(&self, cycles: u64) -> u64 {
+        let base = if self.host {
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+        } else {
+            self.cpu_time_ns(cycles)
+        };
+        base.saturating_add(self.slept_ns)
+    }
+
+    /// Nanoseconds the core needs for `cycles`, whatever the clock source:
+    /// what CLOCK_PROCESS_CPUTIME_ID reports.
+    pub fn cpu_time_ns(&self, cycles: u64) -> u64 {
+        (cycles as u128 * NS_PER_SEC as u128 / self.hz.max(1) as u128) as u64
+    }
+
+    /// Move the clock forward; the guest never actually waits.
+    pub fn sleep(&mut self, ns: u64) {
+        self.slept_ns = self.slept_ns.saturating_add(ns);
+    }
+
+    /// `10 MHz virtual` or `host time`, for the status line.
+    pub fn describe(&self) -> String {
+        if self.host {
+            "host time".into()
+        } else {
+            format!("{} virtual", format_hz(self.hz))
+        }
+    }
+}
+
+/// `250000` → `250 kHz`, `10000000` → `10 MHz`.
+pub fn format_hz(hz: u64) -> String {
+    for (unit, scale) in [("GHz", 1_000_000_000), ("MHz", 1_000_000), ("kHz", 1_000)] {
+        if hz >= scale {
+            return if hz.is_multiple_of(scale) {
+                format!("{} {unit}", hz / scale)
+            } else {
+                format!("{:.3} {unit}", hz as f64 / scale as f64)
+            };
+        }
+    }
+    format!("{hz} Hz")
+}
+
+/// Parse `--clock` values: a number of Hz, optionally with a `khz`, `mhz` or
+/// `ghz` suffix (`50mhz`, `1.5ghz`).
+pub fn parse_hz(s: &str) -> Result<u64, String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (num, scale) = if let Some(n) = lower.strip_suffix("ghz") {
+        (n, 1e9)
+    } else if let Some(n) = lower.strip_suffix("mhz") {
+        (n, 1e6)
+    } else if let Some(n) = lower.strip_suffix("khz") {
+        (n, 1e3)
+    } else {
+        (lower.strip_suffix("hz").unwrap_or(&lower), 1.0)
+    };
+    let value: f64 = num.trim().parse().map_err(|_| format!("invalid clock frequency '{s}' (e.g. 10mhz)"))?;
+    let hz = (value * scale).round();
+    if !(1.0..=u64::MAX as f64).contains(&hz) {
+        return Err(format!("clock frequency '{s}' out of range"));
+    }
+    Ok(hz as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtual_time_follows_cycles_and_sleep() {
+        let mut clock = GuestClock { hz: parse_hz("2MHz").unwrap(), ..GuestClock::default() };
+        assert_eq!(clock.now_ns(3_000_000), 1_500_000_000);
+        clock.sleep(250);
+        assert_eq!(clock.now_ns(2), 1_250);
+        assert_eq!(clock.cpu_time_ns(2), 1_000);
+        assert_eq!(parse_hz("1.5ghz"), Ok(1_500_000_000));
+        assert_eq!(parse_hz("440"), Ok(440));
+        assert!(parse_hz("fast").is_err());
+        assert_eq!(format_hz(10_000_000), "10 MHz");
+    }
+}
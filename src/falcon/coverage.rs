@@ -0,0 +1,206 @@
+// falcon/coverage.rs
+//! Source-level coverage built from the simulator's execution counters.
+//!
+//! The Run tab already counts how many times each address executed
+//! (`exec_counts`). Combined with `Program::line_addrs` this tells us which
+//! source lines ran; adding per-branch taken/not-taken counters tells us which
+//! paths were exercised.
+use std::collections::HashMap;
+
+use crate::falcon::decoder::decode;
+use crate::falcon::instruction::Instruction;
+
+/// Taken / not-taken counters for one conditional branch.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct BranchCounts {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+/// Hit count for one source line that emitted code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineCoverage {
+    /// 0-based source line.
+    pub line: usize,
+    /// Times the first instruction of the line executed.
+    pub hits: u64,
+}
+
+/// Outcome counters for one conditional branch, mapped back to its source line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BranchCoverage {
+    /// 0-based source line.
+    pub line: usize,
+    pub addr: u32,
+    pub counts: BranchCounts,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    /// One entry per code-emitting line, sorted by line.
+    pub lines: Vec<LineCoverage>,
+    /// One entry per conditional branch, sorted by address.
+    pub branches: Vec<BranchCoverage>,
+}
+
+/// Returns true when `word` decodes to a conditional branch (B-type).
+pub fn is_branch(word: u32) -> bool {
+    use Instruction::*;
+    matches!(
+        decode(word),
+        Ok(Beq { .. } | Bne { .. } | Blt { .. } | Bge { .. } | Bltu { .. } | Bgeu { .. })
+    )
+}
+
+/// Record the outcome of the instruction that just executed at `pc`.
+/// `next_pc` is the PC after the step; anything other than `pc + 4` counts as taken.
+pub fn record_branch(counts: &mut HashMap<u32, BranchCounts>, pc: u32, word: u32, next_pc: u32) {
+    if !is_branch(word) {
+        return;
+    }
+    let entry = counts.entry(pc).or_default();
+    if next_pc == pc.wrapping_add(4) {
+        entry.not_taken += 1;
+    } else {
+        entry.taken += 1;
+    }
+}
+
+impl Coverage {
+    /// Build coverage for one source file of a program assembled at `base_pc`.
+    ///
+    /// Each line owns the addresses from its first instruction up to the next
+    /// line's first instruction (or the end of `text`), so every word of an
+    /// expanded pseudo-instruction is attributed to the line that produced it.
+    /// `other_files` holds the first addresses of lines in the program's other
+    /// files (`.include`s), where a line's range ends too.
+    pub fn compute(
+        line_addrs: &HashMap<usize, u32>,
+        other_files: &[u32],
+        text: &[u32],
+        base_pc: u32,
+        exec_counts: &HashMap<u32, u64>,
+        branch_counts: &HashMap<u32, BranchCounts>,
+    ) -> Self {
+        let text_end = base_pc.wrapping_add((text.len() as u32).wrapping_mul(4));
+        let mut starts: Vec<(u32, usize)> = line_addrs.iter().map(|(&l, &a)| (a, l)).collect();
+        starts.sort_unstable();
+        let mut bounds: Vec<u32> = starts.iter().map(|&(a, _)| a).chain(other_files.iter().copied()).collect();
+        bounds.sort_unstable();
+
+        let mut cov = Coverage::default();
+        for &(addr, line) in &starts {
+            let end = bounds.get(bounds.partition_point(|&b| b <= addr)).copied().unwrap_or(text_end);
+            cov.lines.push(LineCoverage {
+                line,
+                hits: exec_counts.get(&addr).copied().unwrap_or(0),
+            });
+            let mut a = addr;
+            while a < end {
+                let idx = (a.wrapping_sub(base_pc) / 4) as usize;
+                if text.get(idx).is_some_and(|&w| is_branch(w)) {
+                    cov.branches.push(BranchCoverage {
+                        line,
+                        addr: a,
+                        counts: branch_counts.get(&a).copied().unwrap_or_default(),
+                    });
+                }
+                a = a.wrapping_add(4);
+            }
+        }
+        cov.lines.sort_by_key(|l| l.line);
+        cov
+    }
+
+    /// Hit count per 0-based line (only lines that emitted code are present).
+    pub fn line_hits(&self) -> HashMap<usize, u64> {
+        self.lines.iter().map(|l| (l.line, l.hits)).collect()
+    }
+
+    /// Lines containing a branch that never went one of its two ways.
+    pub fn partial_branch_lines(&self) -> std::collections::HashSet<usize> {
+        self.branches
+            .iter()
+            .filter(|b| b.counts.taken == 0 || b.counts.not_taken == 0)
+            .map(|b| b.line)
+            .collect()
+    }
+
+    /// Render as an LCOV tracefile (`genhtml`, Codecov and most CI tools read it).
+    pub fn to_lcov(&self, source_file: &str) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        let _ = writeln!(out, "TN:");
+        let _ = writeln!(out, "SF:{source_file}");
+
+        // LCOV numbers branches per line; each conditional branch is one block
+        // with two arms: 0 = taken, 1 = not taken.
+        let mut block_on_line: HashMap<usize, usize> = HashMap::new();
+        let mut brh = 0usize;
+        for b in &self.branches {
+            let block = block_on_line.entry(b.line).or_insert(0);
+            let executed = b.counts.taken + b.counts.not_taken > 0;
+            for (arm, n) in [(0, b.counts.taken), (1, b.counts.not_taken)] {
+                let hits = if executed { n.to_string() } else { "-".to_string() };
+                let _ = writeln!(out, "BRDA:{},{},{},{}", b.line + 1, block, arm, hits);
+                if n > 0 {
+                    brh += 1;
+                }
+            }
+            *block += 1;
+        }
+        let _ = writeln!(out, "BRF:{}", self.branches.len() * 2);
+        let _ = writeln!(out, "BRH:{brh}");
+
+        for l in &self.lines {
+            let _ = writeln!(out, "DA:{},{}", l.line + 1, l.hits);
+        }
+        let _ = writeln!(out, "LF:{}", self.lines.len());
+        let _ = writeln!(out, "LH:{}", self.lines.iter().filter(|l| l.hits > 0).count());
+        let _ = writeln!(out, "end_of_record");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::asm::assemble;
+
+    #[test]
+    fn lines_and_branches_map_back_to_source() {
+        let src = ".text\nli t0, 1\nbeqz t0, skip\nli t1, 2\nskip:\nhalt";
+        let prog = assemble(src, 0).expect("assemble");
+        // Simulate: li, beqz (not taken), li, halt
+        let mut exec = HashMap::new();
+        for pc in [0u32, 4, 8, 12] {
+            exec.insert(pc, 1);
+        }
+        let mut br = HashMap::new();
+        record_branch(&mut br, 4, prog.text[1], 8);
+
+        let cov = Coverage::compute(&prog.line_addrs, &[], &prog.text, 0, &exec, &br);
+        assert_eq!(cov.lines.len(), 4);
+        assert!(cov.lines.iter().all(|l| l.hits == 1));
+        assert_eq!(cov.branches.len(), 1);
+        assert_eq!(cov.branches[0].line, 2);
+        assert_eq!(cov.branches[0].counts, BranchCounts { taken: 0, not_taken: 1 });
+        assert!(cov.partial_branch_lines().contains(&2));
+    }
+
+    #[test]
+    fn lcov_output_marks_unexecuted_lines() {
+        let src = ".text\nj end\nnop\nend: halt";
+        let prog = assemble(src, 0).expect("assemble");
+        let mut exec = HashMap::new();
+        exec.insert(0u32, 1);
+        exec.insert(8u32, 1);
+        let cov = Coverage::compute(&prog.line_addrs, &[], &prog.text, 0, &exec, &HashMap::new());
+        let lcov = cov.to_lcov("prog.fas");
+        assert!(lcov.starts_with("TN:\nSF:prog.fas\n"));
+        assert!(lcov.contains("DA:2,1\n"));
+        assert!(lcov.contains("DA:3,0\n"));
+        assert!(lcov.contains("LF:3\nLH:2\n"));
+        assert!(lcov.ends_with("end_of_record\n"));
+    }
+}
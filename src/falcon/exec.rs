@@ -1,7 +1,9 @@
 // falcon/exec.rs
 use crate::falcon::{errors::FalconError, instruction::Instruction, memory::Bus, registers::Cpu};
 
-use crate::falcon::syscall::handle_syscall;
+use crate::falcon::semihost;
+use crate::falcon::strace::{self, Outcome, Ret, SyscallRecord};
+use crate::falcon::syscall::{describe, handle_syscall};
 use crate::ui::Console;
 
 pub fn step<B: Bus>(
@@ -119,7 +121,7 @@ pub fn step<B: Bus>(
         Instruction::Ecall => {
             let old_pc = pc;
             let code = cpu.read(17);
-            let cont = handle_syscall(code, cpu, mem, console)?;
+            let cont = traced_syscall(code, pc, cpu, mem, console)?;
             if !cont && console.reading {
                 cpu.pc = old_pc;
                 return Ok(false);
@@ -131,6 +133,13 @@ pub fn step<B: Bus>(
             console.push_colored(format!("Halt at 0x{pc:08X}"), crate::ui::console::ConsoleColor::Info);
             return Ok(false);
         }
+        Instruction::Ebreak if semihost::is_call(mem, pc) => {
+            let cont = semihost::handle(cpu, mem, console)?;
+            if !cont && console.reading {
+                cpu.pc = pc;
+            }
+            return Ok(cont);
+        }
         Instruction::Ebreak => {
             cpu.ebreak_hit = true;
             console.push_colored(format!("ebreak at 0x{pc:08X}"), crate::ui::console::ConsoleColor::Warning);
@@ -193,6 +202,42 @@ pub fn step<B: Bus>(
     Ok(true)
 }
 
+/// Run the syscall and add it to `cpu.trace`.
+fn traced_syscall<B: Bus>(
+    code: u32,
+    pc: u32,
+    cpu: &mut Cpu,
+    mem: &mut B,
+    console: &mut Console,
+) -> Result<bool, FalconError> {
+    let desc = describe(code, cpu.abi);
+    let regs: Vec<u32> = (10..16).map(|r| cpu.read(r)).collect();
+    let mut args = desc.map(|d| strace::decode_inputs(&d, &regs, mem)).unwrap_or_default();
+    let res = handle_syscall(code, cpu, mem, console);
+    let outcome = match &res {
+        Ok(true) => {
+            let ret = cpu.read(10);
+            if let Some(d) = &desc {
+                strace::decode_outputs(d, &regs, ret, &mut args, mem);
+            }
+            match desc.map(|d| d.ret) {
+                Some(Ret::Errno) => Outcome::Returned(Some(ret)),
+                Some(Ret::Value) => Outcome::Value(ret),
+                _ => Outcome::Returned(None),
+            }
+        }
+        Ok(false) if console.reading => Outcome::Waiting,
+        Ok(false) => match cpu.exit_code {
+            Some(status) => Outcome::Exited(status),
+            None if desc.is_none() => Outcome::Failed("unimplemented".into()),
+            None => Outcome::Failed("stopped".into()),
+        },
+        Err(e) => Outcome::Failed(e.to_string()),
+    };
+    cpu.trace.push(SyscallRecord { pc, number: code, name: desc.map(|d| d.name), args, outcome, blocked: false });
+    res
+}
+
 fn exec_rtype<B: Bus>(
     instr: Instruction,
     cpu: &mut Cpu,
@@ -959,6 +1004,128 @@ mod tests {
         assert_eq!(mem.load8(addr + 2).unwrap(), b'\n');
     }
 
+    #[test]
+    fn linux_openat_reads_guest_file() {
+        let mut cpu = Cpu::default();
+        let mut mem = Ram::new(128);
+        let mut console = crate::ui::Console::default();
+        cpu.fs.insert("in.txt", b"data".to_vec()).unwrap();
+        for (i, b) in b"in.txt\0".iter().enumerate() {
+            mem.store8(64 + i as u32, *b).unwrap();
+        }
+        let ecall = encoder::encode(Instruction::Ecall).unwrap();
+        mem.store32(0, ecall).unwrap();
+        mem.store32(4, ecall).unwrap();
+
+        cpu.write(17, 56); // openat
+        cpu.write(10, (-100i32) as u32); // AT_FDCWD
+        cpu.write(11, 64); // path
+        cpu.write(12, 0); // O_RDONLY
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.read(10), 3);
+
+        cpu.write(17, 63); // read
+        cpu.write(11, 80); // buf
+        cpu.write(12, 16); // count
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.read(10), 4);
+        assert_eq!(mem.load32(80).unwrap(), u32::from_le_bytes(*b"data"));
+    }
+
+    #[test]
+    fn newlib_isatty_and_abort() {
+        let mut cpu = Cpu::default();
+        let mut mem = Ram::new(256);
+        let mut console = crate::ui::Console::default();
+        let ecall = encoder::encode(Instruction::Ecall).unwrap();
+        for pc in (0..12).step_by(4) {
+            mem.store32(pc, ecall).unwrap();
+        }
+
+        // isatty(1) is fstat(1) and a check for S_IFCHR
+        cpu.write(17, 80);
+        cpu.write(10, 1);
+        cpu.write(11, 128);
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.read(10), 0);
+        assert_eq!(mem.load32(128 + 16).unwrap() & 0o170000, 0o020000);
+
+        // abort() is kill(getpid(), SIGABRT)
+        cpu.write(17, 172);
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        cpu.write(11, 6);
+        cpu.write(17, 129);
+        assert!(!step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.exit_code, Some(134));
+    }
+
+    #[test]
+    fn nanosleep_advances_the_virtual_clock() {
+        let mut cpu = Cpu::default();
+        let mut mem = Ram::new(256);
+        let mut console = crate::ui::Console::default();
+        let ecall = encoder::encode(Instruction::Ecall).unwrap();
+        for pc in (0..12).step_by(4) {
+            mem.store32(pc, ecall).unwrap();
+        }
+
+        // nanosleep(&{1 s, 500 ns}, NULL)
+        mem.store32(128, 1).unwrap();
+        mem.store32(132, 500).unwrap();
+        cpu.write(17, 101);
+        cpu.write(10, 128);
+        cpu.write(11, 0);
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.read(10), 0);
+
+        // clock_gettime64(CLOCK_MONOTONIC) sees the sleep; no cycles ran on a bare Ram
+        cpu.write(17, 403);
+        cpu.write(10, 1);
+        cpu.write(11, 160);
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!((mem.load32(160).unwrap(), mem.load32(168).unwrap()), (1, 500));
+
+        // CLOCK_PROCESS_CPUTIME_ID does not
+        cpu.write(17, 113);
+        cpu.write(10, 2);
+        cpu.write(11, 176);
+        assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!((mem.load32(176).unwrap(), mem.load32(180).unwrap()), (0, 0));
+    }
+
+    #[test]
+    fn anonymous_mmap_and_brk_stay_apart() {
+        let mut cpu = Cpu::default();
+        let mut mem = Ram::new(0x10000);
+        let mut console = crate::ui::Console::default();
+        cpu.mmap = crate::falcon::mmap::Mappings::below_stack(0x10000);
+        cpu.heap_break = 0x2000;
+        let ecall = encoder::encode(Instruction::Ecall).unwrap();
+        for pc in (0..24).step_by(4) {
+            mem.store32(pc, ecall).unwrap();
+        }
+        let mut call = |cpu: &mut Cpu, mem: &mut Ram, a7: u32, args: &[u32]| {
+            cpu.write(17, a7);
+            for (i, &a) in args.iter().enumerate() {
+                cpu.write(10 + i as u8, a);
+            }
+            assert!(step(cpu, mem, &mut console).unwrap());
+            cpu.read(10)
+        };
+
+        // mmap(NULL, 5000, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0):
+        // two pages right below the 16 KiB stack reserve
+        let addr = call(&mut cpu, &mut mem, 222, &[0, 5000, 3, 0x22, u32::MAX, 0]);
+        assert_eq!(addr, 0xA000);
+        // brk can't reach the mapping, but can grow below it
+        assert_eq!(call(&mut cpu, &mut mem, 214, &[0xB000]), 0x2000);
+        assert_eq!(call(&mut cpu, &mut mem, 214, &[0x3000]), 0x3000);
+        // File mappings aren't supported; munmap frees the range for brk
+        assert_eq!(call(&mut cpu, &mut mem, 222, &[0, 4096, 1, 0x02, 3, 0]) as i32, -19);
+        assert_eq!(call(&mut cpu, &mut mem, 215, &[addr, 5000]), 0);
+        assert_eq!(call(&mut cpu, &mut mem, 214, &[0xB000]), 0xB000);
+    }
+
     #[test]
     fn linux_getrandom_writes_bytes() {
         let mut cpu = Cpu::default();
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+
+use super::object::{Object, Reloc, RelocKind, Section};
+use crate::falcon::asm::Program;
+use crate::falcon::program::ELF_TEXT_BASE;
+
+const PAGE: u32 = 0x1000;
+
+/// Where the linked sections go. Text starts at `text_base`; data (followed by
+/// bss) at `data_base`, or on the first page boundary after the text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub text_base: u32,
+    pub data_base: Option<u32>,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self { text_base: ELF_TEXT_BASE, data_base: None }
+    }
+}
+
+impl Layout {
+    /// Parse a layout script: one `.text ADDR` or `.data ADDR` per line, `#`
+    /// comments. Addresses may be decimal or `0x` hex and must be word-aligned.
+    pub fn parse(script: &str) -> Result<Layout, String> {
+        let mut layout = Layout::default();
+        for (i, line) in script.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(section), Some(addr), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("line {}: expected `.text ADDR` or `.data ADDR`", i + 1));
+            };
+            let parsed = match addr.strip_prefix("0x").or_else(|| addr.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16),
+                None => addr.replace('_', "").parse(),
+            };
+            let addr = parsed.map_err(|_| format!("line {}: invalid address `{addr}`", i + 1))?;
+            if addr % 4 != 0 {
+                return Err(format!("line {}: address {addr:#x} is not word-aligned", i + 1));
+            }
+            match section {
+                ".text" => layout.text_base = addr,
+                ".data" => layout.data_base = Some(addr),
+                other => return Err(format!("line {}: unknown section `{other}`", i + 1)),
+            }
+        }
+        Ok(layout)
+    }
+}
+
+/// Link `objects` (each with the name used in diagnostics) into one program and
+/// its entry point.
+///
+/// Sections are concatenated in input order. The entry point is the global
+/// `_start`, wherever it landed, or the first instruction of the first object when
+/// no object defines one. Every relocation is resolved against a symbol of its own
+/// object first, then against the globals of all objects. Errors are prefixed with
+/// the object's name, like `ld`'s.
+pub fn link(objects: &[(String, Object)], layout: &Layout) -> Result<(Program, u32), Vec<String>> {
+    let mut errors = Vec::new();
+
+    // Section base of each object.
+    let mut text_at = Vec::with_capacity(objects.len());
+    let mut text_len = 0u32;
+    for (_, obj) in objects {
+        text_at.push(layout.text_base.wrapping_add(text_len));
+        text_len += obj.text.len() as u32 * 4;
+    }
+    let text_end = layout.text_base as u64 + text_len as u64;
+    let data_base = layout.data_base.unwrap_or_else(|| (text_end as u32).next_multiple_of(PAGE));
+    let mut data_at = Vec::with_capacity(objects.len());
+    let mut data_len = 0u32;
+    for (_, obj) in objects {
+        data_at.push(data_base.wrapping_add(data_len));
+        data_len = (data_len + obj.data.len() as u32).next_multiple_of(4);
+    }
+    let mut bss_at = Vec::with_capacity(objects.len());
+    let mut bss_len = 0u32;
+    for (_, obj) in objects {
+        bss_at.push(data_base.wrapping_add(data_len + bss_len));
+        bss_len = (bss_len + obj.bss_size).next_multiple_of(4);
+    }
+    let data_end = data_base as u64 + data_len as u64 + bss_len as u64;
+    if text_end > u32::MAX as u64 + 1 || data_end > u32::MAX as u64 + 1 {
+        errors.push("sections extend past the end of the address space".to_string());
+    } else if layout.text_base as u64 <= data_base as u64 && (data_base as u64) < text_end
+        || data_base as u64 <= layout.text_base as u64 && (layout.text_base as u64) < data_end && text_len > 0
+    {
+        errors.push(format!(
+            "text {:#x}..{text_end:#x} overlaps data {data_base:#x}..{data_end:#x}",
+            layout.text_base
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let address = |i: usize, section: Section, value: u32| {
+        let base = match section {
+            Section::Text => text_at[i],
+            Section::Data => data_at[i],
+            Section::Bss => bss_at[i],
+        };
+        base.wrapping_add(value)
+    };
+
+    // Global symbols across objects, and every object's own definitions.
+    let mut globals: HashMap<&str, (u32, usize)> = HashMap::new();
+    let mut own: Vec<HashMap<&str, (u32, Section)>> = Vec::with_capacity(objects.len());
+    for (i, (name, obj)) in objects.iter().enumerate() {
+        let mut defined = HashMap::new();
+        for sym in &obj.symbols {
+            let Some(section) = sym.section else { continue };
+            let addr = address(i, section, sym.value);
+            defined.insert(sym.name.as_str(), (addr, section));
+            if sym.global {
+                if let Some(&(_, first)) = globals.get(sym.name.as_str()) {
+                    errors.push(format!(
+                        "{name}: multiple definition of `{}`; first defined in {}",
+                        sym.name, objects[first].0
+                    ));
+                } else {
+                    globals.insert(&sym.name, (addr, i));
+                }
+            }
+        }
+        own.push(defined);
+    }
+
+    let mut text: Vec<u32> = objects.iter().flat_map(|(_, o)| o.text.iter().copied()).collect();
+    let mut data = vec![0u8; data_len as usize];
+    for (i, (_, obj)) in objects.iter().enumerate() {
+        let at = (data_at[i] - data_base) as usize;
+        data[at..at + obj.data.len()].copy_from_slice(&obj.data);
+    }
+
+    for (i, (name, obj)) in objects.iter().enumerate() {
+        let resolve = |symbol: &str| own[i].get(symbol).map(|d| d.0).or_else(|| globals.get(symbol).map(|g| g.0));
+        // A `%pcrel_lo` names the `auipc` whose `%pcrel_hi` it completes: the
+        // offsets those computed, by the `auipc`'s address.
+        let pcrel_hi: HashMap<u32, u32> = obj
+            .relocs
+            .iter()
+            .filter(|r| r.kind == RelocKind::PcrelHi20)
+            .filter_map(|r| {
+                let place = address(i, r.section, r.offset);
+                Some((place, resolve(&r.symbol)?.wrapping_add(r.addend as u32).wrapping_sub(place)))
+            })
+            .collect();
+        for r in &obj.relocs {
+            let Some(target) = resolve(&r.symbol) else {
+                errors.push(format!("{name}: undefined reference to `{}`", r.symbol));
+                continue;
+            };
+            let value = target.wrapping_add(r.addend as u32);
+            let place = address(i, r.section, r.offset);
+            let result = match r.section {
+                Section::Text => {
+                    let idx = (place.wrapping_sub(layout.text_base) / 4) as usize;
+                    let value = match r.kind {
+                        RelocKind::PcrelLo12I | RelocKind::PcrelLo12S => pcrel_hi.get(&value).copied(),
+                        _ => Some(value),
+                    };
+                    match value {
+                        Some(value) => patch_text(&mut text, idx, r, value, place),
+                        None => Err(format!("no %pcrel_hi at {target:#x} for the %pcrel_lo at {:#x}", r.offset)),
+                    }
+                }
+                _ => {
+                    let at = place.wrapping_sub(data_base) as usize;
+                    match (r.kind, data.get_mut(at..at.saturating_add(4))) {
+                        (RelocKind::Abs32, Some(field)) => {
+                            field.copy_from_slice(&value.to_le_bytes());
+                            Ok(())
+                        }
+                        (RelocKind::Abs32, None) => Err(format!("relocation at {:#x} is outside .data", r.offset)),
+                        (kind, _) => Err(format!("{kind:?} relocation in .data")),
+                    }
+                }
+            };
+            if let Err(msg) = result {
+                errors.push(format!("{name}: {msg} (`{}`)", r.symbol));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // Output symbols, from each object's definitions: locals of the same name in
+    // two objects are two labels. Section symbols and `.L*` temporaries only
+    // served the relocations above.
+    let mut labels: HashMap<u32, Vec<String>> = HashMap::new();
+    let mut label_sections = HashMap::new();
+    for (i, (_, obj)) in objects.iter().enumerate() {
+        for sym in obj.symbols.iter().filter(|s| !s.is_internal()) {
+            if let Some(&(addr, section)) = own[i].get(sym.name.as_str()) {
+                labels.entry(addr).or_default().push(sym.name.clone());
+                label_sections.insert((addr, sym.name.clone()), section);
+            }
+        }
+    }
+    for names in labels.values_mut() {
+        names.sort();
+        names.dedup();
+    }
+
+    let entry = globals.get("_start").map_or(layout.text_base, |&(addr, _)| addr);
+    let globals = globals.into_iter().map(|(name, (addr, _))| (name.to_string(), addr)).collect();
+    let prog = Program {
+        text,
+        data,
+        data_base,
+        bss_size: bss_len,
+        comments: HashMap::new(),
+        labels,
+        label_sections,
+        globals,
+        line_addrs: HashMap::new(),
+        data_line_offsets: HashMap::new(),
+        label_to_line: HashMap::new(),
+        includes: Vec::new(),
+        include_line_addrs: HashMap::new(),
+        include_data_line_offsets: HashMap::new(),
+        include_label_lines: HashMap::new(),
+        block_comments: HashMap::new(),
+    };
+    Ok((prog, entry))
+}
+
+/// Apply `r` to the instruction at `text[idx]` (two for a `call`), found at `place`.
+fn patch_text(text: &mut [u32], idx: usize, r: &Reloc, value: u32, place: u32) -> Result<(), String> {
+    let outside = || format!("relocation at {:#x} is outside .text", r.offset);
+    if r.kind == RelocKind::Call {
+        let (Some(&auipc), Some(&jalr)) = (text.get(idx), text.get(idx + 1)) else { return Err(outside()) };
+        text[idx] = patch(auipc, RelocKind::PcrelHi20, value, place)?;
+        text[idx + 1] = patch(jalr, RelocKind::Lo12I, value.wrapping_sub(place), place)?;
+        return Ok(());
+    }
+    let Some(&word) = text.get(idx) else { return Err(outside()) };
+    text[idx] = patch(word, r.kind, value, place)?;
+    Ok(())
+}
+
+/// Put `value` (the symbol's address plus addend; for `%pcrel_lo`, the offset its
+/// `auipc` computed) into the instruction `word` at `place`.
+fn patch(word: u32, kind: RelocKind, value: u32, place: u32) -> Result<u32, String> {
+    let rel = value.wrapping_sub(place) as i32;
+    Ok(match kind {
+        RelocKind::Hi20 => (word & 0xfff) | (value.wrapping_add(0x800) & 0xffff_f000),
+        RelocKind::PcrelHi20 => (word & 0xfff) | ((rel as u32).wrapping_add(0x800) & 0xffff_f000),
+        RelocKind::Lo12I | RelocKind::PcrelLo12I => (word & 0x000f_ffff) | ((value & 0xfff) << 20),
+        RelocKind::Lo12S | RelocKind::PcrelLo12S => {
+            let lo = value & 0xfff;
+            (word & 0x01ff_f07f) | ((lo >> 5) << 25) | ((lo & 0x1f) << 7)
+        }
+        RelocKind::Branch => {
+            if rel % 2 != 0 || !(-4096..4096).contains(&rel) {
+                return Err(format!("branch target out of range ({rel} bytes)"));
+            }
+            let imm = rel as u32;
+            (word & 0x01ff_f07f)
+                | (((imm >> 12) & 1) << 31)
+                | (((imm >> 5) & 0x3f) << 25)
+                | (((imm >> 1) & 0xf) << 8)
+                | (((imm >> 11) & 1) << 7)
+        }
+        RelocKind::Jal => {
+            if rel % 2 != 0 || !(-(1 << 20)..(1 << 20)).contains(&rel) {
+                return Err(format!("jump target out of range ({rel} bytes)"));
+            }
+            let imm = rel as u32;
+            (word & 0xfff)
+                | (((imm >> 20) & 1) << 31)
+                | (((imm >> 1) & 0x3ff) << 21)
+                | (((imm >> 11) & 1) << 20)
+                | (((imm >> 12) & 0xff) << 12)
+        }
+        RelocKind::Abs32 => return Err("32-bit relocation in .text".into()),
+        RelocKind::Call => return Err("call relocation needs an auipc/jalr pair".into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::asm::assemble_object;
+    use crate::falcon::decoder::decode;
+    use crate::falcon::instruction::Instruction;
+    use crate::falcon::link::{Reloc, Symbol};
+
+    fn object(src: &str) -> Object {
+        match assemble_object(src, &Default::default()) {
+            Ok(obj) => obj,
+            Err(errs) => panic!("{errs:?}"),
+        }
+    }
+
+    fn named(objs: &[(&str, &str)]) -> Vec<(String, Object)> {
+        objs.iter().map(|(n, src)| (n.to_string(), object(src))).collect()
+    }
+
+    fn link_errors(objs: &[(&str, &str)], layout: &Layout) -> Vec<String> {
+        match link(&named(objs), layout) {
+            Ok(_) => panic!("linked"),
+            Err(errs) => errs,
+        }
+    }
+
+    const MAIN: &str = "
+        .text
+        .globl _start
+_start: la a0, value
+        lw a0, 0(a0)
+        jal ra, double
+        beqz a0, done
+        lui t0, %hi(table)
+        sw a0, %lo(table)(t0)
+done:   li a7, 93
+        ecall
+";
+    const LIB: &str = "
+        .globl double, value, table
+        .text
+double: add a0, a0, a0
+        ret
+        .data
+value:  .word 21
+table:  .word double, value
+";
+
+    #[test]
+    fn cross_module_references_are_patched() {
+        let objs = named(&[("main.o", MAIN), ("lib.o", LIB)]);
+        let main_len = objs[0].1.text.len() as u32 * 4;
+        let (prog, _) = link(&objs, &Layout::default()).expect("link");
+        let base = ELF_TEXT_BASE;
+        let double = base + main_len;
+        assert_eq!(prog.data_base, (double + 8).next_multiple_of(PAGE));
+        let (value, table) = (prog.data_base, prog.data_base + 4);
+        assert_eq!(prog.labels[&double], ["double"]);
+        assert_eq!(prog.labels[&value], ["value"]);
+
+        // la a0, value
+        let (Ok(Instruction::Lui { imm: hi, .. }), Ok(Instruction::Addi { imm: lo, .. })) =
+            (decode(prog.text[0]), decode(prog.text[1]))
+        else {
+            panic!("la did not become lui+addi")
+        };
+        assert_eq!(hi.wrapping_add(lo) as u32, value);
+        // jal ra, double
+        let jal_at = base + 12;
+        assert!(matches!(decode(prog.text[3]), Ok(Instruction::Jal { rd: 1, imm }) if jal_at.wrapping_add(imm as u32) == double));
+        // sw a0, %lo(table)(t0) after lui t0, %hi(table)
+        let (Ok(Instruction::Lui { imm: hi, .. }), Ok(Instruction::Sw { imm: lo, .. })) =
+            (decode(prog.text[5]), decode(prog.text[6]))
+        else {
+            panic!("%hi/%lo pair did not assemble")
+        };
+        assert_eq!(hi.wrapping_add(lo) as u32, table);
+        // .word double, value
+        let word = |at: usize| u32::from_le_bytes(prog.data[at..at + 4].try_into().unwrap());
+        assert_eq!((word(4), word(8)), (double, value));
+    }
+
+    #[test]
+    fn entry_is_start_in_either_input_order() {
+        let (prog, entry) = link(&named(&[("main.o", MAIN), ("lib.o", LIB)]), &Layout::default()).unwrap();
+        assert_eq!((entry, &prog.labels[&entry][..]), (ELF_TEXT_BASE, &["_start".to_string()][..]));
+        let (prog, entry) = link(&named(&[("lib.o", LIB), ("main.o", MAIN)]), &Layout::default()).unwrap();
+        assert_eq!(entry, ELF_TEXT_BASE + 8);
+        assert_eq!(prog.labels[&entry], ["_start"]);
+        // Without `_start`, execution begins at the first word of text.
+        let (_, entry) = link(&named(&[("lib.o", LIB)]), &Layout::default()).unwrap();
+        assert_eq!(entry, ELF_TEXT_BASE);
+    }
+
+    #[test]
+    fn objects_survive_the_file_format() {
+        let objs: Vec<_> = named(&[("main.o", MAIN), ("lib.o", LIB)])
+            .into_iter()
+            .map(|(n, o)| (n, Object::from_elf(&o.to_elf()).unwrap()))
+            .collect();
+        let (direct, _) = link(&named(&[("main.o", MAIN), ("lib.o", LIB)]), &Layout::default()).unwrap();
+        let (via_files, _) = link(&objs, &Layout::default()).unwrap();
+        assert_eq!((direct.text, direct.data), (via_files.text, via_files.data));
+    }
+
+    #[test]
+    fn gnu_as_call_and_pcrel_pairs_are_patched() {
+        // What GNU as emits for `call double; lw a0, value; sw a0, table+4, t0`
+        let reloc = |offset, kind, symbol: &str, addend| Reloc { section: Section::Text, offset, kind, symbol: symbol.into(), addend };
+        let local = |name: &str, value| Symbol { name: name.into(), section: Some(Section::Text), value, global: false };
+        let gnu = Object {
+            text: vec![0x0000_0097, 0x0000_80e7, 0x0000_0517, 0x0005_2503, 0x0000_0297, 0x00a2_a023],
+            symbols: ["double", "value", "table"]
+                .map(|name| Symbol { name: name.into(), section: None, value: 0, global: true })
+                .into_iter()
+                .chain([local(".L0 ", 8), local(".L1 ", 16)])
+                .collect(),
+            relocs: vec![
+                reloc(0, RelocKind::Call, "double", 0),
+                reloc(8, RelocKind::PcrelHi20, "value", 0),
+                reloc(12, RelocKind::PcrelLo12I, ".L0 ", 0),
+                reloc(16, RelocKind::PcrelHi20, "table", 4),
+                reloc(20, RelocKind::PcrelLo12S, ".L1 ", 0),
+            ],
+            ..Default::default()
+        };
+        let gnu = Object::from_elf(&gnu.to_elf()).unwrap();
+        let objs = vec![("gnu.o".to_string(), gnu), ("lib.o".to_string(), object(LIB))];
+        let (prog, _) = link(&objs, &Layout::default()).expect("link");
+        let base = ELF_TEXT_BASE;
+        let (double, value) = (base + 24, prog.data_base);
+        let pair = |at: usize| match (decode(prog.text[at]), decode(prog.text[at + 1])) {
+            (Ok(Instruction::Auipc { imm: hi, .. }), Ok(Instruction::Jalr { imm: lo, .. } | Instruction::Lw { imm: lo, .. } | Instruction::Sw { imm: lo, .. })) => {
+                (base + at as u32 * 4).wrapping_add(hi.wrapping_add(lo) as u32)
+            }
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(pair(0), double);
+        assert_eq!(pair(2), value);
+        assert_eq!(pair(4), value + 8);
+    }
+
+    #[test]
+    fn locals_stay_private_and_globals_must_be_unique() {
+        let a = object(".globl f\nf: j helper\nhelper: ret\n");
+        assert!(a.symbols.contains(&Symbol { name: "helper".into(), section: Some(Section::Text), value: 4, global: false }));
+
+        let errs = link_errors(&[("a.o", ".globl f\nf: ret\n"), ("b.o", ".globl f\nf: ret\n")], &Layout::default());
+        assert_eq!(errs, ["b.o: multiple definition of `f`; first defined in a.o"]);
+
+        // A local `helper` in b.o does not satisfy a.o's reference.
+        let errs = link_errors(&[("a.o", "call helper\n"), ("b.o", "helper: ret\n")], &Layout::default());
+        assert_eq!(errs, ["a.o: undefined reference to `helper`"]);
+    }
+
+    #[test]
+    fn output_symbols_skip_section_symbols_and_keep_each_objects_locals() {
+        let mut a = object("loop: j loop\n");
+        let internal = |name: &str| Symbol { name: name.into(), section: Some(Section::Text), value: 0, global: false };
+        a.symbols.extend([internal(".text"), internal(".Ltmp0")]);
+        let objs = vec![("a.o".to_string(), a), ("b.o".to_string(), object(".data\nloop: .word 0\n"))];
+        let (prog, entry) = link(&objs, &Layout::default()).expect("link");
+        assert_eq!(prog.labels[&entry], ["loop"]);
+        assert_eq!(prog.labels[&prog.data_base], ["loop"]);
+        assert_eq!(prog.label_sections[&(entry, "loop".to_string())], Section::Text);
+        assert_eq!(prog.label_sections[&(prog.data_base, "loop".to_string())], Section::Data);
+
+        let elf = crate::falcon::program::write_elf(&prog, ELF_TEXT_BASE, entry);
+        let info = crate::falcon::program::inspect_elf(&elf).expect("inspect_elf");
+        let syms: Vec<_> = info.symbol_table.iter().map(|s| (s.name.as_str(), s.binding, s.func)).collect();
+        let local = crate::falcon::program::SymbolBinding::Local;
+        assert_eq!(syms, [("loop", local, true), ("loop", local, false)]);
+    }
+
+    #[test]
+    fn layout_scripts_place_sections() {
+        let layout = Layout::parse("# ROM and RAM\n.text 0x8000_0000\n.data 0x2000 # low RAM\n").unwrap();
+        assert_eq!(layout, Layout { text_base: 0x8000_0000, data_base: Some(0x2000) });
+        let (prog, _) = link(&named(&[("lib.o", LIB)]), &layout).unwrap();
+        assert_eq!(prog.data_base, 0x2000);
+        assert!(prog.labels[&0x8000_0000].contains(&"double".to_string()));
+
+        assert_eq!(Layout::parse(".stack 0x100").unwrap_err(), "line 1: unknown section `.stack`");
+        let overlap = Layout { text_base: 0x1000, data_base: Some(0x1004) };
+        assert!(link_errors(&[("lib.o", LIB)], &overlap)[0].contains("overlaps"));
+    }
+}
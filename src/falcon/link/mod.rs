@@ -0,0 +1,10 @@
+// src/falcon/link/mod.rs
+//
+// Separate compilation: `asm::assemble_object` turns one module into a relocatable
+// `Object` (saved as an ELF32 `ET_REL` file), and `link` places several objects,
+// resolves their symbols and patches the relocated fields into one `Program`.
+mod linker;
+mod object;
+
+pub use linker::{link, Layout};
+pub use object::{Object, Reloc, RelocKind, Section, Symbol};
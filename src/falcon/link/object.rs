@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+
+/// Section a symbol is defined in or a relocation applies to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Section {
+    Text,
+    Data,
+    Bss,
+}
+
+/// A label in an object file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// `None` for a symbol the module uses but does not define.
+    pub section: Option<Section>,
+    /// Offset from the start of `section`.
+    pub value: u32,
+    /// Declared with `.globl` (or undefined): visible to other objects.
+    pub global: bool,
+}
+
+impl Symbol {
+    /// Whether the symbol only exists for relocations to name: a section symbol,
+    /// which [`Object::from_elf`] names after its section, or an assembler
+    /// temporary (`.L*`). Linked programs leave these out of their symbol table.
+    pub fn is_internal(&self) -> bool {
+        self.name.starts_with(".L") || section_for(&self.name).is_some()
+    }
+}
+
+/// How a relocated field is encoded; the RISC-V psABI relocation of the same name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelocKind {
+    /// `.word sym`: the full 32-bit address.
+    Abs32,
+    /// B-type branch offset, PC-relative.
+    Branch,
+    /// J-type `jal` offset, PC-relative.
+    Jal,
+    /// Upper 20 bits of the address (`lui`, first half of `la`, `%hi`).
+    Hi20,
+    /// Low 12 bits in an I-type immediate (`addi`, loads, `%lo`).
+    Lo12I,
+    /// Low 12 bits in an S-type immediate (stores with `%lo`).
+    Lo12S,
+    /// `auipc` + `jalr` pair of a `call`/`tail`, PC-relative (`R_RISCV_CALL`/`CALL_PLT`).
+    Call,
+    /// Upper 20 bits of a PC-relative offset (`auipc`, `%pcrel_hi`).
+    PcrelHi20,
+    /// Low 12 bits, I-type, of the offset computed by the `PcrelHi20` at the symbol.
+    PcrelLo12I,
+    /// Low 12 bits, S-type, of the offset computed by the `PcrelHi20` at the symbol.
+    PcrelLo12S,
+}
+
+impl RelocKind {
+    fn elf_type(self) -> u32 {
+        match self {
+            RelocKind::Abs32 => 1,       // R_RISCV_32
+            RelocKind::Branch => 16,     // R_RISCV_BRANCH
+            RelocKind::Jal => 17,        // R_RISCV_JAL
+            RelocKind::Call => 19,       // R_RISCV_CALL_PLT
+            RelocKind::PcrelHi20 => 23,  // R_RISCV_PCREL_HI20
+            RelocKind::PcrelLo12I => 24, // R_RISCV_PCREL_LO12_I
+            RelocKind::PcrelLo12S => 25, // R_RISCV_PCREL_LO12_S
+            RelocKind::Hi20 => 26,       // R_RISCV_HI20
+            RelocKind::Lo12I => 27,      // R_RISCV_LO12_I
+            RelocKind::Lo12S => 28,      // R_RISCV_LO12_S
+        }
+    }
+
+    fn from_elf_type(ty: u32) -> Option<Self> {
+        Some(match ty {
+            1 => RelocKind::Abs32,
+            16 => RelocKind::Branch,
+            17 => RelocKind::Jal,
+            18 | 19 => RelocKind::Call,
+            23 => RelocKind::PcrelHi20,
+            24 => RelocKind::PcrelLo12I,
+            25 => RelocKind::PcrelLo12S,
+            26 => RelocKind::Hi20,
+            27 => RelocKind::Lo12I,
+            28 => RelocKind::Lo12S,
+            _ => return None,
+        })
+    }
+}
+
+/// A field the linker fills in once `symbol`'s address is known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reloc {
+    /// `Text` or `Data`.
+    pub section: Section,
+    /// Byte offset of the instruction or word within `section`.
+    pub offset: u32,
+    pub kind: RelocKind,
+    pub symbol: String,
+    pub addend: i32,
+}
+
+/// One assembled module before linking: section contents at offset 0, its symbols
+/// and the places that depend on where symbols end up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    pub text: Vec<u32>,
+    pub data: Vec<u8>,
+    pub bss_size: u32,
+    pub symbols: Vec<Symbol>,
+    pub relocs: Vec<Reloc>,
+}
+
+// Section header indices in the files `to_elf` writes.
+const SHN_TEXT: u16 = 1;
+const SHN_DATA: u16 = 2;
+const SHN_BSS: u16 = 3;
+const SH_SYMTAB: u32 = 4;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+const SHF_INFO_LINK: u32 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+/// `R_RISCV_RELAX` only marks a pair the linker may shorten; it needs no patching.
+const R_RISCV_RELAX: u32 = 51;
+/// `R_RISCV_ALIGN` marks `.align` padding a relaxing linker may shrink; without
+/// relaxation the padding is already right.
+const R_RISCV_ALIGN: u32 = 43;
+
+/// Which of our sections an input section joins, by name (`.text.main`,
+/// `.rodata.str1.1`, `.sbss`, ...). `None` for sections that aren't loaded.
+fn section_for(name: &str) -> Option<Section> {
+    let family = |base: &str| name == base || name.strip_prefix(base).is_some_and(|rest| rest.starts_with('.'));
+    if family(".text") {
+        Some(Section::Text)
+    } else if [".data", ".rodata", ".sdata", ".srodata"].into_iter().any(family) {
+        Some(Section::Data)
+    } else if [".bss", ".sbss"].into_iter().any(family) {
+        Some(Section::Bss)
+    } else {
+        None
+    }
+}
+
+fn add_str(table: &mut Vec<u8>, s: &str) -> u32 {
+    let off = table.len() as u32;
+    table.extend_from_slice(s.as_bytes());
+    table.push(0);
+    off
+}
+
+fn align4(out: &mut Vec<u8>) {
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+impl Object {
+    /// Serialise as an ELF32 little-endian RISC-V relocatable file (`ET_REL`) with
+    /// `.text`, `.data`, `.bss`, `.symtab` and `.rela.text` / `.rela.data`.
+    pub fn to_elf(&self) -> Vec<u8> {
+        // Locals first, as ELF requires; `sh_info` of `.symtab` is the first global.
+        let mut order: Vec<&Symbol> = self.symbols.iter().filter(|s| !s.global).collect();
+        let first_global = order.len() + 1;
+        order.extend(self.symbols.iter().filter(|s| s.global));
+        let index: HashMap<&str, u32> = order.iter().enumerate().map(|(i, s)| (s.name.as_str(), i as u32 + 1)).collect();
+
+        let mut strtab = vec![0u8];
+        let mut symtab = vec![0u8; 16];
+        for sym in &order {
+            let (shndx, kind) = match sym.section {
+                Some(Section::Text) => (SHN_TEXT, STT_FUNC),
+                Some(Section::Data) => (SHN_DATA, STT_OBJECT),
+                Some(Section::Bss) => (SHN_BSS, STT_OBJECT),
+                None => (0, STT_NOTYPE),
+            };
+            let bind = if sym.global { STB_GLOBAL } else { STB_LOCAL };
+            symtab.extend_from_slice(&add_str(&mut strtab, &sym.name).to_le_bytes());
+            symtab.extend_from_slice(&sym.value.to_le_bytes());
+            symtab.extend_from_slice(&0u32.to_le_bytes());
+            symtab.push((bind << 4) | kind);
+            symtab.push(0);
+            symtab.extend_from_slice(&shndx.to_le_bytes());
+        }
+        let rela = |section: Section| -> Vec<u8> {
+            let mut out = Vec::new();
+            for r in self.relocs.iter().filter(|r| r.section == section) {
+                let sym = index.get(r.symbol.as_str()).copied().unwrap_or(0);
+                out.extend_from_slice(&r.offset.to_le_bytes());
+                out.extend_from_slice(&((sym << 8) | r.kind.elf_type()).to_le_bytes());
+                out.extend_from_slice(&r.addend.to_le_bytes());
+            }
+            out
+        };
+        let (rela_text, rela_data) = (rela(Section::Text), rela(Section::Data));
+
+        let mut shstrtab = vec![0u8];
+        let names = [".text", ".data", ".bss", ".symtab", ".strtab", ".rela.text", ".rela.data", ".shstrtab"]
+            .map(|n| add_str(&mut shstrtab, n));
+
+        let mut out = vec![0u8; 52];
+        let place = |out: &mut Vec<u8>, bytes: &[u8]| {
+            align4(out);
+            let off = out.len();
+            out.extend_from_slice(bytes);
+            off
+        };
+        let text: Vec<u8> = self.text.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let text_off = place(&mut out, &text);
+        let data_off = place(&mut out, &self.data);
+        let symtab_off = place(&mut out, &symtab);
+        let strtab_off = place(&mut out, &strtab);
+        let rela_text_off = place(&mut out, &rela_text);
+        let rela_data_off = place(&mut out, &rela_data);
+        let shstrtab_off = place(&mut out, &shstrtab);
+        align4(&mut out);
+        let shoff = out.len();
+
+        // name, type, flags, addr, offset, size, link, info, align, entsize
+        let headers: [[u32; 10]; 9] = [
+            [0; 10],
+            [names[0], SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, 0, text_off as u32, text.len() as u32, 0, 0, 4, 0],
+            [names[1], SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, 0, data_off as u32, self.data.len() as u32, 0, 0, 4, 0],
+            [names[2], SHT_NOBITS, SHF_ALLOC | SHF_WRITE, 0, data_off as u32, self.bss_size, 0, 0, 4, 0],
+            [names[3], SHT_SYMTAB, 0, 0, symtab_off as u32, symtab.len() as u32, 5, first_global as u32, 4, 16],
+            [names[4], SHT_STRTAB, 0, 0, strtab_off as u32, strtab.len() as u32, 0, 0, 1, 0],
+            [names[5], SHT_RELA, SHF_INFO_LINK, 0, rela_text_off as u32, rela_text.len() as u32, SH_SYMTAB, SHN_TEXT as u32, 4, 12],
+            [names[6], SHT_RELA, SHF_INFO_LINK, 0, rela_data_off as u32, rela_data.len() as u32, SH_SYMTAB, SHN_DATA as u32, 4, 12],
+            [names[7], SHT_STRTAB, 0, 0, shstrtab_off as u32, shstrtab.len() as u32, 0, 0, 1, 0],
+        ];
+        for h in headers {
+            for v in h {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+
+        let mut eh = Vec::with_capacity(52);
+        eh.extend_from_slice(b"\x7fELF");
+        eh.extend_from_slice(&[1, 1, 1, 0]); // ELFCLASS32, ELFDATA2LSB, EV_CURRENT, ELFOSABI_SYSV
+        eh.extend_from_slice(&[0; 8]);
+        eh.extend_from_slice(&1u16.to_le_bytes()); // ET_REL
+        eh.extend_from_slice(&0xF3u16.to_le_bytes()); // EM_RISCV
+        eh.extend_from_slice(&1u32.to_le_bytes());
+        eh.extend_from_slice(&0u32.to_le_bytes()); // e_entry
+        eh.extend_from_slice(&0u32.to_le_bytes()); // e_phoff
+        eh.extend_from_slice(&(shoff as u32).to_le_bytes());
+        eh.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        eh.extend_from_slice(&52u16.to_le_bytes());
+        eh.extend_from_slice(&0u16.to_le_bytes()); // e_phentsize
+        eh.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+        eh.extend_from_slice(&40u16.to_le_bytes());
+        eh.extend_from_slice(&(headers.len() as u16).to_le_bytes());
+        eh.extend_from_slice(&(headers.len() as u16 - 1).to_le_bytes());
+        out[..52].copy_from_slice(&eh);
+        out
+    }
+
+    /// Read an ELF32 RISC-V relocatable file: the ones `to_elf` writes, or a GNU
+    /// `as` object. `.text*` sections are concatenated into text, `.data*`/`.rodata*`
+    /// (and the small-data variants) into data, `.bss*` into bss; any other loaded
+    /// section with contents, and relocations outside `RelocKind`, are errors.
+    /// Debug info, notes and unwind tables are skipped.
+    pub fn from_elf(bytes: &[u8]) -> Result<Object, String> {
+        if bytes.len() < 52 || &bytes[0..4] != b"\x7fELF" {
+            return Err("not an ELF file".into());
+        }
+        if bytes[4] != 1 || bytes[5] != 1 {
+            return Err("only little-endian ELF32 is supported".into());
+        }
+        let u16le = |o: usize| bytes.get(o..o + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let u32le = |o: usize| bytes.get(o..o + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+        let truncated = || "truncated ELF file".to_string();
+        if u16le(16) != Some(1) {
+            return Err("not a relocatable object (e_type != ET_REL)".into());
+        }
+        if u16le(18) != Some(0xF3) {
+            return Err("not a RISC-V object (e_machine != 0xF3)".into());
+        }
+        let shoff = u32le(32).ok_or_else(truncated)? as usize;
+        let shnum = u16le(48).ok_or_else(truncated)? as usize;
+        let shstrndx = u16le(50).ok_or_else(truncated)? as usize;
+
+        struct Shdr {
+            name: u32,
+            ty: u32,
+            flags: u32,
+            offset: usize,
+            size: usize,
+            link: usize,
+            info: usize,
+        }
+        let mut shdrs = Vec::with_capacity(shnum);
+        for i in 0..shnum {
+            let b = shoff + i * 40;
+            let f = |k: usize| u32le(b + k * 4).ok_or_else(truncated);
+            shdrs.push(Shdr {
+                name: f(0)?,
+                ty: f(1)?,
+                flags: f(2)?,
+                offset: f(4)? as usize,
+                size: f(5)? as usize,
+                link: f(6)? as usize,
+                info: f(7)? as usize,
+            });
+        }
+        let contents = |sh: &Shdr| -> Result<&[u8], String> {
+            if sh.ty == SHT_NOBITS {
+                return Ok(&[]);
+            }
+            bytes.get(sh.offset..sh.offset + sh.size).ok_or_else(truncated)
+        };
+        let cstr = |table: &[u8], off: usize| -> String {
+            let s = table.get(off..).unwrap_or(&[]);
+            let end = s.iter().position(|&b| b == 0).unwrap_or(s.len());
+            String::from_utf8_lossy(&s[..end]).into_owned()
+        };
+        let shstrtab = shdrs.get(shstrndx).map(contents).transpose()?.unwrap_or(&[]);
+        let names: Vec<String> = shdrs.iter().map(|sh| cstr(shstrtab, sh.name as usize)).collect();
+
+        // Map section indices to our three sections: where each input section
+        // starts in ours, and its size.
+        let mut obj = Object::default();
+        let mut placed: HashMap<usize, (Section, u32, u32)> = HashMap::new();
+        for (i, (sh, name)) in shdrs.iter().zip(&names).enumerate() {
+            let Some(section) = section_for(name) else {
+                // Unwind tables only matter to a debugger or C++ runtime
+                if sh.flags & SHF_ALLOC != 0 && sh.size > 0 && !name.starts_with(".eh_frame") {
+                    return Err(format!("unsupported section {name}"));
+                }
+                continue;
+            };
+            if sh.size == 0 {
+                continue;
+            }
+            let body = contents(sh)?;
+            let at = match section {
+                Section::Text => {
+                    let at = obj.text.len() as u32 * 4;
+                    obj.text.extend(body.chunks(4).map(|c| u32::from_le_bytes(c.try_into().unwrap_or([0; 4]))));
+                    at
+                }
+                Section::Data => {
+                    obj.data.resize(obj.data.len().next_multiple_of(4), 0);
+                    let at = obj.data.len() as u32;
+                    obj.data.extend_from_slice(body);
+                    at
+                }
+                Section::Bss => {
+                    let at = obj.bss_size.next_multiple_of(4);
+                    obj.bss_size = u32::try_from(sh.size)
+                        .ok()
+                        .and_then(|size| at.checked_add(size))
+                        .ok_or_else(|| format!("{name} is too large"))?;
+                    at
+                }
+            };
+            placed.insert(i, (section, at, sh.size as u32));
+        }
+
+        // Symbols, by symbol-table index for the relocations below.
+        let mut sym_names: Vec<String> = Vec::new();
+        if let Some(symtab) = shdrs.iter().find(|sh| sh.ty == SHT_SYMTAB) {
+            let strtab = shdrs.get(symtab.link).map(contents).transpose()?.unwrap_or(&[]);
+            for (i, e) in contents(symtab)?.chunks_exact(16).enumerate() {
+                let name_off = u32::from_le_bytes(e[0..4].try_into().unwrap()) as usize;
+                let value = u32::from_le_bytes(e[4..8].try_into().unwrap());
+                let (bind, kind) = (e[12] >> 4, e[12] & 0xF);
+                let shndx = u16::from_le_bytes([e[14], e[15]]) as usize;
+                let name = match kind {
+                    // GNU as relocates against section symbols; name them after the section.
+                    STT_SECTION => names.get(shndx).cloned().unwrap_or_default(),
+                    _ => cstr(strtab, name_off),
+                };
+                sym_names.push(name.clone());
+                if i == 0 || name.is_empty() {
+                    continue;
+                }
+                let section = match (shndx, placed.get(&shndx)) {
+                    (0, _) => None,
+                    (_, Some(&(section, at, size))) => {
+                        // A label may sit just past the end of its section.
+                        let value = (value <= size).then(|| at.checked_add(value)).flatten().ok_or_else(|| {
+                            format!("symbol `{name}` at {value:#x} is outside {}", names[shndx])
+                        })?;
+                        obj.symbols.push(Symbol { name, section: Some(section), value, global: bind != STB_LOCAL });
+                        continue;
+                    }
+                    // Absolute symbols, or sections we don't load (debug info, notes).
+                    (_, None) => continue,
+                };
+                obj.symbols.push(Symbol { name, section, value, global: true });
+            }
+        }
+
+        for sh in shdrs.iter().filter(|sh| sh.ty == SHT_RELA) {
+            let Some(&(section, at, size)) = placed.get(&sh.info) else { continue };
+            for e in contents(sh)?.chunks_exact(12) {
+                let offset = u32::from_le_bytes(e[0..4].try_into().unwrap());
+                let info = u32::from_le_bytes(e[4..8].try_into().unwrap());
+                let addend = i32::from_le_bytes(e[8..12].try_into().unwrap());
+                if matches!(info & 0xFF, R_RISCV_RELAX | R_RISCV_ALIGN) {
+                    continue;
+                }
+                let kind = RelocKind::from_elf_type(info & 0xFF)
+                    .ok_or_else(|| format!("unsupported relocation type {} at {offset:#x}", info & 0xFF))?;
+                let symbol = sym_names.get((info >> 8) as usize).cloned().ok_or("relocation names a missing symbol")?;
+                // Every relocated field is at least a word wide.
+                let outside = || format!("relocation at {offset:#x} is outside {}", names[sh.info]);
+                if offset.checked_add(4).is_none_or(|end| end > size) {
+                    return Err(outside());
+                }
+                let offset = at.checked_add(offset).ok_or_else(outside)?;
+                obj.relocs.push(Reloc { section, offset, kind, symbol, addend });
+            }
+        }
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elf_round_trip_keeps_everything() {
+        let obj = Object {
+            text: vec![0x0000_006f, 0x0000_0537, 0x0005_0513],
+            data: vec![1, 2, 3, 4, 0, 0, 0, 0],
+            bss_size: 12,
+            symbols: vec![
+                Symbol { name: "main".into(), section: Some(Section::Text), value: 0, global: true },
+                Symbol { name: "loop".into(), section: Some(Section::Text), value: 4, global: false },
+                Symbol { name: "buf".into(), section: Some(Section::Bss), value: 8, global: false },
+                Symbol { name: "puts".into(), section: None, value: 0, global: true },
+            ],
+            relocs: vec![
+                Reloc { section: Section::Text, offset: 0, kind: RelocKind::Jal, symbol: "puts".into(), addend: 0 },
+                Reloc { section: Section::Text, offset: 4, kind: RelocKind::Hi20, symbol: "buf".into(), addend: 4 },
+                Reloc { section: Section::Text, offset: 8, kind: RelocKind::Lo12I, symbol: "buf".into(), addend: 4 },
+                Reloc { section: Section::Data, offset: 4, kind: RelocKind::Abs32, symbol: "main".into(), addend: 0 },
+            ],
+        };
+        let back = Object::from_elf(&obj.to_elf()).expect("from_elf");
+        // Locals come first in the file.
+        let mut want = obj.clone();
+        want.symbols.sort_by_key(|s| s.global);
+        assert_eq!(back, want);
+    }
+
+    /// `bytes` with the first `from` section name spelled `to` (same length).
+    fn rename(mut bytes: Vec<u8>, from: &[u8], to: &[u8]) -> Vec<u8> {
+        let at = bytes.windows(from.len()).position(|w| w == from).unwrap();
+        bytes[at..at + to.len()].copy_from_slice(to);
+        bytes
+    }
+
+    #[test]
+    fn gnu_sections_are_merged_or_rejected() {
+        assert_eq!(section_for(".text.startup"), Some(Section::Text));
+        assert_eq!(section_for(".rodata.str1.1"), Some(Section::Data));
+        assert_eq!(section_for(".sbss"), Some(Section::Bss));
+        assert_eq!(section_for(".textual"), None);
+        assert_eq!(section_for(".debug_info"), None);
+
+        let obj = Object {
+            text: vec![0x0000_0013],
+            data: 0x0000_006fu32.to_le_bytes().to_vec(),
+            symbols: vec![Symbol { name: "f".into(), section: Some(Section::Data), value: 0, global: true }],
+            ..Default::default()
+        };
+        // A second text section is appended to the first
+        let two_texts = Object::from_elf(&rename(obj.to_elf(), b".data\0", b".text\0")).unwrap();
+        assert_eq!(two_texts.text, [0x0000_0013, 0x0000_006f]);
+        assert_eq!(two_texts.symbols[0].section, Some(Section::Text));
+        assert_eq!(two_texts.symbols[0].value, 4);
+        // A loaded section that has nowhere to go is an error, not dropped
+        let ctors = rename(obj.to_elf(), b".data\0", b".ctor\0");
+        assert_eq!(Object::from_elf(&ctors).unwrap_err(), "unsupported section .ctor");
+    }
+
+    #[test]
+    fn relocations_and_symbols_outside_their_section_are_rejected() {
+        let obj = Object {
+            text: vec![0x0000_006f],
+            symbols: vec![Symbol { name: "f".into(), section: Some(Section::Text), value: 4, global: true }],
+            relocs: vec![Reloc { section: Section::Text, offset: 0, kind: RelocKind::Jal, symbol: "f".into(), addend: 0 }],
+            ..Default::default()
+        };
+        let bytes = obj.to_elf();
+        assert!(Object::from_elf(&bytes).is_ok(), "a label may end its section");
+
+        // The one .rela.text entry, found by its r_info (symbol 1, R_RISCV_JAL)
+        let info = ((1u32 << 8) | 17).to_le_bytes();
+        let rela = bytes.windows(4).position(|w| w == info).unwrap() - 4;
+        let mut bad = bytes.clone();
+        bad[rela..rela + 4].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        assert_eq!(Object::from_elf(&bad).unwrap_err(), "relocation at 0xfffffff0 is outside .text");
+
+        // `f`'s st_value, the word after its name offset in the second .symtab entry
+        let mut bad = bytes;
+        let symtab = u32::from_le_bytes(bad[32..36].try_into().unwrap()) as usize + 4 * 40;
+        let sym = u32::from_le_bytes(bad[symtab + 16..symtab + 20].try_into().unwrap()) as usize + 16;
+        bad[sym + 4..sym + 8].copy_from_slice(&8u32.to_le_bytes());
+        assert_eq!(Object::from_elf(&bad).unwrap_err(), "symbol `f` at 0x8 is outside .text");
+    }
+
+    #[test]
+    fn executables_are_not_objects() {
+        let mut bytes = Object::default().to_elf();
+        bytes[16] = 2; // ET_EXEC
+        assert_eq!(Object::from_elf(&bytes).unwrap_err(), "not a relocatable object (e_type != ET_REL)");
+    }
+}
@@ -46,6 +46,11 @@ pub trait Bus {
     fn dcache_read32(&mut self, addr: u32) -> Result<u32, FalconError> {
         self.load32(addr)
     }
+
+    /// Ciclos gastos pelo programa até agora (relógio virtual das syscalls de tempo).
+    fn cycles(&self) -> u64 {
+        0
+    }
 }
 
 pub struct Ram { data: Vec<u8> }
@@ -0,0 +1,230 @@
+// falcon/mmap.rs
+//
+// Anonymous memory mappings inside guest RAM. There is no MMU: a mapping is a
+// page-aligned range the program was handed by `mmap`, carved top-down out of
+// the free space between the heap break and the stack. The top of RAM is kept
+// for the stack (`stack_floor` and up); `brk` and `mmap` both stop there, and
+// `brk` also stops at the lowest mapping. Protections are recorded, shown and
+// changed by `mprotect`, but not enforced.
+use super::{errors::FalconError, memory::Bus};
+
+pub const PAGE_SIZE: u32 = 4096;
+
+pub const PROT_READ: u32 = 0x1;
+pub const PROT_WRITE: u32 = 0x2;
+pub const PROT_EXEC: u32 = 0x4;
+
+pub const ENOMEM: u32 = 12;
+pub const EINVAL: u32 = 22;
+
+/// Smallest and largest stack kept free of heap and mappings.
+const STACK_MIN: u64 = 16 * 1024;
+const STACK_MAX: u64 = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mapping {
+    pub start: u32,
+    pub len: u32,
+    pub prot: u32,
+}
+
+impl Mapping {
+    pub fn end(&self) -> u32 {
+        self.start + self.len
+    }
+
+    /// `rw-` style protection string.
+    pub fn prot_str(&self) -> String {
+        [(PROT_READ, 'r'), (PROT_WRITE, 'w'), (PROT_EXEC, 'x')]
+            .iter()
+            .map(|&(bit, c)| if self.prot & bit != 0 { c } else { '-' })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mappings {
+    /// Sorted by address, never overlapping.
+    regions: Vec<Mapping>,
+    /// Lowest address of the stack's reserve; nothing is mapped at or above it.
+    /// Zero until the loader sets the stack up: every request fails with ENOMEM.
+    stack_floor: u32,
+}
+
+fn page_up(n: u32) -> Option<u32> {
+    n.checked_add(PAGE_SIZE - 1).map(|n| n & !(PAGE_SIZE - 1))
+}
+
+impl Mappings {
+    /// No mappings yet; the stack below `ram_end` (the initial `sp`) keeps
+    /// 1/16 of RAM, between 16 KiB and 1 MiB.
+    pub fn below_stack(ram_end: u64) -> Mappings {
+        let reserve = (ram_end / 16).clamp(STACK_MIN, STACK_MAX).next_multiple_of(PAGE_SIZE as u64);
+        let floor = (ram_end & !(PAGE_SIZE as u64 - 1)).saturating_sub(reserve);
+        Mappings { regions: Vec::new(), stack_floor: floor as u32 }
+    }
+
+    pub fn regions(&self) -> &[Mapping] {
+        &self.regions
+    }
+
+    pub fn stack_floor(&self) -> u32 {
+        self.stack_floor
+    }
+
+    /// Highest address `brk` may move the break to.
+    pub fn brk_limit(&self) -> u32 {
+        self.regions.first().map_or(self.stack_floor, |m| m.start)
+    }
+
+    /// Map `len` bytes (rounded up to pages) above `heap_break`. With an `addr`,
+    /// exactly at `addr`, replacing whatever was mapped there; otherwise at the
+    /// highest free range. The pages read as zero. Errors are positive errno.
+    pub fn map<B: Bus>(&mut self, mem: &mut B, heap_break: u32, addr: Option<u32>, len: u32, prot: u32) -> Result<u32, u32> {
+        if len == 0 || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+            return Err(EINVAL);
+        }
+        let len = page_up(len).ok_or(ENOMEM)?;
+        let floor = page_up(heap_break).ok_or(ENOMEM)?;
+        let start = match addr {
+            Some(a) => {
+                if !a.is_multiple_of(PAGE_SIZE) {
+                    return Err(EINVAL);
+                }
+                if a < floor || a.checked_add(len).is_none_or(|end| end > self.stack_floor) {
+                    return Err(ENOMEM);
+                }
+                self.unmap(a, len)?;
+                a
+            }
+            None => self.find_gap(floor, len).ok_or(ENOMEM)?,
+        };
+        zero(mem, start, len).map_err(|_| ENOMEM)?;
+        let at = self.regions.partition_point(|m| m.start < start);
+        self.regions.insert(at, Mapping { start, len, prot });
+        self.merge();
+        Ok(start)
+    }
+
+    /// The highest `len`-byte gap between `floor` and the stack.
+    fn find_gap(&self, floor: u32, len: u32) -> Option<u32> {
+        let mut top = self.stack_floor;
+        for m in self.regions.iter().rev() {
+            if top.saturating_sub(m.end()) >= len {
+                return Some(top - len);
+            }
+            top = m.start;
+        }
+        (top.checked_sub(len)? >= floor).then(|| top - len)
+    }
+
+    /// Drop every mapped page in `[addr, addr + len)`, splitting mappings that
+    /// straddle it. Unmapped pages in the range are fine, as on Linux.
+    pub fn unmap(&mut self, addr: u32, len: u32) -> Result<(), u32> {
+        let end = self.range(addr, len)?;
+        let mut kept = Vec::with_capacity(self.regions.len() + 1);
+        for m in self.regions.drain(..) {
+            if m.end() <= addr || m.start >= end {
+                kept.push(m);
+                continue;
+            }
+            if m.start < addr {
+                kept.push(Mapping { len: addr - m.start, ..m });
+            }
+            if m.end() > end {
+                kept.push(Mapping { start: end, len: m.end() - end, prot: m.prot });
+            }
+        }
+        self.regions = kept;
+        Ok(())
+    }
+
+    /// Change the protection of `[addr, addr + len)`, which must be mapped throughout.
+    pub fn protect(&mut self, addr: u32, len: u32, prot: u32) -> Result<(), u32> {
+        if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+            return Err(EINVAL);
+        }
+        let end = self.range(addr, len)?;
+        let mut covered = addr;
+        for m in self.regions.iter().filter(|m| m.end() > addr && m.start < end) {
+            if m.start > covered {
+                break;
+            }
+            covered = m.end();
+        }
+        if covered < end {
+            return Err(ENOMEM);
+        }
+        self.unmap(addr, len)?;
+        let at = self.regions.partition_point(|m| m.start < addr);
+        self.regions.insert(at, Mapping { start: addr, len: end - addr, prot });
+        self.merge();
+        Ok(())
+    }
+
+    /// Validate a page-aligned range; returns its (page-rounded) end.
+    fn range(&self, addr: u32, len: u32) -> Result<u32, u32> {
+        if !addr.is_multiple_of(PAGE_SIZE) || len == 0 {
+            return Err(EINVAL);
+        }
+        page_up(len).and_then(|len| addr.checked_add(len)).ok_or(EINVAL)
+    }
+
+    /// Join neighbours with the same protection back into one mapping.
+    fn merge(&mut self) {
+        self.regions.dedup_by(|next, prev| {
+            let join = prev.end() == next.start && prev.prot == next.prot;
+            if join {
+                prev.len += next.len;
+            }
+            join
+        });
+    }
+}
+
+/// Clear `[start, start + len)`, skipping words that already read as zero so a
+/// fresh mapping doesn't churn the data cache.
+fn zero<B: Bus>(mem: &mut B, start: u32, len: u32) -> Result<(), FalconError> {
+    for addr in (start..start + len).step_by(4) {
+        if mem.load32(addr)? != 0 {
+            mem.store32(addr, 0)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::Ram;
+
+    #[test]
+    fn map_unmap_and_protect_split_regions() {
+        let mut mem = Ram::new(0x40000);
+        let mut maps = Mappings::below_stack(0x40000);
+        assert_eq!(maps.stack_floor(), 0x3C000);
+
+        mem.store32(0x3B000, 7).unwrap();
+        let a = maps.map(&mut mem, 0x2000, None, 0x2000, PROT_READ | PROT_WRITE).unwrap();
+        assert_eq!(a, 0x3A000);
+        assert_eq!(mem.load32(0x3B000).unwrap(), 0);
+        let b = maps.map(&mut mem, 0x2000, None, 100, PROT_READ).unwrap();
+        assert_eq!(b, 0x39000);
+        assert_eq!(maps.brk_limit(), 0x39000);
+
+        // Punch a hole in the middle of the first mapping, then re-protect across it
+        maps.unmap(0x3A000, 0x1000).unwrap();
+        let spans: Vec<(u32, u32)> = maps.regions().iter().map(|m| (m.start, m.len)).collect();
+        assert_eq!(spans, [(0x39000, 0x1000), (0x3B000, 0x1000)]);
+        assert_eq!(maps.protect(0x39000, 0x3000, PROT_READ), Err(ENOMEM));
+        maps.protect(0x3B000, 0x1000, PROT_READ).unwrap();
+        assert_eq!(maps.regions()[1].prot_str(), "r--");
+
+        // The hole is reused; a fixed mapping can't reach into the stack or the heap
+        assert_eq!(maps.map(&mut mem, 0x2000, None, 0x1000, PROT_READ), Ok(0x3A000));
+        assert_eq!(maps.regions().len(), 1);
+        assert_eq!(maps.map(&mut mem, 0x2000, Some(0x3C000), 0x1000, PROT_READ), Err(ENOMEM));
+        assert_eq!(maps.map(&mut mem, 0x2000, Some(0x1000), 0x1000, PROT_READ), Err(ENOMEM));
+        assert_eq!(maps.map(&mut mem, 0x2000, None, 0x100000, PROT_READ), Err(ENOMEM));
+    }
+}
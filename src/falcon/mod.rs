@@ -1,16 +1,23 @@
 pub mod arch;
 pub mod cache;
+pub mod clock;
+pub mod coverage;
 pub mod errors;
 pub mod exec;
 pub mod instruction;
 pub mod memory;
+pub mod mmap;
 pub mod registers;
+pub mod semihost;
+pub mod strace;
 pub mod syscall;
+pub mod vfs;
 
 pub mod decoder;
 
 pub mod asm;
 pub mod encoder;
+pub mod link;
 
 pub mod program;
 
@@ -0,0 +1,802 @@
+// falcon/program/dwarf.rs — source lines and functions from DWARF debug sections
+//
+// Compilers targeting RISC-V (clang, GCC, rustc) describe which source line each
+// instruction came from in `.debug_line`, and where each function starts and ends in
+// `.debug_info`. Only what source-level stepping needs is read: the line table of
+// every unit (DWARF 2–5) and the name and PC range of every `DW_TAG_subprogram`.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// A source position: index into [`DebugInfo::files`] and 1-based line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLoc {
+    pub file: usize,
+    pub line: u32,
+}
+
+/// A function with code, `low..high`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub low: u32,
+    pub high: u32,
+}
+
+/// Line table and functions of an ELF image.
+#[derive(Clone, Debug, Default)]
+pub struct DebugInfo {
+    /// Every source file named by a line table, deduplicated.
+    pub files: Vec<PathBuf>,
+    /// Address → location of the code from there on; `None` past the end of a sequence.
+    rows: BTreeMap<u32, Option<SourceLoc>>,
+    /// First address of each statement, where stepping by line stops.
+    stmts: BTreeSet<u32>,
+    pub functions: Vec<Function>,
+}
+
+impl DebugInfo {
+    /// Read the debug sections that `section` returns by name. `None` when there
+    /// is no usable line table.
+    pub fn parse<'a>(section: impl Fn(&str) -> Option<&'a [u8]>) -> Option<DebugInfo> {
+        let sections = Sections {
+            line: section(".debug_line")?,
+            line_str: section(".debug_line_str").unwrap_or(&[]),
+            str: section(".debug_str").unwrap_or(&[]),
+            str_offsets: section(".debug_str_offsets").unwrap_or(&[]),
+            addr: section(".debug_addr").unwrap_or(&[]),
+        };
+        let mut info = DebugInfo::default();
+        // Units record their compilation directory in .debug_info, keyed by line table offset.
+        let mut comp_dirs = HashMap::new();
+        if let (Some(data), Some(abbrev)) = (section(".debug_info"), section(".debug_abbrev")) {
+            read_units(data, abbrev, &sections, &mut info.functions, &mut comp_dirs);
+        }
+        let mut off = 0;
+        while off < sections.line.len() {
+            let comp_dir = comp_dirs.get(&off).map(PathBuf::from);
+            match read_line_program(&sections, off, comp_dir.as_deref(), &mut info) {
+                Some(next) => off = next,
+                None => break,
+            }
+        }
+        info.functions.sort_by_key(|f| f.low);
+        (!info.rows.is_empty()).then_some(info)
+    }
+
+    /// Source location of the instruction at `addr`.
+    pub fn location(&self, addr: u32) -> Option<SourceLoc> {
+        self.rows.range(..=addr).next_back().and_then(|(_, &loc)| loc)
+    }
+
+    /// `file:line` for `addr`, with the file name only.
+    pub fn describe(&self, addr: u32) -> Option<String> {
+        let loc = self.location(addr)?;
+        let name = self.files[loc.file].file_name().map_or_else(|| self.files[loc.file].display().to_string(), |n| n.to_string_lossy().into_owned());
+        Some(format!("{name}:{}", loc.line))
+    }
+
+    /// Whether a statement (the first instruction of a source line) starts at `addr`.
+    pub fn is_stmt(&self, addr: u32) -> bool {
+        self.stmts.contains(&addr)
+    }
+
+    /// Statement addresses with their locations, in address order.
+    pub fn stmts(&self) -> impl Iterator<Item = (u32, SourceLoc)> + '_ {
+        self.stmts.iter().filter_map(|&a| Some((a, self.location(a)?)))
+    }
+
+    /// Index of the file `path` refers to: the same path, or else the one sharing
+    /// the longest run of trailing components with it, so a checkout at a
+    /// different root (`/tmp/x/src/main.c` vs `/home/me/proj/src/main.c`) still
+    /// matches.
+    pub fn find_file(&self, path: &Path) -> Option<usize> {
+        if let Some(i) = self.files.iter().position(|f| f == path) {
+            return Some(i);
+        }
+        let shared = |f: &PathBuf| f.components().rev().zip(path.components().rev()).take_while(|(a, b)| a == b).count();
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (shared(f), i))
+            .filter(|&(n, _)| n > 0)
+            .max_by_key(|&(n, i)| (n, usize::MAX - i))
+            .map(|(_, i)| i)
+    }
+
+    /// Statements for `line` of `file`, or of the first line after it that has
+    /// code. Returns the line used and its addresses.
+    pub fn line_addrs(&self, file: usize, line: u32) -> Option<(u32, Vec<u32>)> {
+        let found = self.stmts().filter(|(_, l)| l.file == file && l.line >= line).map(|(_, l)| l.line).min()?;
+        let addrs = self.stmts().filter(|(_, l)| l.file == file && l.line == found).map(|(a, _)| a).collect();
+        Some((found, addrs))
+    }
+
+    /// The function whose code contains `addr`.
+    pub fn function_at(&self, addr: u32) -> Option<&Function> {
+        self.functions.iter().filter(|f| (f.low..f.high).contains(&addr)).min_by_key(|f| f.high - f.low)
+    }
+
+    fn file_index(&mut self, path: PathBuf) -> usize {
+        match self.files.iter().position(|f| *f == path) {
+            Some(i) => i,
+            None => {
+                self.files.push(path);
+                self.files.len() - 1
+            }
+        }
+    }
+}
+
+struct Sections<'a> {
+    line: &'a [u8],
+    line_str: &'a [u8],
+    str: &'a [u8],
+    str_offsets: &'a [u8],
+    addr: &'a [u8],
+}
+
+/// Little-endian cursor over a section.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let b = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(b)
+    }
+
+    fn uint(&mut self, n: usize) -> Option<u64> {
+        Some(self.bytes(n)?.iter().rev().fold(0, |acc, &b| acc << 8 | b as u64))
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.uint(2).map(|v| v as u16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.uint(4).map(|v| v as u32)
+    }
+
+    fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    fn cstr(&mut self) -> Option<&'a str> {
+        let len = self.data.get(self.pos..)?.iter().position(|&b| b == 0)?;
+        let s = std::str::from_utf8(self.bytes(len)?).ok()?;
+        self.pos += 1;
+        Some(s)
+    }
+
+    /// A 32-bit DWARF unit length; 64-bit DWARF is not used by 32-bit targets.
+    fn unit_length(&mut self) -> Option<usize> {
+        match self.u32()? {
+            len @ 0..0xffff_fff0 => Some(len as usize),
+            _ => None,
+        }
+    }
+}
+
+fn str_at(section: &[u8], off: u64) -> Option<String> {
+    Reader::new(section, off as usize).cstr().map(str::to_string)
+}
+
+// Attribute forms (DWARF 5, section 7.5.6)
+const FORM_ADDR: u64 = 0x01;
+const FORM_BLOCK2: u64 = 0x03;
+const FORM_BLOCK4: u64 = 0x04;
+const FORM_DATA2: u64 = 0x05;
+const FORM_DATA4: u64 = 0x06;
+const FORM_DATA8: u64 = 0x07;
+const FORM_STRING: u64 = 0x08;
+const FORM_BLOCK: u64 = 0x09;
+const FORM_BLOCK1: u64 = 0x0a;
+const FORM_DATA1: u64 = 0x0b;
+const FORM_FLAG: u64 = 0x0c;
+const FORM_SDATA: u64 = 0x0d;
+const FORM_STRP: u64 = 0x0e;
+const FORM_UDATA: u64 = 0x0f;
+const FORM_REF_ADDR: u64 = 0x10;
+const FORM_REF1: u64 = 0x11;
+const FORM_REF2: u64 = 0x12;
+const FORM_REF4: u64 = 0x13;
+const FORM_REF8: u64 = 0x14;
+const FORM_REF_UDATA: u64 = 0x15;
+const FORM_INDIRECT: u64 = 0x16;
+const FORM_SEC_OFFSET: u64 = 0x17;
+const FORM_EXPRLOC: u64 = 0x18;
+const FORM_FLAG_PRESENT: u64 = 0x19;
+const FORM_STRX: u64 = 0x1a;
+const FORM_ADDRX: u64 = 0x1b;
+const FORM_REF_SUP4: u64 = 0x1c;
+const FORM_STRP_SUP: u64 = 0x1d;
+const FORM_DATA16: u64 = 0x1e;
+const FORM_LINE_STRP: u64 = 0x1f;
+const FORM_REF_SIG8: u64 = 0x20;
+const FORM_IMPLICIT_CONST: u64 = 0x21;
+const FORM_LOCLISTX: u64 = 0x22;
+const FORM_RNGLISTX: u64 = 0x23;
+const FORM_REF_SUP8: u64 = 0x24;
+const FORM_STRX1: u64 = 0x25;
+const FORM_STRX4: u64 = 0x28;
+const FORM_ADDRX1: u64 = 0x29;
+const FORM_ADDRX4: u64 = 0x2c;
+
+/// An attribute value, before string and address indices are resolved.
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Const(u64),
+    Addr(u64),
+    AddrIndex(u64),
+    Str(&'a str),
+    /// Offset into `.debug_str` or `.debug_line_str`.
+    StrOffset(u64, bool),
+    StrIndex(u64),
+    /// Reference to a DIE, relative to its unit.
+    UnitRef(u64),
+    Other,
+}
+
+/// Offset of the 4-byte entry `i` of a table starting at `base`; `None` past the address space.
+fn table_entry(base: u64, i: u64) -> Option<usize> {
+    usize::try_from(base.checked_add(i.checked_mul(4)?)?).ok()
+}
+
+/// Per-unit values needed to resolve indexed forms.
+#[derive(Default)]
+struct UnitBases {
+    str_offsets: u64,
+    addr: u64,
+}
+
+impl Value<'_> {
+    fn string(self, s: &Sections, bases: &UnitBases) -> Option<String> {
+        match self {
+            Value::Str(v) => Some(v.to_string()),
+            Value::StrOffset(off, false) => str_at(s.str, off),
+            Value::StrOffset(off, true) => str_at(s.line_str, off),
+            Value::StrIndex(i) => {
+                let off = Reader::new(s.str_offsets, table_entry(bases.str_offsets, i)?).u32()?;
+                str_at(s.str, off as u64)
+            }
+            _ => None,
+        }
+    }
+
+    fn address(self, s: &Sections, bases: &UnitBases) -> Option<u32> {
+        match self {
+            Value::Addr(a) => Some(a as u32),
+            Value::AddrIndex(i) => Reader::new(s.addr, table_entry(bases.addr, i)?).u32(),
+            _ => None,
+        }
+    }
+
+    fn constant(self) -> Option<u64> {
+        match self {
+            Value::Const(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Read one value of `form`. `version` and `addr_size` come from the unit header.
+fn read_form<'a>(r: &mut Reader<'a>, form: u64, implicit: i64, version: u16, addr_size: usize) -> Option<Value<'a>> {
+    Some(match form {
+        FORM_ADDR => Value::Addr(r.uint(addr_size)?),
+        FORM_DATA1 | FORM_FLAG => Value::Const(r.uint(1)?),
+        FORM_DATA2 => Value::Const(r.uint(2)?),
+        FORM_DATA4 => Value::Const(r.uint(4)?),
+        FORM_DATA8 => Value::Const(r.uint(8)?),
+        FORM_UDATA => Value::Const(r.uleb()?),
+        FORM_SDATA => Value::Const(r.sleb()? as u64),
+        FORM_IMPLICIT_CONST => Value::Const(implicit as u64),
+        FORM_FLAG_PRESENT => Value::Const(1),
+        FORM_STRING => Value::Str(r.cstr()?),
+        FORM_STRP => Value::StrOffset(r.uint(4)?, false),
+        FORM_LINE_STRP => Value::StrOffset(r.uint(4)?, true),
+        FORM_STRX => Value::StrIndex(r.uleb()?),
+        FORM_STRX1..=FORM_STRX4 => Value::StrIndex(r.uint((form - FORM_STRX1 + 1) as usize)?),
+        FORM_ADDRX => Value::AddrIndex(r.uleb()?),
+        FORM_ADDRX1..=FORM_ADDRX4 => Value::AddrIndex(r.uint((form - FORM_ADDRX1 + 1) as usize)?),
+        FORM_REF1 => Value::UnitRef(r.uint(1)?),
+        FORM_REF2 => Value::UnitRef(r.uint(2)?),
+        FORM_REF4 => Value::UnitRef(r.uint(4)?),
+        FORM_REF8 => Value::UnitRef(r.uint(8)?),
+        FORM_REF_UDATA => Value::UnitRef(r.uleb()?),
+        FORM_REF_ADDR => {
+            r.bytes(if version == 2 { addr_size } else { 4 })?;
+            Value::Other
+        }
+        FORM_SEC_OFFSET | FORM_STRP_SUP | FORM_REF_SUP4 => Value::Const(r.uint(4)?),
+        FORM_REF_SIG8 | FORM_REF_SUP8 => Value::Const(r.uint(8)?),
+        FORM_LOCLISTX | FORM_RNGLISTX => Value::Const(r.uleb()?),
+        FORM_DATA16 => {
+            r.bytes(16)?;
+            Value::Other
+        }
+        FORM_BLOCK1 => {
+            let n = r.uint(1)? as usize;
+            r.bytes(n)?;
+            Value::Other
+        }
+        FORM_BLOCK2 => {
+            let n = r.uint(2)? as usize;
+            r.bytes(n)?;
+            Value::Other
+        }
+        FORM_BLOCK4 => {
+            let n = r.uint(4)? as usize;
+            r.bytes(n)?;
+            Value::Other
+        }
+        FORM_BLOCK | FORM_EXPRLOC => {
+            let n = r.uleb()? as usize;
+            r.bytes(n)?;
+            Value::Other
+        }
+        FORM_INDIRECT => {
+            let form = r.uleb()?;
+            return read_form(r, form, 0, version, addr_size);
+        }
+        _ => return None,
+    })
+}
+
+const TAG_COMPILE_UNIT: u64 = 0x11;
+const TAG_SUBPROGRAM: u64 = 0x2e;
+
+const AT_NAME: u64 = 0x03;
+const AT_STMT_LIST: u64 = 0x10;
+const AT_LOW_PC: u64 = 0x11;
+const AT_HIGH_PC: u64 = 0x12;
+const AT_COMP_DIR: u64 = 0x1b;
+const AT_ABSTRACT_ORIGIN: u64 = 0x31;
+const AT_SPECIFICATION: u64 = 0x47;
+const AT_STR_OFFSETS_BASE: u64 = 0x72;
+const AT_ADDR_BASE: u64 = 0x73;
+
+struct Abbrev {
+    tag: u64,
+    /// (attribute, form, implicit constant)
+    attrs: Vec<(u64, u64, i64)>,
+}
+
+fn read_abbrevs(data: &[u8], off: usize) -> Option<HashMap<u64, Abbrev>> {
+    let mut r = Reader::new(data, off);
+    let mut table = HashMap::new();
+    loop {
+        let code = r.uleb()?;
+        if code == 0 {
+            return Some(table);
+        }
+        let tag = r.uleb()?;
+        r.u8()?; // has children: DIEs are read in order, so nesting does not matter
+        let mut attrs = Vec::new();
+        loop {
+            let (attr, form) = (r.uleb()?, r.uleb()?);
+            if attr == 0 && form == 0 {
+                break;
+            }
+            let implicit = if form == FORM_IMPLICIT_CONST { r.sleb()? } else { 0 };
+            attrs.push((attr, form, implicit));
+        }
+        table.insert(code, Abbrev { tag, attrs });
+    }
+}
+
+/// Walk every compilation unit in `.debug_info`, collecting functions and each
+/// unit's compilation directory (keyed by its line table offset).
+fn read_units(data: &[u8], abbrev: &[u8], s: &Sections, functions: &mut Vec<Function>, comp_dirs: &mut HashMap<usize, String>) {
+    let mut off = 0;
+    while off < data.len() {
+        let mut r = Reader::new(data, off);
+        let Some(end) = r.unit_length().and_then(|len| r.pos.checked_add(len)) else { return };
+        let Some(version) = r.u16() else { return };
+        let header = if version >= 5 {
+            r.u8().and_then(|unit_type| {
+                let addr_size = r.u8()?;
+                let abbrev_off = r.u32()?;
+                // skeleton/split units carry a DWO id, type units a signature and offset
+                match unit_type {
+                    2 | 6 => r.bytes(12)?,
+                    4 | 5 => r.bytes(8)?,
+                    _ => &[],
+                };
+                Some((abbrev_off, addr_size))
+            })
+        } else {
+            r.u32().and_then(|abbrev_off| Some((abbrev_off, r.u8()?)))
+        };
+        if let Some((abbrev_off, addr_size)) = header
+            && let Some(abbrevs) = read_abbrevs(abbrev, abbrev_off as usize)
+        {
+            read_dies(&mut r, off, end.min(data.len()), &abbrevs, version, addr_size as usize, s, functions, comp_dirs);
+        }
+        off = end;
+    }
+}
+
+/// A subprogram whose name may live on another DIE (`DW_AT_abstract_origin`).
+struct PendingFunction<'a> {
+    name: Option<Value<'a>>,
+    origin: Option<u64>,
+    low: Option<Value<'a>>,
+    high: Option<Value<'a>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn read_dies<'a>(
+    r: &mut Reader<'a>,
+    unit_off: usize,
+    end: usize,
+    abbrevs: &HashMap<u64, Abbrev>,
+    version: u16,
+    addr_size: usize,
+    s: &Sections<'a>,
+    functions: &mut Vec<Function>,
+    comp_dirs: &mut HashMap<usize, String>,
+) {
+    let mut bases = UnitBases::default();
+    let mut names: HashMap<u64, (Option<Value>, Option<u64>)> = HashMap::new();
+    let mut pending = Vec::new();
+    let mut unit_dir = None;
+    let mut stmt_list = None;
+    while r.pos < end {
+        let die_off = (r.pos - unit_off) as u64;
+        let Some(code) = r.uleb() else { break };
+        if code == 0 {
+            continue;
+        }
+        let Some(abbrev) = abbrevs.get(&code) else { break };
+        let mut attrs = Vec::with_capacity(abbrev.attrs.len());
+        for &(attr, form, implicit) in &abbrev.attrs {
+            let Some(v) = read_form(r, form, implicit, version, addr_size) else { return };
+            attrs.push((attr, v));
+        }
+        let get = |a: u64| attrs.iter().find(|(at, _)| *at == a).map(|&(_, v)| v);
+        let origin = get(AT_ABSTRACT_ORIGIN).or(get(AT_SPECIFICATION)).and_then(|v| match v {
+            Value::UnitRef(o) => Some(o),
+            _ => None,
+        });
+        match abbrev.tag {
+            TAG_COMPILE_UNIT => {
+                bases.str_offsets = get(AT_STR_OFFSETS_BASE).and_then(Value::constant).unwrap_or(8);
+                bases.addr = get(AT_ADDR_BASE).and_then(Value::constant).unwrap_or(8);
+                unit_dir = get(AT_COMP_DIR);
+                stmt_list = get(AT_STMT_LIST).and_then(Value::constant);
+            }
+            TAG_SUBPROGRAM => {
+                names.insert(die_off, (get(AT_NAME), origin));
+                pending.push(PendingFunction { name: get(AT_NAME), origin, low: get(AT_LOW_PC), high: get(AT_HIGH_PC) });
+            }
+            _ => {}
+        }
+    }
+
+    if let (Some(dir), Some(list)) = (unit_dir.and_then(|d| d.string(s, &bases)), stmt_list) {
+        comp_dirs.insert(list as usize, dir);
+    }
+    for f in pending {
+        let (Some(low), Some(high)) = (f.low.and_then(|v| v.address(s, &bases)), f.high) else { continue };
+        let high = match high {
+            Value::Const(len) => low.wrapping_add(len as u32),
+            v => match v.address(s, &bases) {
+                Some(a) => a,
+                None => continue,
+            },
+        };
+        // Out-of-line copies of inline functions name their abstract instance instead.
+        let mut name = f.name;
+        let mut origin = f.origin;
+        for _ in 0..4 {
+            if name.is_some() {
+                break;
+            }
+            let Some((n, o)) = origin.and_then(|o| names.get(&o)) else { break };
+            (name, origin) = (*n, *o);
+        }
+        if let Some(name) = name.and_then(|n| n.string(s, &bases))
+            && high > low
+        {
+            functions.push(Function { name, low, high });
+        }
+    }
+}
+
+// Line number program opcodes (DWARF 5, section 6.2.5)
+const LNS_COPY: u8 = 1;
+const LNS_ADVANCE_PC: u8 = 2;
+const LNS_ADVANCE_LINE: u8 = 3;
+const LNS_SET_FILE: u8 = 4;
+const LNS_NEGATE_STMT: u8 = 6;
+const LNS_CONST_ADD_PC: u8 = 8;
+const LNS_FIXED_ADVANCE_PC: u8 = 9;
+const LNE_END_SEQUENCE: u8 = 1;
+const LNE_SET_ADDRESS: u8 = 2;
+const LNE_DEFINE_FILE: u8 = 3;
+const LNCT_PATH: u64 = 1;
+const LNCT_DIRECTORY_INDEX: u64 = 2;
+
+/// Run the line number program at `off`, adding its rows to `info`. Returns the
+/// offset of the next program.
+fn read_line_program(s: &Sections, off: usize, comp_dir: Option<&Path>, info: &mut DebugInfo) -> Option<usize> {
+    let mut r = Reader::new(s.line, off);
+    let len = r.unit_length()?;
+    let end = r.pos.checked_add(len)?.min(s.line.len());
+    let version = r.u16()?;
+    if !(2..=5).contains(&version) {
+        return Some(end);
+    }
+    let addr_size = if version >= 5 {
+        let size = r.u8()? as usize;
+        r.u8()?; // segment selector size
+        size
+    } else {
+        4
+    };
+    let header_len = r.u32()? as usize;
+    let program = r.pos.checked_add(header_len)?;
+    let min_inst = r.u8()? as u32;
+    if version >= 4 {
+        r.u8()?; // maximum operations per instruction (VLIW only)
+    }
+    let default_stmt = r.u8()? != 0;
+    let line_base = r.u8()? as i8 as i64;
+    let line_range = r.u8()?;
+    let opcode_base = r.u8()?;
+    if line_range == 0 || opcode_base == 0 {
+        return Some(end);
+    }
+    let std_lengths = r.bytes(opcode_base as usize - 1)?.to_vec();
+
+    // Directories and files; the index a program uses for each file maps into info.files.
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    let mut files: Vec<usize> = Vec::new();
+    let join = |dirs: &[PathBuf], dir: usize, name: &str| dirs.get(dir).cloned().unwrap_or_default().join(name);
+    if version >= 5 {
+        let bases = UnitBases::default();
+        let entries = |r: &mut Reader, is_files: bool, dirs: &[PathBuf]| -> Option<Vec<PathBuf>> {
+            let format_count = r.u8()?;
+            let format: Vec<(u64, u64)> = (0..format_count).map(|_| Some((r.uleb()?, r.uleb()?))).collect::<Option<_>>()?;
+            let count = r.uleb()?;
+            let mut out = Vec::new();
+            for _ in 0..count {
+                let (mut path, mut dir) = (String::new(), 0usize);
+                for &(kind, form) in &format {
+                    let v = read_form(r, form, 0, version, addr_size)?;
+                    match kind {
+                        LNCT_PATH => path = v.string(s, &bases).unwrap_or_default(),
+                        LNCT_DIRECTORY_INDEX => dir = v.constant().unwrap_or(0) as usize,
+                        _ => {}
+                    }
+                }
+                out.push(if is_files { join(dirs, dir, &path) } else { PathBuf::from(path) });
+            }
+            Some(out)
+        };
+        dirs = entries(&mut r, false, &[])?;
+        // Directory 0 is the compilation directory; relative ones hang off it.
+        for i in 1..dirs.len() {
+            if dirs[i].is_relative() {
+                dirs[i] = dirs[0].join(&dirs[i]);
+            }
+        }
+        for path in entries(&mut r, true, &dirs)? {
+            files.push(info.file_index(path));
+        }
+    } else {
+        // Directory 0 is the compilation directory, which only DWARF 5 lists here.
+        dirs.push(comp_dir.map(Path::to_path_buf).unwrap_or_default());
+        loop {
+            let d = r.cstr()?;
+            if d.is_empty() {
+                break;
+            }
+            dirs.push(dirs[0].join(d));
+        }
+        // File 0 does not exist before DWARF 5; keep indices aligned with a placeholder.
+        files.push(usize::MAX);
+        loop {
+            let name = r.cstr()?;
+            if name.is_empty() {
+                break;
+            }
+            let dir = r.uleb()? as usize;
+            r.uleb()?;
+            r.uleb()?;
+            files.push(info.file_index(join(&dirs, dir, name)));
+        }
+    }
+
+    // The state machine
+    r.pos = program;
+    let (mut addr, mut file, mut line, mut stmt) = (0u32, 1u64, 1i64, default_stmt);
+    let mut prev: Option<SourceLoc> = None;
+    let emit = |info: &mut DebugInfo, files: &[usize], addr: u32, file: u64, line: i64, stmt: bool, prev: &mut Option<SourceLoc>| {
+        let loc = files
+            .get(file as usize)
+            .filter(|&&f| f != usize::MAX && line > 0)
+            .map(|&f| SourceLoc { file: f, line: line as u32 });
+        info.rows.insert(addr, loc);
+        if stmt && loc.is_some() && loc != *prev {
+            info.stmts.insert(addr);
+        }
+        *prev = loc;
+    };
+    while r.pos < end {
+        let op = r.u8()?;
+        if op >= opcode_base {
+            let adj = (op - opcode_base) as u32;
+            addr = addr.wrapping_add(adj / line_range as u32 * min_inst);
+            line = line.wrapping_add(line_base + (adj % line_range as u32) as i64);
+            emit(info, &files, addr, file, line, stmt, &mut prev);
+            continue;
+        }
+        match op {
+            0 => {
+                let len = r.uleb()? as usize;
+                let next = r.pos.checked_add(len)?;
+                match r.u8()? {
+                    LNE_END_SEQUENCE => {
+                        info.rows.entry(addr).or_insert(None);
+                        (addr, file, line, stmt) = (0, 1, 1, default_stmt);
+                        prev = None;
+                    }
+                    LNE_SET_ADDRESS => addr = r.uint(len.checked_sub(1)?)? as u32,
+                    LNE_DEFINE_FILE => {
+                        let name = r.cstr()?;
+                        let dir = r.uleb()? as usize;
+                        files.push(info.file_index(join(&dirs, dir, name)));
+                    }
+                    _ => {}
+                }
+                r.pos = next;
+            }
+            LNS_COPY => emit(info, &files, addr, file, line, stmt, &mut prev),
+            LNS_ADVANCE_PC => addr = addr.wrapping_add((r.uleb()? as u32).wrapping_mul(min_inst)),
+            LNS_ADVANCE_LINE => line = line.wrapping_add(r.sleb()?),
+            LNS_SET_FILE => file = r.uleb()?,
+            LNS_NEGATE_STMT => stmt = !stmt,
+            LNS_CONST_ADD_PC => addr = addr.wrapping_add((255 - opcode_base as u32) / line_range as u32 * min_inst),
+            LNS_FIXED_ADVANCE_PC => addr = addr.wrapping_add(r.u16()? as u32),
+            _ => {
+                for _ in 0..std_lengths[op as usize - 1] {
+                    r.uleb()?;
+                }
+            }
+        }
+    }
+    Some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DWARF 4 unit for `/proj/src/main.c` (and `util.h`), with `main` at 0x10000.
+    fn sections() -> HashMap<&'static str, Vec<u8>> {
+        let mut header = vec![1, 1, 1, 0xfb, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1];
+        header.extend_from_slice(b"src\0\0main.c\0\x01\0\0util.h\0\0\0\0\0");
+        let program: &[u8] = &[
+            0, 5, 2, 0x00, 0x00, 0x01, 0x00, // set_address 0x10000
+            3, 2, 1, // line 3
+            75, // +4 bytes, line 4
+            4, 2, 3, 6, 2, 4, 1, // util.h, line 10, +4 bytes
+            4, 1, 3, 0x7a, 6, 9, 4, 0, 1, // main.c, line 4, not a statement, +4 bytes
+            6, 74, // a statement again, +4 bytes, same line
+            2, 4, 0, 1, 1, // +4 bytes, end_sequence
+        ];
+        let mut line = 4u16.to_le_bytes().to_vec();
+        line.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        line.extend_from_slice(&header);
+        line.extend_from_slice(program);
+        let mut debug_line = (line.len() as u32).to_le_bytes().to_vec();
+        debug_line.extend_from_slice(&line);
+
+        let abbrev = vec![1, 0x11, 1, 0x1b, 0x08, 0x10, 0x17, 0, 0, 2, 0x2e, 0, 0x03, 0x08, 0x11, 0x01, 0x12, 0x06, 0, 0, 0];
+        let mut unit = vec![4, 0, 0, 0, 0, 0, 4];
+        unit.extend_from_slice(b"\x01/proj\0\0\0\0\0\x02main\0");
+        unit.extend_from_slice(&0x10000u32.to_le_bytes());
+        unit.extend_from_slice(&0x14u32.to_le_bytes());
+        unit.push(0);
+        let mut info = (unit.len() as u32).to_le_bytes().to_vec();
+        info.extend_from_slice(&unit);
+
+        HashMap::from([(".debug_line", debug_line), (".debug_abbrev", abbrev), (".debug_info", info)])
+    }
+
+    #[test]
+    fn line_table_maps_addresses_to_source() {
+        let sections = sections();
+        let debug = DebugInfo::parse(|name| sections.get(name).map(Vec::as_slice)).expect("line table");
+        assert_eq!(debug.files, [PathBuf::from("/proj/src/main.c"), PathBuf::from("/proj/util.h")]);
+
+        let loc = |addr| debug.location(addr).map(|l| (l.file, l.line));
+        assert_eq!(loc(0xfffc), None);
+        assert_eq!(loc(0x10000), Some((0, 3)));
+        assert_eq!(loc(0x10006), Some((0, 4)));
+        assert_eq!(loc(0x10008), Some((1, 10)));
+        assert_eq!(loc(0x10010), Some((0, 4)));
+        assert_eq!(loc(0x10014), None);
+        assert_eq!(debug.describe(0x10008).as_deref(), Some("util.h:10"));
+
+        // Non-statement rows and rows that stay on the same line are not stops.
+        let stmts: Vec<u32> = debug.stmts().map(|(a, _)| a).collect();
+        assert_eq!(stmts, [0x10000, 0x10004, 0x10008]);
+
+        assert_eq!(debug.find_file(Path::new("src/main.c")), Some(0));
+        assert_eq!(debug.find_file(Path::new("/tmp/checkout/src/main.c")), Some(0));
+        assert_eq!(debug.find_file(Path::new("other.c")), None);
+        assert_eq!(debug.line_addrs(0, 4), Some((4, vec![0x10004])));
+        assert_eq!(debug.line_addrs(1, 2), Some((10, vec![0x10008])));
+        assert_eq!(debug.line_addrs(0, 5), None);
+
+        assert_eq!(debug.function_at(0x10010).map(|f| f.name.as_str()), Some("main"));
+        assert_eq!(debug.function_at(0x10014), None);
+    }
+
+    #[test]
+    fn malformed_line_programs_and_indices_do_not_overflow() {
+        let programs: [&[u8]; 5] = [
+            &[0, 0, 2],                                                           // set_address of length 0
+            &[0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 1], // extended op past usize::MAX
+            &[2, 0xff, 0xff, 0xff, 0xff, 0x0f, 1],                                // advance_pc × min_inst wraps
+            &[3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 3, 1, 1], // line past i64
+            &[0, 1, 1],
+        ];
+        for program in programs {
+            let mut sections = sections();
+            let line = sections.get_mut(".debug_line").unwrap();
+            line[10] = 4; // minimum instruction length
+            let start = 10 + u32::from_le_bytes(line[6..10].try_into().unwrap()) as usize;
+            line.truncate(start);
+            line.extend_from_slice(program);
+            let len = (line.len() - 4) as u32;
+            line[..4].copy_from_slice(&len.to_le_bytes());
+            let _ = DebugInfo::parse(|name| sections.get(name).map(Vec::as_slice));
+        }
+        assert_eq!(table_entry(8, u64::MAX), None);
+        assert_eq!(table_entry(u64::MAX, 1), None);
+        assert_eq!(table_entry(8, 2), Some(16));
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
+use std::ops::Range;
 use crate::falcon::{errors::FalconError, memory::Bus};
-use super::{load_bytes, zero_bytes};
+use super::{load_bytes, zero_bytes, DebugInfo};
 
 /// A data/rodata/bss section extracted from an ELF for the sections viewer.
 pub struct ElfSection {
@@ -11,14 +12,47 @@ pub struct ElfSection {
     pub bytes: Vec<u8>,
 }
 
+/// How far an ELF symbol is visible (`STB_*`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolBinding {
+    Local,
+    Global,
+    Weak,
+}
+
+/// A function or object from the ELF symbol table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElfSymbol {
+    pub name:    String,
+    pub addr:    u32,
+    /// `st_size`: bytes the function or object covers (0 when the toolchain left it out).
+    pub size:    u32,
+    pub binding: SymbolBinding,
+    /// STT_FUNC rather than STT_OBJECT.
+    pub func:    bool,
+}
+
+/// The sized function in `symbols` whose extent holds `addr`; the innermost one
+/// when several overlap.
+pub fn function_at(symbols: &[ElfSymbol], addr: u32) -> Option<&ElfSymbol> {
+    symbols
+        .iter()
+        .filter(|s| s.func && s.size > 0 && addr.wrapping_sub(s.addr) < s.size)
+        .min_by_key(|s| s.size)
+}
+
 /// Information about a loaded ELF32 image.
 pub struct ElfInfo {
     /// Virtual address of the entry point.
     pub entry:      u32,
-    /// Virtual address of the first executable PT_LOAD segment (used as base_pc).
+    /// Virtual address of the executable PT_LOAD segment holding the entry point,
+    /// or of the first one (used as base_pc).
     pub text_base:  u32,
-    /// Raw bytes of the executable segment (for the disassembler).
+    /// Raw bytes of that segment (for the disassembler).
     pub text_bytes: Vec<u8>,
+    /// Every executable PT_LOAD segment (`.init`, `.text`, code placed by a linker
+    /// script, …) as (vaddr, bytes), in address order.
+    pub code_segments: Vec<(u32, Vec<u8>)>,
     /// Virtual address of the first non-executable PT_LOAD segment (used as data_base).
     /// Falls back to `entry` when there is no writable segment.
     pub data_base:  u32,
@@ -28,8 +62,12 @@ pub struct ElfInfo {
     pub heap_start: u32,
     /// Symbol table: addr → list of names (STT_FUNC / STT_OBJECT, non-empty, non-`$`-prefixed).
     pub symbols:  HashMap<u32, Vec<String>>,
+    /// The same symbols with their size and binding, in address order.
+    pub symbol_table: Vec<ElfSymbol>,
     /// Data/rodata/bss sections for the sections viewer.
     pub sections: Vec<ElfSection>,
+    /// Source lines and functions from the DWARF sections, when the image has them.
+    pub debug: Option<DebugInfo>,
 }
 
 /// Parse and load an ELF32 LE RISC-V executable into `mem`.
@@ -79,8 +117,7 @@ pub fn load_elf<B: Bus>(bytes: &[u8], mem: &mut B) -> Result<ElfInfo, FalconErro
     const PT_LOAD: u32 = 1;
     const PF_X:    u32 = 1;
 
-    let mut text_bytes  = Vec::<u8>::new();
-    let mut text_base   = e_entry;
+    let mut code        = Vec::<(u32, &[u8])>::new();
     let mut data_base   = e_entry; // fallback
     let mut total_bytes = 0usize;
     let mut seg_end_max = 0u32; // highest byte past all PT_LOAD segments
@@ -115,8 +152,7 @@ pub fn load_elf<B: Bus>(bytes: &[u8], mem: &mut B) -> Result<ElfInfo, FalconErro
         }
 
         if p_flags & PF_X != 0 && p_filesz > 0 {
-            text_bytes = bytes[p_offset..p_offset + p_filesz].to_vec();
-            text_base  = p_vaddr;
+            code.push((p_vaddr, &bytes[p_offset..p_offset + p_filesz]));
         } else if data_base == e_entry && p_filesz > 0 {
             data_base = p_vaddr;
         }
@@ -129,25 +165,71 @@ pub fn load_elf<B: Bus>(bytes: &[u8], mem: &mut B) -> Result<ElfInfo, FalconErro
     // Heap starts right after all segments, 16-byte aligned
     let heap_start = (seg_end_max.wrapping_add(15)) & !15;
 
+    code.sort_by_key(|&(vaddr, _)| vaddr);
+    let code_segments: Vec<(u32, Vec<u8>)> = code.iter().map(|&(vaddr, seg)| (vaddr, seg.to_vec())).collect();
+    let main = code.iter().position(|&(vaddr, seg)| e_entry.wrapping_sub(vaddr) < seg.len() as u32).unwrap_or(0);
+    let (text_base, text_bytes) = code.get(main).map_or((e_entry, Vec::new()), |&(vaddr, seg)| (vaddr, seg.to_vec()));
+
     // ── Parse section headers (best-effort, non-fatal) ───────────────────
-    let (symbols, sections) = parse_sections(bytes, e_shoff, e_shentsize, e_shnum, e_shstrndx);
+    let (symbol_table, sections, debug) = parse_sections(bytes, e_shoff, e_shentsize, e_shnum, e_shstrndx);
+    let mut symbols: HashMap<u32, Vec<String>> = HashMap::new();
+    for sym in &symbol_table {
+        symbols.entry(sym.addr).or_default().push(sym.name.clone());
+    }
+
+    Ok(ElfInfo {
+        entry: e_entry,
+        text_base,
+        text_bytes,
+        code_segments,
+        data_base,
+        total_bytes,
+        heap_start,
+        symbols,
+        symbol_table,
+        sections,
+        debug,
+    })
+}
+
+impl ElfInfo {
+    /// Address ranges of the executable segments, in address order.
+    pub fn code_ranges(&self) -> Vec<Range<u32>> {
+        self.code_segments.iter().map(|(vaddr, seg)| *vaddr..vaddr.wrapping_add(seg.len() as u32)).collect()
+    }
+}
 
-    Ok(ElfInfo { entry: e_entry, text_base, text_bytes, data_base, total_bytes, heap_start, symbols, sections })
+/// Parse an ELF32 image like `load_elf` does, without loading it anywhere.
+pub fn inspect_elf(bytes: &[u8]) -> Result<ElfInfo, FalconError> {
+    load_elf(bytes, &mut Discard)
 }
 
-/// Parse section headers to extract the symbol table and data/rodata/bss sections.
-/// Returns empty maps on any structural problem (non-fatal).
+/// A bus that accepts every store and reads back zeros.
+struct Discard;
+
+impl Bus for Discard {
+    fn load8(&self, _: u32) -> Result<u8, FalconError> { Ok(0) }
+    fn load16(&self, _: u32) -> Result<u16, FalconError> { Ok(0) }
+    fn load32(&self, _: u32) -> Result<u32, FalconError> { Ok(0) }
+    fn store8(&mut self, _: u32, _: u8) -> Result<(), FalconError> { Ok(()) }
+    fn store16(&mut self, _: u32, _: u16) -> Result<(), FalconError> { Ok(()) }
+    fn store32(&mut self, _: u32, _: u32) -> Result<(), FalconError> { Ok(()) }
+}
+
+/// Parse section headers to extract the symbol table (in address order),
+/// data/rodata/bss sections and debug info. Returns empty tables on any
+/// structural problem (non-fatal).
 fn parse_sections(
     bytes: &[u8],
     e_shoff: usize,
     e_shentsize: usize,
     e_shnum: usize,
     e_shstrndx: usize,
-) -> (HashMap<u32, Vec<String>>, Vec<ElfSection>) {
-    let mut symbols: HashMap<u32, Vec<String>> = HashMap::new();
+) -> (Vec<ElfSymbol>, Vec<ElfSection>, Option<DebugInfo>) {
+    let mut symbols: Vec<ElfSymbol> = Vec::new();
     let mut sections: Vec<ElfSection> = Vec::new();
 
-    if e_shoff == 0 || e_shentsize < 40 || e_shnum == 0 { return (symbols, sections); }
+    if e_shoff == 0 || e_shentsize < 40 || e_shnum == 0 { return (symbols, sections, None); }
 
     let u32le = |o: usize| -> Option<u32> {
         bytes.get(o..o+4).map(|s| u32::from_le_bytes(s.try_into().unwrap()))
@@ -159,7 +241,7 @@ fn parse_sections(
     let mut shdrs: Vec<Shdr> = Vec::with_capacity(e_shnum);
     for i in 0..e_shnum {
         let base = e_shoff + i * e_shentsize;
-        if base + 40 > bytes.len() { return (symbols, sections); }
+        if base + 40 > bytes.len() { return (symbols, sections, None); }
         shdrs.push(Shdr {
             name_off: u32le_r(base),
             sh_type:  u32le_r(base + 4),
@@ -186,10 +268,13 @@ fn parse_sections(
     // ── Find .symtab section ──────────────────────────────────────────────
     const SHT_SYMTAB: u32 = 2;
     const SHT_STRTAB: u32 = 3;
+    const SHT_NOBITS: u32 = 8;
     const STT_OBJECT: u8  = 1;
     const STT_FUNC:   u8  = 2;
+    const STB_GLOBAL: u8  = 1;
+    const STB_WEAK:   u8  = 2;
 
-    for (i, sh) in shdrs.iter().enumerate() {
+    for sh in &shdrs {
         if sh.sh_type == SHT_SYMTAB {
             // Linked .strtab
             let strtab: &[u8] = if (sh.link as usize) < shdrs.len() {
@@ -207,25 +292,30 @@ fn parse_sections(
                 let o = j * 16;
                 let st_name  = u32::from_le_bytes(sym_data[o..o+4].try_into().unwrap()) as usize;
                 let st_value = u32::from_le_bytes(sym_data[o+4..o+8].try_into().unwrap());
+                let st_size  = u32::from_le_bytes(sym_data[o+8..o+12].try_into().unwrap());
                 let st_info  = sym_data[o + 12];
                 let sym_type = st_info & 0x0F;
                 if sym_type != STT_FUNC && sym_type != STT_OBJECT { continue; }
                 if st_value == 0 { continue; }
                 let name = cstr(strtab, st_name);
                 if name.is_empty() || name.starts_with('$') || name.starts_with(".L") { continue; }
-                symbols.entry(st_value).or_default().push(name);
+                let binding = match st_info >> 4 {
+                    STB_GLOBAL => SymbolBinding::Global,
+                    STB_WEAK => SymbolBinding::Weak,
+                    _ => SymbolBinding::Local,
+                };
+                symbols.push(ElfSymbol { name, addr: st_value, size: st_size, binding, func: sym_type == STT_FUNC });
             }
-            let _ = i; // suppress unused warning
         }
     }
+    symbols.sort_by_key(|s| s.addr);
 
     // ── Collect data/rodata/bss sections for the viewer ───────────────────
     for sh in &shdrs {
         if sh.addr == 0 || sh.size == 0 { continue; }
         let name = cstr(shstrtab, sh.name_off as usize);
         if !is_viewer_section(&name) { continue; }
-        // .bss sections have sh_type=SHT_NOBITS(8), no file bytes
-        const SHT_NOBITS: u32 = 8;
+        // .bss sections have sh_type=SHT_NOBITS, no file bytes
         let sec_bytes: Vec<u8> = if sh.sh_type == SHT_NOBITS {
             Vec::new()
         } else if sh.file_off + sh.size <= bytes.len() {
@@ -238,7 +328,13 @@ fn parse_sections(
     // Sort by address for stable display
     sections.sort_by_key(|s| s.addr);
 
-    (symbols, sections)
+    // ── DWARF line table and functions ────────────────────────────────────
+    let debug = DebugInfo::parse(|name| {
+        let sh = shdrs.iter().find(|sh| sh.sh_type != SHT_NOBITS && cstr(shstrtab, sh.name_off as usize) == name)?;
+        bytes.get(sh.file_off..sh.file_off.checked_add(sh.size)?)
+    });
+
+    (symbols, sections, debug)
 }
 
 /// Returns true for sections that should appear in the sections viewer:
@@ -288,6 +384,56 @@ mod tests {
         assert_ne!(word, 0, "entry point word is zero — segment not loaded");
     }
 
+    /// An image with `.init` and `.text` in separate executable segments, a data
+    /// segment, and a symbol table with sizes and bindings.
+    fn two_code_segments() -> Vec<u8> {
+        let mut b = vec![0u8; 0x520];
+        let put = |b: &mut Vec<u8>, at: usize, words: &[u32]| {
+            for (i, w) in words.iter().enumerate() {
+                b[at + i * 4..at + i * 4 + 4].copy_from_slice(&w.to_le_bytes());
+            }
+        };
+        b[0..6].copy_from_slice(b"\x7fELF\x01\x01");
+        put(&mut b, 16, &[0x00f3_0002, 1, 0x8004, 52, 0x480]);
+        put(&mut b, 40, &[0x0020_0034, 0x0028_0003, 0x0003_0004]);
+        // PT_LOAD: .init (RX), .text (RX), .data (RW)
+        put(&mut b, 52, &[1, 0x100, 0x1000, 0x1000, 8, 8, 5, 4]);
+        put(&mut b, 84, &[1, 0x200, 0x8000, 0x8000, 12, 12, 5, 4]);
+        put(&mut b, 116, &[1, 0x300, 0x9000, 0x9000, 4, 4, 6, 4]);
+        put(&mut b, 0x100, &[0x0000_0013, 0x0000_8067]);
+        put(&mut b, 0x200, &[0x0000_0013, 0x0000_0013, 0x0000_8067]);
+        // symbols: _init (local func), main (global func), table (weak object)
+        put(&mut b, 0x410, &[1, 0x1000, 8, 0x0002]);
+        put(&mut b, 0x420, &[7, 0x8000, 12, 0x0012]);
+        put(&mut b, 0x430, &[12, 0x9000, 4, 0x0021]);
+        b[0x440..0x452].copy_from_slice(b"\0_init\0main\0table\0");
+        b[0x460..0x47b].copy_from_slice(b"\0.symtab\0.strtab\0.shstrtab\0");
+        put(&mut b, 0x4a8, &[1, 2, 0, 0, 0x400, 0x40, 2, 1, 4, 16]);
+        put(&mut b, 0x4d0, &[9, 3, 0, 0, 0x440, 0x12, 0, 0, 1, 0]);
+        put(&mut b, 0x4f8, &[17, 3, 0, 0, 0x460, 0x1b, 0, 0, 1, 0]);
+        b
+    }
+
+    #[test]
+    fn every_code_segment_and_symbol_extent_is_kept() {
+        let info = inspect_elf(&two_code_segments()).expect("inspect_elf");
+        assert_eq!(info.code_ranges(), [0x1000..0x1008, 0x8000..0x800c]);
+        // text_base is the segment holding the entry point, not the last one seen
+        assert_eq!((info.text_base, info.text_bytes.len()), (0x8000, 12));
+        assert_eq!(info.data_base, 0x9000);
+
+        let syms: Vec<_> = info.symbol_table.iter().map(|s| (s.name.as_str(), s.addr, s.size, s.binding, s.func)).collect();
+        assert_eq!(syms, [
+            ("_init", 0x1000, 8, SymbolBinding::Local, true),
+            ("main", 0x8000, 12, SymbolBinding::Global, true),
+            ("table", 0x9000, 4, SymbolBinding::Weak, false),
+        ]);
+        assert_eq!(info.symbols[&0x1000], ["_init"]);
+        assert_eq!(function_at(&info.symbol_table, 0x8008).map(|s| s.name.as_str()), Some("main"));
+        assert_eq!(function_at(&info.symbol_table, 0x800c), None);
+        assert_eq!(function_at(&info.symbol_table, 0x9000), None);
+    }
+
     #[test]
     fn bad_magic_returns_error() {
         let mut bytes = vec![0u8; 64];
@@ -0,0 +1,321 @@
+use crate::falcon::asm::Program;
+use crate::falcon::link::Section;
+
+/// Where exported executables are assembled: the usual RV32 Linux text address,
+/// above `mmap_min_addr` so QEMU user mode can map it and symbols are never 0.
+pub const ELF_TEXT_BASE: u32 = 0x1_0000;
+
+const PAGE: usize = 0x1000;
+const EHDR_SIZE: usize = 52;
+const PHDR_SIZE: usize = 32;
+const SHDR_SIZE: usize = 40;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+/// Section indices in the header table written below.
+const SHN_TEXT: u16 = 1;
+const SHN_DATA: u16 = 2;
+const SHN_BSS: u16 = 3;
+
+/// One section header, in the order of the ELF32 `Elf32_Shdr` fields we set.
+struct Shdr {
+    name: u32,
+    ty: u32,
+    flags: u32,
+    addr: u32,
+    offset: usize,
+    size: usize,
+    link: u32,
+    info: u32,
+    align: u32,
+    entsize: u32,
+}
+
+/// Append `s` NUL-terminated to a string table and return its offset.
+fn add_str(table: &mut Vec<u8>, s: &str) -> u32 {
+    let off = table.len() as u32;
+    table.extend_from_slice(s.as_bytes());
+    table.push(0);
+    off
+}
+
+/// Smallest file offset `>= cur` congruent to `vaddr` modulo the page size, as
+/// `PT_LOAD` requires.
+fn congruent(cur: usize, vaddr: u32) -> usize {
+    let want = vaddr as usize % PAGE;
+    let base = cur - cur % PAGE + want;
+    if base >= cur { base } else { base + PAGE }
+}
+
+fn pad_to(out: &mut Vec<u8>, len: usize) {
+    out.resize(len, 0);
+}
+
+fn align(out: &mut Vec<u8>, to: usize) {
+    let len = out.len().next_multiple_of(to);
+    pad_to(out, len);
+}
+
+/// Serialise an assembled program as an ELF32 little-endian RISC-V executable.
+///
+/// `base_pc` is the address `prog` was assembled at; text gets a `PT_LOAD` segment
+/// and data another whose memory size runs on through bss, both at their assembled
+/// addresses. Execution starts at `entry`: `base_pc` for a program assembled on
+/// its own, as when RAVEN runs the source, or wherever the linker put `_start`.
+///
+/// Every label gets a `.symtab` entry (functions in `.text`, objects elsewhere, by
+/// the section it was defined in), so `load_elf` and binutils see the same names.
+/// Labels exported with `.globl` are global; the rest are local and come first.
+pub fn write_elf(prog: &Program, base_pc: u32, entry: u32) -> Vec<u8> {
+    let text: Vec<u8> = prog.text.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let bss_base = prog.data_base.wrapping_add(prog.data.len() as u32);
+
+    // (vaddr, file bytes, memory size, flags) for each non-empty segment; bss is
+    // the zero-filled tail of the data segment.
+    let segments: Vec<(u32, &[u8], usize, u32)> = [
+        (base_pc, &text[..], text.len(), PF_R | PF_X),
+        (prog.data_base, &prog.data[..], prog.data.len() + prog.bss_size as usize, PF_R | PF_W),
+    ]
+    .into_iter()
+    .filter(|&(_, _, memsz, _)| memsz > 0)
+    .collect();
+
+    let mut out = vec![0u8; EHDR_SIZE + segments.len() * PHDR_SIZE];
+    let mut offsets = Vec::with_capacity(segments.len());
+    for &(vaddr, bytes, _, _) in &segments {
+        let off = congruent(out.len(), vaddr);
+        pad_to(&mut out, off);
+        out.extend_from_slice(bytes);
+        offsets.push(off);
+    }
+
+    // Program headers.
+    for (i, (&(vaddr, bytes, memsz, flags), &off)) in segments.iter().zip(&offsets).enumerate() {
+        let ph = EHDR_SIZE + i * PHDR_SIZE;
+        let fields = [PT_LOAD, off as u32, vaddr, vaddr, bytes.len() as u32, memsz as u32, flags, PAGE as u32];
+        for (j, v) in fields.into_iter().enumerate() {
+            out[ph + j * 4..ph + j * 4 + 4].copy_from_slice(&v.to_le_bytes());
+        }
+    }
+    let offset_of = |vaddr: u32| {
+        segments.iter().zip(&offsets).find(|(s, _)| s.0 == vaddr).map_or(out.len(), |(_, &o)| o)
+    };
+    let (text_off, data_off) = (offset_of(base_pc), offset_of(prog.data_base));
+    let bss_off = data_off + prog.data.len();
+
+    // Symbols, locals before globals as ELF requires, each sorted by address then
+    // name so the output is reproducible.
+    let mut labels: Vec<(bool, u32, &String)> = prog
+        .labels
+        .iter()
+        .flat_map(|(&addr, names)| names.iter().map(move |n| (prog.globals.get(n) == Some(&addr), addr, n)))
+        .collect();
+    labels.sort();
+    let first_global = 1 + labels.iter().filter(|&&(global, _, _)| !global).count() as u32;
+    let mut strtab = vec![0u8];
+    let mut symtab = vec![0u8; 16]; // index 0: the undefined symbol
+    let text_end = base_pc.wrapping_add(text.len() as u32);
+    for (global, addr, name) in labels {
+        let section = prog.label_sections.get(&(addr, name.clone())).copied().unwrap_or(if (base_pc..text_end).contains(&addr) {
+            Section::Text
+        } else if addr < bss_base {
+            Section::Data
+        } else {
+            Section::Bss
+        });
+        let (kind, shndx) = match section {
+            Section::Text => (STT_FUNC, SHN_TEXT),
+            Section::Data => (STT_OBJECT, SHN_DATA),
+            Section::Bss => (STT_OBJECT, SHN_BSS),
+        };
+        symtab.extend_from_slice(&add_str(&mut strtab, name).to_le_bytes());
+        symtab.extend_from_slice(&addr.to_le_bytes());
+        symtab.extend_from_slice(&0u32.to_le_bytes()); // st_size: not tracked
+        let bind = if global { STB_GLOBAL } else { STB_LOCAL };
+        symtab.push((bind << 4) | kind);
+        symtab.push(0); // st_other: default visibility
+        symtab.extend_from_slice(&shndx.to_le_bytes());
+    }
+
+    let mut shstrtab = vec![0u8];
+    let names = [".text", ".data", ".bss", ".symtab", ".strtab", ".shstrtab"].map(|n| add_str(&mut shstrtab, n));
+
+    align(&mut out, 4);
+    let symtab_off = out.len();
+    out.extend_from_slice(&symtab);
+    let strtab_off = out.len();
+    out.extend_from_slice(&strtab);
+    let shstrtab_off = out.len();
+    out.extend_from_slice(&shstrtab);
+    align(&mut out, 4);
+    let shoff = out.len();
+
+    let sections = [
+        Shdr { name: 0, ty: 0, flags: 0, addr: 0, offset: 0, size: 0, link: 0, info: 0, align: 0, entsize: 0 },
+        Shdr {
+            name: names[0],
+            ty: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_EXECINSTR,
+            addr: base_pc,
+            offset: text_off,
+            size: text.len(),
+            link: 0,
+            info: 0,
+            align: 4,
+            entsize: 0,
+        },
+        Shdr {
+            name: names[1],
+            ty: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            addr: prog.data_base,
+            offset: data_off,
+            size: prog.data.len(),
+            link: 0,
+            info: 0,
+            align: 4,
+            entsize: 0,
+        },
+        Shdr {
+            name: names[2],
+            ty: SHT_NOBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            addr: bss_base,
+            offset: bss_off,
+            size: prog.bss_size as usize,
+            link: 0,
+            info: 0,
+            align: 4,
+            entsize: 0,
+        },
+        // sh_link → .strtab; sh_info = first global symbol
+        Shdr { name: names[3], ty: SHT_SYMTAB, flags: 0, addr: 0, offset: symtab_off, size: symtab.len(), link: 5, info: first_global, align: 4, entsize: 16 },
+        Shdr { name: names[4], ty: SHT_STRTAB, flags: 0, addr: 0, offset: strtab_off, size: strtab.len(), link: 0, info: 0, align: 1, entsize: 0 },
+        Shdr { name: names[5], ty: SHT_STRTAB, flags: 0, addr: 0, offset: shstrtab_off, size: shstrtab.len(), link: 0, info: 0, align: 1, entsize: 0 },
+    ];
+    for sh in &sections {
+        let fields = [sh.name, sh.ty, sh.flags, sh.addr, sh.offset as u32, sh.size as u32, sh.link, sh.info, sh.align, sh.entsize];
+        for v in fields {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+
+    // ELF header.
+    let mut eh = Vec::with_capacity(EHDR_SIZE);
+    eh.extend_from_slice(b"\x7fELF");
+    eh.extend_from_slice(&[1, 1, 1, 0]); // ELFCLASS32, ELFDATA2LSB, EV_CURRENT, ELFOSABI_SYSV
+    eh.extend_from_slice(&[0; 8]);
+    eh.extend_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+    eh.extend_from_slice(&0xF3u16.to_le_bytes()); // EM_RISCV
+    eh.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    eh.extend_from_slice(&entry.to_le_bytes()); // e_entry
+    eh.extend_from_slice(&(EHDR_SIZE as u32).to_le_bytes()); // e_phoff
+    eh.extend_from_slice(&(shoff as u32).to_le_bytes());
+    eh.extend_from_slice(&0u32.to_le_bytes()); // e_flags: soft-float ABI, no RVC
+    eh.extend_from_slice(&(EHDR_SIZE as u16).to_le_bytes());
+    eh.extend_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+    eh.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+    eh.extend_from_slice(&(SHDR_SIZE as u16).to_le_bytes());
+    eh.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    eh.extend_from_slice(&(sections.len() as u16 - 1).to_le_bytes()); // .shstrtab is last
+    out[..EHDR_SIZE].copy_from_slice(&eh);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::asm::assemble;
+    use crate::falcon::memory::Bus;
+    use crate::falcon::program::load_elf;
+    use crate::falcon::Ram;
+
+    #[test]
+    fn written_elf_loads_back_with_symbols() {
+        let src = ".text\nmain:\n  la a0, msg\n  lw a1, 0(a0)\n  halt\n.data\nmsg: .word 0x11223344\n.bss\nbuf: .space 16\n";
+        let prog = assemble(src, ELF_TEXT_BASE).expect("assemble");
+        let bytes = write_elf(&prog, ELF_TEXT_BASE, ELF_TEXT_BASE);
+
+        let mut mem = Ram::new(0x2_0000);
+        let info = load_elf(&bytes, &mut mem).expect("load_elf");
+        assert_eq!(info.entry, ELF_TEXT_BASE);
+        assert_eq!(info.text_base, ELF_TEXT_BASE);
+        assert_eq!(info.data_base, prog.data_base);
+        assert_eq!(info.heap_start, (prog.data_base + 4 + 16).next_multiple_of(16));
+        assert_eq!(mem.load32(ELF_TEXT_BASE).unwrap(), prog.text[0]);
+        assert_eq!(mem.load32(prog.data_base).unwrap(), 0x1122_3344);
+        assert_eq!(info.symbols[&ELF_TEXT_BASE], ["main"]);
+        assert_eq!(info.symbols[&prog.data_base], ["msg"]);
+        assert_eq!(info.symbols[&(prog.data_base + 4)], ["buf"]);
+        let names: Vec<_> = info.sections.iter().map(|s| (s.name.as_str(), s.size)).collect();
+        assert_eq!(names, [(".data", 4), (".bss", 16)]);
+    }
+
+    #[test]
+    fn segment_offsets_match_their_addresses_modulo_a_page() {
+        let prog = assemble("nop\n.data\n.byte 1\n", ELF_TEXT_BASE + 0x20).expect("assemble");
+        let bytes = write_elf(&prog, ELF_TEXT_BASE + 0x20, ELF_TEXT_BASE + 0x20);
+        let u32le = |o: usize| u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap());
+        let phnum = u16::from_le_bytes([bytes[44], bytes[45]]) as usize;
+        assert_eq!(phnum, 2);
+        for i in 0..phnum {
+            let ph = EHDR_SIZE + i * PHDR_SIZE;
+            assert_eq!(u32le(ph + 4) as usize % PAGE, u32le(ph + 8) as usize % PAGE);
+        }
+    }
+
+    #[test]
+    fn bss_extends_the_data_segment_and_end_labels_keep_their_section() {
+        let src = "main:\n  halt\nend:\n.data\nmsg: .word 1\nmsg_end:\n.bss\nbuf: .space 16\n";
+        let prog = assemble(src, ELF_TEXT_BASE).expect("assemble");
+        let bytes = write_elf(&prog, ELF_TEXT_BASE, ELF_TEXT_BASE);
+        let u32le = |o: usize| u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap());
+        assert_eq!(u16::from_le_bytes([bytes[44], bytes[45]]), 2);
+        let data_ph = EHDR_SIZE + PHDR_SIZE;
+        assert_eq!((u32le(data_ph + 8), u32le(data_ph + 16), u32le(data_ph + 20)), (prog.data_base, 4, 20));
+
+        let info = crate::falcon::program::inspect_elf(&bytes).expect("inspect_elf");
+        let func = |name: &str| info.symbol_table.iter().find(|s| s.name == name).unwrap().func;
+        assert!(func("end"), "a label after the last instruction stays in .text");
+        assert!(!func("msg_end"));
+        let bss = info.sections.iter().find(|s| s.name == ".bss").unwrap();
+        assert_eq!((bss.addr, bss.size), (prog.data_base + 4, 16));
+    }
+
+    #[test]
+    fn only_globl_labels_are_global_and_locals_come_first() {
+        let src = ".globl main\n.text\nmain:\nloop:\n  j loop\n.data\nmsg: .word 1\n";
+        let prog = assemble(src, ELF_TEXT_BASE).expect("assemble");
+        let bytes = write_elf(&prog, ELF_TEXT_BASE, ELF_TEXT_BASE);
+
+        let info = crate::falcon::program::inspect_elf(&bytes).expect("inspect_elf");
+        let binding = |name: &str| info.symbol_table.iter().find(|s| s.name == name).unwrap().binding;
+        use crate::falcon::program::SymbolBinding;
+        assert_eq!(binding("main"), SymbolBinding::Global);
+        assert_eq!(binding("loop"), SymbolBinding::Local);
+        assert_eq!(binding("msg"), SymbolBinding::Local);
+
+        // .symtab is section 4; its sh_info is the index of the first global.
+        let u32le = |o: usize| u32::from_le_bytes(bytes[o..o + 4].try_into().unwrap());
+        let symtab = u32le(32) as usize + 4 * SHDR_SIZE;
+        assert_eq!(u32le(symtab + 28), 3);
+        let main = u32le(symtab + 16) as usize + 3 * 16;
+        assert_eq!(bytes[main + 12] >> 4, STB_GLOBAL);
+    }
+}
@@ -0,0 +1,50 @@
+/// A program split out of a FALC container (what Export → `[BIN]` writes):
+/// `"FALC"`, the text, data and bss sizes as u32 LE, then the text and data bytes.
+/// BSS is not stored; the loader zero-fills it.
+pub struct Falc<'a> {
+    pub text: &'a [u8],
+    pub data: &'a [u8],
+    pub bss_size: u32,
+}
+
+/// Split `bytes` into text, data and bss. Without the FALC magic it is a flat
+/// binary: all text, no data.
+pub fn parse_falc(bytes: &[u8]) -> Result<Falc<'_>, &'static str> {
+    if bytes.len() < 16 || &bytes[0..4] != b"FALC" {
+        return Ok(Falc { text: bytes, data: &[], bss_size: 0 });
+    }
+    let field = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let (text_sz, data_sz) = (field(4) as usize, field(8) as usize);
+    let body = &bytes[16..];
+    match text_sz.checked_add(data_sz) {
+        Some(len) if len <= body.len() => Ok(Falc {
+            text: &body[..text_sz],
+            data: &body[text_sz..len],
+            bss_size: field(12),
+        }),
+        _ => Err("Binary truncated or corrupt"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn containers_split_and_flat_binaries_are_all_text() {
+        let mut falc = b"FALC".to_vec();
+        for n in [4u32, 2, 8] {
+            falc.extend_from_slice(&n.to_le_bytes());
+        }
+        falc.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let img = parse_falc(&falc).unwrap();
+        assert_eq!((img.text, img.data, img.bss_size), (&[1, 2, 3, 4][..], &[5, 6][..], 8));
+
+        assert!(parse_falc(&falc[..21]).is_err());
+        falc[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_falc(&falc).is_err());
+
+        let flat = parse_falc(&[0x13, 0, 0, 0]).unwrap();
+        assert_eq!((flat.text, flat.data, flat.bss_size), (&[0x13, 0, 0, 0][..], &[][..], 0));
+    }
+}
@@ -1,5 +1,13 @@
 mod loader;
+pub mod dwarf;
 pub mod elf;
+mod elf_writer;
+mod falc;
+mod stack;
 
 pub use loader::{load_words, load_bytes, zero_bytes};
-pub use elf::{load_elf, ElfSection};
+pub use dwarf::DebugInfo;
+pub use elf::{function_at, inspect_elf, load_elf, ElfSection, ElfSymbol, SymbolBinding};
+pub use elf_writer::{write_elf, ELF_TEXT_BASE};
+pub use falc::{parse_falc, Falc};
+pub use stack::{init_stack, ProgramArgs};
@@ -0,0 +1,173 @@
+// falcon/program/stack.rs
+//
+// The initial stack a Linux RISC-V process starts with (psABI): from `sp` up,
+// argc, the argv pointers and a NULL, the envp pointers and a NULL, then auxv
+// (type, value) pairs ending in AT_NULL. The strings they point to sit above,
+// below the top of RAM.
+use crate::falcon::{errors::FalconError, memory::Bus, Cpu};
+use super::load_bytes;
+
+const AT_NULL: u32 = 0;
+const AT_PAGESZ: u32 = 6;
+const AT_ENTRY: u32 = 9;
+const AT_RANDOM: u32 = 25;
+const AT_EXECFN: u32 = 31;
+
+/// Arguments and environment handed to the guest program.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgramArgs {
+    /// argv[1..]; argv[0] is the program name.
+    pub args: Vec<String>,
+    /// `NAME=value` entries.
+    pub env: Vec<String>,
+}
+
+impl ProgramArgs {
+    pub fn is_empty(&self) -> bool {
+        self.args.is_empty() && self.env.is_empty()
+    }
+
+    /// argv as one line, `argv0` first: what semihosting's `SYS_GET_CMDLINE` reports.
+    pub fn command_line(&self, argv0: &str) -> String {
+        let argv = std::iter::once(argv0.to_string()).chain(self.args.iter().cloned()).collect();
+        ProgramArgs { args: argv, env: Vec::new() }.to_string()
+    }
+
+    /// Split a command line the way a shell would for simple cases: words are
+    /// separated by spaces, quotes group them, and leading `NAME=value` words
+    /// become environment variables (`DEBUG=1 in.txt -v`).
+    pub fn parse(line: &str) -> Result<ProgramArgs, String> {
+        let mut words = Vec::new();
+        let mut word: Option<String> = None;
+        let mut quote = None;
+        for c in line.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => word.get_or_insert_default().push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    word.get_or_insert_default();
+                }
+                (None, c) if c.is_whitespace() => words.extend(word.take()),
+                (None, c) => word.get_or_insert_default().push(c),
+            }
+        }
+        if quote.is_some() {
+            return Err("unterminated quote".into());
+        }
+        words.extend(word);
+        let env_len = words.iter().take_while(|w| is_assignment(w)).count();
+        let args = words.split_off(env_len);
+        Ok(ProgramArgs { args, env: words })
+    }
+}
+
+/// The command line `parse` reads back: env first, words with spaces or quotes quoted.
+impl std::fmt::Display for ProgramArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, word) in self.env.iter().chain(&self.args).enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            if word.is_empty() || word.contains([' ', '\t', '"', '\'']) {
+                let q = if word.contains('\'') { '"' } else { '\'' };
+                write!(f, "{sep}{q}{word}{q}")?;
+            } else {
+                write!(f, "{sep}{word}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `NAME=value` with a valid variable name.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// Lay out argc/argv/envp/auxv below `cpu`'s current `sp` and move `sp` down to
+/// argc. `a0`, `a1` and `a2` also get argc, argv and envp, so assembly programs
+/// can use them without walking the stack. AT_ENTRY is the current PC.
+pub fn init_stack<B: Bus>(cpu: &mut Cpu, mem: &mut B, argv0: &str, args: &ProgramArgs) -> Result<(), FalconError> {
+    let mut top = cpu.x[2];
+    let env: Vec<u32> = args.env.iter().map(|s| push_str(mem, &mut top, s)).collect::<Result<_, _>>()?;
+    let mut argv = vec![push_str(mem, &mut top, argv0)?];
+    for a in &args.args {
+        argv.push(push_str(mem, &mut top, a)?);
+    }
+    // Fixed bytes keep runs reproducible; AT_RANDOM only needs to point at 16 of them.
+    let random = push(mem, &mut top, b"RAVEN-RISCV-SEED")?;
+    let auxv = [(AT_PAGESZ, 4096), (AT_ENTRY, cpu.pc), (AT_RANDOM, random), (AT_EXECFN, argv[0]), (AT_NULL, 0)];
+
+    let mut words = vec![argv.len() as u32];
+    words.extend(&argv);
+    words.push(0);
+    words.extend(&env);
+    words.push(0);
+    words.extend(auxv.iter().flat_map(|&(ty, val)| [ty, val]));
+    let sp = (top & !15).wrapping_sub(words.len() as u32 * 4) & !15;
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    load_bytes(mem, sp, &bytes)?;
+
+    cpu.write(2, sp);
+    cpu.write(10, argv.len() as u32);
+    cpu.write(11, sp + 4);
+    cpu.write(12, sp + 4 * (argv.len() as u32 + 2));
+    Ok(())
+}
+
+/// Store `bytes` just below `top`, moving it down; returns their address.
+fn push<B: Bus>(mem: &mut B, top: &mut u32, bytes: &[u8]) -> Result<u32, FalconError> {
+    *top = top.wrapping_sub(bytes.len() as u32);
+    load_bytes(mem, *top, bytes)?;
+    Ok(*top)
+}
+
+fn push_str<B: Bus>(mem: &mut B, top: &mut u32, s: &str) -> Result<u32, FalconError> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.push(0);
+    push(mem, top, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::Ram;
+
+    fn c_string(mem: &Ram, mut addr: u32) -> String {
+        let mut s = String::new();
+        while let Ok(b) = mem.load8(addr) && b != 0 {
+            s.push(b as char);
+            addr += 1;
+        }
+        s
+    }
+
+    #[test]
+    fn stack_holds_argc_argv_envp_and_auxv() {
+        let mut mem = Ram::new(0x1000);
+        let mut cpu = Cpu::default();
+        cpu.write(2, 0x1000);
+        cpu.pc = 0x400;
+        let args = ProgramArgs::parse("HOME=/home/raven 'in put.txt' -v").unwrap();
+        assert_eq!(args.env, ["HOME=/home/raven"]);
+        assert_eq!(ProgramArgs::parse(&args.to_string()).unwrap(), args);
+        init_stack(&mut cpu, &mut mem, "wc", &args).unwrap();
+
+        let sp = cpu.x[2];
+        assert_eq!(sp % 16, 0);
+        let word = |i: u32| mem.load32(sp + 4 * i).unwrap();
+        assert_eq!((word(0), cpu.x[10], cpu.x[11], cpu.x[12]), (3, 3, sp + 4, sp + 20));
+        let argv: Vec<String> = (1..4).map(|i| c_string(&mem, word(i))).collect();
+        assert_eq!(argv, ["wc", "in put.txt", "-v"]);
+        assert_eq!(word(4), 0);
+        assert_eq!(c_string(&mem, word(5)), "HOME=/home/raven");
+        assert_eq!(word(6), 0);
+        let auxv: Vec<(u32, u32)> = (0..5).map(|i| (word(7 + 2 * i), word(8 + 2 * i))).collect();
+        assert_eq!(auxv[0], (AT_PAGESZ, 4096));
+        assert_eq!(auxv[1], (AT_ENTRY, 0x400));
+        assert_eq!(c_string(&mem, auxv[3].1), "wc");
+        assert_eq!(auxv[4], (AT_NULL, 0));
+    }
+}
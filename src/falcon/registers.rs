@@ -1,4 +1,11 @@
 // falcon/registers.rs
+use super::clock::GuestClock;
+use super::mmap::Mappings;
+use super::semihost::Semihost;
+use super::strace::SyscallTrace;
+use super::syscall::{RandomStreams, SyscallAbi};
+use super::vfs::Vfs;
+
 #[derive(Default, Clone)]
 pub struct Cpu {
     pub x: [u32; 32],   // x0..x31 (integer registers)
@@ -17,6 +24,20 @@ pub struct Cpu {
     pub ebreak_hit: bool,
     /// Current program break (heap end). Set by the loader; advanced by SYS_BRK.
     pub heap_break: u32,
+    /// `mmap` regions and the stack reserve `brk` and `mmap` stay out of.
+    pub mmap: Mappings,
+    /// Guest files and this run's open descriptors.
+    pub fs: Vfs,
+    /// What `clock_gettime` and friends read; `nanosleep` advances it.
+    pub clock: GuestClock,
+    /// Every `ecall` this run made, for the syscall panel and `--strace`.
+    pub trace: SyscallTrace,
+    /// Which syscall numbers `ecall` answers to.
+    pub abi: SyscallAbi,
+    /// RARS's random number streams.
+    pub random: RandomStreams,
+    /// Semihosting command line and errno.
+    pub semihost: Semihost,
 }
 
 impl Cpu {
@@ -0,0 +1,323 @@
+// falcon/semihost.rs
+//
+// RISC-V semihosting: an `ebreak` between `slli x0, x0, 0x1f` and
+// `srai x0, x0, 7` asks the host for a service instead of stopping. `a0` holds
+// the operation and `a1` its argument (usually the address of a block of
+// words); the result goes back in `a0`. Covers what semihosting-enabled
+// picolibc and `riscv-rt` test binaries use: console output, files, the clock,
+// the command line and exit.
+use super::{errors::FalconError, memory::Bus, registers::Cpu, vfs};
+use super::syscall::{console_write_bytes, console_write_bytes_colored, read_zstr, store_bytes};
+use crate::ui::Console;
+use crate::ui::console::ConsoleColor;
+
+/// `slli x0, x0, 0x1f`, the word before the `ebreak`.
+pub const ENTRY_NOP: u32 = 0x01f0_1013;
+/// `srai x0, x0, 7`, the word after it.
+pub const EXIT_NOP: u32 = 0x4070_5013;
+
+pub const SYS_OPEN: u32 = 0x01;
+pub const SYS_CLOSE: u32 = 0x02;
+pub const SYS_WRITEC: u32 = 0x03;
+pub const SYS_WRITE0: u32 = 0x04;
+pub const SYS_WRITE: u32 = 0x05;
+pub const SYS_READ: u32 = 0x06;
+pub const SYS_CLOCK: u32 = 0x10;
+pub const SYS_TIME: u32 = 0x11;
+pub const SYS_ERRNO: u32 = 0x13;
+pub const SYS_GET_CMDLINE: u32 = 0x15;
+pub const SYS_EXIT: u32 = 0x18;
+pub const SYS_EXIT_EXTENDED: u32 = 0x20;
+
+/// `ADP_Stopped_ApplicationExit`: the only exit reason that means success.
+const APPLICATION_EXIT: u32 = 0x20026;
+/// What a failed call leaves in `a0`.
+const FAILED: u32 = u32::MAX;
+
+const O_WRONLY: u32 = 0o1;
+const O_RDWR: u32 = 0o2;
+const O_CREAT: u32 = 0o100;
+const O_TRUNC: u32 = 0o1000;
+const O_APPEND: u32 = 0o2000;
+
+/// Per-run semihosting state.
+#[derive(Clone, Debug, Default)]
+pub struct Semihost {
+    /// What `SYS_GET_CMDLINE` reports: argv[0] and the program arguments.
+    pub cmdline: String,
+    /// errno of the last call that failed, for `SYS_ERRNO`.
+    errno: u32,
+}
+
+/// Whether the `ebreak` at `pc` sits inside the semihosting sequence.
+pub fn is_call<B: Bus>(mem: &B, pc: u32) -> bool {
+    let Some(before) = pc.checked_sub(4) else { return false };
+    mem.load32(before).is_ok_and(|w| w == ENTRY_NOP)
+        && mem.load32(pc.wrapping_add(4)).is_ok_and(|w| w == EXIT_NOP)
+}
+
+/// Run the operation in `a0`. Same contract as `handle_syscall`: `Ok(false)`
+/// with `console.reading` set means the call is waiting for input and must be
+/// retried; otherwise `Ok(false)` stops the run.
+pub fn handle<B: Bus>(cpu: &mut Cpu, mem: &mut B, console: &mut Console) -> Result<bool, FalconError> {
+    let (op, arg) = (cpu.read(10), cpu.read(11));
+    let word = |mem: &B, i: u32| mem.load32(arg.wrapping_add(4 * i));
+    let ret = match op {
+        SYS_OPEN => {
+            let (name, mode, len) = (word(mem, 0)?, word(mem, 1)?, word(mem, 2)?);
+            let mut path = Vec::new();
+            for i in 0..len {
+                path.push(mem.load8(name.wrapping_add(i))?);
+            }
+            let path = String::from_utf8_lossy(&path);
+            if path == ":tt" {
+                // stdin for the read modes, stdout for "w", stderr for "a"
+                (mode / 4).min(2)
+            } else {
+                let r = open_flags(mode).ok_or(vfs::EINVAL).and_then(|flags| cpu.fs.open(&path, flags));
+                cpu.semihost.result(r)
+            }
+        }
+        SYS_CLOSE => {
+            let handle = word(mem, 0)?;
+            if handle < vfs::FIRST_FD { 0 } else { cpu.semihost.result(cpu.fs.close(handle).map(|()| 0)) }
+        }
+        SYS_WRITEC => {
+            let byte = mem.load8(arg)?;
+            write_console(cpu, console, 1, &[byte]);
+            return Ok(true);
+        }
+        SYS_WRITE0 => {
+            let bytes = read_zstr(mem, arg)?;
+            write_console(cpu, console, 1, &bytes);
+            return Ok(true);
+        }
+        SYS_WRITE => {
+            // Returns how many bytes were *not* written
+            let (handle, buf, len) = (word(mem, 0)?, word(mem, 1)?, word(mem, 2)?);
+            let mut bytes = Vec::new();
+            for i in 0..len {
+                bytes.push(mem.load8(buf.wrapping_add(i))?);
+            }
+            match handle {
+                1 | 2 => {
+                    write_console(cpu, console, handle, &bytes);
+                    0
+                }
+                h if h >= vfs::FIRST_FD => match cpu.fs.write(h, &bytes) {
+                    Ok(n) => len - n,
+                    Err(e) => {
+                        cpu.semihost.errno = e;
+                        len
+                    }
+                },
+                _ => {
+                    cpu.semihost.errno = vfs::EBADF;
+                    len
+                }
+            }
+        }
+        SYS_READ => {
+            // Returns how many bytes were *not* read: `len` at end of file
+            let (handle, buf, len) = (word(mem, 0)?, word(mem, 1)?, word(mem, 2)?);
+            let bytes = match handle {
+                0 => {
+                    if len > 0 && cpu.stdin.is_empty() {
+                        let Some(line) = console.read_line() else {
+                            console.reading = true;
+                            return Ok(false);
+                        };
+                        console.reading = false;
+                        cpu.stdin.extend_from_slice(line.as_bytes());
+                        cpu.stdin.push(b'\n');
+                    }
+                    let n = (len as usize).min(cpu.stdin.len());
+                    cpu.stdin.drain(..n).collect()
+                }
+                h if h >= vfs::FIRST_FD => cpu.fs.read(h, len as usize).unwrap_or_else(|e| {
+                    cpu.semihost.errno = e;
+                    Vec::new()
+                }),
+                _ => {
+                    cpu.semihost.errno = vfs::EBADF;
+                    Vec::new()
+                }
+            };
+            store_bytes(mem, buf, &bytes)?;
+            len - bytes.len() as u32
+        }
+        SYS_CLOCK => {
+            // Centiseconds since the program started
+            let ns = cpu.clock.cpu_time_ns(mem.cycles()).saturating_add(cpu.clock.slept_ns);
+            (ns / 10_000_000) as u32
+        }
+        SYS_TIME => (cpu.clock.now_ns(mem.cycles()) / 1_000_000_000) as u32,
+        SYS_ERRNO => cpu.semihost.errno,
+        SYS_GET_CMDLINE => {
+            // [buf, len]: the line, NUL-terminated, and its length back in `len`
+            let (buf, len) = (word(mem, 0)?, word(mem, 1)?);
+            let line = cpu.semihost.cmdline.as_bytes();
+            if line.len() >= len as usize {
+                FAILED
+            } else {
+                store_bytes(mem, buf, line)?;
+                mem.store8(buf.wrapping_add(line.len() as u32), 0)?;
+                mem.store32(arg.wrapping_add(4), line.len() as u32)?;
+                0
+            }
+        }
+        SYS_EXIT => {
+            // On RV32 `a1` is the reason itself, not a block
+            return Ok(exit(cpu, console, if arg == APPLICATION_EXIT { 0 } else { 1 }));
+        }
+        SYS_EXIT_EXTENDED => {
+            let (reason, code) = (word(mem, 0)?, word(mem, 1)?);
+            return Ok(exit(cpu, console, if reason == APPLICATION_EXIT { code } else { 1 }));
+        }
+        _ => {
+            console.push_error(format!("Unimplemented semihosting call 0x{op:02X}"));
+            return Ok(false);
+        }
+    };
+    cpu.write(10, ret);
+    Ok(true)
+}
+
+impl Semihost {
+    /// `a0` for a VFS result: the value, or -1 after saving the errno.
+    fn result(&mut self, r: Result<u32, u32>) -> u32 {
+        r.unwrap_or_else(|e| {
+            self.errno = e;
+            FAILED
+        })
+    }
+}
+
+/// `fopen` mode index (`r`, `rb`, `r+`, `r+b`, `w`, ... `a+b`) as open flags.
+fn open_flags(mode: u32) -> Option<u32> {
+    let access = if mode & 2 != 0 { O_RDWR } else { 0 };
+    match mode / 4 {
+        0 => Some(access),
+        1 => Some(access.max(O_WRONLY) | O_CREAT | O_TRUNC),
+        2 => Some(access.max(O_WRONLY) | O_CREAT | O_APPEND),
+        _ => None,
+    }
+}
+
+fn write_console(cpu: &mut Cpu, console: &mut Console, handle: u32, bytes: &[u8]) {
+    cpu.stdout.extend_from_slice(bytes);
+    if handle == 2 {
+        console_write_bytes_colored(console, bytes, ConsoleColor::Error);
+    } else {
+        console_write_bytes(console, bytes);
+    }
+}
+
+/// End the run with `code`, as the Linux `exit` does. Always `false`.
+fn exit(cpu: &mut Cpu, console: &mut Console, code: u32) -> bool {
+    cpu.exit_code = Some(code);
+    console.push_error(format!("Exit {}", code as i32));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::{exec::step, memory::Ram};
+
+    /// The semihosting sequence at 0, with `a0 = op` and `a1 = arg`.
+    fn setup(op: u32, arg: u32) -> (Cpu, Ram, Console) {
+        let mut mem = Ram::new(1024);
+        for (i, w) in [ENTRY_NOP, 0x0010_0073, EXIT_NOP].into_iter().enumerate() {
+            mem.store32(4 * i as u32, w).unwrap();
+        }
+        let mut cpu = Cpu::default();
+        cpu.write(10, op);
+        cpu.write(11, arg);
+        (cpu, mem, Console::default())
+    }
+
+    fn run(cpu: &mut Cpu, mem: &mut Ram, console: &mut Console) -> bool {
+        step(cpu, mem, console).unwrap() && step(cpu, mem, console).unwrap() && step(cpu, mem, console).unwrap()
+    }
+
+    #[test]
+    fn write0_prints_and_continues_past_the_sequence() {
+        let (mut cpu, mut mem, mut console) = setup(SYS_WRITE0, 0x100);
+        store_bytes(&mut mem, 0x100, b"hi\n\0").unwrap();
+        assert!(run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.stdout, b"hi\n");
+        assert_eq!(cpu.pc, 12);
+        assert!(!cpu.ebreak_hit);
+    }
+
+    #[test]
+    fn plain_ebreak_still_pauses() {
+        let (mut cpu, mut mem, mut console) = setup(SYS_WRITE0, 0x100);
+        mem.store32(8, 0x0000_0013).unwrap(); // nop instead of srai x0, x0, 7
+        assert!(!run(&mut cpu, &mut mem, &mut console));
+        assert!(cpu.ebreak_hit);
+    }
+
+    #[test]
+    fn file_round_trip_and_cmdline() {
+        let (mut cpu, mut mem, mut console) = setup(SYS_OPEN, 0x100);
+        store_bytes(&mut mem, 0x200, b"out.txt").unwrap();
+        for (i, w) in [0x200, 4, 7].into_iter().enumerate() {
+            mem.store32(0x100 + 4 * i as u32, w).unwrap(); // "w"
+        }
+        assert!(run(&mut cpu, &mut mem, &mut console));
+        let fd = cpu.read(10);
+        assert!(fd >= vfs::FIRST_FD);
+
+        cpu.pc = 0;
+        cpu.write(10, SYS_WRITE);
+        for (i, w) in [fd, 0x200, 3].into_iter().enumerate() {
+            mem.store32(0x100 + 4 * i as u32, w).unwrap();
+        }
+        assert!(run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.read(10), 0);
+        assert_eq!(cpu.fs.files().find(|(p, _)| *p == "out.txt").unwrap().1, b"out");
+
+        cpu.pc = 0;
+        cpu.semihost.cmdline = "prog -v".into();
+        cpu.write(10, SYS_GET_CMDLINE);
+        mem.store32(0x100, 0x300).unwrap();
+        mem.store32(0x104, 4).unwrap();
+        assert!(run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.read(10), FAILED); // doesn't fit
+        cpu.pc = 0;
+        cpu.write(10, SYS_GET_CMDLINE);
+        mem.store32(0x104, 64).unwrap();
+        assert!(run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.read(10), 0);
+        assert_eq!(read_zstr(&mem, 0x300).unwrap(), b"prog -v");
+        assert_eq!(mem.load32(0x104).unwrap(), 7);
+    }
+
+    #[test]
+    fn bogus_lengths_fault_without_allocating_them() {
+        for op in [SYS_OPEN, SYS_WRITE] {
+            let (mut cpu, mut mem, mut console) = setup(op, 0x100);
+            let block = if op == SYS_OPEN { [0x200, 4, 0xFFFF_FFF0] } else { [1, 0x200, 0xFFFF_FFF0] };
+            for (i, w) in block.into_iter().enumerate() {
+                mem.store32(0x100 + 4 * i as u32, w).unwrap();
+            }
+            assert!(step(&mut cpu, &mut mem, &mut console).unwrap());
+            assert!(step(&mut cpu, &mut mem, &mut console).is_err());
+        }
+    }
+
+    #[test]
+    fn exit_reports_the_status() {
+        let (mut cpu, mut mem, mut console) = setup(SYS_EXIT, APPLICATION_EXIT);
+        assert!(!run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.exit_code, Some(0));
+
+        let (mut cpu, mut mem, mut console) = setup(SYS_EXIT_EXTENDED, 0x100);
+        mem.store32(0x100, APPLICATION_EXIT).unwrap();
+        mem.store32(0x104, 3).unwrap();
+        assert!(!run(&mut cpu, &mut mem, &mut console));
+        assert_eq!(cpu.exit_code, Some(3));
+    }
+}
@@ -0,0 +1,306 @@
+// falcon/strace.rs
+//
+// strace-style record of every `ecall` a run made: where it came from, what it
+// asked for (strings and buffers read out of guest memory), what it got back
+// and whether it had to wait for console input. `exec::step` records; the Run
+// tab's syscall panel and `raven run --strace` show it.
+use std::collections::VecDeque;
+
+use super::memory::Bus;
+
+/// Oldest records are dropped past this many.
+const MAX_RECORDS: usize = 10_000;
+/// Bytes of a string or buffer argument shown before `...`.
+const MAX_SHOWN: usize = 32;
+
+/// How an argument register is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arg {
+    /// Signed decimal.
+    Int,
+    /// Unsigned decimal (sizes, counts).
+    Uint,
+    /// Hexadecimal (addresses, flags).
+    Hex,
+    /// NUL-terminated string at the address.
+    Str,
+    /// Buffer the call reads; its length is the argument at this index.
+    InBuf(usize),
+    /// Buffer the call fills; its length is the return value.
+    OutBuf,
+    /// NUL-terminated string the call stores.
+    OutStr,
+}
+
+/// What a syscall leaves in `a0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ret {
+    /// Nothing (most teaching calls).
+    Nothing,
+    /// A result or `-errno` (the Linux calls).
+    Errno,
+    /// A plain value that may be negative (RARS's `ReadInt`, `Sbrk`, ...).
+    Value,
+}
+
+/// Name and argument layout of a syscall.
+#[derive(Clone, Copy, Debug)]
+pub struct SyscallDesc {
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    pub ret: Ret,
+}
+
+/// How a traced call ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// Returned; the result or `-errno` left in `a0`, if the call has one.
+    Returned(Option<u32>),
+    /// Returned a plain value in `a0`.
+    Value(u32),
+    /// Still waiting for console input.
+    Waiting,
+    /// Ended the program with this status.
+    Exited(u32),
+    /// Stopped the run without returning (unknown call, bad address, ...).
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct SyscallRecord {
+    pub pc: u32,
+    /// `a7` at the `ecall`.
+    pub number: u32,
+    /// `None` for numbers the handler doesn't know.
+    pub name: Option<&'static str>,
+    /// Decoded `a0..`, as shown in the trace.
+    pub args: Vec<String>,
+    pub outcome: Outcome,
+    /// Waited for console input before completing.
+    pub blocked: bool,
+}
+
+impl SyscallRecord {
+    /// `write(1, "hi\n", 3) = 3`
+    pub fn call(&self) -> String {
+        let name = self.name.map_or_else(|| format!("syscall_{}", self.number), str::to_string);
+        let ret = match &self.outcome {
+            Outcome::Returned(Some(v)) => format_ret(*v),
+            Outcome::Returned(None) => "?".to_string(),
+            Outcome::Value(v) if (*v as i32) < 0 => (*v as i32).to_string(),
+            Outcome::Value(v) => format_value(*v),
+            Outcome::Waiting => "? <waiting for input>".to_string(),
+            Outcome::Exited(code) => format!("? <exit {}>", *code as i32),
+            Outcome::Failed(why) => format!("? <{why}>"),
+        };
+        format!("{name}({}) = {ret}", self.args.join(", "))
+    }
+
+    /// One line of the exported trace: `0x00000010 [64] write(1, "hi\n", 3) = 3`.
+    pub fn line(&self) -> String {
+        let blocked = if self.blocked && self.outcome != Outcome::Waiting { "  [blocked on input]" } else { "" };
+        format!("0x{:08x} [{}] {}{blocked}", self.pc, self.number, self.call())
+    }
+
+    pub fn is_error(&self) -> bool {
+        match self.outcome {
+            Outcome::Returned(Some(v)) => errno_name(v).is_some(),
+            Outcome::Failed(_) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SyscallTrace {
+    pub records: VecDeque<SyscallRecord>,
+    /// Records dropped to stay under the limit.
+    pub dropped: u64,
+}
+
+impl SyscallTrace {
+    /// Add the record for a completed (or blocked) `ecall`. An `ecall` retried
+    /// after waiting for input replaces its own `Waiting` record.
+    pub fn push(&mut self, mut record: SyscallRecord) {
+        if let Some(last) = self.records.back_mut()
+            && last.outcome == Outcome::Waiting
+            && last.pc == record.pc
+            && last.number == record.number
+        {
+            record.blocked = true;
+            *last = record;
+            return;
+        }
+        if self.records.len() == MAX_RECORDS {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(record);
+    }
+
+    /// The whole trace as text, one call per line.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        if self.dropped > 0 {
+            out.push_str(&format!("# {} earlier calls dropped\n", self.dropped));
+        }
+        for r in &self.records {
+            out.push_str(&r.line());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Decode the arguments the call reads, before it runs. Output buffers are
+/// filled in by [`decode_outputs`] once the call has returned.
+pub fn decode_inputs<B: Bus>(desc: &SyscallDesc, regs: &[u32], mem: &B) -> Vec<String> {
+    desc.args
+        .iter()
+        .zip(regs)
+        .map(|(&kind, &v)| match kind {
+            Arg::Int => (v as i32).to_string(),
+            Arg::Uint => v.to_string(),
+            Arg::Hex | Arg::OutBuf | Arg::OutStr => format!("0x{v:x}"),
+            Arg::Str => show_zstr(mem, v),
+            Arg::InBuf(len) => show_buf(mem, v, regs.get(len).copied().unwrap_or(0)),
+        })
+        .collect()
+}
+
+/// Replace output buffer addresses with what the call stored there.
+pub fn decode_outputs<B: Bus>(desc: &SyscallDesc, regs: &[u32], ret: u32, args: &mut [String], mem: &B) {
+    for ((&kind, &v), shown) in desc.args.iter().zip(regs).zip(args) {
+        match kind {
+            Arg::OutBuf if errno_name(ret).is_none() => *shown = show_buf(mem, v, ret),
+            Arg::OutStr => *shown = show_zstr(mem, v),
+            _ => {}
+        }
+    }
+}
+
+/// `3`, or `-9 EBADF` for the `-errno` range.
+fn format_ret(v: u32) -> String {
+    match errno_name(v) {
+        Some(name) => format!("{} {name}", v as i32),
+        None => format_value(v),
+    }
+}
+
+/// Small values in decimal, addresses in hex.
+fn format_value(v: u32) -> String {
+    if v >= 0x1_0000 { format!("0x{v:x}") } else { v.to_string() }
+}
+
+/// Name of the errno a `-errno` return value carries.
+pub fn errno_name(v: u32) -> Option<&'static str> {
+    let e = (v as i32).checked_neg().filter(|e| (1..4096).contains(e))?;
+    Some(match e {
+        1 => "EPERM",
+        2 => "ENOENT",
+        3 => "ESRCH",
+        5 => "EIO",
+        9 => "EBADF",
+        12 => "ENOMEM",
+        13 => "EACCES",
+        14 => "EFAULT",
+        17 => "EEXIST",
+        19 => "ENODEV",
+        20 => "ENOTDIR",
+        21 => "EISDIR",
+        22 => "EINVAL",
+        24 => "EMFILE",
+        27 => "EFBIG",
+        29 => "ESPIPE",
+        36 => "ENAMETOOLONG",
+        38 => "ENOSYS",
+        _ => "E?",
+    })
+}
+
+fn show_zstr<B: Bus>(mem: &B, addr: u32) -> String {
+    let mut bytes = Vec::new();
+    let mut a = addr;
+    loop {
+        match mem.load8(a) {
+            Ok(0) => break,
+            Ok(b) => bytes.push(b),
+            Err(_) => return format!("0x{addr:x}"),
+        }
+        if bytes.len() > MAX_SHOWN {
+            break;
+        }
+        a = a.wrapping_add(1);
+    }
+    quote(&bytes)
+}
+
+fn show_buf<B: Bus>(mem: &B, addr: u32, len: u32) -> String {
+    let shown = (len as usize).min(MAX_SHOWN + 1) as u32;
+    let mut bytes = Vec::with_capacity(shown as usize);
+    for i in 0..shown {
+        match mem.load8(addr.wrapping_add(i)) {
+            Ok(b) => bytes.push(b),
+            Err(_) => return format!("0x{addr:x}"),
+        }
+    }
+    quote(&bytes)
+}
+
+/// `"hi\n"`, cut to [`MAX_SHOWN`] bytes with `...` after the quote.
+fn quote(bytes: &[u8]) -> String {
+    let mut s = String::from("\"");
+    for &b in bytes.iter().take(MAX_SHOWN) {
+        match b {
+            b'\n' => s.push_str("\\n"),
+            b'\r' => s.push_str("\\r"),
+            b'\t' => s.push_str("\\t"),
+            b'"' => s.push_str("\\\""),
+            b'\\' => s.push_str("\\\\"),
+            0x20..=0x7e => s.push(b as char),
+            _ => s.push_str(&format!("\\x{b:02x}")),
+        }
+    }
+    s.push('"');
+    if bytes.len() > MAX_SHOWN {
+        s.push_str("...");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::memory::Ram;
+
+    fn record(pc: u32, outcome: Outcome) -> SyscallRecord {
+        SyscallRecord { pc, number: 63, name: Some("read"), args: vec!["0".into()], outcome, blocked: false }
+    }
+
+    #[test]
+    fn formats_strings_buffers_and_errno() {
+        let mut mem = Ram::new(256);
+        for (i, &b) in b"hi\n\0".iter().enumerate() {
+            mem.store8(16 + i as u32, b).unwrap();
+        }
+        const DESC: SyscallDesc = SyscallDesc { name: "write", args: &[Arg::Int, Arg::InBuf(2), Arg::Uint], ret: Ret::Errno };
+        let args = decode_inputs(&DESC, &[1, 16, 3], &mem);
+        let r = SyscallRecord { pc: 8, number: 64, name: Some("write"), args, outcome: Outcome::Returned(Some(3)), blocked: false };
+        assert_eq!(r.line(), "0x00000008 [64] write(1, \"hi\\n\", 3) = 3");
+        assert_eq!(show_zstr(&mem, 16), "\"hi\\n\"");
+        assert_eq!(format_ret((-9i32) as u32), "-9 EBADF");
+        assert!(!r.is_error());
+    }
+
+    #[test]
+    fn retried_call_replaces_its_waiting_record() {
+        let mut trace = SyscallTrace::default();
+        trace.push(record(4, Outcome::Waiting));
+        trace.push(record(4, Outcome::Returned(Some(5))));
+        assert_eq!(trace.records.len(), 1);
+        assert!(trace.records[0].blocked);
+        assert!(trace.records[0].line().ends_with("= 5  [blocked on input]"));
+        trace.push(record(4, Outcome::Returned(Some(0))));
+        assert_eq!(trace.records.len(), 2);
+    }
+}
@@ -1,13 +1,41 @@
 use crate::{
-    falcon::{errors::FalconError, memory::Bus, registers::Cpu},
+    falcon::{
+        errors::FalconError,
+        memory::Bus,
+        registers::Cpu,
+        strace::{Arg, Ret, SyscallDesc},
+        vfs,
+    },
     ui::{console::ConsoleColor, Console},
 };
 
+const SYS_UNLINKAT: u32 = 35;
+const SYS_OPENAT: u32 = 56;
+const SYS_CLOSE: u32 = 57;
+const SYS_LSEEK: u32 = 62;
 const SYS_READ: u32 = 63;
 const SYS_WRITE: u32 = 64;
-const SYS_EXIT: u32 = 93;
-const SYS_EXIT_GROUP: u32 = 94;
+const SYS_FACCESSAT: u32 = 48;
+const SYS_FSTATAT: u32 = 79;
+const SYS_FSTAT: u32 = 80;
+const SYS_KILL: u32 = 129;
+const SYS_NANOSLEEP: u32 = 101;
+const SYS_CLOCK_GETTIME: u32 = 113;
+const SYS_GETTIMEOFDAY: u32 = 169;
+const SYS_GETPID: u32 = 172;
+const SYS_CLOCK_GETTIME64: u32 = 403;
+// Legacy numbers newlib's libgloss still uses
+const SYS_OPEN: u32 = 1024;
+const SYS_UNLINK: u32 = 1026;
+const SYS_ACCESS: u32 = 1033;
+const SYS_STAT: u32 = 1038;
+const SYS_LSTAT: u32 = 1039;
+pub(crate) const SYS_EXIT: u32 = 93;
+pub(crate) const SYS_EXIT_GROUP: u32 = 94;
 const SYS_BRK:       u32 = 214;
+const SYS_MUNMAP:    u32 = 215;
+const SYS_MMAP:      u32 = 222;
+const SYS_MPROTECT:  u32 = 226;
 const SYS_GETRANDOM: u32 = 278;
 
 const FALCON_PRINT_INT: u32 = 1000;
@@ -18,15 +46,263 @@ const FALCON_READ_U8: u32 = 1010;
 const FALCON_READ_U16: u32 = 1011;
 const FALCON_READ_U32: u32 = 1012;
 
+// RARS services (`--syscalls rars`). Its file calls use the Linux numbers above
+// (`close` 57, `lseek` 62, `read` 63, `write` 64, `exit` 93, `open` 1024).
+const RARS_PRINT_INT: u32 = 1;
+const RARS_PRINT_FLOAT: u32 = 2;
+const RARS_PRINT_STRING: u32 = 4;
+const RARS_READ_INT: u32 = 5;
+const RARS_READ_FLOAT: u32 = 6;
+const RARS_READ_STRING: u32 = 8;
+const RARS_SBRK: u32 = 9;
+pub(crate) const RARS_EXIT: u32 = 10;
+const RARS_PRINT_CHAR: u32 = 11;
+const RARS_READ_CHAR: u32 = 12;
+const RARS_TIME: u32 = 30;
+const RARS_MIDI_OUT: u32 = 31;
+const RARS_SLEEP: u32 = 32;
+const RARS_MIDI_OUT_SYNC: u32 = 33;
+const RARS_PRINT_INT_HEX: u32 = 34;
+const RARS_PRINT_INT_BINARY: u32 = 35;
+const RARS_PRINT_INT_UNSIGNED: u32 = 36;
+const RARS_RAND_SEED: u32 = 40;
+const RARS_RAND_INT: u32 = 41;
+const RARS_RAND_INT_RANGE: u32 = 42;
+const RARS_RAND_FLOAT: u32 = 43;
+
 const LINUX_EBADF: u32 = (-9i32) as u32;
 const LINUX_EFAULT: u32 = (-14i32) as u32;
 const LINUX_EIO: u32 = (-5i32) as u32;
 const LINUX_EINVAL: u32 = (-22i32) as u32;
+const LINUX_ESRCH: u32 = (-3i32) as u32;
+const LINUX_ENODEV: u32 = (-19i32) as u32;
+const LINUX_ESPIPE: u32 = (-29i32) as u32;
+const LINUX_ENAMETOOLONG: u32 = (-36i32) as u32;
+
+/// `openat`/`unlinkat` dirfd meaning "relative to the current directory".
+const AT_FDCWD: u32 = (-100i32) as u32;
+const AT_REMOVEDIR: u32 = 0x200;
+/// Longest path `openat`/`unlinkat` will read from guest memory.
+const PATH_MAX: usize = 4096;
+
+const S_IFCHR: u32 = 0o020000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+// clock_gettime clock ids. The CPU-time clocks count only the cycles the
+// program ran; the others also include what it slept.
+const CLOCK_PROCESS_CPUTIME_ID: u32 = 2;
+const CLOCK_THREAD_CPUTIME_ID: u32 = 3;
+const CLOCK_BOOTTIME: u32 = 7;
+
+const NS_PER_SEC: u64 = 1_000_000_000;
+
+// mmap flags
+const MAP_TYPE: u32 = 0x0f;
+const MAP_SHARED: u32 = 0x01;
+const MAP_PRIVATE: u32 = 0x02;
+const MAP_FIXED: u32 = 0x10;
+const MAP_ANONYMOUS: u32 = 0x20;
+
+/// What `getpid` reports: the guest is the only process.
+const GUEST_PID: u32 = 1;
+/// Highest signal number `kill` accepts (SIGRTMAX).
+const MAX_SIGNAL: u32 = 64;
+
+// Linux open flags RARS's `open` modes stand for
+const O_WRONLY: u32 = 0o1;
+const O_CREAT: u32 = 0o100;
+const O_TRUNC: u32 = 0o1000;
+const O_APPEND: u32 = 0o2000;
+
+/// Which syscall numbers `ecall` answers to (`--syscalls`, `B` on the Run tab).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyscallAbi {
+    /// The Linux calls (and newlib's legacy numbers) only.
+    Linux,
+    /// Linux plus the teaching calls at 1000+ the pseudo-instructions use.
+    #[default]
+    Raven,
+    /// RARS's services (`1` print int, `4` print string, `10` exit, ...),
+    /// then everything RAVEN answers to.
+    Rars,
+}
+
+impl SyscallAbi {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "linux" => Ok(Self::Linux),
+            "raven" => Ok(Self::Raven),
+            "rars" | "mars" => Ok(Self::Rars),
+            _ => Err(format!("unknown syscall ABI '{s}' (expected linux, raven or rars)")),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Linux => "Linux",
+            Self::Raven => "RAVEN",
+            Self::Rars => "RARS",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Linux => Self::Raven,
+            Self::Raven => Self::Rars,
+            Self::Rars => Self::Linux,
+        }
+    }
+}
+
+/// `java.util.Random`, which RARS's random services use: a stream seeded with
+/// `RandSeed` gives the same numbers it would in RARS.
+#[derive(Clone, Copy, Debug)]
+struct JavaRandom(u64);
+
+impl JavaRandom {
+    const MULTIPLIER: u64 = 0x5_DEEC_E66D;
+    const MASK: u64 = (1 << 48) - 1;
+
+    fn new(seed: i64) -> Self {
+        Self((seed as u64 ^ Self::MULTIPLIER) & Self::MASK)
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.0 = self.0.wrapping_mul(Self::MULTIPLIER).wrapping_add(0xB) & Self::MASK;
+        (self.0 >> (48 - bits)) as i32
+    }
+
+    fn next_int(&mut self) -> i32 {
+        self.next(32)
+    }
+
+    /// Uniform in `0..bound`; `bound` must be positive.
+    fn next_int_below(&mut self, bound: i32) -> i32 {
+        if bound & bound.wrapping_neg() == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let val = bits % bound;
+            if bits.wrapping_sub(val).wrapping_add(bound - 1) >= 0 {
+                return val;
+            }
+        }
+    }
+
+    fn next_float(&mut self) -> f32 {
+        self.next(24) as f32 / (1 << 24) as f32
+    }
+}
+
+/// RARS's random streams by id. A stream used before `RandSeed` is seeded from the host.
+#[derive(Clone, Debug, Default)]
+pub struct RandomStreams(std::collections::HashMap<u32, JavaRandom>);
+
+impl RandomStreams {
+    fn stream(&mut self, id: u32) -> &mut JavaRandom {
+        self.0.entry(id).or_insert_with(|| {
+            let mut seed = [0u8; 8];
+            let _ = getrandom::fill(&mut seed);
+            JavaRandom::new(i64::from_le_bytes(seed))
+        })
+    }
+}
+
+/// Name and arguments of syscall `code` under `abi`, for the syscall trace.
+pub fn describe(code: u32, abi: SyscallAbi) -> Option<SyscallDesc> {
+    use Arg::*;
+    let linux = |name, args: &'static [Arg]| Some(SyscallDesc { name, args, ret: Ret::Errno });
+    let falcon = |name, args: &'static [Arg]| Some(SyscallDesc { name, args, ret: Ret::Nothing });
+    let rars = |name, args: &'static [Arg], ret| Some(SyscallDesc { name, args, ret });
+    if abi == SyscallAbi::Rars {
+        let desc = match code {
+            RARS_PRINT_INT => rars("PrintInt", &[Int], Ret::Nothing),
+            RARS_PRINT_FLOAT => rars("PrintFloat", &[], Ret::Nothing),
+            RARS_PRINT_STRING => rars("PrintString", &[Str], Ret::Nothing),
+            RARS_READ_INT => rars("ReadInt", &[], Ret::Value),
+            RARS_READ_FLOAT => rars("ReadFloat", &[], Ret::Nothing),
+            RARS_READ_STRING => rars("ReadString", &[OutStr, Int], Ret::Nothing),
+            RARS_SBRK => rars("Sbrk", &[Int], Ret::Value),
+            RARS_EXIT => rars("Exit", &[], Ret::Nothing),
+            RARS_PRINT_CHAR => rars("PrintChar", &[Int], Ret::Nothing),
+            RARS_READ_CHAR => rars("ReadChar", &[], Ret::Value),
+            RARS_TIME => rars("Time", &[], Ret::Value),
+            RARS_MIDI_OUT => rars("MidiOut", &[Int, Int, Int, Int], Ret::Nothing),
+            RARS_SLEEP => rars("Sleep", &[Int], Ret::Nothing),
+            RARS_MIDI_OUT_SYNC => rars("MidiOutSync", &[Int, Int, Int, Int], Ret::Nothing),
+            RARS_PRINT_INT_HEX => rars("PrintIntHex", &[Hex], Ret::Nothing),
+            RARS_PRINT_INT_BINARY => rars("PrintIntBinary", &[Hex], Ret::Nothing),
+            RARS_PRINT_INT_UNSIGNED => rars("PrintIntUnsigned", &[Uint], Ret::Nothing),
+            RARS_RAND_SEED => rars("RandSeed", &[Uint, Int], Ret::Nothing),
+            RARS_RAND_INT => rars("RandInt", &[Uint], Ret::Value),
+            RARS_RAND_INT_RANGE => rars("RandIntRange", &[Uint, Int], Ret::Value),
+            RARS_RAND_FLOAT => rars("RandFloat", &[Uint], Ret::Nothing),
+            SYS_OPEN => linux("Open", &[Str, Uint]),
+            _ => None,
+        };
+        if desc.is_some() {
+            return desc;
+        }
+    }
+    if abi == SyscallAbi::Linux && is_teaching_call(code) {
+        return None;
+    }
+    match code {
+        SYS_READ => linux("read", &[Int, OutBuf, Uint]),
+        SYS_WRITE => linux("write", &[Int, InBuf(2), Uint]),
+        SYS_OPENAT => linux("openat", &[Int, Str, Hex, Hex]),
+        SYS_OPEN => linux("open", &[Str, Hex, Hex]),
+        SYS_CLOSE => linux("close", &[Int]),
+        SYS_LSEEK => linux("lseek", &[Int, Int, Uint]),
+        SYS_UNLINKAT => linux("unlinkat", &[Int, Str, Hex]),
+        SYS_UNLINK => linux("unlink", &[Str]),
+        SYS_FSTATAT => linux("fstatat", &[Int, Str, Hex, Hex]),
+        SYS_STAT => linux("stat", &[Str, Hex]),
+        SYS_LSTAT => linux("lstat", &[Str, Hex]),
+        SYS_FSTAT => linux("fstat", &[Int, Hex]),
+        SYS_FACCESSAT => linux("faccessat", &[Int, Str, Hex, Hex]),
+        SYS_ACCESS => linux("access", &[Str, Hex]),
+        SYS_GETTIMEOFDAY => linux("gettimeofday", &[Hex, Hex]),
+        SYS_CLOCK_GETTIME => linux("clock_gettime", &[Uint, Hex]),
+        SYS_CLOCK_GETTIME64 => linux("clock_gettime64", &[Uint, Hex]),
+        SYS_NANOSLEEP => linux("nanosleep", &[Hex, Hex]),
+        SYS_GETPID => linux("getpid", &[]),
+        SYS_KILL => linux("kill", &[Int, Int]),
+        SYS_BRK => linux("brk", &[Hex]),
+        SYS_MMAP => linux("mmap", &[Hex, Uint, Hex, Hex, Int, Uint]),
+        SYS_MUNMAP => linux("munmap", &[Hex, Uint]),
+        SYS_MPROTECT => linux("mprotect", &[Hex, Uint, Hex]),
+        SYS_GETRANDOM => linux("getrandom", &[OutBuf, Uint, Hex]),
+        SYS_EXIT => linux("exit", &[Int]),
+        SYS_EXIT_GROUP => linux("exit_group", &[Int]),
+        FALCON_PRINT_INT => falcon("print_int", &[Int]),
+        FALCON_PRINT_ZSTR => falcon("print_zstr", &[Str]),
+        FALCON_PRINT_ZSTR_LN => falcon("print_zstr_ln", &[Str]),
+        FALCON_READ_LINE_Z => falcon("read_line_z", &[OutStr]),
+        FALCON_READ_U8 => falcon("read_u8", &[Hex]),
+        FALCON_READ_U16 => falcon("read_u16", &[Hex]),
+        FALCON_READ_U32 => falcon("read_u32", &[Hex]),
+        _ => None,
+    }
+}
 
 /// Handles syscalls invoked via `ecall`.
 ///
-/// - Linux-like subset: `read(63)`, `write(64)`, `exit(93)`, `exit_group(94)`
-/// - Falcon teaching extensions: `1000..` (used by assembler pseudos)
+/// - Linux-like subset: `read(63)`, `write(64)`, `exit(93)`, `exit_group(94)`,
+///   and files in the guest's [`vfs`]: `openat(56)`, `close(57)`, `lseek(62)`,
+///   `fstat(80)`, `unlinkat(35)`
+/// - Time from the run's [`GuestClock`](super::clock::GuestClock):
+///   `gettimeofday(169)`, `clock_gettime(113)`, `clock_gettime64(403)`, `nanosleep(101)`
+/// - Memory from the guest's [`mmap`](super::mmap) regions: `brk(214)`, and anonymous
+///   `mmap(222)`, `munmap(215)`, `mprotect(226)`
+/// - What newlib/picolibc's libgloss needs besides: `fstatat(79)`, `faccessat(48)`,
+///   `getpid(172)`, `kill(129)` and the legacy `open(1024)`, `unlink(1026)`, `access(1033)`,
+///   `stat(1038)`, `lstat(1039)`
+/// - Falcon teaching extensions: `1000..` (used by assembler pseudos), unless
+///   `cpu.abi` is [`SyscallAbi::Linux`]
+/// - With [`SyscallAbi::Rars`], RARS's services `1..=43` come first
 ///
 /// ABI (Linux-style):
 /// - `a7` = syscall number
@@ -38,20 +314,104 @@ pub fn handle_syscall<B: Bus>(
     mem: &mut B,
     console: &mut Console,
 ) -> Result<bool, FalconError> {
+    match cpu.abi {
+        SyscallAbi::Rars => {
+            if let Some(res) = rars_syscall(code, cpu, mem, console) {
+                return res;
+            }
+        }
+        SyscallAbi::Linux if is_teaching_call(code) => {
+            console.push_error(format!("Unimplemented syscall {code} (teaching calls are off in the Linux ABI)"));
+            return Ok(false);
+        }
+        _ => {}
+    }
     match code {
         // --- Linux ABI subset ---
         SYS_READ => linux_read(cpu, mem, console),
         SYS_WRITE => linux_write(cpu, mem, console),
+        SYS_OPENAT | SYS_OPEN | SYS_UNLINKAT | SYS_UNLINK | SYS_FSTATAT | SYS_STAT | SYS_LSTAT
+        | SYS_FACCESSAT | SYS_ACCESS => {
+            let ret = path_syscall(code, cpu, mem);
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        SYS_CLOSE => {
+            // Closing the console is allowed and changes nothing
+            let fd = cpu.read(10);
+            let ret = if fd < vfs::FIRST_FD { 0 } else { errno(cpu.fs.close(fd).map(|()| 0)) };
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        SYS_LSEEK => {
+            let fd = cpu.read(10);
+            let ret = if fd < vfs::FIRST_FD {
+                LINUX_ESPIPE
+            } else {
+                errno(cpu.fs.seek(fd, cpu.read(11) as i32, cpu.read(12)))
+            };
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        SYS_FSTAT => linux_fstat(cpu, mem),
+        SYS_GETTIMEOFDAY | SYS_CLOCK_GETTIME | SYS_CLOCK_GETTIME64 => linux_gettime(code, cpu, mem),
+        SYS_NANOSLEEP => linux_nanosleep(cpu, mem),
+        SYS_GETPID => {
+            cpu.write(10, GUEST_PID);
+            Ok(true)
+        }
+        SYS_KILL => {
+            // kill(pid=a0, sig=a1): newlib's abort() raises SIGABRT this way.
+            // The guest dies with the shell's 128 + signal status.
+            let (pid, sig) = (cpu.read(10), cpu.read(11));
+            if pid != GUEST_PID && pid != 0 {
+                cpu.write(10, LINUX_ESRCH);
+                return Ok(true);
+            }
+            if sig == 0 {
+                cpu.write(10, 0);
+                return Ok(true);
+            }
+            let Some(status) = 128u32.checked_add(sig).filter(|_| sig <= MAX_SIGNAL) else {
+                cpu.write(10, LINUX_EINVAL);
+                return Ok(true);
+            };
+            cpu.exit_code = Some(status);
+            console.push_error(format!("Killed by signal {sig}"));
+            Ok(false)
+        }
         SYS_BRK => {
-            // brk(0) → query current break; brk(addr) → extend break to addr.
-            // Returns the new (or current) break; returns current break on failure.
+            // brk(0) → query current break; brk(addr) → move the break up to addr.
+            // Growth into a mapping or the stack fails: the break stays where it was.
             let requested = cpu.read(10);
-            if requested == 0 || requested <= cpu.heap_break {
-                cpu.write(10, cpu.heap_break);
-            } else {
+            if requested > cpu.heap_break && requested <= cpu.mmap.brk_limit() {
                 cpu.heap_break = requested;
-                cpu.write(10, requested);
             }
+            cpu.write(10, cpu.heap_break);
+            Ok(true)
+        }
+        SYS_MMAP => {
+            // mmap(addr=a0, len=a1, prot=a2, flags=a3, fd=a4, pgoff=a5): anonymous only
+            let (addr, len, prot, flags) = (cpu.read(10), cpu.read(11), cpu.read(12), cpu.read(13));
+            let ret = if flags & MAP_ANONYMOUS == 0 {
+                LINUX_ENODEV
+            } else if !matches!(flags & MAP_TYPE, MAP_SHARED | MAP_PRIVATE) {
+                LINUX_EINVAL
+            } else {
+                let fixed = (flags & MAP_FIXED != 0).then_some(addr);
+                errno(cpu.mmap.map(mem, cpu.heap_break, fixed, len, prot))
+            };
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        SYS_MUNMAP => {
+            let ret = errno(cpu.mmap.unmap(cpu.read(10), cpu.read(11)).map(|()| 0));
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        SYS_MPROTECT => {
+            let ret = errno(cpu.mmap.protect(cpu.read(10), cpu.read(11), cpu.read(12)).map(|()| 0));
+            cpu.write(10, ret);
             Ok(true)
         }
         SYS_GETRANDOM => linux_getrandom(cpu, mem, console),
@@ -109,15 +469,231 @@ pub fn handle_syscall<B: Bus>(
     }
 }
 
+fn is_teaching_call(code: u32) -> bool {
+    (FALCON_PRINT_INT..=FALCON_READ_U32).contains(&code)
+}
+
+/// RARS's services. `None` for numbers RARS shares with RAVEN (or doesn't
+/// have), which the caller handles as usual.
+fn rars_syscall<B: Bus>(
+    code: u32,
+    cpu: &mut Cpu,
+    mem: &mut B,
+    console: &mut Console,
+) -> Option<Result<bool, FalconError>> {
+    let a0 = cpu.read(10);
+    let a1 = cpu.read(11);
+    let print = |cpu: &mut Cpu, console: &mut Console, s: &str| {
+        cpu.stdout.extend_from_slice(s.as_bytes());
+        console_write_bytes(console, s.as_bytes());
+        Ok(true)
+    };
+    Some(match code {
+        RARS_PRINT_INT => print(cpu, console, &(a0 as i32).to_string()),
+        RARS_PRINT_FLOAT => {
+            let s = java_float(cpu.fread(10));
+            print(cpu, console, &s)
+        }
+        RARS_PRINT_STRING => match read_zstr(mem, a0) {
+            Ok(bytes) => print(cpu, console, &String::from_utf8_lossy(&bytes)),
+            Err(e) => Err(e),
+        },
+        RARS_PRINT_CHAR => print(cpu, console, &String::from_utf8_lossy(&[a0 as u8])),
+        RARS_PRINT_INT_HEX => print(cpu, console, &format!("0x{a0:08x}")),
+        RARS_PRINT_INT_BINARY => print(cpu, console, &format!("{a0:032b}")),
+        RARS_PRINT_INT_UNSIGNED => print(cpu, console, &a0.to_string()),
+        RARS_READ_INT => rars_read_number(console, "ReadInt", |s| {
+            let v: i32 = s.parse().ok()?;
+            cpu.write(10, v as u32);
+            Some(())
+        }),
+        RARS_READ_FLOAT => rars_read_number(console, "ReadFloat", |s| {
+            let v: f32 = s.parse().ok()?;
+            cpu.fwrite(10, v);
+            Some(())
+        }),
+        RARS_READ_STRING => {
+            // ReadString(buf=a0, max=a1): up to max-1 bytes, the newline if it fits, NUL
+            let Some(line) = console.read_line() else {
+                console.reading = true;
+                return Some(Ok(false));
+            };
+            console.reading = false;
+            let max = (a1 as i32).max(0) as usize;
+            if max == 0 {
+                return Some(Ok(true));
+            }
+            let mut bytes = line.into_bytes();
+            bytes.push(b'\n');
+            bytes.truncate(max - 1);
+            bytes.push(0);
+            store_bytes(mem, a0, &bytes).map(|()| true)
+        }
+        RARS_READ_CHAR => {
+            // One byte of the console line; the rest waits for the next ReadChar or read
+            if cpu.stdin.is_empty() {
+                let Some(line) = console.read_line() else {
+                    console.reading = true;
+                    return Some(Ok(false));
+                };
+                cpu.stdin.extend_from_slice(line.as_bytes());
+                cpu.stdin.push(b'\n');
+                console.reading = false;
+            }
+            let c = cpu.stdin.remove(0);
+            cpu.write(10, c as u32);
+            Ok(true)
+        }
+        RARS_SBRK => {
+            // Sbrk(bytes=a0) -> a0 = the old break, which starts the new block.
+            // RARS keeps the heap word-aligned.
+            let grow = (a0 as i32).max(0) as u32;
+            let old = cpu.heap_break;
+            match old.checked_add(grow.next_multiple_of(4)) {
+                Some(new) if (a0 as i32) >= 0 && new <= cpu.mmap.brk_limit() => {
+                    cpu.heap_break = new;
+                    cpu.write(10, old);
+                    Ok(true)
+                }
+                _ => {
+                    console.push_error(format!("Sbrk: cannot grow the heap by {} bytes", a0 as i32));
+                    Ok(false)
+                }
+            }
+        }
+        RARS_EXIT => {
+            cpu.exit_code = Some(0);
+            console.push_error("Exit 0");
+            Ok(false)
+        }
+        RARS_TIME => {
+            // Time() -> a0/a1 = low/high words of milliseconds since the epoch
+            let ms = cpu.clock.now_ns(mem.cycles()) / 1_000_000;
+            cpu.write(10, ms as u32);
+            cpu.write(11, (ms >> 32) as u32);
+            Ok(true)
+        }
+        RARS_SLEEP => {
+            cpu.clock.sleep((a0 as i32).max(0) as u64 * 1_000_000);
+            Ok(true)
+        }
+        // There is no synthesizer: MidiOut is silent, MidiOutSync only takes its duration (a1 ms)
+        RARS_MIDI_OUT => Ok(true),
+        RARS_MIDI_OUT_SYNC => {
+            cpu.clock.sleep((a1 as i32).max(0) as u64 * 1_000_000);
+            Ok(true)
+        }
+        RARS_RAND_SEED => {
+            cpu.random.0.insert(a0, JavaRandom::new(a1 as i32 as i64));
+            Ok(true)
+        }
+        RARS_RAND_INT => {
+            let v = cpu.random.stream(a0).next_int();
+            cpu.write(10, v as u32);
+            Ok(true)
+        }
+        RARS_RAND_INT_RANGE => {
+            if a1 as i32 <= 0 {
+                console.push_error(format!("RandIntRange: upper bound must be positive, got {}", a1 as i32));
+                return Some(Ok(false));
+            }
+            let v = cpu.random.stream(a0).next_int_below(a1 as i32);
+            cpu.write(10, v as u32);
+            Ok(true)
+        }
+        RARS_RAND_FLOAT => {
+            let v = cpu.random.stream(a0).next_float();
+            cpu.fwrite(10, v);
+            Ok(true)
+        }
+        SYS_OPEN => {
+            // Open(path=a0, mode=a1): 0 = read, 1 = write (create/truncate), 9 = append.
+            // The path goes through the VFS with the Linux flags those stand for.
+            let flags = match a1 {
+                0 => 0,
+                1 => O_WRONLY | O_CREAT | O_TRUNC,
+                9 => O_WRONLY | O_CREAT | O_APPEND,
+                _ => {
+                    cpu.write(10, LINUX_EINVAL);
+                    return Some(Ok(true));
+                }
+            };
+            let ret = match guest_path(cpu, mem, AT_FDCWD, a0) {
+                Ok(path) => errno(cpu.fs.open(&path, flags)),
+                Err(e) => e,
+            };
+            cpu.write(10, ret);
+            Ok(true)
+        }
+        _ => return None,
+    })
+}
+
+/// ReadInt / ReadFloat: parse a console line with `store`; a bad number is
+/// reported and the line asked for again.
+fn rars_read_number(console: &mut Console, name: &str, store: impl FnOnce(&str) -> Option<()>) -> Result<bool, FalconError> {
+    let Some(line) = console.read_line() else {
+        console.reading = true;
+        return Ok(false);
+    };
+    if store(line.trim()).is_some() {
+        console.reading = false;
+        Ok(true)
+    } else {
+        console.push_error(format!("{name}: invalid number"));
+        console.reading = true;
+        Ok(false)
+    }
+}
+
+/// A float the way Java's `Float.toString` (and so RARS) prints it: `1.0`,
+/// `0.1`, `1.0E10`, `1.5E-5`.
+fn java_float(v: f32) -> String {
+    if v.is_nan() {
+        return "NaN".into();
+    }
+    if v.is_infinite() {
+        return if v > 0.0 { "Infinity" } else { "-Infinity" }.into();
+    }
+    let a = v.abs();
+    if a == 0.0 || (1e-3..1e7).contains(&a) {
+        let s = v.to_string();
+        return if s.contains('.') { s } else { s + ".0" };
+    }
+    let s = format!("{v:e}");
+    let (mantissa, exp) = s.split_once('e').unwrap_or((&s, "0"));
+    let dot = if mantissa.contains('.') { "" } else { ".0" };
+    format!("{mantissa}{dot}E{exp}")
+}
+
 fn linux_read<B: Bus>(cpu: &mut Cpu, mem: &mut B, console: &mut Console) -> Result<bool, FalconError> {
     // Linux: read(fd=a0, buf=a1, count=a2) -> a0 = n or -errno
     let fd = cpu.read(10);
     let buf = cpu.read(11);
     let count = cpu.read(12) as usize;
 
+    if fd >= vfs::FIRST_FD {
+        let bytes = match cpu.fs.read(fd, count) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                cpu.write(10, e.wrapping_neg());
+                console.push_error(format!("read: fd {fd} is not open for reading"));
+                return Ok(true);
+            }
+        };
+        let ret = match store_bytes(mem, buf, &bytes) {
+            Ok(()) => bytes.len() as u32,
+            Err(e) => {
+                console.push_error(format!("read: {e}"));
+                LINUX_EFAULT
+            }
+        };
+        cpu.write(10, ret);
+        return Ok(true);
+    }
     if fd != 0 {
         cpu.write(10, LINUX_EBADF);
-        console.push_error(format!("read: unsupported fd {fd} (only fd=0 supported)"));
+        console.push_error(format!("read: fd {fd} is not open for reading"));
         return Ok(true);
     }
     if count == 0 {
@@ -159,9 +735,9 @@ fn linux_write<B: Bus>(cpu: &mut Cpu, mem: &mut B, console: &mut Console) -> Res
     let buf = cpu.read(11);
     let count = cpu.read(12) as usize;
 
-    if fd != 1 && fd != 2 {
+    if fd != 1 && fd != 2 && fd < vfs::FIRST_FD {
         cpu.write(10, LINUX_EBADF);
-        console.push_error(format!("write: unsupported fd {fd} (only fd=1/2 supported)"));
+        console.push_error(format!("write: fd {fd} is not open for writing"));
         return Ok(true);
     }
     if count == 0 {
@@ -182,6 +758,20 @@ fn linux_write<B: Bus>(cpu: &mut Cpu, mem: &mut B, console: &mut Console) -> Res
         }
     }
 
+    if fd >= vfs::FIRST_FD {
+        let ret = match cpu.fs.write(fd, &bytes) {
+            Ok(n) => n,
+            Err(e) => {
+                if e == vfs::EBADF {
+                    console.push_error(format!("write: fd {fd} is not open for writing"));
+                }
+                e.wrapping_neg()
+            }
+        };
+        cpu.write(10, ret);
+        return Ok(true);
+    }
+
     cpu.stdout.extend_from_slice(&bytes);
     if fd == 2 {
         console_write_bytes_colored(console, &bytes, ConsoleColor::Error);
@@ -192,6 +782,171 @@ fn linux_write<B: Bus>(cpu: &mut Cpu, mem: &mut B, console: &mut Console) -> Res
     Ok(true)
 }
 
+/// Calls that name a guest file. newlib's libgloss uses the legacy numbers
+/// (`open` 1024, `unlink` 1026, ...), which take the same arguments without the
+/// leading dirfd. Returns the value for `a0`.
+fn path_syscall<B: Bus>(code: u32, cpu: &mut Cpu, mem: &mut B) -> u32 {
+    let a = [cpu.read(10), cpu.read(11), cpu.read(12), cpu.read(13)];
+    let (dirfd, args) = if code >= SYS_OPEN { (AT_FDCWD, &a[..]) } else { (a[0], &a[1..]) };
+    let path = match guest_path(cpu, mem, dirfd, args[0]) {
+        Ok(path) => path,
+        Err(e) => return e,
+    };
+    match code {
+        // open(path, flags, mode) / openat(dirfd, path, flags, mode) -> fd
+        SYS_OPEN | SYS_OPENAT => errno(cpu.fs.open(&path, args[1])),
+        // Directories only exist while they hold files, so AT_REMOVEDIR has nothing to remove
+        SYS_UNLINKAT if args[1] & AT_REMOVEDIR != 0 => vfs::ENOENT.wrapping_neg(),
+        SYS_UNLINK | SYS_UNLINKAT => errno(cpu.fs.unlink(&path).map(|()| 0)),
+        // stat(path, buf) / fstatat(dirfd, path, buf, flags); there are no symlinks
+        SYS_STAT | SYS_LSTAT | SYS_FSTATAT => match cpu.fs.stat_path(&path) {
+            Ok(Some((ino, size))) => write_stat(mem, args[1], S_IFREG | 0o644, ino, size),
+            Ok(None) => write_stat(mem, args[1], S_IFDIR | 0o755, 0, 0),
+            Err(e) => e.wrapping_neg(),
+        },
+        // access(path, mode) / faccessat(dirfd, path, mode, flags): everything is readable and writable
+        _ => errno(cpu.fs.stat_path(&path).map(|_| 0)),
+    }
+}
+
+fn linux_fstat<B: Bus>(cpu: &mut Cpu, mem: &mut B) -> Result<bool, FalconError> {
+    // Linux: fstat(fd=a0, statbuf=a1) -> a0 = 0 or -errno. The console is a
+    // character device, which is how newlib's isatty() tells it apart.
+    let fd = cpu.read(10);
+    let buf = cpu.read(11);
+    let ret = if fd < vfs::FIRST_FD {
+        write_stat(mem, buf, S_IFCHR | 0o620, 0, 0)
+    } else {
+        match cpu.fs.stat(fd) {
+            Ok((ino, size)) => write_stat(mem, buf, S_IFREG | 0o644, ino, size),
+            Err(e) => e.wrapping_neg(),
+        }
+    };
+    cpu.write(10, ret);
+    Ok(true)
+}
+
+/// Fill a `struct stat`: the 128-byte RISC-V layout with 64-bit
+/// dev/ino/rdev/size/blocks, which newlib's libgloss uses too. Returns `0` or `-EFAULT`.
+fn write_stat<B: Bus>(mem: &mut B, buf: u32, mode: u32, ino: u32, size: u32) -> u32 {
+    let mut st = [0u8; 128];
+    let mut put = |off: usize, v: u32| st[off..off + 4].copy_from_slice(&v.to_le_bytes());
+    put(8, ino); // st_ino
+    put(16, mode); // st_mode
+    put(20, 1); // st_nlink
+    put(48, size); // st_size
+    put(56, 4096); // st_blksize
+    put(64, size.div_ceil(512)); // st_blocks
+    match store_bytes(mem, buf, &st) {
+        Ok(()) => 0,
+        Err(_) => LINUX_EFAULT,
+    }
+}
+
+fn linux_gettime<B: Bus>(code: u32, cpu: &mut Cpu, mem: &mut B) -> Result<bool, FalconError> {
+    // gettimeofday(tv=a0, tz=a1) -> struct timeval { i64 tv_sec; i32 tv_usec; }
+    // clock_gettime64(clk=a0, ts=a1) -> struct timespec { i64 tv_sec; i64 tv_nsec; }
+    // clock_gettime(clk=a0, ts=a1) -> the old rv32 timespec { i32 tv_sec; i32 tv_nsec; }
+    // (newlib's time_t is 64-bit; libgloss on rv32 reads the clock with clock_gettime64)
+    let cycles = mem.cycles();
+    let (buf, ns) = if code == SYS_GETTIMEOFDAY {
+        (cpu.read(10), cpu.clock.now_ns(cycles))
+    } else {
+        let ns = match cpu.read(10) {
+            CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => cpu.clock.cpu_time_ns(cycles),
+            clk if clk <= CLOCK_BOOTTIME => cpu.clock.now_ns(cycles),
+            _ => {
+                cpu.write(10, LINUX_EINVAL);
+                return Ok(true);
+            }
+        };
+        (cpu.read(11), ns)
+    };
+    let (secs, nanos) = (ns / NS_PER_SEC, (ns % NS_PER_SEC) as u32);
+    let mut tv = [0u8; 16];
+    let len = match code {
+        SYS_GETTIMEOFDAY => {
+            tv[..8].copy_from_slice(&secs.to_le_bytes());
+            tv[8..12].copy_from_slice(&(nanos / 1000).to_le_bytes());
+            16
+        }
+        SYS_CLOCK_GETTIME64 => {
+            tv[..8].copy_from_slice(&secs.to_le_bytes());
+            tv[8..12].copy_from_slice(&nanos.to_le_bytes());
+            16
+        }
+        _ => {
+            tv[..4].copy_from_slice(&(secs as u32).to_le_bytes());
+            tv[4..8].copy_from_slice(&nanos.to_le_bytes());
+            8
+        }
+    };
+    let ret = if buf == 0 {
+        0
+    } else {
+        match store_bytes(mem, buf, &tv[..len]) {
+            Ok(()) => 0,
+            Err(_) => LINUX_EFAULT,
+        }
+    };
+    cpu.write(10, ret);
+    Ok(true)
+}
+
+fn linux_nanosleep<B: Bus>(cpu: &mut Cpu, mem: &mut B) -> Result<bool, FalconError> {
+    // nanosleep(req=a0, rem=a1), old rv32 timespecs { i32 tv_sec; i32 tv_nsec; }.
+    // The guest never waits: the clock jumps ahead and the sleep is never interrupted.
+    let (req, rem) = (cpu.read(10), cpu.read(11));
+    let (Ok(secs), Ok(nanos)) = (mem.load32(req), mem.load32(req.wrapping_add(4))) else {
+        cpu.write(10, LINUX_EFAULT);
+        return Ok(true);
+    };
+    if (secs as i32) < 0 || nanos as u64 >= NS_PER_SEC {
+        cpu.write(10, LINUX_EINVAL);
+        return Ok(true);
+    }
+    cpu.clock.sleep(secs as u64 * NS_PER_SEC + nanos as u64);
+    let ret = if rem != 0 && store_bytes(mem, rem, &[0; 8]).is_err() { LINUX_EFAULT } else { 0 };
+    cpu.write(10, ret);
+    Ok(true)
+}
+
+/// The path argument of `openat`/`unlinkat`. Relative paths need `AT_FDCWD`:
+/// the guest has no directory descriptors. Errors come back as `-errno`.
+fn guest_path<B: Bus>(cpu: &Cpu, mem: &B, dirfd: u32, addr: u32) -> Result<String, u32> {
+    let mut bytes = Vec::new();
+    let mut a = addr;
+    loop {
+        match mem.load8(a) {
+            Ok(0) => break,
+            Ok(b) => bytes.push(b),
+            Err(_) => return Err(LINUX_EFAULT),
+        }
+        if bytes.len() >= PATH_MAX {
+            return Err(LINUX_ENAMETOOLONG);
+        }
+        a = a.wrapping_add(1);
+    }
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    if dirfd != AT_FDCWD && !path.starts_with('/') {
+        let open = dirfd < vfs::FIRST_FD || cpu.fs.stat(dirfd).is_ok();
+        return Err(if open { vfs::ENOTDIR } else { vfs::EBADF }.wrapping_neg());
+    }
+    Ok(path)
+}
+
+/// A VFS result as the syscall's return value.
+fn errno(r: Result<u32, u32>) -> u32 {
+    r.unwrap_or_else(u32::wrapping_neg)
+}
+
+pub(crate) fn store_bytes<B: Bus>(mem: &mut B, addr: u32, bytes: &[u8]) -> Result<(), FalconError> {
+    for (i, &b) in bytes.iter().enumerate() {
+        mem.store8(addr.wrapping_add(i as u32), b)?;
+    }
+    Ok(())
+}
+
 fn linux_getrandom<B: Bus>(
     cpu: &mut Cpu,
     mem: &mut B,
@@ -241,7 +996,7 @@ fn linux_getrandom<B: Bus>(
     Ok(true)
 }
 
-fn read_zstr(mem: &impl Bus, mut addr: u32) -> Result<Vec<u8>, FalconError> {
+pub(crate) fn read_zstr(mem: &impl Bus, mut addr: u32) -> Result<Vec<u8>, FalconError> {
     let mut bytes = Vec::new();
     loop {
         let b = mem.load8(addr)?;
@@ -254,7 +1009,7 @@ fn read_zstr(mem: &impl Bus, mut addr: u32) -> Result<Vec<u8>, FalconError> {
     Ok(bytes)
 }
 
-fn console_write_bytes(console: &mut Console, bytes: &[u8]) {
+pub(crate) fn console_write_bytes(console: &mut Console, bytes: &[u8]) {
     let mut start = 0;
     for (i, &b) in bytes.iter().enumerate() {
         if b == b'\n' {
@@ -270,7 +1025,7 @@ fn console_write_bytes(console: &mut Console, bytes: &[u8]) {
     }
 }
 
-fn console_write_bytes_colored(console: &mut Console, bytes: &[u8], color: ConsoleColor) {
+pub(crate) fn console_write_bytes_colored(console: &mut Console, bytes: &[u8], color: ConsoleColor) {
     let mut start = 0;
     for (i, &b) in bytes.iter().enumerate() {
         if b == b'\n' {
@@ -373,3 +1128,45 @@ fn parse_u64(s: &str) -> Option<u64> {
             .and_then(|v| if v < 0 { None } else { Some(v as u64) })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::falcon::memory::Ram;
+
+    #[test]
+    fn kill_rejects_out_of_range_signals() {
+        let (mut cpu, mut mem, mut console) = (Cpu::default(), Ram::new(64), Console::default());
+        cpu.write(10, GUEST_PID);
+        cpu.write(11, u32::MAX);
+        assert!(handle_syscall(SYS_KILL, &mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.read(10), LINUX_EINVAL);
+        assert_eq!(cpu.exit_code, None);
+
+        cpu.write(10, GUEST_PID);
+        cpu.write(11, 6);
+        assert!(!handle_syscall(SYS_KILL, &mut cpu, &mut mem, &mut console).unwrap());
+        assert_eq!(cpu.exit_code, Some(134));
+    }
+
+    #[test]
+    fn rars_random_matches_java() {
+        // new java.util.Random(42)
+        let mut r = JavaRandom::new(42);
+        assert_eq!(r.next_int(), -1170105035);
+        let mut r = JavaRandom::new(42);
+        assert_eq!(r.next_int_below(10), 0);
+        assert!((0.0..1.0).contains(&JavaRandom::new(7).next_float()));
+    }
+
+    #[test]
+    fn floats_print_like_rars() {
+        assert_eq!(java_float(1.0), "1.0");
+        assert_eq!(java_float(-0.1), "-0.1");
+        assert_eq!(java_float(3.25), "3.25");
+        assert_eq!(java_float(1e10), "1.0E10");
+        assert_eq!(java_float(1.5e-5), "1.5E-5");
+        assert_eq!(java_float(f32::NEG_INFINITY), "-Infinity");
+        assert_eq!(SyscallAbi::parse("RARS"), Ok(SyscallAbi::Rars));
+    }
+}
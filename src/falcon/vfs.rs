@@ -0,0 +1,392 @@
+// falcon/vfs.rs
+//
+// The guest's filesystem, kept in memory. Files are addressed by a normalised
+// path ("data/in.txt"); directories only exist as the prefixes of those paths.
+// Each run has its own table of open descriptors, numbered from 3 (0-2 are the
+// console and are handled by the syscall layer). `raven run --fs DIR` seeds the
+// files from a host directory and writes the changes back when the run ends;
+// paths can never leave that directory.
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+pub const ENOENT: u32 = 2;
+pub const EBADF: u32 = 9;
+pub const EEXIST: u32 = 17;
+pub const ENOTDIR: u32 = 20;
+pub const EISDIR: u32 = 21;
+pub const EINVAL: u32 = 22;
+pub const EMFILE: u32 = 24;
+pub const EFBIG: u32 = 27;
+
+const O_ACCMODE: u32 = 0o3;
+const O_WRONLY: u32 = 0o1;
+const O_RDWR: u32 = 0o2;
+const O_CREAT: u32 = 0o100;
+const O_EXCL: u32 = 0o200;
+const O_TRUNC: u32 = 0o1000;
+const O_APPEND: u32 = 0o2000;
+const O_DIRECTORY: u32 = 0o200000;
+
+const SEEK_SET: u32 = 0;
+const SEEK_CUR: u32 = 1;
+const SEEK_END: u32 = 2;
+
+/// First descriptor handed out by `open`.
+pub const FIRST_FD: u32 = 3;
+/// Open descriptors a program may hold at once.
+const MAX_FDS: usize = 64;
+/// Largest file a program can grow, so a stray `lseek` can't exhaust host memory.
+const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Debug, Default)]
+pub struct Vfs {
+    /// Path → index into `inodes`.
+    names: BTreeMap<String, usize>,
+    /// File contents. An unlinked file stays here while a descriptor has it open.
+    inodes: Vec<Vec<u8>>,
+    /// Open descriptors, indexed by `fd - FIRST_FD`.
+    fds: Vec<Option<OpenFile>>,
+}
+
+#[derive(Clone, Debug)]
+struct OpenFile {
+    inode: usize,
+    pos: u32,
+    read: bool,
+    write: bool,
+    append: bool,
+}
+
+impl Vfs {
+    /// Every file with its contents, sorted by path.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.names.iter().map(|(name, &i)| (name.as_str(), self.inodes[i].as_slice()))
+    }
+
+    /// Descriptors currently open on `path`.
+    pub fn fds_of(&self, path: &str) -> Vec<u32> {
+        let Some(&inode) = self.names.get(path) else { return Vec::new() };
+        self.open_files().filter(|(_, f)| f.inode == inode).map(|(fd, _)| fd).collect()
+    }
+
+    /// Create or replace `path` (outside of any run, e.g. an imported file).
+    pub fn insert(&mut self, path: &str, bytes: Vec<u8>) -> Result<String, u32> {
+        let name = normalize(path)?;
+        if self.is_dir(&name) {
+            return Err(EISDIR);
+        }
+        match self.names.get(&name) {
+            Some(&i) => self.inodes[i] = bytes,
+            None => {
+                self.inodes.push(bytes);
+                self.names.insert(name.clone(), self.inodes.len() - 1);
+            }
+        }
+        Ok(name)
+    }
+
+    /// `openat(AT_FDCWD, path, flags)`: the new descriptor.
+    pub fn open(&mut self, path: &str, flags: u32) -> Result<u32, u32> {
+        let name = normalize(path)?;
+        // Directories can't be opened: there is nothing to list them with
+        if self.is_dir(&name) {
+            return Err(EISDIR);
+        }
+        if flags & O_DIRECTORY != 0 {
+            return Err(if self.names.contains_key(&name) { ENOTDIR } else { ENOENT });
+        }
+        let (read, write) = match flags & O_ACCMODE {
+            O_WRONLY => (false, true),
+            O_RDWR => (true, true),
+            0 => (true, false),
+            _ => return Err(EINVAL),
+        };
+        let inode = match self.names.get(&name) {
+            Some(_) if flags & O_CREAT != 0 && flags & O_EXCL != 0 => return Err(EEXIST),
+            Some(&i) => i,
+            None if flags & O_CREAT != 0 => {
+                // A parent that is a file can't hold anything
+                if name.match_indices('/').any(|(at, _)| self.names.contains_key(&name[..at])) {
+                    return Err(ENOTDIR);
+                }
+                self.inodes.push(Vec::new());
+                self.names.insert(name, self.inodes.len() - 1);
+                self.inodes.len() - 1
+            }
+            None => return Err(ENOENT),
+        };
+        if write && flags & O_TRUNC != 0 {
+            self.inodes[inode].clear();
+        }
+        let file = OpenFile { inode, pos: 0, read, write, append: flags & O_APPEND != 0 };
+        let slot = match self.fds.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None if self.fds.len() < MAX_FDS => {
+                self.fds.push(None);
+                self.fds.len() - 1
+            }
+            None => return Err(EMFILE),
+        };
+        self.fds[slot] = Some(file);
+        Ok(FIRST_FD + slot as u32)
+    }
+
+    pub fn close(&mut self, fd: u32) -> Result<(), u32> {
+        let file = self.slot(fd)?.take().ok_or(EBADF)?;
+        self.release(file.inode);
+        Ok(())
+    }
+
+    /// Up to `count` bytes from the descriptor's position, which moves past them.
+    pub fn read(&mut self, fd: u32, count: usize) -> Result<Vec<u8>, u32> {
+        let file = self.file(fd)?;
+        if !file.read {
+            return Err(EBADF);
+        }
+        let (inode, pos) = (file.inode, file.pos as usize);
+        let data = &self.inodes[inode];
+        let bytes = data.get(pos..).map_or(&[][..], |rest| &rest[..count.min(rest.len())]).to_vec();
+        self.file_mut(fd)?.pos += bytes.len() as u32;
+        Ok(bytes)
+    }
+
+    /// Write at the descriptor's position (the end with `O_APPEND`); a gap left
+    /// by seeking past the end reads back as zeros.
+    pub fn write(&mut self, fd: u32, bytes: &[u8]) -> Result<u32, u32> {
+        let file = self.file(fd)?;
+        if !file.write {
+            return Err(EBADF);
+        }
+        let (inode, append, pos) = (file.inode, file.append, file.pos as usize);
+        let data = &mut self.inodes[inode];
+        let pos = if append { data.len() } else { pos };
+        let end = pos + bytes.len();
+        if end > MAX_FILE_SIZE {
+            return Err(EFBIG);
+        }
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[pos..end].copy_from_slice(bytes);
+        self.file_mut(fd)?.pos = end as u32;
+        Ok(bytes.len() as u32)
+    }
+
+    /// `lseek`: the new position.
+    pub fn seek(&mut self, fd: u32, offset: i32, whence: u32) -> Result<u32, u32> {
+        let file = self.file(fd)?;
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => file.pos as i64,
+            SEEK_END => self.inodes[file.inode].len() as i64,
+            _ => return Err(EINVAL),
+        };
+        let pos = u32::try_from(base + offset as i64).map_err(|_| EINVAL)?;
+        self.file_mut(fd)?.pos = pos;
+        Ok(pos)
+    }
+
+    /// `(inode, size)` of the file open on `fd`, for `fstat`.
+    pub fn stat(&self, fd: u32) -> Result<(u32, u32), u32> {
+        let file = self.file(fd)?;
+        Ok((file.inode as u32 + 1, self.inodes[file.inode].len() as u32))
+    }
+
+    /// `(inode, size)` of the file at `path`; `None` when it is a directory.
+    pub fn stat_path(&self, path: &str) -> Result<Option<(u32, u32)>, u32> {
+        let name = normalize(path)?;
+        match self.names.get(&name) {
+            Some(&i) => Ok(Some((i as u32 + 1, self.inodes[i].len() as u32))),
+            None if self.is_dir(&name) => Ok(None),
+            None => Err(ENOENT),
+        }
+    }
+
+    /// Remove `path`. Descriptors already open on it keep working.
+    pub fn unlink(&mut self, path: &str) -> Result<(), u32> {
+        let name = normalize(path)?;
+        let inode = self.names.remove(&name).ok_or(if self.is_dir(&name) { EISDIR } else { ENOENT })?;
+        self.release(inode);
+        Ok(())
+    }
+
+    /// Every regular file under `dir`, named by its path relative to it.
+    pub fn load_dir(dir: &Path) -> io::Result<Vfs> {
+        fn walk(vfs: &mut Vfs, root: &Path, dir: &Path) -> io::Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                // `file_type` doesn't follow symlinks, which are skipped: the guest
+                // could otherwise reach host files through them.
+                let (ty, path) = (entry.file_type()?, entry.path());
+                if ty.is_dir() {
+                    walk(vfs, root, &path)?;
+                } else if ty.is_file() {
+                    let rel = path.strip_prefix(root).unwrap_or(&path);
+                    let name = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                    let _ = vfs.insert(&name, std::fs::read(&path)?);
+                }
+            }
+            Ok(())
+        }
+        let mut vfs = Vfs::default();
+        walk(&mut vfs, dir, dir)?;
+        Ok(vfs)
+    }
+
+    /// Apply what changed since `before` to `dir`: new and modified files are
+    /// written, unlinked ones removed.
+    pub fn sync_dir(&self, dir: &Path, before: &Vfs) -> io::Result<()> {
+        for (name, data) in self.files() {
+            if before.names.get(name).map(|&i| before.inodes[i].as_slice()) != Some(data) {
+                let path = host_path(dir, name)?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, data)?;
+            }
+        }
+        for name in before.names.keys().filter(|n| !self.names.contains_key(*n)) {
+            match std::fs::remove_file(host_path(dir, name)?) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `name` is a directory, i.e. the prefix of some file's path.
+    fn is_dir(&self, name: &str) -> bool {
+        let prefix = format!("{name}/");
+        name.is_empty() || self.names.range(prefix.clone()..).next().is_some_and(|(n, _)| n.starts_with(&prefix))
+    }
+
+    fn open_files(&self) -> impl Iterator<Item = (u32, &OpenFile)> {
+        self.fds.iter().enumerate().filter_map(|(i, f)| Some((FIRST_FD + i as u32, f.as_ref()?)))
+    }
+
+    fn slot(&mut self, fd: u32) -> Result<&mut Option<OpenFile>, u32> {
+        fd.checked_sub(FIRST_FD).and_then(|i| self.fds.get_mut(i as usize)).ok_or(EBADF)
+    }
+
+    fn file(&self, fd: u32) -> Result<&OpenFile, u32> {
+        fd.checked_sub(FIRST_FD).and_then(|i| self.fds.get(i as usize)?.as_ref()).ok_or(EBADF)
+    }
+
+    fn file_mut(&mut self, fd: u32) -> Result<&mut OpenFile, u32> {
+        self.slot(fd)?.as_mut().ok_or(EBADF)
+    }
+
+    /// Drop the contents of an inode nothing refers to any more.
+    fn release(&mut self, inode: usize) {
+        let named = self.names.values().any(|&i| i == inode);
+        if !named && !self.open_files().any(|(_, f)| f.inode == inode) {
+            self.inodes[inode] = Vec::new();
+        }
+    }
+}
+
+/// `path` relative to the root, with `.` and `..` resolved; `..` above the root
+/// is an error (the sandbox has nothing there). `\\` and `:` are rejected, since a
+/// Windows host would read them as separators or a drive.
+pub fn normalize(path: &str) -> Result<String, u32> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop().ok_or(ENOENT)?;
+            }
+            p if p.contains(['\\', ':']) => return Err(EINVAL),
+            p => parts.push(p),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// Where the file `name` lives under `dir`. Names that could leave `dir` on this
+/// host, and paths through a symlink, are refused rather than joined.
+fn host_path(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let outside = || io::Error::new(io::ErrorKind::InvalidInput, format!("`{name}` is outside the sandbox"));
+    let rel = Path::new(name);
+    if name.contains(['\\', ':']) || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(outside());
+    }
+    let mut path = dir.to_path_buf();
+    for part in rel.components() {
+        path.push(part);
+        if std::fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return Err(outside());
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_write_seek_read_and_unlink() {
+        let mut vfs = Vfs::default();
+        vfs.insert("data/in.txt", b"one two".to_vec()).unwrap();
+        assert_eq!(vfs.open("./data/../data/in.txt", 0), Ok(3));
+        assert_eq!(vfs.read(3, 3).unwrap(), b"one");
+        assert_eq!(vfs.write(3, b"x"), Err(EBADF));
+        assert_eq!(vfs.seek(3, -3, SEEK_END), Ok(4));
+        assert_eq!(vfs.read(3, 100).unwrap(), b"two");
+        assert_eq!(vfs.read(3, 100).unwrap(), b"");
+
+        assert_eq!(vfs.open("out.txt", O_WRONLY), Err(ENOENT));
+        assert_eq!(vfs.open("data", 0), Err(EISDIR));
+        assert_eq!(vfs.open("data/in.txt/x", O_WRONLY | O_CREAT), Err(ENOTDIR));
+        assert_eq!(vfs.open("../etc/passwd", 0), Err(ENOENT));
+        let out = vfs.open("out.txt", O_WRONLY | O_CREAT | O_TRUNC).unwrap();
+        assert_eq!(out, 4);
+        vfs.seek(out, 2, SEEK_SET).unwrap();
+        vfs.write(out, b"hi").unwrap();
+        assert_eq!(vfs.open("out.txt", O_WRONLY | O_CREAT | O_EXCL), Err(EEXIST));
+        assert_eq!(vfs.fds_of("out.txt"), [4]);
+
+        // Unlinked while open: the name goes, the data stays readable through fd 3
+        vfs.unlink("data/in.txt").unwrap();
+        assert_eq!(vfs.open("data/in.txt", 0), Err(ENOENT));
+        vfs.seek(3, 0, SEEK_SET).unwrap();
+        assert_eq!(vfs.read(3, 3).unwrap(), b"one");
+        vfs.close(3).unwrap();
+        assert_eq!(vfs.close(3), Err(EBADF));
+        assert_eq!(vfs.open("again.txt", O_RDWR | O_CREAT), Ok(3));
+
+        let files: Vec<(&str, &[u8])> = vfs.files().collect();
+        assert_eq!(files, [("again.txt", &b""[..]), ("out.txt", &b"\0\0hi"[..])]);
+    }
+
+    #[test]
+    fn host_paths_stay_inside_the_sandbox() {
+        assert_eq!(normalize("..\\..\\x"), Err(EINVAL));
+        assert_eq!(normalize("C:\\x"), Err(EINVAL));
+        let dir = std::env::temp_dir().join(format!("raven_vfs_{}", std::process::id()));
+        assert_eq!(host_path(&dir, "a/b.txt").unwrap(), dir.join("a").join("b.txt"));
+        for name in ["../x", "/etc/passwd", "a\\..\\..\\x", "C:x"] {
+            assert!(host_path(&dir, name).is_err(), "{name}");
+        }
+
+        #[cfg(unix)]
+        {
+            std::fs::create_dir_all(&dir).unwrap();
+            let host = dir.with_extension("host");
+            std::fs::write(&host, b"host").unwrap();
+            std::os::unix::fs::symlink(&host, dir.join("link")).unwrap();
+            std::fs::write(dir.join("own.txt"), b"own").unwrap();
+            let before = Vfs::load_dir(&dir).unwrap();
+            assert_eq!(before.files().map(|(n, _)| n).collect::<Vec<_>>(), ["own.txt"]);
+
+            let mut after = Vfs::load_dir(&dir).unwrap();
+            after.insert("link", b"guest".to_vec()).unwrap();
+            assert!(after.sync_dir(&dir, &before).is_err());
+            assert_eq!(std::fs::read(&host).unwrap(), b"host");
+            let _ = std::fs::remove_dir_all(&dir);
+            let _ = std::fs::remove_file(&host);
+        }
+    }
+}
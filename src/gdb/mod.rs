@@ -0,0 +1,608 @@
+// gdb/mod.rs — GDB Remote Serial Protocol stub (`raven --gdb <port>`)
+//
+// The stub is polled from the TUI's tick loop, so the Run and Cache tabs keep
+// updating while `gdb` / `lldb` drive the program. It never blocks: the
+// listener and the client socket are both non-blocking, and `continue` only
+// flips the simulator into running mode — the stop reply is sent later by
+// `report_stop` once the App notices execution has paused.
+pub mod packet;
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use packet::{Incoming, from_hex, parse_hex_u32, to_hex};
+
+/// Register numbering follows GDB's RISC-V target description:
+/// x0–x31 = 0–31, pc = 32, f0–f31 = 33–64, CSRs at 65 + csr number.
+pub const REG_PC: usize = 32;
+pub const REG_F0: usize = 33;
+pub const REG_FFLAGS: usize = 65 + 0x001;
+pub const REG_FRM: usize = 65 + 0x002;
+pub const REG_FCSR: usize = 65 + 0x003;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <architecture>riscv:rv32</architecture>
+  <feature name="org.gnu.gdb.riscv.cpu">
+    <reg name="zero" bitsize="32" type="int" regnum="0"/>
+    <reg name="ra" bitsize="32" type="code_ptr"/>
+    <reg name="sp" bitsize="32" type="data_ptr"/>
+    <reg name="gp" bitsize="32" type="data_ptr"/>
+    <reg name="tp" bitsize="32" type="data_ptr"/>
+    <reg name="t0" bitsize="32" type="int"/>
+    <reg name="t1" bitsize="32" type="int"/>
+    <reg name="t2" bitsize="32" type="int"/>
+    <reg name="fp" bitsize="32" type="data_ptr"/>
+    <reg name="s1" bitsize="32" type="int"/>
+    <reg name="a0" bitsize="32" type="int"/>
+    <reg name="a1" bitsize="32" type="int"/>
+    <reg name="a2" bitsize="32" type="int"/>
+    <reg name="a3" bitsize="32" type="int"/>
+    <reg name="a4" bitsize="32" type="int"/>
+    <reg name="a5" bitsize="32" type="int"/>
+    <reg name="a6" bitsize="32" type="int"/>
+    <reg name="a7" bitsize="32" type="int"/>
+    <reg name="s2" bitsize="32" type="int"/>
+    <reg name="s3" bitsize="32" type="int"/>
+    <reg name="s4" bitsize="32" type="int"/>
+    <reg name="s5" bitsize="32" type="int"/>
+    <reg name="s6" bitsize="32" type="int"/>
+    <reg name="s7" bitsize="32" type="int"/>
+    <reg name="s8" bitsize="32" type="int"/>
+    <reg name="s9" bitsize="32" type="int"/>
+    <reg name="s10" bitsize="32" type="int"/>
+    <reg name="s11" bitsize="32" type="int"/>
+    <reg name="t3" bitsize="32" type="int"/>
+    <reg name="t4" bitsize="32" type="int"/>
+    <reg name="t5" bitsize="32" type="int"/>
+    <reg name="t6" bitsize="32" type="int"/>
+    <reg name="pc" bitsize="32" type="code_ptr"/>
+  </feature>
+  <feature name="org.gnu.gdb.riscv.fpu">
+    <reg name="ft0" bitsize="32" type="ieee_single" regnum="33"/>
+    <reg name="ft1" bitsize="32" type="ieee_single"/>
+    <reg name="ft2" bitsize="32" type="ieee_single"/>
+    <reg name="ft3" bitsize="32" type="ieee_single"/>
+    <reg name="ft4" bitsize="32" type="ieee_single"/>
+    <reg name="ft5" bitsize="32" type="ieee_single"/>
+    <reg name="ft6" bitsize="32" type="ieee_single"/>
+    <reg name="ft7" bitsize="32" type="ieee_single"/>
+    <reg name="fs0" bitsize="32" type="ieee_single"/>
+    <reg name="fs1" bitsize="32" type="ieee_single"/>
+    <reg name="fa0" bitsize="32" type="ieee_single"/>
+    <reg name="fa1" bitsize="32" type="ieee_single"/>
+    <reg name="fa2" bitsize="32" type="ieee_single"/>
+    <reg name="fa3" bitsize="32" type="ieee_single"/>
+    <reg name="fa4" bitsize="32" type="ieee_single"/>
+    <reg name="fa5" bitsize="32" type="ieee_single"/>
+    <reg name="fa6" bitsize="32" type="ieee_single"/>
+    <reg name="fa7" bitsize="32" type="ieee_single"/>
+    <reg name="fs2" bitsize="32" type="ieee_single"/>
+    <reg name="fs3" bitsize="32" type="ieee_single"/>
+    <reg name="fs4" bitsize="32" type="ieee_single"/>
+    <reg name="fs5" bitsize="32" type="ieee_single"/>
+    <reg name="fs6" bitsize="32" type="ieee_single"/>
+    <reg name="fs7" bitsize="32" type="ieee_single"/>
+    <reg name="fs8" bitsize="32" type="ieee_single"/>
+    <reg name="fs9" bitsize="32" type="ieee_single"/>
+    <reg name="fs10" bitsize="32" type="ieee_single"/>
+    <reg name="fs11" bitsize="32" type="ieee_single"/>
+    <reg name="ft8" bitsize="32" type="ieee_single"/>
+    <reg name="ft9" bitsize="32" type="ieee_single"/>
+    <reg name="ft10" bitsize="32" type="ieee_single"/>
+    <reg name="ft11" bitsize="32" type="ieee_single"/>
+    <reg name="fflags" bitsize="32" type="int" regnum="66"/>
+    <reg name="frm" bitsize="32" type="int" regnum="67"/>
+    <reg name="fcsr" bitsize="32" type="int" regnum="68"/>
+  </feature>
+</target>
+"#;
+
+/// Which accesses a watchpoint traps on (`Z2` / `Z3` / `Z4`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+impl WatchKind {
+    /// Whether a load (`is_store == false`) or store triggers this watchpoint.
+    pub fn matches(self, is_store: bool) -> bool {
+        match self {
+            WatchKind::Write => is_store,
+            WatchKind::Read => !is_store,
+            WatchKind::Access => true,
+        }
+    }
+
+    fn stop_key(self) -> &'static str {
+        match self {
+            WatchKind::Write => "watch",
+            WatchKind::Read => "rwatch",
+            WatchKind::Access => "awatch",
+        }
+    }
+}
+
+/// Why the target stopped; turned into a stop-reply packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// A single step completed.
+    Step,
+    /// PC reached a software breakpoint.
+    Breakpoint,
+    /// A watchpoint triggered on an access to `addr`.
+    Watch(WatchKind, u32),
+    /// The client (Ctrl-C) or the TUI user paused execution.
+    Interrupted,
+    /// The program executed `ebreak`.
+    Ebreak,
+    /// The program faulted (bus error, illegal instruction, left the text segment).
+    Fault,
+    /// The program exited with this status.
+    Exited(u32),
+}
+
+impl StopReason {
+    fn reply(self) -> String {
+        match self {
+            StopReason::Step | StopReason::Ebreak => "S05".into(),
+            StopReason::Breakpoint => "T05swbreak:;".into(),
+            StopReason::Watch(kind, addr) => format!("T05{}:{addr:08x};", kind.stop_key()),
+            StopReason::Interrupted => "S02".into(),
+            StopReason::Fault => "S0b".into(),
+            StopReason::Exited(code) => format!("W{:02x}", code & 0xff),
+        }
+    }
+}
+
+/// What the stub needs from the simulator.
+pub trait Target {
+    /// Read register `n` (GDB numbering). `None` for unknown registers.
+    fn read_reg(&self, n: usize) -> Option<u32>;
+    fn write_reg(&mut self, n: usize, val: u32) -> bool;
+    /// Cache-coherent memory read; `None` if any byte is out of range.
+    fn read_mem(&self, addr: u32, len: u32) -> Option<Vec<u8>>;
+    fn write_mem(&mut self, addr: u32, data: &[u8]) -> bool;
+    fn set_breakpoint(&mut self, addr: u32, enabled: bool);
+    fn set_watchpoint(&mut self, kind: WatchKind, addr: u32, len: u32, enabled: bool);
+    /// Execute one instruction. `None` means the step is blocked (e.g. waiting
+    /// for console input) and will complete later.
+    fn step(&mut self) -> Option<StopReason>;
+    /// Start free-running; the stop is reported via `GdbServer::report_stop`.
+    fn resume(&mut self);
+    fn interrupt(&mut self);
+    /// Output for `monitor <cmd>`.
+    fn monitor(&mut self, cmd: &str) -> String;
+}
+
+/// An execution request still waiting for its stop reply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pending {
+    Continue,
+    Step,
+}
+
+pub struct GdbServer {
+    listener: TcpListener,
+    conn: Option<TcpStream>,
+    rx: Vec<u8>,
+    no_ack: bool,
+    pending: Option<Pending>,
+    pub port: u16,
+}
+
+impl GdbServer {
+    /// Listen on `127.0.0.1:<port>`.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        Ok(Self { listener, conn: None, rx: Vec::new(), no_ack: false, pending: None, port })
+    }
+
+    /// The `c`/`s` request the client is waiting on, if any.
+    pub fn pending(&self) -> Option<Pending> {
+        self.pending
+    }
+
+    /// Accept a client, then handle every complete packet received so far.
+    pub fn poll<T: Target>(&mut self, target: &mut T) {
+        if self.conn.is_none() {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        let _ = stream.set_nodelay(true);
+                        self.conn = Some(stream);
+                        self.rx.clear();
+                        self.no_ack = false;
+                        self.pending = None;
+                    }
+                }
+                Err(_) => return,
+            }
+        }
+        let Some(conn) = self.conn.as_mut() else { return };
+        let mut buf = [0u8; 4096];
+        loop {
+            match conn.read(&mut buf) {
+                Ok(0) => {
+                    self.disconnect();
+                    return;
+                }
+                Ok(n) => self.rx.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => {
+                    self.disconnect();
+                    return;
+                }
+            }
+        }
+        while let Some(unit) = packet::next(&mut self.rx) {
+            match unit {
+                Incoming::Interrupt => {
+                    if self.pending.is_some() {
+                        target.interrupt();
+                    }
+                }
+                Incoming::Packet { ok: false, .. } => self.send_raw(b"-"),
+                Incoming::Packet { payload, ok: true } => {
+                    if !self.no_ack {
+                        self.send_raw(b"+");
+                    }
+                    if let Some(reply) = self.handle(&payload, target) {
+                        self.send(reply.as_bytes());
+                    }
+                    // The `OK` above still goes out under acks; nothing after it does.
+                    if payload == b"QStartNoAckMode" {
+                        self.no_ack = true;
+                    }
+                }
+            }
+            if self.conn.is_none() {
+                return;
+            }
+        }
+    }
+
+    /// Send the stop reply for a pending `c`/`s`.
+    pub fn report_stop(&mut self, reason: StopReason) {
+        if self.pending.take().is_some() {
+            self.send(reason.reply().as_bytes());
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.conn = None;
+        self.pending = None;
+        self.rx.clear();
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        if let Some(conn) = self.conn.as_mut() {
+            // The socket is non-blocking; replies are small, so retry until the kernel takes them.
+            let mut off = 0;
+            while off < bytes.len() {
+                match conn.write(&bytes[off..]) {
+                    Ok(n) => off += n,
+                    Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+                        std::thread::yield_now();
+                    }
+                    Err(_) => {
+                        self.disconnect();
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn send(&mut self, payload: &[u8]) {
+        let framed = packet::encode(payload);
+        self.send_raw(&framed);
+    }
+
+    /// Handle one packet; returns the reply payload (`None` = no reply now).
+    fn handle<T: Target>(&mut self, payload: &[u8], target: &mut T) -> Option<String> {
+        let Some(&cmd) = payload.first() else { return Some(String::new()) };
+        // Binary payloads (`X`) are only valid after the `:`; everything else is ASCII.
+        if cmd == b'X' {
+            return Some(self.handle_write_binary(payload, target));
+        }
+        let args = String::from_utf8_lossy(&payload[1..]).into_owned();
+        let args = args.as_str();
+        let reply = match cmd {
+            b'?' => "S05".to_string(),
+            b'g' => {
+                let mut out = String::with_capacity(33 * 8);
+                for n in 0..=REG_PC {
+                    out.push_str(&to_hex(&target.read_reg(n).unwrap_or(0).to_le_bytes()));
+                }
+                out
+            }
+            b'G' => match from_hex(args) {
+                Some(bytes) if bytes.len() >= (REG_PC + 1) * 4 => {
+                    for (n, chunk) in bytes.chunks_exact(4).take(REG_PC + 1).enumerate() {
+                        target.write_reg(n, u32::from_le_bytes(chunk.try_into().unwrap()));
+                    }
+                    "OK".into()
+                }
+                _ => "E01".into(),
+            },
+            b'p' => match usize::from_str_radix(args, 16).ok().and_then(|n| target.read_reg(n)) {
+                Some(v) => to_hex(&v.to_le_bytes()),
+                None => "E01".into(),
+            },
+            b'P' => {
+                let parsed = args.split_once('=').and_then(|(n, v)| {
+                    let n = usize::from_str_radix(n, 16).ok()?;
+                    let bytes = from_hex(v)?;
+                    Some((n, u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?)))
+                });
+                match parsed {
+                    Some((n, v)) if target.write_reg(n, v) => "OK".into(),
+                    _ => "E01".into(),
+                }
+            }
+            b'm' => {
+                let parsed = args.split_once(',').and_then(|(a, l)| Some((parse_hex_u32(a)?, parse_hex_u32(l)?)));
+                match parsed.and_then(|(a, l)| target.read_mem(a, l.min(0x1000))) {
+                    Some(bytes) => to_hex(&bytes),
+                    None => "E14".into(),
+                }
+            }
+            b'M' => {
+                let parsed = args.split_once(':').and_then(|(head, data)| {
+                    let (a, _) = head.split_once(',')?;
+                    Some((parse_hex_u32(a)?, from_hex(data)?))
+                });
+                match parsed {
+                    Some((a, data)) if target.write_mem(a, &data) => "OK".into(),
+                    _ => "E14".into(),
+                }
+            }
+            b'Z' | b'z' => Self::handle_point(cmd == b'Z', args, target),
+            b's' | b'c' => {
+                if let Some(addr) = parse_hex_u32(args) {
+                    target.write_reg(REG_PC, addr);
+                }
+                if cmd == b's' {
+                    self.pending = Some(Pending::Step);
+                    match target.step() {
+                        Some(reason) => {
+                            self.pending = None;
+                            reason.reply()
+                        }
+                        None => return None,
+                    }
+                } else {
+                    self.pending = Some(Pending::Continue);
+                    target.resume();
+                    return None;
+                }
+            }
+            b'H' | b'T' => "OK".into(),
+            b'k' => {
+                self.disconnect();
+                return None;
+            }
+            b'D' => {
+                self.send(b"OK");
+                self.disconnect();
+                return None;
+            }
+            b'q' | b'Q' => self.handle_query(&format!("{}{args}", cmd as char), target),
+            // `vCont` is optional; an empty reply makes GDB fall back to `s`/`c`.
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn handle_write_binary<T: Target>(&mut self, payload: &[u8], target: &mut T) -> String {
+        let Some(colon) = payload.iter().position(|&b| b == b':') else { return "E01".into() };
+        let head = String::from_utf8_lossy(&payload[1..colon]);
+        let Some(addr) = head.split_once(',').and_then(|(a, _)| parse_hex_u32(a)) else { return "E01".into() };
+        let data = packet::unescape(&payload[colon + 1..]);
+        if data.is_empty() || target.write_mem(addr, &data) { "OK".into() } else { "E14".into() }
+    }
+
+    fn handle_point<T: Target>(insert: bool, args: &str, target: &mut T) -> String {
+        let mut parts = args.split(',');
+        let (Some(kind), Some(addr), Some(len)) = (parts.next(), parts.next(), parts.next()) else {
+            return "E01".into();
+        };
+        let (Some(addr), Some(len)) = (parse_hex_u32(addr), parse_hex_u32(len)) else { return "E01".into() };
+        match kind {
+            // Hardware breakpoints behave exactly like software ones here.
+            "0" | "1" => target.set_breakpoint(addr, insert),
+            "2" => target.set_watchpoint(WatchKind::Write, addr, len, insert),
+            "3" => target.set_watchpoint(WatchKind::Read, addr, len, insert),
+            "4" => target.set_watchpoint(WatchKind::Access, addr, len, insert),
+            _ => return String::new(),
+        }
+        "OK".into()
+    }
+
+    fn handle_query<T: Target>(&mut self, text: &str, target: &mut T) -> String {
+        if text.starts_with("qSupported") {
+            return "PacketSize=4000;qXfer:features:read+;swbreak+;hwbreak+;QStartNoAckMode+".into();
+        }
+        if text == "QStartNoAckMode" {
+            return "OK".into();
+        }
+        if let Some(rest) = text.strip_prefix("qXfer:features:read:") {
+            let Some((annex, range)) = rest.split_once(':') else { return "E01".into() };
+            if annex != "target.xml" {
+                return "E00".into();
+            }
+            let Some((off, len)) = range.split_once(',').and_then(|(o, l)| Some((parse_hex_u32(o)?, parse_hex_u32(l)?)))
+            else {
+                return "E01".into();
+            };
+            let xml = TARGET_XML.as_bytes();
+            let start = (off as usize).min(xml.len());
+            let end = (start + len as usize).min(xml.len());
+            let marker = if end == xml.len() { 'l' } else { 'm' };
+            return format!("{marker}{}", String::from_utf8_lossy(&xml[start..end]));
+        }
+        if let Some(hex) = text.strip_prefix("qRcmd,") {
+            let cmd = from_hex(hex).map(|b| String::from_utf8_lossy(&b).into_owned()).unwrap_or_default();
+            let mut out = target.monitor(cmd.trim());
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            self.send(format!("O{}", to_hex(out.as_bytes())).as_bytes());
+            return "OK".into();
+        }
+        match text {
+            "qAttached" => "1".into(),
+            "qC" => "QC1".into(),
+            "qfThreadInfo" => "m1".into(),
+            "qsThreadInfo" => "l".into(),
+            _ => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[derive(Default)]
+    struct Mock {
+        regs: Vec<u32>,
+        mem: Vec<u8>,
+        breakpoints: HashSet<u32>,
+        watch: Vec<(WatchKind, u32, u32)>,
+    }
+
+    impl Target for Mock {
+        fn read_reg(&self, n: usize) -> Option<u32> {
+            self.regs.get(n).copied()
+        }
+        fn write_reg(&mut self, n: usize, val: u32) -> bool {
+            self.regs.get_mut(n).map(|r| *r = val).is_some()
+        }
+        fn read_mem(&self, addr: u32, len: u32) -> Option<Vec<u8>> {
+            self.mem.get(addr as usize..(addr + len) as usize).map(|s| s.to_vec())
+        }
+        fn write_mem(&mut self, addr: u32, data: &[u8]) -> bool {
+            match self.mem.get_mut(addr as usize..addr as usize + data.len()) {
+                Some(dst) => {
+                    dst.copy_from_slice(data);
+                    true
+                }
+                None => false,
+            }
+        }
+        fn set_breakpoint(&mut self, addr: u32, enabled: bool) {
+            if enabled {
+                self.breakpoints.insert(addr);
+            } else {
+                self.breakpoints.remove(&addr);
+            }
+        }
+        fn set_watchpoint(&mut self, kind: WatchKind, addr: u32, len: u32, enabled: bool) {
+            if enabled {
+                self.watch.push((kind, addr, len));
+            }
+        }
+        fn step(&mut self) -> Option<StopReason> {
+            self.regs[REG_PC] += 4;
+            Some(StopReason::Step)
+        }
+        fn resume(&mut self) {}
+        fn interrupt(&mut self) {}
+        fn monitor(&mut self, _cmd: &str) -> String {
+            "hits 1".into()
+        }
+    }
+
+    /// Send one packet, poll the server, and return the replies it produced.
+    fn exchange(server: &mut GdbServer, target: &mut Mock, client: &mut TcpStream, payload: &[u8]) -> Vec<u8> {
+        client.write_all(&packet::encode(payload)).unwrap();
+        let mut out = Vec::new();
+        let mut buf = [0u8; 8192];
+        for _ in 0..200 {
+            server.poll(target);
+            match client.read(&mut buf) {
+                Ok(n) if n > 0 => {
+                    out.extend_from_slice(&buf[..n]);
+                    // Done once a full `...#cs` frame has arrived.
+                    if out.len() >= 3 && out[out.len() - 3] == b'#' {
+                        break;
+                    }
+                }
+                _ => std::thread::sleep(Duration::from_millis(2)),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn serves_registers_memory_and_breakpoints_over_tcp() {
+        let mut server = GdbServer::bind(0).expect("bind");
+        let mut client = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+        let mut target = Mock { regs: vec![0; REG_PC + 1], mem: vec![0; 64], ..Default::default() };
+        target.regs[10] = 0x1234_5678;
+
+        let r = exchange(&mut server, &mut target, &mut client, b"qSupported:swbreak+");
+        assert!(r.starts_with(b"+$PacketSize=4000;qXfer:features:read+"));
+
+        let r = exchange(&mut server, &mut target, &mut client, b"pa");
+        assert_eq!(r, [b"+".as_slice(), &packet::encode(b"78563412")].concat());
+
+        exchange(&mut server, &mut target, &mut client, b"M10,2:abcd");
+        assert_eq!(&target.mem[0x10..0x12], &[0xab, 0xcd]);
+        let r = exchange(&mut server, &mut target, &mut client, b"m10,2");
+        assert!(r.ends_with(&packet::encode(b"abcd")));
+
+        exchange(&mut server, &mut target, &mut client, b"Z0,20,4");
+        assert!(target.breakpoints.contains(&0x20));
+        exchange(&mut server, &mut target, &mut client, b"Z2,10,4");
+        assert_eq!(target.watch, vec![(WatchKind::Write, 0x10, 4)]);
+
+        let r = exchange(&mut server, &mut target, &mut client, b"s");
+        assert!(r.ends_with(&packet::encode(b"S05")));
+        assert_eq!(target.regs[REG_PC], 4);
+
+        let r = exchange(&mut server, &mut target, &mut client, b"qXfer:features:read:target.xml:0,40");
+        assert!(r.windows(6).any(|w| w == b"$m<?xm"));
+    }
+
+    #[test]
+    fn no_ack_mode_sends_exactly_one_ok() {
+        let mut server = GdbServer::bind(0).expect("bind");
+        let mut client = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+        let mut target = Mock { regs: vec![0; REG_PC + 1], ..Default::default() };
+        target.regs[10] = 0x1234_5678;
+
+        let r = exchange(&mut server, &mut target, &mut client, b"QStartNoAckMode");
+        assert_eq!(r, [b"+".as_slice(), &packet::encode(b"OK")].concat());
+        let r = exchange(&mut server, &mut target, &mut client, b"pa");
+        assert_eq!(r, packet::encode(b"78563412"));
+    }
+
+    #[test]
+    fn non_ascii_command_byte_is_unsupported() {
+        let mut server = GdbServer::bind(0).expect("bind");
+        let mut target = Mock::default();
+        assert_eq!(server.handle(&[0xc3, b'a'], &mut target), Some(String::new()));
+        assert_eq!(server.handle("\u{e9}x".as_bytes(), &mut target), Some(String::new()));
+    }
+
+    #[test]
+    fn stop_replies_match_rsp_format() {
+        assert_eq!(StopReason::Breakpoint.reply(), "T05swbreak:;");
+        assert_eq!(StopReason::Watch(WatchKind::Read, 0x1000).reply(), "T05rwatch:00001000;");
+        assert_eq!(StopReason::Exited(7).reply(), "W07");
+    }
+}
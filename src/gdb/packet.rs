@@ -0,0 +1,132 @@
+// gdb/packet.rs — GDB Remote Serial Protocol framing
+//
+// A packet is `$<payload>#<two hex digit checksum>`; the checksum is the sum of
+// the payload bytes modulo 256. Inside a payload `}` escapes the next byte
+// (XOR 0x20), which binary packets (`X`, `qXfer` replies) rely on.
+
+/// Sum of the payload bytes modulo 256.
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b))
+}
+
+/// Frame `payload` as `$...#cs`, escaping bytes that would confuse the framing.
+pub fn encode(payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(payload.len() + 4);
+    for &b in payload {
+        if matches!(b, b'$' | b'#' | b'}' | b'*') {
+            body.push(b'}');
+            body.push(b ^ 0x20);
+        } else {
+            body.push(b);
+        }
+    }
+    let mut out = Vec::with_capacity(body.len() + 4);
+    out.push(b'$');
+    out.extend_from_slice(&body);
+    out.push(b'#');
+    out.extend_from_slice(format!("{:02x}", checksum(&body)).as_bytes());
+    out
+}
+
+/// Undo `}` escaping in a received payload.
+pub fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut it = data.iter();
+    while let Some(&b) = it.next() {
+        if b == b'}' {
+            if let Some(&n) = it.next() {
+                out.push(n ^ 0x20);
+            }
+        } else {
+            out.push(b);
+        }
+    }
+    out
+}
+
+/// One unit pulled off the wire.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Incoming {
+    /// A complete packet; `ok` is false when the checksum did not match.
+    Packet { payload: Vec<u8>, ok: bool },
+    /// Ctrl-C sent by the client to interrupt a running target.
+    Interrupt,
+}
+
+/// Extract the next packet or interrupt from `buf`, consuming the bytes used.
+/// Acks (`+`/`-`) and stray bytes before a `$` are dropped.
+/// Returns `None` when `buf` holds no complete unit yet.
+pub fn next(buf: &mut Vec<u8>) -> Option<Incoming> {
+    loop {
+        let first = *buf.first()?;
+        match first {
+            0x03 => {
+                buf.remove(0);
+                return Some(Incoming::Interrupt);
+            }
+            b'$' => break,
+            _ => {
+                buf.remove(0);
+            }
+        }
+    }
+    let hash = buf.iter().position(|&b| b == b'#')?;
+    if buf.len() < hash + 3 {
+        return None;
+    }
+    let body = buf[1..hash].to_vec();
+    let sent = std::str::from_utf8(&buf[hash + 1..hash + 3]).ok().and_then(|s| u8::from_str_radix(s, 16).ok());
+    buf.drain(..hash + 3);
+    let ok = sent == Some(checksum(&body));
+    Some(Incoming::Packet { payload: body, ok })
+}
+
+/// Lowercase hex encoding, as used for register and memory contents.
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Parse a hex number field such as an address or length.
+pub fn parse_hex_u32(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_appends_checksum_and_escapes() {
+        assert_eq!(encode(b"OK"), b"$OK#9a".to_vec());
+        assert_eq!(encode(b"a#b"), b"$a}\x03b#43".to_vec());
+        assert_eq!(unescape(b"a}\x03b"), b"a#b".to_vec());
+    }
+
+    #[test]
+    fn next_splits_stream_into_packets() {
+        let mut buf = b"+$g#67\x03$m0,4#00".to_vec();
+        assert_eq!(next(&mut buf), Some(Incoming::Packet { payload: b"g".to_vec(), ok: true }));
+        assert_eq!(next(&mut buf), Some(Incoming::Interrupt));
+        assert_eq!(next(&mut buf), Some(Incoming::Packet { payload: b"m0,4".to_vec(), ok: false }));
+        assert!(buf.is_empty());
+
+        let mut partial = b"$qSupp".to_vec();
+        assert_eq!(next(&mut partial), None);
+        assert_eq!(partial, b"$qSupp".to_vec());
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x10]), "00ab10");
+        assert_eq!(from_hex("00ab10"), Some(vec![0x00, 0xab, 0x10]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(parse_hex_u32("1000"), Some(0x1000));
+    }
+}
@@ -0,0 +1,536 @@
+// headless.rs — run a program without the TUI (`raven run <file>`)
+//
+// Used by graders and CI: program output goes to stdout/stderr, input is read
+// from stdin, and the process exits with the guest's exit code.
+use std::collections::HashMap;
+use std::ops::Range;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::falcon::cache::CacheConfig;
+use crate::falcon::clock::{parse_hz, GuestClock};
+use crate::falcon::mmap::Mappings;
+use crate::falcon::coverage::{self, BranchCounts, Coverage};
+use crate::falcon::program::{function_at, init_stack, ElfSymbol, ProgramArgs};
+use crate::falcon::syscall::SyscallAbi;
+use crate::falcon::{self, CacheController, Cpu};
+use crate::ui::console::ConsoleColor;
+use crate::ui::Console;
+
+/// Default step budget; protects graders from programs that never terminate.
+const DEFAULT_MAX_STEPS: u64 = 50_000_000;
+
+pub struct RunOptions {
+    pub path: PathBuf,
+    pub mem_size: Option<usize>,
+    pub max_steps: u64,
+    /// Write an LCOV coverage report here when the run ends.
+    pub lcov: Option<PathBuf>,
+    /// Write the syscall trace here when the run ends.
+    pub strace: Option<PathBuf>,
+    /// Write an assembly listing (`.lst`) here before running.
+    pub listing: Option<PathBuf>,
+    /// Extra directories searched by `.include` (`-I <dir>`, repeatable).
+    pub include_dirs: Vec<PathBuf>,
+    /// Print instructions executed per function to stderr when the run ends.
+    pub profile: bool,
+    /// Arguments after `--` and `--env NAME=value` variables for the guest.
+    pub args: ProgramArgs,
+    /// Host directory the guest's files come from and are written back to.
+    pub fs: Option<PathBuf>,
+    /// Where the guest's time comes from (`--clock <freq>`, `--host-time`).
+    pub clock: GuestClock,
+    /// Which syscall numbers the program uses (`--syscalls linux|raven|rars`).
+    pub abi: SyscallAbi,
+}
+
+pub fn usage() -> &'static str {
+    "usage: raven run <file.fas|file.elf|file.bin> [--mem <size>] [--max-steps <n>] [--lcov <out.info>] [--strace <out.txt>] [--listing <out.lst>] [--profile] [-I <dir>] [--env NAME=value] [--fs <dir>] [--clock <freq>] [--host-time] [--syscalls linux|raven|rars] [-- <args>...]"
+}
+
+/// Parse the arguments following `run`.
+pub fn parse_args(args: &[String], mem_size: Option<usize>) -> Result<RunOptions, String> {
+    let mut path = None;
+    let mut max_steps = DEFAULT_MAX_STEPS;
+    let mut lcov = None;
+    let mut strace = None;
+    let mut listing = None;
+    let mut include_dirs = Vec::new();
+    let mut profile = false;
+    let mut program_args = ProgramArgs::default();
+    let mut fs = None;
+    let mut clock = GuestClock::default();
+    let mut abi = SyscallAbi::default();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--max-steps" => {
+                let v = args.get(i + 1).ok_or("--max-steps requires a value")?;
+                max_steps = v.parse().map_err(|_| format!("invalid --max-steps value '{v}'"))?;
+                i += 2;
+            }
+            "--lcov" => {
+                let v = args.get(i + 1).ok_or("--lcov requires an output path")?;
+                lcov = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "--strace" => {
+                let v = args.get(i + 1).ok_or("--strace requires an output path")?;
+                strace = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "--listing" => {
+                let v = args.get(i + 1).ok_or("--listing requires an output path")?;
+                listing = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "--env" => {
+                let v = args.get(i + 1).ok_or("--env requires NAME=value")?;
+                if !v.contains('=') {
+                    return Err(format!("invalid --env value '{v}' (expected NAME=value)"));
+                }
+                program_args.env.push(v.clone());
+                i += 2;
+            }
+            // Everything after `--` goes to the program
+            "--" => {
+                program_args.args = args[i + 1..].to_vec();
+                break;
+            }
+            "--fs" => {
+                let v = args.get(i + 1).ok_or("--fs requires a directory")?;
+                fs = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "--clock" => {
+                let v = args.get(i + 1).ok_or("--clock requires a frequency (e.g. 10mhz)")?;
+                clock.hz = parse_hz(v)?;
+                i += 2;
+            }
+            "--host-time" => {
+                clock.host = true;
+                i += 1;
+            }
+            "--syscalls" => {
+                let v = args.get(i + 1).ok_or("--syscalls requires linux, raven or rars")?;
+                abi = SyscallAbi::parse(v)?;
+                i += 2;
+            }
+            "--profile" => {
+                profile = true;
+                i += 1;
+            }
+            "-I" => {
+                let v = args.get(i + 1).ok_or("-I requires a directory")?;
+                include_dirs.push(PathBuf::from(v));
+                i += 2;
+            }
+            // --mem is parsed globally in main
+            "--mem" => i += 2,
+            a if a.starts_with("--") => return Err(format!("unknown option '{a}'")),
+            a => {
+                if path.is_some() {
+                    return Err(format!("unexpected argument '{a}'"));
+                }
+                path = Some(PathBuf::from(a));
+                i += 1;
+            }
+        }
+    }
+    let path = path.ok_or("missing program file")?;
+    Ok(RunOptions { path, mem_size, max_steps, lcov, strace, listing, include_dirs, profile, args: program_args, fs, clock, abi })
+}
+
+/// A program loaded into a fresh machine, ready to execute.
+pub(crate) struct Loaded {
+    pub cpu: Cpu,
+    pub mem: CacheController,
+    /// Start of the main text segment.
+    pub text_base: u32,
+    /// Executable ranges (every code segment of an ELF); leaving them halts the run.
+    pub code: Vec<Range<u32>>,
+    /// Assembled words (empty for ELF images).
+    pub text: Vec<u32>,
+    /// Source line → first address; only present for `.fas` sources.
+    pub line_addrs: Option<HashMap<usize, u32>>,
+    /// The same for each file pulled in with `.include`.
+    pub include_line_addrs: Vec<(PathBuf, HashMap<usize, u32>)>,
+    /// Label / symbol names by address (assembler labels or ELF symbols).
+    pub labels: HashMap<u32, Vec<String>>,
+    /// ELF symbols with their sizes (empty otherwise).
+    pub symbols: Vec<ElfSymbol>,
+    /// DWARF line table and functions of an ELF built with `-g`.
+    pub debug: Option<falcon::program::DebugInfo>,
+    pub data_base: u32,
+    /// Assembler lint warnings, formatted `path:line:col: warning: ...`.
+    pub warnings: Vec<String>,
+}
+
+impl Loaded {
+    pub fn in_code(&self, pc: u32) -> bool {
+        self.code.iter().any(|r| r.contains(&pc))
+    }
+
+    /// The function `pc` is in: the ELF symbol whose extent covers it, or else
+    /// the nearest label at or before it in the same code range.
+    pub fn function_name(&self, pc: u32) -> Option<&str> {
+        if let Some(sym) = function_at(&self.symbols, pc) {
+            return Some(&sym.name);
+        }
+        let range = self.code.iter().find(|r| r.contains(&pc))?;
+        let (_, names) = self.labels.iter().filter(|&(&a, _)| range.start <= a && a <= pc).max_by_key(|&(&a, _)| a)?;
+        names.first().map(String::as_str)
+    }
+}
+
+/// Assemble or load `path` the same way the TUI does. `include_dirs` are searched
+/// by `.include` after the source's own directory. ELF images always start with
+/// argc/argv/envp/auxv on the stack; other programs only when `args` has any.
+pub(crate) fn load_program(
+    path: &Path,
+    mem_size: Option<usize>,
+    include_dirs: &[PathBuf],
+    args: &ProgramArgs,
+) -> Result<Loaded, String> {
+    use falcon::program::{load_bytes, load_elf, load_words, zero_bytes};
+
+    let argv0 = path.to_string_lossy();
+
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let is_source = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("fas") || e.eq_ignore_ascii_case("s"));
+
+    if is_source {
+        let text = String::from_utf8_lossy(&bytes);
+        let paths = falcon::asm::IncludePaths { main: Some(path.to_path_buf()), dirs: include_dirs.to_vec(), ..Default::default() };
+        let (prog, diags) = falcon::asm::assemble_diagnostics_with(&text, 0, &paths);
+        let report: Vec<String> =
+            diags.iter().map(|d| format!("{}:{d}", d.file.as_deref().unwrap_or(path).display())).collect();
+        let Some(prog) = prog else {
+            return Err(format!("{} failed to assemble\n{}", path.display(), report.join("\n")));
+        };
+        let mem_size = mem_size.unwrap_or(128 * 1024);
+        let mut mem = CacheController::new(CacheConfig::default(), CacheConfig::default(), vec![], mem_size);
+        load_words(&mut mem.ram, 0, &prog.text).map_err(|e| e.to_string())?;
+        load_bytes(&mut mem.ram, prog.data_base, &prog.data).map_err(|e| e.to_string())?;
+        if prog.bss_size > 0 {
+            let bss_base = prog.data_base.saturating_add(prog.data.len() as u32);
+            zero_bytes(&mut mem.ram, bss_base, prog.bss_size).map_err(|e| e.to_string())?;
+        }
+        let mut cpu = fresh_cpu(mem_size);
+        let bss_end = prog.data_base + prog.data.len() as u32 + prog.bss_size;
+        cpu.heap_break = (bss_end.wrapping_add(15)) & !15;
+        if !args.is_empty() {
+            init_stack(&mut cpu, &mut mem.ram, &argv0, args).map_err(|e| e.to_string())?;
+        }
+        let text_end = (prog.text.len() as u32) * 4;
+        let mut include_line_addrs: Vec<_> = prog.includes.iter().map(|p| (p.clone(), HashMap::new())).collect();
+        for (&(file, line), &addr) in &prog.include_line_addrs {
+            include_line_addrs[file].1.insert(line, addr);
+        }
+        return Ok(Loaded {
+            cpu,
+            mem,
+            text_base: 0,
+            code: std::iter::once(0..text_end).collect(),
+            text: prog.text,
+            line_addrs: Some(prog.line_addrs),
+            include_line_addrs,
+            labels: prog.labels,
+            symbols: Vec::new(),
+            debug: None,
+            data_base: prog.data_base,
+            warnings: report,
+        });
+    }
+
+    let mem_size = mem_size.unwrap_or(16 * 1024 * 1024);
+    let mut mem = CacheController::new(CacheConfig::default(), CacheConfig::default(), vec![], mem_size);
+    let mut cpu = fresh_cpu(mem_size);
+
+    if bytes.len() >= 4 && &bytes[0..4] == b"\x7fELF" {
+        let info = load_elf(&bytes, &mut mem.ram).map_err(|e| e.to_string())?;
+        cpu.pc = info.entry;
+        cpu.heap_break = info.heap_start;
+        init_stack(&mut cpu, &mut mem.ram, &argv0, args).map_err(|e| e.to_string())?;
+        return Ok(Loaded {
+            cpu,
+            mem,
+            text_base: info.text_base,
+            code: info.code_ranges(),
+            text: Vec::new(),
+            line_addrs: None,
+            include_line_addrs: Vec::new(),
+            labels: info.symbols,
+            symbols: info.symbol_table,
+            debug: info.debug,
+            data_base: info.data_base,
+            warnings: Vec::new(),
+        });
+    }
+
+    // FALC container or flat binary, laid out like the assembler does (text at 0,
+    // data on the first page after it)
+    let falcon::program::Falc { text: text_bytes, data: data_bytes, bss_size } = falcon::program::parse_falc(&bytes)?;
+    let data_base = falcon::asm::data_base_after(0, text_bytes.len() as u32).ok_or("Binary too large")?;
+    load_bytes(&mut mem.ram, 0, text_bytes).map_err(|e| e.to_string())?;
+    load_bytes(&mut mem.ram, data_base, data_bytes).map_err(|e| e.to_string())?;
+    if bss_size > 0 {
+        zero_bytes(&mut mem.ram, data_base + data_bytes.len() as u32, bss_size).map_err(|e| e.to_string())?;
+    }
+    let bss_end = data_base + data_bytes.len() as u32 + bss_size;
+    cpu.heap_break = (bss_end.wrapping_add(15)) & !15;
+    if !args.is_empty() {
+        init_stack(&mut cpu, &mut mem.ram, &argv0, args).map_err(|e| e.to_string())?;
+    }
+    Ok(Loaded {
+        cpu,
+        mem,
+        text_base: 0,
+        code: std::iter::once(0..text_bytes.len() as u32).collect(),
+        text: Vec::new(),
+        line_addrs: None,
+        include_line_addrs: Vec::new(),
+        labels: HashMap::new(),
+        symbols: Vec::new(),
+        debug: None,
+        data_base,
+        warnings: Vec::new(),
+    })
+}
+
+/// Instructions executed per function, busiest first.
+/// A CPU for `mem_size` bytes of RAM: sp at the top, mmap below the stack.
+/// `run` adds the guest files, clock and syscall ABI.
+fn fresh_cpu(mem_size: usize) -> Cpu {
+    let mut cpu = Cpu::default();
+    cpu.write(2, mem_size as u32);
+    cpu.mmap = Mappings::below_stack(mem_size as u64);
+    cpu
+}
+
+fn profile(loaded: &Loaded, exec_counts: &HashMap<u32, u64>) -> String {
+    let mut per_fn: HashMap<String, u64> = HashMap::new();
+    for (&pc, &n) in exec_counts {
+        let name = loaded.function_name(pc).map_or_else(|| format!("0x{pc:08x}"), str::to_string);
+        *per_fn.entry(name).or_insert(0) += n;
+    }
+    let mut rows: Vec<(String, u64)> = per_fn.into_iter().filter(|&(_, n)| n > 0).collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let total: u64 = rows.iter().map(|(_, n)| n).sum();
+    let mut out = format!("profile: {total} instructions\n{:>12} {:>6}  function\n", "count", "%");
+    for (name, n) in rows {
+        out.push_str(&format!("{n:>12} {:>6.1}  {name}\n", n as f64 * 100.0 / total as f64));
+    }
+    out
+}
+
+/// Assemble the source at `path` again, as `load_program` did, and write its listing to `out`.
+fn write_listing(path: &Path, include_dirs: &[PathBuf], out: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let paths = falcon::asm::IncludePaths { main: Some(path.to_path_buf()), dirs: include_dirs.to_vec(), ..Default::default() };
+    let prog = falcon::asm::assemble_with(&text, 0, &paths).map_err(|e| format!("{}: {e}", path.display()))?;
+    let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+    let lst = falcon::asm::listing(&name, &text, &prog, 0, crate::ui::view::disasm::disasm_word);
+    std::fs::write(out, lst).map_err(|e| format!("writing {}: {e}", out.display()))
+}
+
+/// Forwards console lines as they are produced. The last line may still grow
+/// (`append_str`), so we remember how much of it was already written.
+#[derive(Default)]
+pub(crate) struct ConsoleSink {
+    line: usize,
+    col: usize,
+}
+
+impl ConsoleSink {
+    /// Hand everything printed since the last call to `emit` as
+    /// `(text, color, ends_line)`.
+    pub(crate) fn drain(&mut self, console: &Console, mut emit: impl FnMut(&str, ConsoleColor, bool)) {
+        while self.line < console.lines.len() {
+            let l = &console.lines[self.line];
+            let rest = l.text.get(self.col..).unwrap_or("");
+            let last = self.line + 1 == console.lines.len();
+            if !rest.is_empty() || !last {
+                emit(rest, l.color, !last);
+            }
+            if last {
+                self.col = l.text.len();
+                break;
+            }
+            self.line += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Don't echo `input` back: call right before `Console::push_input`.
+    pub(crate) fn skip_input(&mut self, console: &Console, input: &str) {
+        self.line = console.lines.len();
+        self.col = input.len();
+    }
+
+    fn flush(&mut self, console: &Console) {
+        let mut out = io::stdout().lock();
+        let mut err = io::stderr().lock();
+        self.drain(console, |text, color, ends_line| {
+            let w: &mut dyn Write = if color == ConsoleColor::Normal { &mut out } else { &mut err };
+            let _ = write!(w, "{text}");
+            if ends_line {
+                let _ = writeln!(w);
+            }
+        });
+        let _ = out.flush();
+        let _ = err.flush();
+    }
+
+    fn finish(&mut self, console: &Console) {
+        self.flush(console);
+        if console.lines.last().is_some_and(|l| !l.text.is_empty()) {
+            println!();
+        }
+    }
+}
+
+/// Run the program to completion. Returns the process exit code.
+pub fn run(opts: &RunOptions) -> i32 {
+    let mut loaded = match load_program(&opts.path, opts.mem_size, &opts.include_dirs, &opts.args) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 2;
+        }
+    };
+    for w in &loaded.warnings {
+        eprintln!("{w}");
+    }
+    if opts.lcov.is_some() && loaded.line_addrs.is_none() {
+        eprintln!("error: --lcov needs an assembly source (.fas) to map addresses back to lines");
+        return 2;
+    }
+    if opts.listing.is_some() && loaded.line_addrs.is_none() {
+        eprintln!("error: --listing needs an assembly source (.fas)");
+        return 2;
+    }
+    if let Some(out) = &opts.listing
+        && let Err(e) = write_listing(&opts.path, &opts.include_dirs, out)
+    {
+        eprintln!("error: {e}");
+        return 2;
+    }
+
+    // The guest sees the files under --fs; what it changes is written back at the end
+    let host_files = match &opts.fs {
+        Some(dir) => match falcon::vfs::Vfs::load_dir(dir) {
+            Ok(files) => Some(files),
+            Err(e) => {
+                eprintln!("error: {}: {e}", dir.display());
+                return 2;
+            }
+        },
+        None => None,
+    };
+    if let Some(files) = &host_files {
+        loaded.cpu.fs = files.clone();
+    }
+    loaded.cpu.clock = opts.clock;
+    loaded.cpu.abi = opts.abi;
+    loaded.cpu.semihost.cmdline = opts.args.command_line(&opts.path.to_string_lossy());
+
+    let mut console = Console::default();
+    let mut sink = ConsoleSink::default();
+    let mut exec_counts: HashMap<u32, u64> = HashMap::new();
+    let mut branch_counts: HashMap<u32, BranchCounts> = HashMap::new();
+    let stdin = io::stdin();
+    let mut steps = 0u64;
+
+    let faulted = loop {
+        let pc = loaded.cpu.pc;
+        if !loaded.in_code(pc) {
+            console.push_error(format!(
+                "Execution reached 0x{pc:08X}, outside the loaded program. \
+                 Add `li a7, 93; ecall` to terminate cleanly."
+            ));
+            break true;
+        }
+        if steps >= opts.max_steps {
+            console.push_error(format!("Step limit reached ({} instructions); use --max-steps to raise it", opts.max_steps));
+            break true;
+        }
+        let word = loaded.mem.peek32(pc).unwrap_or(0);
+        let alive = match falcon::exec::step(&mut loaded.cpu, &mut loaded.mem, &mut console) {
+            Ok(v) => v,
+            Err(e) => {
+                console.push_error(e.to_string());
+                break true;
+            }
+        };
+        steps += 1;
+        loaded.mem.snapshot_stats();
+        *exec_counts.entry(pc).or_insert(0) += 1;
+        if alive {
+            coverage::record_branch(&mut branch_counts, pc, word, loaded.cpu.pc);
+            continue;
+        }
+        if console.reading {
+            // The ecall is retried once input is queued (PC was rewound by `step`).
+            *exec_counts.entry(pc).or_insert(0) -= 1;
+            sink.flush(&console);
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    console.push_error("stdin closed while the program was waiting for input");
+                    break true;
+                }
+                Ok(_) => {
+                    let line = line.trim_end_matches(['\n', '\r']).to_string();
+                    // The terminal already shows what was typed.
+                    sink.skip_input(&console, &line);
+                    console.push_input(line);
+                    console.reading = false;
+                    continue;
+                }
+            }
+        }
+        break loaded.cpu.exit_code.is_none() && !loaded.cpu.ebreak_hit;
+    };
+    sink.finish(&console);
+
+    if let (Some(dir), Some(before)) = (&opts.fs, &host_files)
+        && let Err(e) = loaded.cpu.fs.sync_dir(dir, before)
+    {
+        eprintln!("error: writing back to {}: {e}", dir.display());
+        return 2;
+    }
+
+    if let Some(out) = &opts.strace
+        && let Err(e) = std::fs::write(out, loaded.cpu.trace.to_text())
+    {
+        eprintln!("error: writing {}: {e}", out.display());
+        return 2;
+    }
+
+    if let (Some(out), Some(line_addrs)) = (&opts.lcov, &loaded.line_addrs) {
+        // One record per source file: the program, then everything it includes.
+        let files = std::iter::once((opts.path.clone(), line_addrs)).chain(loaded.include_line_addrs.iter().map(|(p, m)| (p.clone(), m)));
+        let files: Vec<_> = files.collect();
+        let mut report = String::new();
+        for (i, (path, addrs)) in files.iter().enumerate() {
+            let others: Vec<u32> = files.iter().enumerate().filter(|&(j, _)| j != i).flat_map(|(_, (_, m))| m.values().copied()).collect();
+            let cov = Coverage::compute(addrs, &others, &loaded.text, loaded.text_base, &exec_counts, &branch_counts);
+            report.push_str(&cov.to_lcov(&path.to_string_lossy()));
+        }
+        if let Err(e) = std::fs::write(out, report) {
+            eprintln!("error: writing {}: {e}", out.display());
+            return 2;
+        }
+    }
+
+    if opts.profile {
+        eprint!("{}", profile(&loaded, &exec_counts));
+    }
+
+    if faulted {
+        1
+    } else {
+        loaded.cpu.exit_code.map_or(0, |c| c as i32)
+    }
+}
@@ -0,0 +1,558 @@
+// lsp.rs — Language Server Protocol server for `.fas` assembly (`raven lsp`)
+//
+// Brings the editor tab's help to other editors: diagnostics from the real
+// assembler, go-to-definition / find-references for labels and `.equ`
+// symbols, hover text from the Docs tab instruction reference, and completion
+// for mnemonics, directives, registers and the file's own symbols.
+//
+// Symbols are found with a lexical scan rather than from an assembled
+// `Program`, so navigation keeps working while the file has errors.
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::falcon::asm::utils::{parse_freg, parse_reg};
+use crate::falcon::asm::{assemble_diagnostics_with, IncludePaths, Program, Severity};
+use crate::json::{self, Json};
+use crate::ui::view::disasm::{freg_name, reg_name};
+use crate::ui::view::docs::{doc_rows, lookup_doc};
+
+// LSP enum values used below
+const SEVERITY_ERROR: u32 = 1;
+const SEVERITY_WARNING: u32 = 2;
+const KIND_FUNCTION: u32 = 3;
+const KIND_VARIABLE: u32 = 6;
+const KIND_KEYWORD: u32 = 14;
+const KIND_CONSTANT: u32 = 21;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SymKind {
+    Label,
+    Equate,
+}
+
+/// One identifier occurrence: 0-based line and `[start, end)` character columns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Span {
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Def {
+    name: String,
+    kind: SymKind,
+    at: Span,
+    /// Right-hand side of an equate.
+    expr: Option<String>,
+}
+
+#[derive(Default)]
+struct Symbols {
+    defs: Vec<Def>,
+    /// Uses of defined symbols (definitions excluded).
+    refs: Vec<(String, Span)>,
+}
+
+impl Symbols {
+    fn def(&self, name: &str) -> Option<&Def> {
+        self.defs.iter().find(|d| d.name == name)
+    }
+}
+
+/// The part of a line before a `#` or `;` comment, cut the same way the
+/// assembler's preprocessor does.
+fn code_part(line: &str) -> &str {
+    line.find(['#', ';']).map_or(line, |i| &line[..i])
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '$')
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+/// Identifier tokens of `code` as `(text, start_col, end_col)`, skipping literals.
+fn idents(code: &str) -> Vec<(&str, usize, usize)> {
+    let chars: Vec<(usize, char)> = code.char_indices().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if c == '"' || c == '\'' {
+            i += 1;
+            while i < chars.len() && chars[i].1 != c {
+                if chars[i].1 == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+        } else if c.is_ascii_digit() {
+            // Numbers, including 0x.. and local labels like `1f`
+            while i < chars.len() && is_ident_char(chars[i].1) {
+                i += 1;
+            }
+        } else if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i].1) {
+                i += 1;
+            }
+            let from = chars[start].0;
+            let to = chars.get(i).map_or(code.len(), |&(b, _)| b);
+            out.push((&code[from..to], start, i));
+        } else {
+            i += 1;
+        }
+    }
+    out
+}
+
+fn scan(text: &str) -> Symbols {
+    let mut syms = Symbols::default();
+    let mut uses = Vec::new();
+    for (line, raw) in text.lines().enumerate() {
+        let code = code_part(raw);
+        let toks = idents(code);
+        let mut rest = &toks[..];
+
+        // `name:` label definition (possibly followed by an instruction)
+        if let Some(&(name, start, end)) = rest.first() {
+            let after = code.char_indices().nth(end).map_or("", |(b, _)| &code[b..]);
+            if after.trim_start().starts_with(':') {
+                syms.defs.push(Def { name: name.into(), kind: SymKind::Label, at: Span { line, start, end }, expr: None });
+                rest = &rest[1..];
+            }
+        }
+
+        // `.equ name, expr` / `.set name, expr` / `name = expr`
+        let equ = match rest {
+            [(dir, ..), (name, start, end), ..] if matches!(*dir, ".equ" | ".set") => Some((*name, *start, *end)),
+            [(name, start, end), ..] if !name.starts_with('.') => {
+                let after = code.char_indices().nth(*end).map_or("", |(b, _)| &code[b..]);
+                after.trim_start().starts_with('=').then_some((*name, *start, *end))
+            }
+            _ => None,
+        };
+        if let Some((name, start, end)) = equ {
+            let after = code.char_indices().nth(end).map_or("", |(b, _)| &code[b..]);
+            let expr = after.trim_start().trim_start_matches([',', '=']).trim().to_string();
+            syms.defs.push(Def { name: name.into(), kind: SymKind::Equate, at: Span { line, start, end }, expr: Some(expr) });
+        }
+
+        for &(name, start, end) in rest {
+            if equ.is_some_and(|(_, s, _)| s == start) {
+                continue;
+            }
+            uses.push((name.to_string(), Span { line, start, end }));
+        }
+    }
+    syms.refs = uses.into_iter().filter(|(n, _)| syms.def(n).is_some()).collect();
+    syms
+}
+
+/// Identifier under `(line, col)`, with its span.
+fn word_at(text: &str, line: usize, col: usize) -> Option<(String, Span)> {
+    let raw = text.lines().nth(line)?;
+    idents(code_part(raw))
+        .into_iter()
+        .find(|&(_, s, e)| s <= col && col <= e)
+        .map(|(w, start, end)| (w.to_string(), Span { line, start, end }))
+}
+
+fn register_role(n: u8) -> &'static str {
+    match n {
+        0 => "hard-wired zero",
+        1 => "return address",
+        2 => "stack pointer",
+        3 => "global pointer",
+        4 => "thread pointer",
+        5..=7 | 28..=31 => "temporary (caller-saved)",
+        8 => "saved register / frame pointer (callee-saved)",
+        9 | 18..=27 => "saved register (callee-saved)",
+        10 | 11 => "function argument / return value",
+        _ => "function argument",
+    }
+}
+
+/// Hover for `word`; label addresses come from `prog`, the document as last assembled.
+fn hover_text(word: &str, syms: &Symbols, prog: Option<&Program>) -> Option<String> {
+    if let Some(d) = syms.def(word) {
+        return Some(match d.kind {
+            SymKind::Label => {
+                let addr = prog.and_then(|p| {
+                    p.labels.iter().find(|(_, names)| names.iter().any(|n| n == word)).map(|(&a, _)| a)
+                });
+                match addr {
+                    Some(a) => format!("label `{word}` — address `0x{a:08x}` (line {})", d.at.line + 1),
+                    None => format!("label `{word}` (line {})", d.at.line + 1),
+                }
+            }
+            SymKind::Equate => format!("`.equ {word}, {}` (line {})", d.expr.as_deref().unwrap_or(""), d.at.line + 1),
+        });
+    }
+    if let Some(row) = lookup_doc(word) {
+        let mut s = format!("**{}** `{}`\n\n{}", word.to_ascii_lowercase(), row.operands, row.desc);
+        if !row.expands.is_empty() {
+            s.push_str(&format!("\n\nExpands to: `{}`", row.expands));
+        }
+        return Some(s);
+    }
+    if let Some(n) = parse_reg(word) {
+        return Some(format!("register `{}` (x{n}) — {}", reg_name(n), register_role(n)));
+    }
+    if let Some(n) = parse_freg(word) {
+        return Some(format!("float register `{}` (f{n})", freg_name(n)));
+    }
+    None
+}
+
+fn completions(text: &str, line: usize, col: usize, syms: &Symbols) -> Vec<Json> {
+    let raw = text.lines().nth(line).unwrap_or("");
+    let before: String = raw.chars().take(col).collect();
+    // Drop a leading `label:` so `loop: ad|` still completes mnemonics.
+    let stmt = match before.split_once(':') {
+        Some((head, tail)) if idents(head).len() == 1 => tail,
+        _ => before.as_str(),
+    };
+    let item = |label: String, kind: u32, detail: String| {
+        Json::obj([("label", Json::from(label)), ("kind", Json::from(kind)), ("detail", Json::from(detail))])
+    };
+    let mut items = Vec::new();
+    if !stmt.trim_start().contains(char::is_whitespace) {
+        // First word: mnemonics and directives
+        for row in doc_rows() {
+            for m in row.mnemonic.split('/') {
+                items.push(item(m.to_string(), KIND_KEYWORD, format!("{} {}", row.operands, row.desc).trim().to_string()));
+            }
+        }
+    } else {
+        for n in 0..32u8 {
+            items.push(item(reg_name(n).to_string(), KIND_VARIABLE, format!("x{n} — {}", register_role(n))));
+        }
+        for n in 0..32u8 {
+            items.push(item(freg_name(n).to_string(), KIND_VARIABLE, format!("f{n}")));
+        }
+        for d in &syms.defs {
+            let (kind, detail) = match d.kind {
+                SymKind::Label => (KIND_FUNCTION, format!("label, line {}", d.at.line + 1)),
+                SymKind::Equate => (KIND_CONSTANT, format!("= {}", d.expr.as_deref().unwrap_or(""))),
+            };
+            items.push(item(d.name.clone(), kind, detail));
+        }
+    }
+    items
+}
+
+fn range(at: &Span) -> Json {
+    let pos = |c: usize| Json::obj([("line", Json::from(at.line)), ("character", Json::from(c))]);
+    Json::obj([("start", pos(at.start)), ("end", pos(at.end))])
+}
+
+fn location(uri: &str, at: &Span) -> Json {
+    Json::obj([("uri", Json::from(uri)), ("range", range(at))])
+}
+
+/// Local path of a `file://` URI, with `%XX` escapes decoded.
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::with_capacity(rest.len());
+    let mut it = rest.bytes();
+    while let Some(b) = it.next() {
+        let hex = |b: Option<u8>| (b? as char).to_digit(16);
+        match b {
+            b'%' => bytes.push((hex(it.next())? * 16 + hex(it.next())?) as u8),
+            b => bytes.push(b),
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+fn path_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for c in path.to_string_lossy().chars() {
+        match c {
+            ' ' => uri.push_str("%20"),
+            '%' => uri.push_str("%25"),
+            '#' => uri.push_str("%23"),
+            c => uri.push(c),
+        }
+    }
+    uri
+}
+
+/// Assemble a document, `.include`s resolved through `paths`: the program, when it
+/// assembles, and its problems, each with the included file it belongs to, or
+/// `None` for the document itself.
+fn diagnostics(text: &str, paths: &IncludePaths) -> (Option<Program>, Vec<(Option<PathBuf>, Json)>) {
+    let (prog, diags) = assemble_diagnostics_with(text, 0, paths);
+    let diags = diags
+        .into_iter()
+        .map(|d| {
+            let severity = match d.severity {
+                Severity::Error => SEVERITY_ERROR,
+                Severity::Warning => SEVERITY_WARNING,
+            };
+            let at = Span { line: d.line, start: d.cols.start, end: d.cols.end };
+            let mut diag = vec![
+                ("range", range(&at)),
+                ("severity", Json::from(severity)),
+                ("source", Json::from("raven")),
+                ("message", Json::from(d.msg)),
+            ];
+            if let Some(code) = d.code {
+                diag.push(("code", Json::from(code)));
+            }
+            (d.file, Json::obj(diag))
+        })
+        .collect();
+    (prog, diags)
+}
+
+struct Server {
+    out: io::Stdout,
+    docs: HashMap<String, String>,
+    /// Each document as last assembled for diagnostics, for label addresses in hovers.
+    programs: HashMap<String, Program>,
+    /// Included files each document last published problems for, to clear them later.
+    included: HashMap<String, Vec<String>>,
+}
+
+impl Server {
+    fn send(&mut self, msg: Json) {
+        let _ = json::write_message(&mut self.out.lock(), &msg);
+    }
+
+    fn reply(&mut self, id: &Json, result: Json) {
+        self.send(Json::obj([("jsonrpc", Json::from("2.0")), ("id", id.clone()), ("result", result)]));
+    }
+
+    fn reply_error(&mut self, id: &Json, code: i64, message: &str) {
+        let err = Json::obj([("code", Json::from(code)), ("message", Json::from(message))]);
+        self.send(Json::obj([("jsonrpc", Json::from("2.0")), ("id", id.clone()), ("error", err)]));
+    }
+
+    /// Where `.include`s in `uri` resolve: next to it, reading other open documents
+    /// instead of the disk.
+    fn include_paths(&self, uri: &str) -> IncludePaths {
+        let open = self
+            .docs
+            .iter()
+            .filter(|(u, _)| *u != uri)
+            .filter_map(|(u, text)| Some((uri_path(u)?.canonicalize().ok()?, text.clone())))
+            .collect();
+        IncludePaths { main: uri_path(uri), open, ..Default::default() }
+    }
+
+    fn publish(&mut self, uri: &str) {
+        let (prog, diags) = match self.docs.get(uri) {
+            Some(text) => diagnostics(text, &self.include_paths(uri)),
+            None => (None, Vec::new()),
+        };
+        match prog {
+            Some(prog) => self.programs.insert(uri.to_string(), prog),
+            None => self.programs.remove(uri),
+        };
+        // Problems in included files go to those files; ones that went away are cleared.
+        let mut by_uri: Vec<(String, Vec<Json>)> = self.included.remove(uri).unwrap_or_default().into_iter().map(|u| (u, Vec::new())).collect();
+        let mut own = Vec::new();
+        for (file, diag) in diags {
+            let Some(file) = file else {
+                own.push(diag);
+                continue;
+            };
+            let file = path_uri(&file);
+            match by_uri.iter_mut().find(|(u, _)| *u == file) {
+                Some((_, list)) => list.push(diag),
+                None => by_uri.push((file, vec![diag])),
+            }
+        }
+        let included: Vec<String> = by_uri.iter().filter(|(_, l)| !l.is_empty()).map(|(u, _)| u.clone()).collect();
+        if !included.is_empty() {
+            self.included.insert(uri.to_string(), included);
+        }
+        by_uri.insert(0, (uri.to_string(), own));
+        for (uri, diags) in by_uri {
+            let params = Json::obj([("uri", Json::from(uri)), ("diagnostics", Json::Arr(diags))]);
+            self.send(Json::obj([
+                ("jsonrpc", Json::from("2.0")),
+                ("method", Json::from("textDocument/publishDiagnostics")),
+                ("params", params),
+            ]));
+        }
+    }
+
+    /// Handle one message; returns false on `exit`.
+    fn handle(&mut self, msg: &Json) -> bool {
+        let id = msg.get("id");
+        let params = msg.get("params");
+        let uri = params.get("textDocument").get("uri").as_str().unwrap_or("").to_string();
+        let pos = params.get("position");
+        let (line, col) = (
+            pos.get("line").as_i64().unwrap_or(0).max(0) as usize,
+            pos.get("character").as_i64().unwrap_or(0).max(0) as usize,
+        );
+        match msg.get("method").as_str().unwrap_or("") {
+            "initialize" => {
+                let caps = Json::obj([
+                    ("textDocumentSync", Json::from(1u32)),
+                    ("hoverProvider", Json::from(true)),
+                    ("definitionProvider", Json::from(true)),
+                    ("referencesProvider", Json::from(true)),
+                    ("completionProvider", Json::obj([("triggerCharacters", Json::Arr(vec![Json::from(".")]))])),
+                ]);
+                let info = Json::obj([("name", Json::from("raven")), ("version", Json::from(env!("CARGO_PKG_VERSION")))]);
+                self.reply(id, Json::obj([("capabilities", caps), ("serverInfo", info)]));
+            }
+            "shutdown" => self.reply(id, Json::Null),
+            "exit" => return false,
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").get("text").as_str().unwrap_or("").to_string();
+                self.docs.insert(uri.clone(), text);
+                self.publish(&uri);
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole document.
+                if let Some(text) = params.get("contentChanges").as_array().last().and_then(|c| c.get("text").as_str()) {
+                    self.docs.insert(uri.clone(), text.to_string());
+                    self.publish(&uri);
+                }
+            }
+            "textDocument/didClose" => {
+                self.docs.remove(&uri);
+                self.programs.remove(&uri);
+            }
+            "textDocument/hover" => {
+                let text = self.docs.get(&uri).cloned().unwrap_or_default();
+                let result = word_at(&text, line, col).and_then(|(w, at)| {
+                    let value = hover_text(&w, &scan(&text), self.programs.get(&uri))?;
+                    Some(Json::obj([
+                        ("contents", Json::obj([("kind", Json::from("markdown")), ("value", Json::from(value))])),
+                        ("range", range(&at)),
+                    ]))
+                });
+                self.reply(id, result.unwrap_or(Json::Null));
+            }
+            "textDocument/definition" => {
+                let text = self.docs.get(&uri).cloned().unwrap_or_default();
+                let syms = scan(&text);
+                let result = word_at(&text, line, col).and_then(|(w, _)| syms.def(&w).map(|d| location(&uri, &d.at)));
+                self.reply(id, result.unwrap_or(Json::Null));
+            }
+            "textDocument/references" => {
+                let text = self.docs.get(&uri).cloned().unwrap_or_default();
+                let syms = scan(&text);
+                let include_decl = params.get("context").get("includeDeclaration").as_bool().unwrap_or(true);
+                let mut locs = Vec::new();
+                if let Some((w, _)) = word_at(&text, line, col)
+                    && let Some(d) = syms.def(&w)
+                {
+                    if include_decl {
+                        locs.push(location(&uri, &d.at));
+                    }
+                    locs.extend(syms.refs.iter().filter(|(n, _)| *n == w).map(|(_, at)| location(&uri, at)));
+                }
+                self.reply(id, Json::Arr(locs));
+            }
+            "textDocument/completion" => {
+                let text = self.docs.get(&uri).cloned().unwrap_or_default();
+                let items = completions(&text, line, col, &scan(&text));
+                self.reply(id, Json::Arr(items));
+            }
+            _ if *id != Json::Null => self.reply_error(id, -32601, "method not supported"),
+            _ => {} // unhandled notification
+        }
+        true
+    }
+}
+
+/// Serve LSP on stdin/stdout until `exit` or end of input.
+pub fn run() -> i32 {
+    let mut server = Server { out: io::stdout(), docs: HashMap::new(), programs: HashMap::new(), included: HashMap::new() };
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Ok(Some(msg)) = json::read_message(&mut input) {
+        if !server.handle(&msg) {
+            break;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = ".data\nmsg: .asciz \"hi\"  # greeting\n.equ LEN, 3\n.text\nmain: la a0, msg\n  li t0, LEN\nloop: addi t0, t0, -1\n  bnez t0, loop\n  j main\n";
+
+    #[test]
+    fn scan_finds_labels_equates_and_uses() {
+        let syms = scan(SRC);
+        let names: Vec<_> = syms.defs.iter().map(|d| (d.name.as_str(), d.kind, d.at.line)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("msg", SymKind::Label, 1),
+                ("LEN", SymKind::Equate, 2),
+                ("main", SymKind::Label, 4),
+                ("loop", SymKind::Label, 6),
+            ]
+        );
+        assert_eq!(syms.def("LEN").unwrap().expr.as_deref(), Some("3"));
+        let uses: Vec<_> = syms.refs.iter().map(|(n, at)| (n.as_str(), at.line, at.start)).collect();
+        assert_eq!(uses, vec![("msg", 4, 13), ("LEN", 5, 9), ("loop", 7, 11), ("main", 8, 4)]);
+    }
+
+    #[test]
+    fn hover_uses_docs_registers_and_symbols() {
+        let syms = scan(SRC);
+        let (prog, _) = diagnostics(SRC, &IncludePaths::default());
+        assert!(hover_text("addi", &syms, None).unwrap().contains("rd = rs1 + imm"));
+        assert!(hover_text("beqz", &syms, None).unwrap().contains("Branch if rs == 0"));
+        assert!(hover_text("a0", &syms, None).unwrap().contains("x10"));
+        assert!(hover_text("loop", &syms, prog.as_ref()).unwrap().contains("label `loop` — address"));
+    }
+
+    #[test]
+    fn hover_addresses_come_from_the_program_with_its_includes() {
+        let dir = std::env::temp_dir().join(format!("raven_lsp_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (main, lib) = (dir.join("main.fas"), dir.join("lib.fas"));
+        let text = ".text\nmain:\njal ra, f\n.include \"lib.fas\"\nend:\nnop\n";
+        std::fs::write(&main, text).unwrap();
+        std::fs::write(&lib, "f:\nnop\nret\n").unwrap();
+        let mut server = Server { out: io::stdout(), docs: HashMap::new(), programs: HashMap::new(), included: HashMap::new() };
+        let (main_uri, lib_uri) = (path_uri(&main), path_uri(&lib));
+        // The open lib.fas has an unsaved edit dropping the `nop`.
+        server.docs.insert(lib_uri, "f:\nret\n".into());
+        server.docs.insert(main_uri.clone(), text.into());
+        let (prog, diags) = diagnostics(text, &server.include_paths(&main_uri));
+        assert!(prog.is_some(), "{diags:?}");
+        let hover = hover_text("end", &scan(text), prog.as_ref()).unwrap();
+        assert_eq!(hover, "label `end` — address `0x00000008` (line 5)");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn completion_depends_on_position() {
+        let syms = scan(SRC);
+        let has = |items: &[Json], l: &str| items.iter().any(|i| i.get("label").as_str() == Some(l));
+        let first = completions("loop: ad", 0, 8, &syms);
+        assert!(has(&first, "addi") && has(&first, ".word") && !has(&first, "a0"));
+        let operand = completions("  bnez t0, lo", 0, 13, &syms);
+        assert!(has(&operand, "a0") && has(&operand, "loop") && has(&operand, "LEN"));
+    }
+
+    #[test]
+    fn diagnostics_point_at_failing_line() {
+        let src = ".text\nli t0, 1\nfoo t1\nadd t0, t9, t1\n";
+        let d: Vec<Json> = diagnostics(src, &IncludePaths::default()).1.into_iter().map(|(_, d)| d).collect();
+        assert_eq!(d.len(), 2);
+        assert_eq!(d[0].get("range").get("start").get("line").as_i64(), Some(2));
+        assert_eq!(d[1].get("range").get("start").get("line").as_i64(), Some(3));
+        assert!(diagnostics(SRC, &IncludePaths::default()).1.is_empty());
+    }
+}
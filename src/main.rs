@@ -1,4 +1,11 @@
+mod dap;
+mod disasm;
 mod falcon;
+mod gdb;
+mod headless;
+mod json;
+mod lsp;
+mod toolchain;
 mod ui;
 
 use ratatui::DefaultTerminal;
@@ -50,6 +57,12 @@ fn main() -> io::Result<()> {
     let quit_flag = setup_sigint();
 
     let mut ram_override: Option<usize> = None;
+    let mut gdb_port: Option<u16> = None;
+    let mut open_path: Option<std::path::PathBuf> = None;
+    let mut program_args = falcon::program::ProgramArgs::default();
+    let mut fs_dir: Option<std::path::PathBuf> = None;
+    let mut clock = falcon::clock::GuestClock::default();
+    let mut abi = falcon::syscall::SyscallAbi::default();
     let args: Vec<String> = std::env::args().collect();
     let mut i = 1;
     while i < args.len() {
@@ -61,11 +74,119 @@ fn main() -> io::Result<()> {
                 },
                 None => { eprintln!("error: --mem requires a value (e.g. --mem 16mb)"); return Ok(()); }
             }
+        } else if args[i] == "--gdb" {
+            match args.get(i + 1).map(|v| v.parse::<u16>()) {
+                Some(Ok(port)) => { gdb_port = Some(port); i += 2; }
+                _ => { eprintln!("error: --gdb requires a TCP port (e.g. --gdb 1234)"); return Ok(()); }
+            }
+        } else if args[i] == "--env" {
+            match args.get(i + 1) {
+                Some(val) if val.contains('=') => { program_args.env.push(val.clone()); i += 2; }
+                _ => { eprintln!("error: --env requires NAME=value"); return Ok(()); }
+            }
+        } else if args[i] == "--fs" {
+            match args.get(i + 1) {
+                Some(dir) => { fs_dir = Some(std::path::PathBuf::from(dir)); i += 2; }
+                None => { eprintln!("error: --fs requires a directory"); return Ok(()); }
+            }
+        } else if args[i] == "--clock" {
+            match args.get(i + 1).map(|v| falcon::clock::parse_hz(v)) {
+                Some(Ok(hz)) => { clock.hz = hz; i += 2; }
+                Some(Err(e)) => { eprintln!("error: {e}"); return Ok(()); }
+                None => { eprintln!("error: --clock requires a frequency (e.g. --clock 10mhz)"); return Ok(()); }
+            }
+        } else if args[i] == "--host-time" {
+            clock.host = true;
+            i += 1;
+        } else if args[i] == "--syscalls" {
+            match args.get(i + 1).map(|v| falcon::syscall::SyscallAbi::parse(v)) {
+                Some(Ok(v)) => { abi = v; i += 2; }
+                Some(Err(e)) => { eprintln!("error: {e}"); return Ok(()); }
+                None => { eprintln!("error: --syscalls requires linux, raven or rars"); return Ok(()); }
+            }
+        } else if args[i] == "--" {
+            // Everything after `--` is passed to the program
+            program_args.args = args[i + 1..].to_vec();
+            break;
         } else {
+            // A bare path is opened at startup (`raven run` parses its own arguments)
+            if !args[i].starts_with("--") {
+                open_path = Some(std::path::PathBuf::from(&args[i]));
+            }
             i += 1;
         }
     }
 
+    // `raven run <file>`: execute without the TUI
+    if args.get(1).map(String::as_str) == Some("run") {
+        let opts = match headless::parse_args(&args[2..], ram_override) {
+            Ok(o) => o,
+            Err(e) => {
+                eprintln!("error: {e}\n{}", headless::usage());
+                std::process::exit(2);
+            }
+        };
+        std::process::exit(headless::run(&opts));
+    }
+
+    // `raven as` / `raven ld`: relocatable objects and the linker
+    if args.get(1).map(String::as_str) == Some("as") {
+        if args.len() < 3 {
+            eprintln!("{}", toolchain::as_usage());
+            std::process::exit(2);
+        }
+        std::process::exit(toolchain::assemble(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some("ld") {
+        if args.len() < 3 {
+            eprintln!("{}", toolchain::ld_usage());
+            std::process::exit(2);
+        }
+        std::process::exit(toolchain::link_files(&args[2..]));
+    }
+
+    // `raven disasm`: disassemble a binary, optionally back to source
+    if args.get(1).map(String::as_str) == Some("disasm") {
+        if args.len() < 3 {
+            eprintln!("{}", disasm::usage());
+            std::process::exit(2);
+        }
+        std::process::exit(disasm::run(&args[2..]));
+    }
+
+    // `raven dap`: Debug Adapter Protocol server on stdin/stdout
+    if args.get(1).map(String::as_str) == Some("dap") {
+        std::process::exit(dap::run(ram_override));
+    }
+
+    // `raven lsp`: Language Server Protocol server on stdin/stdout
+    if args.get(1).map(String::as_str) == Some("lsp") {
+        std::process::exit(lsp::run());
+    }
+
+    let mut app = ui::App::new(ram_override);
+    app.set_program_args(program_args);
+    app.set_guest_clock(clock);
+    app.set_syscall_abi(abi);
+    if let Some(dir) = &fs_dir {
+        match falcon::vfs::Vfs::load_dir(dir) {
+            Ok(files) => app.set_guest_files(files),
+            Err(e) => { eprintln!("error: {}: {e}", dir.display()); return Ok(()); }
+        }
+    }
+    if let Some(path) = &open_path
+        && let Err(e) = app.open_file(path)
+    {
+        eprintln!("error: {}: {e}", path.display());
+        return Ok(());
+    }
+    if let Some(port) = gdb_port {
+        match gdb::GdbServer::bind(port) {
+            Ok(server) => app.attach_gdb(server),
+            Err(e) => { eprintln!("error: cannot listen on port {port}: {e}"); return Ok(()); }
+        }
+    }
+
     // Send xterm-compatible maximize hint before entering raw/alternate mode.
     // Works in most modern terminal emulators (alacritty, kitty, xterm, Windows Terminal).
     // Silently ignored by terminals that don't support it.
@@ -75,9 +196,9 @@ fn main() -> io::Result<()> {
     let mut terminal: DefaultTerminal = ratatui::init();
 
     #[cfg(unix)]
-    let res = ui::run(&mut terminal, ui::App::new(ram_override), quit_flag);
+    let res = ui::run(&mut terminal, app, quit_flag);
     #[cfg(not(unix))]
-    let res = ui::run(&mut terminal, ui::App::new(ram_override));
+    let res = ui::run(&mut terminal, app);
 
     ratatui::restore();
 
@@ -0,0 +1,136 @@
+// toolchain.rs — separate compilation from the command line
+//
+// `raven as` assembles one module to a relocatable ELF object and `raven ld` links
+// objects (or sources, assembled on the fly) into an executable ELF that
+// `raven run` and the TUI load like any other.
+use std::path::{Path, PathBuf};
+
+use crate::falcon::asm::{assemble_object, IncludePaths};
+use crate::falcon::link::{link, Layout, Object};
+use crate::falcon::program::write_elf;
+
+pub fn as_usage() -> &'static str {
+    "usage: raven as <file.fas> [-o <file.o>] [-I <dir>]"
+}
+
+pub fn ld_usage() -> &'static str {
+    "usage: raven ld <file.o|file.fas>... [-T <layout>] [-o <out.elf>] [-I <dir>]"
+}
+
+struct Args {
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    layout: Option<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+}
+
+fn parse_args(args: &[String], allow_layout: bool) -> Result<Args, String> {
+    let mut parsed = Args { inputs: Vec::new(), output: None, layout: None, include_dirs: Vec::new() };
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-o" => {
+                let v = args.get(i + 1).ok_or("-o requires an output path")?;
+                parsed.output = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "-T" if allow_layout => {
+                let v = args.get(i + 1).ok_or("-T requires a layout file")?;
+                parsed.layout = Some(PathBuf::from(v));
+                i += 2;
+            }
+            "-I" => {
+                let v = args.get(i + 1).ok_or("-I requires a directory")?;
+                parsed.include_dirs.push(PathBuf::from(v));
+                i += 2;
+            }
+            // --mem is parsed globally in main
+            "--mem" => i += 2,
+            a if a.starts_with('-') => return Err(format!("unknown option '{a}'")),
+            a => {
+                parsed.inputs.push(PathBuf::from(a));
+                i += 1;
+            }
+        }
+    }
+    if parsed.inputs.is_empty() {
+        return Err("no input files".into());
+    }
+    Ok(parsed)
+}
+
+/// Assemble the source at `path` to an object, formatting errors `path: line N: msg`.
+fn assemble_file(path: &Path, include_dirs: &[PathBuf]) -> Result<Object, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let paths = IncludePaths { main: Some(path.to_path_buf()), dirs: include_dirs.to_vec(), ..Default::default() };
+    assemble_object(&text, &paths).map_err(|errors| {
+        let lines: Vec<String> = errors
+            .iter()
+            .map(|e| match e.file {
+                Some(_) => e.to_string(),
+                None => format!("{}: {e}", path.display()),
+            })
+            .collect();
+        lines.join("\n")
+    })
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// `raven as`: returns the process exit code.
+pub fn assemble(args: &[String]) -> i32 {
+    let run = || -> Result<(), String> {
+        let args = parse_args(args, false)?;
+        let [input] = args.inputs.as_slice() else {
+            return Err("raven as takes exactly one source file".into());
+        };
+        let obj = assemble_file(input, &args.include_dirs)?;
+        let output = args.output.unwrap_or_else(|| input.with_extension("o"));
+        write(&output, &obj.to_elf())
+    };
+    match run() {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+/// `raven ld`: returns the process exit code.
+pub fn link_files(args: &[String]) -> i32 {
+    let run = || -> Result<(), String> {
+        let args = parse_args(args, true)?;
+        let layout = match &args.layout {
+            Some(path) => {
+                let script = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+                Layout::parse(&script).map_err(|e| format!("{}: {e}", path.display()))?
+            }
+            None => Layout::default(),
+        };
+        let mut objects = Vec::with_capacity(args.inputs.len());
+        for input in &args.inputs {
+            let is_source =
+                input.extension().is_some_and(|e| e.eq_ignore_ascii_case("fas") || e.eq_ignore_ascii_case("s"));
+            let obj = if is_source {
+                assemble_file(input, &args.include_dirs)?
+            } else {
+                let bytes = std::fs::read(input).map_err(|e| format!("{}: {e}", input.display()))?;
+                Object::from_elf(&bytes).map_err(|e| format!("{}: {e}", input.display()))?
+            };
+            objects.push((input.display().to_string(), obj));
+        }
+        let (prog, entry) = link(&objects, &layout).map_err(|errors| errors.join("\n"))?;
+        let output = args.output.unwrap_or_else(|| PathBuf::from("a.out"));
+        write(&output, &write_elf(&prog, layout.text_base, entry))
+    };
+    match run() {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
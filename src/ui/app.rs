@@ -5,6 +5,15 @@ use super::{
     view::ui,
 };
 
+/// Status-line note for lint warnings left after a successful assembly.
+fn warnings_suffix(n: usize) -> String {
+    match n {
+        0 => String::new(),
+        1 => "  1 warning (F8 = next)".into(),
+        n => format!("  {n} warnings (F8 = next)"),
+    }
+}
+
 /// Extract the identifier-like word at the given character column in a line.
 fn word_at(line: &str, col: usize) -> String {
     let chars: Vec<char> = line.chars().collect();
@@ -16,6 +25,12 @@ fn word_at(line: &str, col: usize) -> String {
     chars[start..end].iter().collect()
 }
 use crate::falcon::{self, Cpu, CacheController};
+use crate::falcon::asm::{IncludePaths, Program};
+use crate::falcon::program::ProgramArgs;
+use crate::falcon::clock::GuestClock;
+use crate::falcon::syscall::SyscallAbi;
+use crate::falcon::mmap::Mappings;
+use crate::falcon::vfs::Vfs;
 use crate::falcon::cache::CacheConfig;
 use crossterm::{
     event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event},
@@ -343,6 +358,7 @@ pub(super) enum MemRegion {
     Stack,
     Access,   // auto-follows last memory read/write
     Heap,     // auto-follows cpu.heap_break (sbrk pointer)
+    Mmap(usize), // n-th region in cpu.mmap
     Custom,
 }
 
@@ -406,8 +422,25 @@ pub(super) enum RunButton {
 
 // ── State per tab ──────────────────────────────────────────────────────────────
 
+/// A buffer set aside when F12 follows an `.include`; `Alt+←` brings it back.
+pub(super) struct SavedBuffer {
+    pub(super) path: Option<std::path::PathBuf>,
+    pub(super) lines: Vec<String>,
+    pub(super) cursor: (usize, usize),
+}
+
 pub(super) struct EditorState {
     pub(super) buf: Editor,
+    /// File the buffer was opened from or saved to; `.include` resolves next to it.
+    pub(super) file_path: Option<std::path::PathBuf>,
+    /// Buffers left by following includes, oldest first. While any are set, the shown
+    /// buffer is an included file and `include_back[0]` is the program that gets assembled.
+    pub(super) include_back: Vec<SavedBuffer>,
+    /// Unsaved text of included files, by path, kept while another buffer is shown.
+    /// Assembly reads them instead of the disk and F12 reopens them.
+    pub(super) include_edits: std::collections::HashMap<std::path::PathBuf, Vec<String>>,
+    /// Where every label is defined (`None` file = main program), for F12 across files.
+    pub(super) label_files: std::collections::HashMap<String, (Option<std::path::PathBuf>, usize)>,
     pub(super) dirty: bool,
     pub(super) last_edit_at: Option<Instant>,
     pub(super) auto_check_delay: Duration,
@@ -429,11 +462,17 @@ pub(super) struct EditorState {
     pub(super) diag_line: Option<usize>,
     pub(super) diag_msg: Option<String>,
     pub(super) diag_line_text: Option<String>,
+    /// Every problem from the last assembly, sorted by line.
+    pub(super) diagnostics: Vec<falcon::asm::Diagnostic>,
 
     // Source-level metadata from last successful assembly
     pub(super) label_to_line: std::collections::HashMap<String, usize>,
     pub(super) line_to_addr: std::collections::HashMap<usize, u32>,
+    /// First addresses of lines in the program's other files, where coverage ranges end.
+    pub(super) other_file_addrs: Vec<u32>,
     pub(super) show_addr_hints: bool,
+    /// Coverage markers (hit / partial branch / never run) in the gutter separator.
+    pub(super) show_coverage: bool,
 
     /// Popup shown when user tries to edit while an ELF binary is loaded.
     pub(super) elf_prompt_open: bool,
@@ -450,12 +489,49 @@ pub(super) struct EditorState {
     pub(super) goto_query: String,
 }
 
+impl EditorState {
+    /// The included file shown in the editor, or `None` while the main program is shown.
+    pub(super) fn shown_file(&self) -> Option<&std::path::Path> {
+        if self.include_back.is_empty() { None } else { self.file_path.as_deref() }
+    }
+}
+
+/// Short name of a source file for status messages and the Problems panel.
+pub(super) fn file_name(path: &std::path::Path) -> String {
+    path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned())
+}
+
+/// Instruction-list separators for an ELF with DWARF line info: `main.c:12  x = f(y);`
+/// at the first instruction of each source line (the text when the file is readable).
+fn source_line_comments(debug: &falcon::program::DebugInfo) -> std::collections::HashMap<u32, String> {
+    let sources: Vec<Option<Vec<String>>> = debug
+        .files
+        .iter()
+        .map(|f| std::fs::read_to_string(f).ok().map(|t| t.lines().map(|l| l.trim().to_string()).collect()))
+        .collect();
+    debug
+        .stmts()
+        .map(|(addr, loc)| {
+            let place = format!("{}:{}", file_name(&debug.files[loc.file]), loc.line);
+            let text = sources[loc.file].as_ref().and_then(|lines| lines.get((loc.line as usize).checked_sub(1)?));
+            (addr, match text {
+                Some(text) if !text.is_empty() => format!("{place}  {text}"),
+                _ => place,
+            })
+        })
+        .collect()
+}
+
 pub(super) struct RunState {
     pub(super) cpu: Cpu,
     pub(super) prev_x: [u32; 32],
     pub(super) prev_pc: u32,
     pub(super) mem: CacheController,
     pub(super) breakpoints: std::collections::HashSet<u32>,
+    // Watchpoints set by an attached debugger: (kind, addr, len)
+    pub(super) watchpoints: Vec<(crate::gdb::WatchKind, u32, u32)>,
+    // Watchpoint that stopped the last step, if any: (kind, accessed addr)
+    pub(super) watch_hit: Option<(crate::gdb::WatchKind, u32)>,
     pub(super) mem_size: usize,
     pub(super) base_pc: u32,
     pub(super) data_base: u32,
@@ -494,6 +570,22 @@ pub(super) struct RunState {
     pub(super) imem_collapsed: bool,
     pub(super) imem_search_open: bool,
     pub(super) imem_search_query: String,
+    // Program arguments/environment (`A` prompt) and argv[0] of the loaded program
+    pub(super) program_args: ProgramArgs,
+    pub(super) program_name: String,
+    pub(super) args_prompt: Option<String>,
+    // Guest files every run starts with (imported / `--fs`); the live ones are `cpu.fs`
+    pub(super) guest_files: Vfs,
+    pub(super) files_open: bool,
+    pub(super) files_selected: usize,
+    // Syscall trace panel (`T`): filter text and rows scrolled up from the newest call
+    pub(super) syscalls_open: bool,
+    pub(super) syscalls_filter: String,
+    pub(super) syscalls_scroll: usize,
+    // Clock settings every run starts with (`--clock`, `--host-time`); the live clock is `cpu.clock`
+    pub(super) guest_clock: GuestClock,
+    // Syscall numbering every run starts with (`--syscalls`, `B`); the live one is `cpu.abi`
+    pub(super) guest_abi: SyscallAbi,
 
     // Details panel (collapsible)
     pub(super) details_collapsed: bool,
@@ -522,9 +614,18 @@ pub(super) struct RunState {
 
     // ELF sections for the sections viewer (empty when loaded from ASM)
     pub(super) elf_sections: Vec<falcon::program::ElfSection>,
+    // Every executable segment of an ELF, in address order (empty when loaded from ASM)
+    pub(super) code_ranges: Vec<std::ops::Range<u32>>,
+    // ELF symbols with their sizes and bindings (empty when loaded from ASM)
+    pub(super) elf_symbols: Vec<falcon::program::ElfSymbol>,
+    // DWARF line table of an ELF built with -g (None otherwise)
+    pub(super) debug: Option<falcon::program::DebugInfo>,
+    // PC a source-line step (`S`) started from; running until another line begins
+    pub(super) line_step: Option<u32>,
 
     // Execution statistics
     pub(super) exec_counts: std::collections::HashMap<u32, u64>,
+    pub(super) branch_counts: std::collections::HashMap<u32, falcon::coverage::BranchCounts>,
     pub(super) exec_trace: std::collections::VecDeque<(u32, String)>,
 
     // Register highlight age: 0 = just changed, 255 = unchanged for long
@@ -702,6 +803,9 @@ pub struct App {
 
     // TUI path input bar (fallback when OS file dialog returns None)
     pub(super) path_input: PathInput,
+
+    // GDB remote stub from the --gdb CLI flag; polled every tick.
+    pub(super) gdb: Option<crate::gdb::GdbServer>,
 }
 
 pub(super) fn compute_find_matches(query: &str, lines: &[String]) -> Vec<(usize, usize)> {
@@ -755,9 +859,16 @@ impl App {
                 diag_line: None,
                 diag_msg: None,
                 diag_line_text: None,
+                diagnostics: Vec::new(),
+                file_path: None,
+                include_back: Vec::new(),
+                include_edits: std::collections::HashMap::new(),
+                label_files: std::collections::HashMap::new(),
                 label_to_line: std::collections::HashMap::new(),
                 line_to_addr: std::collections::HashMap::new(),
+                other_file_addrs: Vec::new(),
                 show_addr_hints: false,
+                show_coverage: false,
                 elf_prompt_open: false,
                 find_open: false,
                 find_query: String::new(),
@@ -776,6 +887,8 @@ impl App {
                 mem_size,
                 mem: CacheController::new(CacheConfig::default(), CacheConfig::default(), vec![], mem_size),
                 breakpoints: std::collections::HashSet::new(),
+                watchpoints: Vec::new(),
+                watch_hit: None,
                 base_pc,
                 data_base,
                 mem_view_addr: data_base,
@@ -803,6 +916,17 @@ impl App {
                 imem_collapsed: false,
                 imem_search_open: false,
                 imem_search_query: String::new(),
+                program_args: ProgramArgs::default(),
+                program_name: String::from("main.fas"),
+                args_prompt: None,
+                guest_files: Vfs::default(),
+                files_open: false,
+                files_selected: 0,
+                syscalls_open: false,
+                syscalls_filter: String::new(),
+                syscalls_scroll: 0,
+                guest_clock: GuestClock::default(),
+                guest_abi: SyscallAbi::default(),
                 details_collapsed: false,
                 console_height: 5,
                 hover_console_bar: false,
@@ -819,7 +943,12 @@ impl App {
                 comments: std::collections::HashMap::new(),
                 labels: std::collections::HashMap::new(),
                 elf_sections: Vec::new(),
+                code_ranges: Vec::new(),
+                elf_symbols: Vec::new(),
+                debug: None,
+                line_step: None,
                 exec_counts: std::collections::HashMap::new(),
+                branch_counts: std::collections::HashMap::new(),
                 exec_trace: std::collections::VecDeque::new(),
                 reg_age: [255u8; 32],
                 show_trace: false,
@@ -917,21 +1046,77 @@ impl App {
             ram_override,
             splash_start: Some(Instant::now()),
             path_input: PathInput::new(),
+            gdb: None,
         };
         app.assemble_and_load();
         app
     }
 
+    /// Arguments and environment from the command line (`--env`, `--`), used by
+    /// every load and restart until changed with `A`.
+    pub fn set_program_args(&mut self, args: crate::falcon::program::ProgramArgs) {
+        self.run.program_args = args;
+    }
+
+    /// Files from `--fs DIR`: every run starts with them.
+    pub fn set_guest_files(&mut self, files: crate::falcon::vfs::Vfs) {
+        self.run.cpu.fs = files.clone();
+        self.run.guest_files = files;
+    }
+
+    /// Clock frequency and source from `--clock` / `--host-time`, for every run.
+    pub fn set_guest_clock(&mut self, clock: crate::falcon::clock::GuestClock) {
+        self.run.cpu.clock = clock;
+        self.run.guest_clock = clock;
+    }
+
+    /// Syscall numbering from `--syscalls`, for every run.
+    pub fn set_syscall_abi(&mut self, abi: crate::falcon::syscall::SyscallAbi) {
+        self.run.cpu.abi = abi;
+        self.run.guest_abi = abi;
+    }
+
+    /// Load a program given on the command line: `.fas`/`.s` go through the
+    /// editor, anything else is loaded as a binary (ELF, FALC or flat).
+    pub fn open_file(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+        let is_source = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("fas") || e.eq_ignore_ascii_case("s"));
+        if is_source {
+            // Through `open_source`, so `.include` resolves next to the file
+            let content = std::fs::read_to_string(path)?;
+            self.open_source(path.to_path_buf(), &content);
+        } else {
+            let bytes = std::fs::read(path)?;
+            self.load_binary(&bytes, &path.file_name().unwrap_or_default().to_string_lossy());
+        }
+        // Don't let the startup auto-check reassemble over the loaded program.
+        self.editor.dirty = false;
+        self.tab = Tab::Run;
+        Ok(())
+    }
+
+    /// A CPU ready for a new run of `run.mem_size` bytes: the guest files,
+    /// clock and syscall ABI set for every run, sp at the top of RAM and pc
+    /// at `run.base_pc`.
+    fn fresh_cpu(&self) -> Cpu {
+        let mut cpu = Cpu {
+            fs: self.run.guest_files.clone(),
+            clock: self.run.guest_clock,
+            abi: self.run.guest_abi,
+            pc: self.run.base_pc,
+            ..Cpu::default()
+        };
+        cpu.write(2, self.run.mem_size as u32);
+        cpu.mmap = Mappings::below_stack(self.run.mem_size as u64);
+        cpu
+    }
+
     pub(super) fn assemble_and_load(&mut self) {
-        use falcon::asm::assemble;
         use falcon::program::{load_bytes, load_words, zero_bytes};
 
         self.run.prev_x = self.run.cpu.x;
         self.run.mem_size = self.ram_override.unwrap_or(128 * 1024);
-        self.run.cpu = Cpu::default();
-        self.run.cpu.pc = self.run.base_pc;
+        self.run.cpu = self.fresh_cpu();
         self.run.prev_pc = self.run.cpu.pc;
-        self.run.cpu.write(2, self.run.mem_size as u32);
         self.run.mem = CacheController::new(
             self.cache.pending_icache.clone(),
             self.cache.pending_dcache.clone(),
@@ -940,8 +1125,10 @@ impl App {
         );
         self.run.faulted = false;
 
-        match assemble(&self.editor.buf.text(), self.run.base_pc) {
-            Ok(prog) => {
+        let (prog, diags) = self.check_main();
+        match prog {
+            Some(mut prog) => {
+                self.set_source_tables(&mut prog);
                 // Write directly to RAM (bypass cache) so invalidate() won't discard data
                 if let Err(e) = load_words(&mut self.run.mem.ram, self.run.base_pc, &prog.text) {
                     self.console.push_error(e.to_string());
@@ -954,6 +1141,7 @@ impl App {
                     return;
                 }
                 let bss_base = prog.data_base.saturating_add(prog.data.len() as u32);
+                self.run.cpu.heap_break = (bss_base.wrapping_add(prog.bss_size).wrapping_add(15)) & !15;
                 if prog.bss_size > 0 {
                     if let Err(e) = zero_bytes(&mut self.run.mem.ram, bss_base, prog.bss_size) {
                         self.console.push_error(e.to_string());
@@ -961,6 +1149,10 @@ impl App {
                         return;
                     }
                 }
+                self.run.program_name = self.source_program_name();
+                if !self.init_program_stack(false) {
+                    return;
+                }
                 self.run.data_base = prog.data_base;
                 self.run.mem_view_addr = prog.data_base;
                 self.run.mem_region = MemRegion::Data;
@@ -971,12 +1163,14 @@ impl App {
                 self.run.comments = prog.comments;
                 self.run.block_comments = prog.block_comments;
                 self.run.labels = prog.labels;
+                self.run.code_ranges.clear();
+                self.run.elf_symbols.clear();
+                self.run.debug = None;
                 self.run.exec_counts.clear();
+                self.run.branch_counts.clear();
                 self.run.exec_trace.clear();
                 self.run.reg_age = [255u8; 32];
                 self.run.reg_last_write_pc = [None; 32];
-                self.editor.label_to_line = prog.label_to_line;
-                self.editor.line_to_addr = prog.line_addrs;
                 self.editor.last_ok_text = Some(prog.text.clone());
                 self.editor.last_ok_data = Some(prog.data.clone());
                 self.editor.last_ok_data_base = Some(prog.data_base);
@@ -985,32 +1179,35 @@ impl App {
                 self.run.hover_imem_addr = None;
 
                 self.editor.last_assemble_msg = Some(format!(
-                    "Assembled {} instructions, {} data bytes, {} bss bytes.",
+                    "Assembled {} instructions, {} data bytes, {} bss bytes.{}",
                     prog.text.len(),
                     prog.data.len(),
-                    prog.bss_size
+                    prog.bss_size,
+                    warnings_suffix(diags.len())
                 ));
                 self.editor.last_compile_ok = Some(true);
                 self.editor.last_ok_elf_bytes = None;
                 self.editor.diag_line = None;
                 self.editor.diag_msg = None;
                 self.editor.diag_line_text = None;
+                // Only lint warnings remain when the program assembled.
+                self.editor.diagnostics = diags;
             }
-            Err(e) => {
-                self.editor.diag_line = Some(e.line);
-                self.editor.diag_msg = Some(e.msg.clone());
-                self.editor.diag_line_text = self.editor.buf.lines.get(e.line).cloned();
-                self.editor.last_compile_ok = Some(false);
+            None => {
+                self.set_diagnostics(diags);
+                let line = self.editor.diag_line.map_or(0, |l| l + 1);
+                let err = self.editor.diag_msg.as_deref().unwrap_or("");
                 self.editor.last_assemble_msg =
-                    Some(format!("Assemble error at line {}: {}", e.line + 1, e.msg));
+                    Some(format!("Assemble error at line {line}: {err}{}", self.more_problems_suffix()));
             }
         }
     }
 
     fn check_assemble(&mut self) {
-        use falcon::asm::assemble;
-        match assemble(&self.editor.buf.text(), self.run.base_pc) {
-            Ok(prog) => {
+        let (prog, diags) = self.check_main();
+        match prog {
+            Some(mut prog) => {
+                self.set_source_tables(&mut prog);
                 self.editor.last_ok_text = Some(prog.text.clone());
                 self.editor.last_ok_data = Some(prog.data.clone());
                 self.editor.last_ok_data_base = Some(prog.data_base);
@@ -1018,39 +1215,233 @@ impl App {
                 self.editor.last_ok_comments = prog.comments;
                 self.editor.last_ok_block_comments = prog.block_comments;
                 self.editor.last_ok_labels = prog.labels.clone();
-                self.editor.label_to_line = prog.label_to_line;
-                self.editor.line_to_addr = prog.line_addrs;
                 self.editor.last_assemble_msg = Some(format!(
-                    "OK: {} instructions, {} data bytes, {} bss bytes",
+                    "OK: {} instructions, {} data bytes, {} bss bytes{}",
                     prog.text.len(),
                     prog.data.len(),
-                    prog.bss_size
+                    prog.bss_size,
+                    warnings_suffix(diags.len())
                 ));
                 self.editor.last_compile_ok = Some(true);
                 self.editor.last_ok_elf_bytes = None;
                 self.editor.diag_line = None;
                 self.editor.diag_msg = None;
                 self.editor.diag_line_text = None;
+                // Only lint warnings remain when the program assembled.
+                self.editor.diagnostics = diags;
             }
-            Err(e) => {
-                self.editor.diag_line = Some(e.line);
-                self.editor.diag_msg = Some(e.msg.clone());
-                self.editor.diag_line_text = self.editor.buf.lines.get(e.line).cloned();
-                self.editor.last_compile_ok = Some(false);
-                let line = e.line + 1;
+            None => {
+                self.set_diagnostics(diags);
+                let line = self.editor.diag_line.map_or(0, |l| l + 1);
                 let text = self.editor.diag_line_text.as_deref().unwrap_or("");
                 let err = self.editor.diag_msg.as_deref().unwrap_or("");
                 self.editor.last_assemble_msg =
-                    Some(format!("Error line {}: {} ({})", line, text, err));
+                    Some(format!("Error line {}: {} ({}){}", line, text, err, self.more_problems_suffix()));
             }
         }
         self.editor.dirty = false;
     }
 
+    /// The main program's text and where its includes resolve: the buffer, or the
+    /// buffer F12 left when an included file is shown. Included files with unsaved
+    /// edits, the shown one included, are read from the editor.
+    fn main_source(&self) -> (String, IncludePaths) {
+        let (text, main) = match self.editor.include_back.first() {
+            Some(root) => (root.lines.join("\n"), root.path.clone()),
+            None => (self.editor.buf.text(), self.editor.file_path.clone()),
+        };
+        let mut open: std::collections::HashMap<_, _> =
+            self.editor.include_edits.iter().map(|(path, lines)| (path.clone(), lines.join("\n"))).collect();
+        if let Some(shown) = self.editor.shown_file() {
+            open.insert(shown.to_path_buf(), self.editor.buf.text());
+        }
+        (text, IncludePaths { main, open, ..Default::default() })
+    }
+
+    pub(super) fn assemble_main(&self) -> Result<Program, falcon::asm::AsmError> {
+        self.assemble_main_at(self.run.base_pc)
+    }
+
+    pub(super) fn assemble_main_at(&self, base_pc: u32) -> Result<Program, falcon::asm::AsmError> {
+        let (text, paths) = self.main_source();
+        falcon::asm::assemble_with(&text, base_pc, &paths)
+    }
+
+    /// `.lst` listing of the program in the editor, assembled as it runs.
+    pub(super) fn main_listing(&self) -> Result<String, falcon::asm::AsmError> {
+        let (text, paths) = self.main_source();
+        let prog = falcon::asm::assemble_with(&text, self.run.base_pc, &paths)?;
+        let name = paths.main.as_deref().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned());
+        let name = name.unwrap_or_else(|| "untitled.fas".into());
+        Ok(falcon::asm::listing(&name, &text, &prog, self.run.base_pc, crate::ui::view::disasm::disasm_word))
+    }
+
+    fn check_main(&self) -> (Option<Program>, Vec<falcon::asm::Diagnostic>) {
+        let (text, paths) = self.main_source();
+        falcon::asm::assemble_diagnostics_with(&text, self.run.base_pc, &paths)
+    }
+
+    /// Fill the label and line tables for the file shown in the editor, and `label_files`.
+    fn set_source_tables(&mut self, prog: &mut Program) {
+        let main = prog.label_to_line.iter().map(|(l, &n)| (l.clone(), (None, n)));
+        let included = prog.include_label_lines.iter().map(|(l, &(f, n))| (l.clone(), (Some(prog.includes[f].clone()), n)));
+        self.editor.label_files = main.chain(included).collect();
+        if self.editor.include_back.is_empty() {
+            self.editor.label_to_line = std::mem::take(&mut prog.label_to_line);
+            self.editor.line_to_addr = std::mem::take(&mut prog.line_addrs);
+            self.editor.other_file_addrs = prog.include_line_addrs.values().copied().collect();
+            return;
+        }
+        let shown = prog.includes.iter().position(|p| Some(p) == self.editor.file_path.as_ref());
+        let elsewhere = prog.include_line_addrs.iter().filter(|((f, _), _)| Some(*f) != shown).map(|(_, &a)| a);
+        self.editor.other_file_addrs = prog.line_addrs.values().copied().chain(elsewhere).collect();
+        self.editor.label_to_line =
+            prog.include_label_lines.iter().filter(|(_, (f, _))| Some(*f) == shown).map(|(l, &(_, n))| (l.clone(), n)).collect();
+        self.editor.line_to_addr =
+            prog.include_line_addrs.iter().filter(|((f, _), _)| Some(*f) == shown).map(|(&(_, n), &a)| (n, a)).collect();
+    }
+
+    /// Store a failed assembly's diagnostics; the first one also fills the single-line status.
+    fn set_diagnostics(&mut self, diags: Vec<falcon::asm::Diagnostic>) {
+        let first = diags.first();
+        let shown = self.editor.shown_file().map(std::path::Path::to_path_buf);
+        let shown = shown.as_deref();
+        self.editor.diag_line = first.filter(|d| d.file.as_deref() == shown).map(|d| d.line);
+        self.editor.diag_msg = first.map(|d| match &d.file {
+            Some(f) if d.file.as_deref() != shown => format!("{}: {}", file_name(f), d.msg),
+            _ => d.msg.clone(),
+        });
+        self.editor.diag_line_text = self.editor.diag_line.and_then(|l| self.editor.buf.lines.get(l).cloned());
+        self.editor.diagnostics = diags;
+        self.editor.last_compile_ok = Some(false);
+    }
+
+    fn more_problems_suffix(&self) -> String {
+        match self.editor.diagnostics.len() {
+            0 | 1 => String::new(),
+            n => format!(" (+{} more, F8 = next)", n - 1),
+        }
+    }
+
+    /// Move the cursor to the next diagnostic after the cursor line, wrapping around.
+    /// With none left in the shown file, opens the file of the first one elsewhere.
+    pub(super) fn goto_next_problem(&mut self) {
+        let row = self.editor.buf.cursor_row;
+        let shown = self.editor.shown_file();
+        let diags = &self.editor.diagnostics;
+        let mut here = diags.iter().filter(|d| d.file.as_deref() == shown);
+        let next = here.clone().find(|d| d.line > row).or_else(|| here.next()).or_else(|| diags.first());
+        let Some(d) = next else { return };
+        let (file, line, col) = (d.file.clone(), d.line, d.cols.start);
+        self.show_location(file, line, col);
+    }
+
+    /// Put the cursor on `line` of `file` (`None` = the main program), switching the
+    /// editor to that file first when it isn't the one shown.
+    fn show_location(&mut self, file: Option<std::path::PathBuf>, line: usize, col: usize) {
+        if file.as_deref() != self.editor.shown_file() {
+            match file {
+                None => {
+                    while !self.editor.include_back.is_empty() {
+                        self.include_go_back();
+                    }
+                }
+                Some(path) => {
+                    let lines = match self.editor.include_edits.get(&path) {
+                        Some(lines) => lines.clone(),
+                        None => match std::fs::read_to_string(&path) {
+                            Ok(content) => content.lines().map(str::to_string).collect(),
+                            Err(_) => {
+                                self.editor.last_assemble_msg = Some(format!("Cannot open {}", path.display()));
+                                return;
+                            }
+                        },
+                    };
+                    self.stash_shown_include();
+                    let saved = SavedBuffer {
+                        path: self.editor.file_path.replace(path),
+                        lines: std::mem::replace(&mut self.editor.buf.lines, lines),
+                        cursor: (self.editor.buf.cursor_row, self.editor.buf.cursor_col),
+                    };
+                    if self.editor.buf.lines.is_empty() {
+                        self.editor.buf.lines.push(String::new());
+                    }
+                    self.editor.include_back.push(saved);
+                    self.check_assemble();
+                }
+            }
+        }
+        self.editor.buf.cursor_row = line.min(self.editor.buf.lines.len().saturating_sub(1));
+        self.editor.buf.cursor_col = col;
+    }
+
+    /// Return from an included file to the buffer F12 left (Alt+Left). Unsaved edits
+    /// to the file being left are kept in `include_edits`.
+    pub(super) fn include_go_back(&mut self) {
+        if self.editor.include_back.is_empty() {
+            return;
+        }
+        self.stash_shown_include();
+        let saved = self.editor.include_back.pop().unwrap();
+        // An include shown again may have been edited further up the stack.
+        let edited = match &saved.path {
+            Some(path) if !self.editor.include_back.is_empty() => self.editor.include_edits.get(path).cloned(),
+            _ => None,
+        };
+        self.editor.buf.lines = edited.unwrap_or(saved.lines);
+        self.editor.file_path = saved.path;
+        (self.editor.buf.cursor_row, self.editor.buf.cursor_col) = saved.cursor;
+        self.check_assemble();
+    }
+
+    /// Before the editor switches away from an included file: keep its text in
+    /// `include_edits` while it differs from the file on disk.
+    fn stash_shown_include(&mut self) {
+        let Some(path) = self.editor.shown_file().map(std::path::Path::to_path_buf) else { return };
+        let lines = &self.editor.buf.lines;
+        if std::fs::read_to_string(&path).is_ok_and(|disk| disk.lines().eq(lines.iter().map(String::as_str))) {
+            self.editor.include_edits.remove(&path);
+        } else {
+            self.editor.include_edits.insert(path, lines.clone());
+        }
+    }
+
+    /// Load a source file into the editor (Ctrl+O), as the new main program.
+    pub(super) fn open_source(&mut self, path: std::path::PathBuf, content: &str) {
+        self.editor.buf.lines = content.lines().map(|s| s.to_string()).collect();
+        self.editor.buf.cursor_row = 0;
+        self.editor.buf.cursor_col = 0;
+        self.editor.file_path = Some(path);
+        self.editor.include_back.clear();
+        self.editor.include_edits.clear();
+        self.assemble_and_load();
+    }
+
+    /// Write the buffer to `path` (Ctrl+S); it becomes the main program's location.
+    pub(super) fn save_source(&mut self, path: std::path::PathBuf) {
+        if std::fs::write(&path, self.editor.buf.text()).is_ok() && self.editor.include_back.is_empty() {
+            self.editor.file_path = Some(path);
+        }
+    }
+
+    /// Ctrl+S on an included file saves it in place; returns false when the main program is shown.
+    pub(super) fn save_shown_include(&mut self) -> bool {
+        let Some(path) = self.editor.shown_file().map(std::path::Path::to_path_buf) else { return false };
+        match std::fs::write(&path, self.editor.buf.text()) {
+            Ok(()) => {
+                self.editor.include_edits.remove(&path);
+                self.check_assemble();
+            }
+            Err(e) => self.editor.last_assemble_msg = Some(format!("Cannot save {}: {e}", path.display())),
+        }
+        true
+    }
+
     fn load_last_ok_program(&mut self) {
         // ELF path: re-parse the original bytes so all segments are restored correctly.
         if let Some(elf_bytes) = self.editor.last_ok_elf_bytes.clone() {
-            self.load_binary(&elf_bytes);
+            let name = self.run.program_name.clone();
+            self.load_binary(&elf_bytes, &name);
             return;
         }
 
@@ -1062,10 +1453,8 @@ impl App {
         ) {
             self.run.prev_x = self.run.cpu.x;
             self.run.mem_size = self.ram_override.unwrap_or(128 * 1024);
-            self.run.cpu = Cpu::default();
-            self.run.cpu.pc = self.run.base_pc;
+            self.run.cpu = self.fresh_cpu();
             self.run.prev_pc = self.run.cpu.pc;
-            self.run.cpu.write(2, self.run.mem_size as u32);
             self.run.mem = CacheController::new(
                 self.cache.pending_icache.clone(),
                 self.cache.pending_dcache.clone(),
@@ -1085,6 +1474,8 @@ impl App {
                 self.run.faulted = true;
                 return;
             }
+            let bss_end = data_base + data.len() as u32 + self.editor.last_ok_bss_size.unwrap_or(0);
+            self.run.cpu.heap_break = (bss_end.wrapping_add(15)) & !15;
             if let Some(bss) = self.editor.last_ok_bss_size {
                 if bss > 0 {
                     let bss_base = data_base.saturating_add(data.len() as u32);
@@ -1105,6 +1496,9 @@ impl App {
             self.run.comments = self.editor.last_ok_comments.clone();
             self.run.block_comments = self.editor.last_ok_block_comments.clone();
             self.run.labels = self.editor.last_ok_labels.clone();
+            self.run.code_ranges.clear();
+            self.run.elf_symbols.clear();
+            self.run.debug = None;
 
             let bss_sz = self.editor.last_ok_bss_size.unwrap_or(0);
             self.editor.last_assemble_msg = Some(format!(
@@ -1115,6 +1509,7 @@ impl App {
             ));
             self.run.imem_scroll = 0;
             self.run.hover_imem_addr = None;
+            self.init_program_stack(false);
         }
     }
 
@@ -1124,16 +1519,96 @@ impl App {
         self.run.cpu.ebreak_hit = false;
         self.run.reg_last_write_pc = [None; 32];
         self.run.exec_counts.clear();
+        self.run.branch_counts.clear();
         self.run.exec_trace.clear();
         self.run.mem_access_log.clear();
         self.load_last_ok_program();
     }
 
-    pub(super) fn load_binary(&mut self, bytes: &[u8]) {
+    /// argv[0] for a program assembled from the editor: its file name, if any.
+    fn source_program_name(&self) -> String {
+        self.editor.file_path.as_deref()
+            .and_then(std::path::Path::file_name)
+            .map_or_else(|| "main.fas".to_string(), |n| n.to_string_lossy().into_owned())
+    }
+
+    /// Lay out argc/argv/envp/auxv below `sp`. ELF programs always get them (their
+    /// `_start` expects the Linux stack); others only when arguments were given.
+    /// Returns false, after reporting the error, when they don't fit in RAM. The
+    /// semihosting command line is set either way.
+    fn init_program_stack(&mut self, elf: bool) -> bool {
+        let name = self.run.program_name.clone();
+        self.run.cpu.semihost.cmdline = self.run.program_args.command_line(&name);
+        if !elf && self.run.program_args.is_empty() {
+            return true;
+        }
+        match falcon::program::init_stack(&mut self.run.cpu, &mut self.run.mem.ram, &name, &self.run.program_args) {
+            Ok(()) => true,
+            Err(e) => {
+                self.console.push_error(e.to_string());
+                self.run.faulted = true;
+                false
+            }
+        }
+    }
+
+    /// Next memory region: DATA → STACK → R/W → HEAP → each mmap region → DATA.
+    pub(super) fn cycle_mem_region(&mut self) {
+        let align = !(self.run.mem_view_bytes - 1);
+        let next = match self.run.mem_region {
+            MemRegion::Data | MemRegion::Custom => MemRegion::Stack,
+            MemRegion::Stack => MemRegion::Access,
+            MemRegion::Access => MemRegion::Heap,
+            MemRegion::Heap => MemRegion::Mmap(0),
+            MemRegion::Mmap(i) => MemRegion::Mmap(i + 1),
+        };
+        self.run.mem_region = match next {
+            MemRegion::Stack => {
+                self.run.mem_view_addr = self.run.cpu.x[2] & align;
+                next
+            }
+            MemRegion::Heap => {
+                self.run.mem_view_addr = self.run.cpu.heap_break & align;
+                next
+            }
+            MemRegion::Mmap(i) if i < self.run.cpu.mmap.regions().len() => {
+                self.run.mem_view_addr = self.run.cpu.mmap.regions()[i].start;
+                next
+            }
+            MemRegion::Mmap(_) => {
+                self.run.mem_view_addr = self.run.data_base;
+                MemRegion::Data
+            }
+            _ => next,
+        };
+        self.run.show_registers = false;
+        self.run.show_dyn = false;
+    }
+
+    /// Add a host file to the guest's filesystem, under its file name: for this
+    /// run and every restart.
+    pub(super) fn import_guest_file(&mut self, path: &std::path::Path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let _ = self.run.cpu.fs.insert(&name, bytes.clone());
+                let _ = self.run.guest_files.insert(&name, bytes);
+            }
+            Err(e) => self.console.push_error(format!("{}: {e}", path.display())),
+        }
+    }
+
+    /// Remove a guest file from this run and from the files every run starts with.
+    pub(super) fn delete_guest_file(&mut self, name: &str) {
+        let _ = self.run.cpu.fs.unlink(name);
+        let _ = self.run.guest_files.unlink(name);
+    }
+
+    /// `name` becomes the program's argv[0].
+    pub(super) fn load_binary(&mut self, bytes: &[u8], name: &str) {
         self.run.prev_x = self.run.cpu.x;
         self.run.mem_size = self.ram_override.unwrap_or(16 * 1024 * 1024); // default 16 MB for ELF (heap support)
-        self.run.cpu = Cpu::default();
-        self.run.cpu.write(2, self.run.mem_size as u32);
+        self.run.cpu = self.fresh_cpu();
         self.run.mem = CacheController::new(
             self.cache.pending_icache.clone(),
             self.cache.pending_dcache.clone(),
@@ -1156,6 +1631,10 @@ impl App {
 
             self.run.cpu.pc        = info.entry;
             self.run.prev_pc       = info.entry;
+            self.run.program_name  = name.to_string();
+            if !self.init_program_stack(true) {
+                return;
+            }
             self.run.base_pc       = info.text_base;
             self.run.data_base     = info.data_base;
             self.run.mem_view_addr = info.data_base;
@@ -1164,9 +1643,14 @@ impl App {
             self.run.mem.reset_stats();
 
             // Populate labels and sections viewer from ELF symbol table
+            self.run.code_ranges = info.code_ranges();
             self.run.labels = info.symbols;
             self.run.elf_sections = info.sections;
+            self.run.elf_symbols = info.symbol_table;
             self.run.cpu.heap_break = info.heap_start;
+            // Each source line starts with a `file:line  source` separator
+            self.run.block_comments = info.debug.as_ref().map(source_line_comments).unwrap_or_default();
+            self.run.debug = info.debug;
 
             let mut words = Vec::with_capacity(info.text_bytes.len() / 4);
             for chunk in info.text_bytes.chunks(4) {
@@ -1189,30 +1673,39 @@ impl App {
         } else {
             // ── FALC or flat binary ──────────────────────────────────────
             self.run.elf_sections = Vec::new();
-            use falcon::program::{load_bytes, zero_bytes};
-            let (text_bytes, data_bytes, bss_size): (Vec<u8>, Vec<u8>, u32) =
-                if bytes.len() >= 16 && &bytes[0..4] == b"FALC" {
-                    let text_sz = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
-                    let data_sz = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
-                    let bss_sz  = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
-                    let body    = &bytes[16..];
-                    if body.len() < text_sz + data_sz {
-                        self.console.push_error("Binary truncated or corrupt");
-                        self.run.faulted = true;
-                        return;
-                    }
-                    (body[..text_sz].to_vec(), body[text_sz..text_sz + data_sz].to_vec(), bss_sz)
-                } else {
-                    (bytes.to_vec(), Vec::new(), 0)
-                };
+            self.run.code_ranges.clear();
+            self.run.elf_symbols.clear();
+            self.run.block_comments.clear();
+            self.run.debug = None;
+            use falcon::program::{load_bytes, zero_bytes, Falc};
+            let Falc { text: text_bytes, data: data_bytes, bss_size } = match falcon::program::parse_falc(bytes) {
+                Ok(falc) => falc,
+                Err(e) => {
+                    self.console.push_error(e);
+                    self.run.faulted = true;
+                    return;
+                }
+            };
+            // Same placement the assembler used when the container was exported.
+            match falcon::asm::data_base_after(self.run.base_pc, text_bytes.len() as u32) {
+                Some(base) => {
+                    self.run.data_base = base;
+                    self.run.mem_view_addr = base;
+                }
+                None => {
+                    self.console.push_error("Binary too large");
+                    self.run.faulted = true;
+                    return;
+                }
+            }
 
-            if let Err(e) = load_bytes(&mut self.run.mem.ram, self.run.base_pc, &text_bytes) {
+            if let Err(e) = load_bytes(&mut self.run.mem.ram, self.run.base_pc, text_bytes) {
                 self.console.push_error(e.to_string());
                 self.run.faulted = true;
                 return;
             }
             if !data_bytes.is_empty() {
-                if let Err(e) = load_bytes(&mut self.run.mem.ram, self.run.data_base, &data_bytes) {
+                if let Err(e) = load_bytes(&mut self.run.mem.ram, self.run.data_base, data_bytes) {
                     self.console.push_error(e.to_string());
                     self.run.faulted = true;
                     return;
@@ -1229,6 +1722,10 @@ impl App {
 
             self.run.cpu.pc  = self.run.base_pc;
             self.run.prev_pc = self.run.base_pc;
+            self.run.program_name = name.to_string();
+            if !self.init_program_stack(false) {
+                return;
+            }
             self.run.mem.invalidate_all();
             self.run.mem.reset_stats();
 
@@ -1246,7 +1743,7 @@ impl App {
             }
             let total = text_bytes.len() + data_bytes.len();
             self.editor.last_ok_text       = Some(words);
-            self.editor.last_ok_data       = Some(data_bytes);
+            self.editor.last_ok_data       = Some(data_bytes.to_vec());
             self.editor.last_ok_data_base  = Some(self.run.data_base);
             self.editor.last_ok_bss_size   = Some(bss_size);
             self.editor.last_ok_elf_bytes  = None;
@@ -1261,6 +1758,7 @@ impl App {
         self.editor.diag_line          = None;
         self.editor.diag_msg           = None;
         self.editor.diag_line_text     = None;
+        self.editor.diagnostics.clear();
         self.run.imem_scroll           = 0;
         self.run.hover_imem_addr       = None;
         // Lock the editor when a binary is loaded; close any stale prompt.
@@ -1419,8 +1917,10 @@ impl App {
 
     // ── Instruction-memory scroll helpers (visual-row units) ─────────────────
 
-    fn imem_in_range(&self, addr: u32) -> bool {
-        if let Some(text) = &self.editor.last_ok_text {
+    pub(super) fn imem_in_range(&self, addr: u32) -> bool {
+        if !self.run.code_ranges.is_empty() {
+            self.run.code_ranges.iter().any(|r| r.contains(&addr))
+        } else if let Some(text) = &self.editor.last_ok_text {
             let start = self.run.base_pc;
             let end = start.saturating_add((text.len() as u32).saturating_mul(4));
             addr >= start && addr < end
@@ -1429,16 +1929,32 @@ impl App {
         }
     }
 
+    /// First address of the instruction list: the lowest code segment of an ELF,
+    /// otherwise base_pc. None when there is nothing to list.
+    pub(super) fn imem_first(&self) -> Option<u32> {
+        let addr = self.run.code_ranges.first().map_or(self.run.base_pc, |r| r.start);
+        self.imem_in_range(addr).then_some(addr)
+    }
+
+    /// Address listed after `addr`: the next word, or the start of the next code
+    /// segment when `addr` ends one.
+    pub(super) fn imem_next(&self, addr: u32) -> Option<u32> {
+        let next = addr.wrapping_add(4);
+        if next > addr && self.imem_in_range(next) {
+            return Some(next);
+        }
+        self.run.code_ranges.iter().map(|r| r.start).find(|&start| start > addr)
+    }
+
     /// Total visual rows in the instruction list (block_comment + labels + instruction per addr).
     pub(super) fn imem_total_visual_rows(&self) -> usize {
         let mut count = 0usize;
-        let mut addr = self.run.base_pc;
-        loop {
-            if !self.imem_in_range(addr) { break; }
+        let mut next = self.imem_first();
+        while let Some(addr) = next {
             if self.run.block_comments.contains_key(&addr) { count += 1; }
             if let Some(names) = self.run.labels.get(&addr) { count += names.len(); }
             count += 1;
-            addr = addr.wrapping_add(4);
+            next = self.imem_next(addr);
         }
         count
     }
@@ -1447,11 +1963,10 @@ impl App {
     /// header_skip = how many block_comment/label rows to skip at the top of start_addr's block.
     pub(super) fn imem_addr_skip_for_scroll(&self) -> (u32, usize) {
         let scroll = self.run.imem_scroll;
-        let base = self.run.base_pc;
+        let Some(base) = self.imem_first() else { return (self.run.base_pc, 0) };
         let mut vrow = 0usize;
         let mut addr = base;
         loop {
-            if !self.imem_in_range(addr) { return (base, 0); }
             let bc = if self.run.block_comments.contains_key(&addr) { 1 } else { 0 };
             let lbls = self.run.labels.get(&addr).map_or(0, |v| v.len());
             let block = bc + lbls + 1;
@@ -1459,24 +1974,16 @@ impl App {
                 return (addr, scroll - vrow);
             }
             vrow += block;
-            addr = addr.wrapping_add(4);
+            match self.imem_next(addr) {
+                Some(next) => addr = next,
+                None => return (base, 0),
+            }
         }
     }
 
     /// Visual row of the current PC within the full instruction list.
     pub(super) fn imem_visual_row_of_pc(&self) -> Option<usize> {
-        let pc = self.run.cpu.pc;
-        if pc < self.run.base_pc { return None; }
-        let mut vrow = 0usize;
-        let mut addr = self.run.base_pc;
-        loop {
-            if !self.imem_in_range(addr) { return None; }
-            if self.run.block_comments.contains_key(&addr) { vrow += 1; }
-            if let Some(names) = self.run.labels.get(&addr) { vrow += names.len(); }
-            if addr == pc { return Some(vrow); }
-            vrow += 1;
-            addr = addr.wrapping_add(4);
-        }
+        self.imem_visual_row_of_addr(self.run.cpu.pc)
     }
 
     /// Ensure PC is visible in the imem panel, updating imem_scroll if needed.
@@ -1496,17 +2003,18 @@ impl App {
 
     /// Visual row of an arbitrary address within the full instruction list.
     pub(super) fn imem_visual_row_of_addr(&self, target: u32) -> Option<usize> {
-        if target < self.run.base_pc { return None; }
+        if !self.imem_in_range(target) { return None; }
         let mut vrow = 0usize;
-        let mut addr = self.run.base_pc;
-        loop {
-            if !self.imem_in_range(addr) { return None; }
+        let mut next = self.imem_first();
+        while let Some(addr) = next {
+            if addr > target { return None; }
             if self.run.block_comments.contains_key(&addr) { vrow += 1; }
             if let Some(names) = self.run.labels.get(&addr) { vrow += names.len(); }
             if addr == target { return Some(vrow); }
             vrow += 1;
-            addr = addr.wrapping_add(4);
+            next = self.imem_next(addr);
         }
+        None
     }
 
     /// Scroll the instruction memory panel to bring `addr` near the top.
@@ -1522,9 +2030,15 @@ impl App {
                 self.splash_start = None;
             }
         }
+        self.poll_gdb();
 
+        if !self.run.is_running {
+            self.run.line_step = None;
+        }
         if self.run.is_running {
-            match self.run.speed {
+            // A source-line step runs flat out, whatever the speed setting
+            let speed = if self.run.line_step.is_some() { RunSpeed::Instant } else { self.run.speed };
+            match speed {
                 RunSpeed::X1 => {
                     if self.run.last_step_time.elapsed() >= self.run.step_interval {
                         self.single_step();
@@ -1560,6 +2074,7 @@ impl App {
                 }
             }
         }
+        self.report_gdb_stop();
         // Scroll instruction list to follow PC (skipped in Instant to avoid pointless churn)
         if self.run.is_running && !matches!(self.run.speed, RunSpeed::Instant) {
             self.ensure_pc_visible_in_imem();
@@ -1576,11 +2091,33 @@ impl App {
         }
     }
 
+    /// Run until execution reaches another source line (`S`). Images without
+    /// DWARF line info step a single instruction.
+    pub(super) fn step_source_line(&mut self) {
+        if self.run.debug.is_some() {
+            self.run.line_step = Some(self.run.cpu.pc);
+            self.run.is_running = true;
+        } else {
+            self.single_step();
+        }
+    }
+
+    /// Addresses of `file:line` in the DWARF line table (a line without code
+    /// moves to the next one that has some), with the line used.
+    pub(super) fn source_line_addrs(&self, query: &str) -> Option<(String, Vec<u32>)> {
+        let debug = self.run.debug.as_ref()?;
+        let (file, line) = query.trim().rsplit_once(':')?;
+        let file = debug.find_file(std::path::Path::new(file))?;
+        let (line, addrs) = debug.line_addrs(file, line.trim().parse().ok()?)?;
+        Some((format!("{}:{line}", file_name(&debug.files[file])), addrs))
+    }
+
     pub(super) fn single_step(&mut self) {
         self.run.prev_x = self.run.cpu.x;
         self.run.prev_f = self.run.cpu.f;
         self.run.prev_pc = self.run.cpu.pc;
         let step_pc = self.run.cpu.pc;
+        self.run.watch_hit = None;
 
         // Halt gracefully when PC leaves the loaded text segment.
         if !self.imem_in_range(step_pc) {
@@ -1628,6 +2165,9 @@ impl App {
         *self.run.exec_counts.entry(step_pc).or_insert(0) += 1;
         let disasm = {
             let word = self.run.mem.peek32(step_pc).unwrap_or(0);
+            if alive {
+                falcon::coverage::record_branch(&mut self.run.branch_counts, step_pc, word, self.run.cpu.pc);
+            }
             match falcon::decoder::decode(word) {
                 Ok(instr) => format!("{instr:?}"),
                 Err(_) => format!("0x{word:08x}"),
@@ -1689,10 +2229,28 @@ impl App {
             }
         }
 
+        // Check debugger watchpoints against this step's load/store
+        if alive && let Some((addr, size, is_store)) = mem_access {
+            let hit = self.run.watchpoints.iter().find(|&&(kind, w_addr, w_len)| {
+                kind.matches(is_store) && addr < w_addr.wrapping_add(w_len.max(1)) && w_addr < addr.wrapping_add(size)
+            });
+            if let Some(&(kind, _, _)) = hit {
+                self.run.watch_hit = Some((kind, addr));
+                self.run.is_running = false;
+            }
+        }
+
         // Check breakpoints: stop if the new PC is a breakpoint
         if alive && self.run.breakpoints.contains(&self.run.cpu.pc) {
             self.run.is_running = false;
         }
+        // Source-line step: stop on the first statement of a different line
+        if let (Some(from), Some(debug)) = (self.run.line_step, &self.run.debug) {
+            let pc = self.run.cpu.pc;
+            if debug.is_stmt(pc) && debug.location(pc) != debug.location(from) {
+                self.run.is_running = false;
+            }
+        }
         if !alive {
             self.run.is_running = false;
             if !self.console.reading {
@@ -1711,11 +2269,21 @@ impl App {
         let col = self.editor.buf.cursor_col;
         if row >= self.editor.buf.lines.len() { return; }
         let line = &self.editor.buf.lines[row];
+        if let Some(name) = line.trim().strip_prefix(".include").map(str::trim) {
+            let name = name.trim_matches('"');
+            let dir = self.editor.file_path.as_deref().and_then(std::path::Path::parent).unwrap_or(std::path::Path::new(""));
+            let path = dir.join(name);
+            let path = path.canonicalize().unwrap_or(path);
+            self.show_location(Some(path), 0, 0);
+            return;
+        }
         let word = word_at(line, col);
         if word.is_empty() { return; }
         if let Some(&target_line) = self.editor.label_to_line.get(&word) {
             self.editor.buf.cursor_row = target_line;
             self.editor.buf.cursor_col = 0;
+        } else if let Some((file, target_line)) = self.editor.label_files.get(&word).cloned() {
+            self.show_location(file, target_line, 0);
         }
     }
 
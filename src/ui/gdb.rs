@@ -0,0 +1,206 @@
+// ui/gdb.rs — connects the GDB remote stub to the simulator state in `App`
+use crate::falcon::Bus;
+use crate::gdb::{GdbServer, Pending, StopReason, Target, WatchKind, REG_F0, REG_FCSR, REG_FFLAGS, REG_FRM, REG_PC};
+
+use super::app::App;
+
+impl App {
+    /// Attach a GDB stub; the client is accepted on the next tick.
+    pub fn attach_gdb(&mut self, server: GdbServer) {
+        self.console.push_colored(
+            format!("GDB stub listening on 127.0.0.1:{} — `target remote :{}`", server.port, server.port),
+            crate::ui::console::ConsoleColor::Info,
+        );
+        self.gdb = Some(server);
+    }
+
+    /// Handle pending debugger packets. Called at the start of every tick.
+    pub(super) fn poll_gdb(&mut self) {
+        let Some(mut gdb) = self.gdb.take() else { return };
+        gdb.poll(self);
+        // A step that blocked on console input resumes when the user presses Enter,
+        // which sets `is_running`; finish it as a single step instead of a run.
+        if gdb.pending() == Some(Pending::Step) && self.run.is_running && !self.console.reading {
+            self.run.is_running = false;
+            self.single_step();
+            if !self.console.reading {
+                gdb.report_stop(self.gdb_stop_reason(true));
+            }
+        }
+        self.gdb = Some(gdb);
+    }
+
+    /// Send the stop reply once a debugger `continue` has come to rest.
+    /// Called at the end of every tick.
+    pub(super) fn report_gdb_stop(&mut self) {
+        let Some(gdb) = self.gdb.as_mut() else { return };
+        if gdb.pending() != Some(Pending::Continue) || self.console.reading {
+            return;
+        }
+        if self.run.faulted {
+            self.run.is_running = false;
+        }
+        if !self.run.is_running {
+            let reason = self.gdb_stop_reason(false);
+            if let Some(gdb) = self.gdb.as_mut() {
+                gdb.report_stop(reason);
+            }
+        }
+    }
+
+    fn gdb_stop_reason(&self, stepped: bool) -> StopReason {
+        if let Some(code) = self.run.cpu.exit_code {
+            StopReason::Exited(code)
+        } else if self.run.faulted {
+            StopReason::Fault
+        } else if let Some((kind, addr)) = self.run.watch_hit {
+            StopReason::Watch(kind, addr)
+        } else if self.run.cpu.ebreak_hit {
+            StopReason::Ebreak
+        } else if stepped {
+            StopReason::Step
+        } else if self.run.breakpoints.contains(&self.run.cpu.pc) {
+            StopReason::Breakpoint
+        } else {
+            StopReason::Interrupted
+        }
+    }
+
+    fn gdb_cache_report(&self) -> String {
+        use std::fmt::Write;
+        let mem = &self.run.mem;
+        let mut out = String::new();
+        let _ = writeln!(out, "instructions: {}", mem.instruction_count);
+        let _ = writeln!(out, "cycles:       {}", mem.total_program_cycles());
+        let _ = writeln!(out, "CPI:          {:.3}", mem.overall_cpi());
+        let mut level = |name: &str, stats: &crate::falcon::cache::CacheStats, amat: f64| {
+            let _ = writeln!(
+                out,
+                "{name:<4} hits {:>10}  misses {:>10}  hit rate {:>6.2}%  AMAT {:.2}",
+                stats.hits,
+                stats.misses,
+                stats.hit_rate(),
+                amat,
+            );
+        };
+        level("I$", &mem.icache.stats, mem.icache_amat());
+        level("D$", &mem.dcache.stats, mem.dcache_amat());
+        for (i, cache) in mem.extra_levels.iter().enumerate() {
+            let name = crate::falcon::CacheController::extra_level_name(i);
+            level(&name, &cache.stats, mem.extra_level_amat(i));
+        }
+        out
+    }
+}
+
+impl Target for App {
+    fn read_reg(&self, n: usize) -> Option<u32> {
+        let cpu = &self.run.cpu;
+        match n {
+            0..=31 => Some(cpu.read(n as u8)),
+            REG_PC => Some(cpu.pc),
+            _ if (REG_F0..REG_F0 + 32).contains(&n) => Some(cpu.f[n - REG_F0]),
+            REG_FFLAGS => Some(cpu.fcsr & 0x1f),
+            REG_FRM => Some((cpu.fcsr >> 5) & 0x7),
+            REG_FCSR => Some(cpu.fcsr),
+            _ => None,
+        }
+    }
+
+    fn write_reg(&mut self, n: usize, val: u32) -> bool {
+        let cpu = &mut self.run.cpu;
+        match n {
+            0..=31 => cpu.write(n as u8, val),
+            REG_PC => cpu.pc = val,
+            _ if (REG_F0..REG_F0 + 32).contains(&n) => cpu.f[n - REG_F0] = val,
+            REG_FFLAGS => cpu.fcsr = (cpu.fcsr & !0x1f) | (val & 0x1f),
+            REG_FRM => cpu.fcsr = (cpu.fcsr & !0xe0) | ((val & 0x7) << 5),
+            REG_FCSR => cpu.fcsr = val & 0xff,
+            _ => return false,
+        }
+        true
+    }
+
+    fn read_mem(&self, addr: u32, len: u32) -> Option<Vec<u8>> {
+        (0..len).map(|i| self.run.mem.load8(addr.wrapping_add(i)).ok()).collect()
+    }
+
+    fn write_mem(&mut self, addr: u32, data: &[u8]) -> bool {
+        for (i, &b) in data.iter().enumerate() {
+            let a = addr.wrapping_add(i as u32);
+            // Keep the D-cache coherent without counting debugger pokes as accesses:
+            // only bytes held dirty in the cache go through it.
+            let res = if self.run.mem.is_dirty_cached(a, 1) {
+                self.run.mem.store8(a, b)
+            } else {
+                self.run.mem.ram.store8(a, b)
+            };
+            if res.is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn set_breakpoint(&mut self, addr: u32, enabled: bool) {
+        if enabled {
+            self.run.breakpoints.insert(addr);
+        } else {
+            self.run.breakpoints.remove(&addr);
+        }
+    }
+
+    fn set_watchpoint(&mut self, kind: WatchKind, addr: u32, len: u32, enabled: bool) {
+        let wp = (kind, addr, len);
+        if enabled {
+            if !self.run.watchpoints.contains(&wp) {
+                self.run.watchpoints.push(wp);
+            }
+        } else {
+            self.run.watchpoints.retain(|&w| w != wp);
+        }
+    }
+
+    fn step(&mut self) -> Option<StopReason> {
+        if self.run.faulted || self.run.cpu.exit_code.is_some() {
+            return Some(self.gdb_stop_reason(true));
+        }
+        if self.console.reading {
+            return None;
+        }
+        self.run.cpu.ebreak_hit = false;
+        self.single_step();
+        if self.console.reading {
+            None
+        } else {
+            Some(self.gdb_stop_reason(true))
+        }
+    }
+
+    fn resume(&mut self) {
+        // Exited or faulted programs can't run; report_gdb_stop replies right away.
+        if self.run.faulted || self.run.cpu.exit_code.is_some() {
+            return;
+        }
+        self.run.cpu.ebreak_hit = false;
+        self.run.is_running = true;
+    }
+
+    fn interrupt(&mut self) {
+        self.run.is_running = false;
+    }
+
+    fn monitor(&mut self, cmd: &str) -> String {
+        match cmd {
+            "" | "cache" | "stats" => self.gdb_cache_report(),
+            "reset" => {
+                self.restart_simulation();
+                "program reloaded\n".into()
+            }
+            "help" => "monitor cache   cache hit rates, AMAT and cycle counts\n\
+                       monitor reset   reload the program and clear statistics\n"
+                .into(),
+            other => format!("unknown monitor command '{other}' (try `monitor help`)\n"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::falcon::cache::{CacheConfig, ReplacementPolicy, WriteAllocPolicy, WritePolicy, extra_level_presets, Cache};
+use crate::falcon::program::ProgramArgs;
 use crate::ui::app::{App, CacheResultsSnapshot, CacheScope, CacheSubtab, CpiConfig, DocsPage, EditorMode, LevelSnapshot, MemRegion, PathInput, PathInputAction, Tab};
 use crate::ui::view::docs::{docs_body_line_count, ALL_MASK, FILTER_ITEMS};
 use crossterm::{event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers}, terminal};
@@ -205,10 +206,104 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
         return Ok(false);
     }
 
+    // Guest file browser intercept
+    if matches!(app.tab, Tab::Run) && app.run.files_open {
+        let names: Vec<String> = app.run.cpu.fs.files().map(|(n, _)| n.to_string()).collect();
+        let selected = names.get(app.run.files_selected).cloned();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('F') => app.run.files_open = false,
+            KeyCode::Up => app.run.files_selected = app.run.files_selected.saturating_sub(1),
+            KeyCode::Down => {
+                app.run.files_selected = (app.run.files_selected + 1).min(names.len().saturating_sub(1));
+            }
+            KeyCode::Char('i') => {
+                if let Some(paths) = OSFileDialog::new().pick_files() {
+                    for path in paths {
+                        app.import_guest_file(&path);
+                    }
+                }
+            }
+            KeyCode::Char('x') => {
+                if let Some(name) = &selected
+                    && let Some((_, data)) = app.run.cpu.fs.files().find(|(n, _)| n == name)
+                    && let Some(path) = OSFileDialog::new()
+                        .set_file_name(name.rsplit('/').next().unwrap_or(name))
+                        .save_file()
+                    && let Err(e) = std::fs::write(&path, data)
+                {
+                    app.console.push_error(format!("{}: {e}", path.display()));
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(name) = &selected {
+                    app.delete_guest_file(name);
+                    app.run.files_selected = app.run.files_selected.min(names.len().saturating_sub(2));
+                }
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Syscall trace panel intercept: typing filters, arrows scroll
+    if matches!(app.tab, Tab::Run) && app.run.syscalls_open {
+        match key.code {
+            KeyCode::Esc => app.run.syscalls_open = false,
+            KeyCode::Up => app.run.syscalls_scroll += 1,
+            KeyCode::Down => app.run.syscalls_scroll = app.run.syscalls_scroll.saturating_sub(1),
+            KeyCode::PageUp => app.run.syscalls_scroll += 10,
+            KeyCode::PageDown => app.run.syscalls_scroll = app.run.syscalls_scroll.saturating_sub(10),
+            KeyCode::End => app.run.syscalls_scroll = 0,
+            KeyCode::Backspace => {
+                app.run.syscalls_filter.pop();
+                app.run.syscalls_scroll = 0;
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                app.run.syscalls_filter.push(c);
+                app.run.syscalls_scroll = 0;
+            }
+            _ => {}
+        }
+        return Ok(false);
+    }
+
+    // Program arguments prompt intercept
+    if matches!(app.tab, Tab::Run) && let Some(line) = app.run.args_prompt.as_mut() {
+        match key.code {
+            KeyCode::Enter => {
+                match ProgramArgs::parse(line) {
+                    Ok(args) => {
+                        app.run.program_args = args;
+                        app.run.args_prompt = None;
+                        app.restart_simulation();
+                    }
+                    Err(e) => app.console.push_error(format!("Program arguments: {e}")),
+                }
+            }
+            KeyCode::Esc => app.run.args_prompt = None,
+            KeyCode::Backspace => { line.pop(); }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => line.push(c),
+            _ => {}
+        }
+        return Ok(false);
+    }
+
     // Imem label search bar intercept
     if matches!(app.tab, Tab::Run) && app.run.imem_search_open {
         match key.code {
-            KeyCode::Esc | KeyCode::Enter => {
+            KeyCode::Enter => {
+                // `file:line` (ELF with DWARF info): toggle a breakpoint on that line
+                if let Some((_, addrs)) = app.source_line_addrs(&app.run.imem_search_query) {
+                    if addrs.iter().all(|a| app.run.breakpoints.contains(a)) {
+                        addrs.iter().for_each(|a| { app.run.breakpoints.remove(a); });
+                    } else {
+                        app.run.breakpoints.extend(addrs);
+                    }
+                }
+                app.run.imem_search_open = false;
+                app.run.imem_search_query.clear();
+            }
+            KeyCode::Esc => {
                 app.run.imem_search_open = false;
                 app.run.imem_search_query.clear();
             }
@@ -333,11 +428,8 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                     .add_filter("Falcon ASM", &["fas", "asm"])
                     .pick_file()
                 {
-                    if let Ok(content) = std::fs::read_to_string(path) {
-                        app.editor.buf.lines = content.lines().map(|s| s.to_string()).collect();
-                        app.editor.buf.cursor_row = 0;
-                        app.editor.buf.cursor_col = 0;
-                        app.assemble_and_load();
+                    if let Ok(content) = std::fs::read_to_string(&path) {
+                        app.open_source(path, &content);
                     }
                 } else {
                     open_path_input(app, PathInputAction::OpenFas);
@@ -345,12 +437,15 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 return Ok(false);
             }
             if ctrl && matches!(key.code, KeyCode::Char('s')) {
+                if app.save_shown_include() {
+                    return Ok(false);
+                }
                 if let Some(path) = OSFileDialog::new()
                     .add_filter("Falcon ASM", &["fas", "asm"])
                     .set_file_name("program.fas")
                     .save_file()
                 {
-                    let _ = std::fs::write(path, app.editor.buf.text());
+                    app.save_source(path);
                 } else {
                     open_path_input(app, PathInputAction::SaveFas);
                 }
@@ -387,6 +482,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
                 return Ok(false);
@@ -399,6 +495,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
                 return Ok(false);
@@ -415,6 +512,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                     app.editor.diag_line = None;
                     app.editor.diag_msg = None;
                     app.editor.diag_line_text = None;
+                    app.editor.diagnostics.clear();
                     app.editor.last_compile_ok = None;
                     app.editor.last_assemble_msg = None;
                 }
@@ -475,18 +573,36 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
             }
 
 
-            // F12: go to label definition
+            // F12: go to label definition (or open the file of an `.include` line)
             if key.code == KeyCode::F(12) && matches!(app.tab, Tab::Editor) {
                 app.goto_label_definition();
                 return Ok(false);
             }
 
+            // Alt+Left: back to the file F12 came from
+            if key.code == KeyCode::Left && key.modifiers.contains(KeyModifiers::ALT) && matches!(app.tab, Tab::Editor) {
+                app.include_go_back();
+                return Ok(false);
+            }
+
             // F2: toggle address hints gutter
             if key.code == KeyCode::F(2) && matches!(app.tab, Tab::Editor) {
                 app.editor.show_addr_hints = !app.editor.show_addr_hints;
                 return Ok(false);
             }
 
+            // F3: toggle coverage gutter markers
+            if key.code == KeyCode::F(3) && matches!(app.tab, Tab::Editor) {
+                app.editor.show_coverage = !app.editor.show_coverage;
+                return Ok(false);
+            }
+
+            // F8: jump to next assembler problem
+            if key.code == KeyCode::F(8) && matches!(app.tab, Tab::Editor) {
+                app.goto_next_problem();
+                return Ok(false);
+            }
+
             // Ctrl+/: toggle line comment
             if ctrl && matches!(key.code, KeyCode::Char('/')) && matches!(app.tab, Tab::Editor) {
                 app.editor.buf.toggle_comment();
@@ -495,6 +611,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
                 return Ok(false);
@@ -564,6 +681,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
             }
@@ -616,6 +734,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
                 return Ok(false);
@@ -628,6 +747,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 app.editor.diag_line = None;
                 app.editor.diag_msg = None;
                 app.editor.diag_line_text = None;
+                app.editor.diagnostics.clear();
                 app.editor.last_compile_ok = None;
                 app.editor.last_assemble_msg = None;
                 return Ok(false);
@@ -644,6 +764,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                     app.editor.diag_line = None;
                     app.editor.diag_msg = None;
                     app.editor.diag_line_text = None;
+                    app.editor.diagnostics.clear();
                     app.editor.last_compile_ok = None;
                     app.editor.last_assemble_msg = None;
                 }
@@ -655,11 +776,8 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                     .add_filter("Falcon ASM", &["fas"])
                     .pick_file()
                 {
-                    if let Ok(content) = std::fs::read_to_string(path) {
-                        app.editor.buf.lines = content.lines().map(|s| s.to_string()).collect();
-                        app.editor.buf.cursor_row = 0;
-                        app.editor.buf.cursor_col = 0;
-                        app.assemble_and_load();
+                    if let Ok(content) = std::fs::read_to_string(&path) {
+                        app.open_source(path, &content);
                     }
                 } else {
                     open_path_input(app, PathInputAction::OpenFas);
@@ -667,12 +785,15 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 return Ok(false);
             }
             if ctrl && matches!(key.code, KeyCode::Char('s')) {
+                if app.save_shown_include() {
+                    return Ok(false);
+                }
                 if let Some(path) = OSFileDialog::new()
                     .add_filter("Falcon ASM", &["fas"])
                     .set_file_name("program.fas")
                     .save_file()
                 {
-                    let _ = std::fs::write(path, app.editor.buf.text());
+                    app.save_source(path);
                 } else {
                     open_path_input(app, PathInputAction::SaveFas);
                 }
@@ -768,6 +889,27 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                         app.single_step();
                     }
                 }
+                // S: step to the next source line (ELF with DWARF line info)
+                (KeyCode::Char('S'), Tab::Run) if !app.run.faulted => app.step_source_line(),
+                // F: browse the guest's files
+                (KeyCode::Char('F'), Tab::Run) => {
+                    app.run.files_open = true;
+                    app.run.files_selected = 0;
+                }
+                // T: syscall trace
+                (KeyCode::Char('T'), Tab::Run) => {
+                    app.run.syscalls_open = true;
+                    app.run.syscalls_scroll = 0;
+                }
+                // B: cycle the syscall ABI (Linux → RAVEN → RARS); applies from the next ecall
+                (KeyCode::Char('B'), Tab::Run) => {
+                    app.run.guest_abi = app.run.guest_abi.next();
+                    app.run.cpu.abi = app.run.guest_abi;
+                }
+                // A: edit the program's arguments and environment
+                (KeyCode::Char('A'), Tab::Run) => {
+                    app.run.args_prompt = Some(app.run.program_args.to_string());
+                }
                 (KeyCode::Char('r'), Tab::Run) => {
                     if !app.run.faulted {
                         app.run.is_running = true;
@@ -808,30 +950,10 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 (KeyCode::Char('y'), Tab::Run) => {
                     app.run.show_instr_type = !app.run.show_instr_type;
                 }
-                // k: cycle memory region DATA → STACK → R/W → HEAP → DATA (only in pure RAM mode)
+                // k: cycle memory region DATA → STACK → R/W → HEAP → MMAP… → DATA (only in pure RAM mode)
                 (KeyCode::Char('k'), Tab::Run)
                     if !app.run.show_registers && !app.run.show_dyn => {
-                    match app.run.mem_region {
-                        MemRegion::Data | MemRegion::Custom => {
-                            app.run.mem_region = MemRegion::Stack;
-                            let sp = app.run.cpu.x[2];
-                            app.run.mem_view_addr = sp & !(app.run.mem_view_bytes - 1);
-                        }
-                        MemRegion::Stack => {
-                            app.run.mem_region = MemRegion::Access;
-                        }
-                        MemRegion::Access => {
-                            app.run.mem_region = MemRegion::Heap;
-                            let hb = app.run.cpu.heap_break;
-                            app.run.mem_view_addr = hb & !(app.run.mem_view_bytes - 1);
-                        }
-                        MemRegion::Heap => {
-                            app.run.mem_region = MemRegion::Data;
-                            app.run.mem_view_addr = app.run.data_base;
-                        }
-                    }
-                    app.run.show_registers = false;
-                    app.run.show_dyn = false;
+                    app.cycle_mem_region();
                 }
                 // P (shift+p): pin/unpin the currently selected register
                 (KeyCode::Char('P'), Tab::Run) if app.run.show_registers => {
@@ -1104,27 +1226,7 @@ pub fn handle_key(app: &mut App, key: KeyEvent) -> io::Result<bool> {
                 (KeyCode::Char('k'), Tab::Cache)
                     if !matches!(app.cache.subtab, CacheSubtab::Config)
                     && !app.run.show_registers && !app.run.show_dyn => {
-                    match app.run.mem_region {
-                        MemRegion::Data | MemRegion::Custom => {
-                            app.run.mem_region = MemRegion::Stack;
-                            let sp = app.run.cpu.x[2];
-                            app.run.mem_view_addr = sp & !(app.run.mem_view_bytes - 1);
-                        }
-                        MemRegion::Stack => {
-                            app.run.mem_region = MemRegion::Access;
-                        }
-                        MemRegion::Access => {
-                            app.run.mem_region = MemRegion::Heap;
-                            let hb = app.run.cpu.heap_break;
-                            app.run.mem_view_addr = hb & !(app.run.mem_view_bytes - 1);
-                        }
-                        MemRegion::Heap => {
-                            app.run.mem_region = MemRegion::Data;
-                            app.run.mem_view_addr = app.run.data_base;
-                        }
-                    }
-                    app.run.show_registers = false;
-                    app.run.show_dyn = false;
+                    app.cycle_mem_region();
                 }
                 (KeyCode::Char('e'), Tab::Cache) if !matches!(app.cache.subtab, CacheSubtab::Config) => {
                     app.run.show_exec_count = !app.run.show_exec_count;
@@ -1205,6 +1307,7 @@ pub fn paste_from_terminal(app: &mut App, text: &str) {
     app.editor.diag_line = None;
     app.editor.diag_msg = None;
     app.editor.diag_line_text = None;
+    app.editor.diagnostics.clear();
     app.editor.last_compile_ok = None;
     app.editor.last_assemble_msg = None;
 }
@@ -1226,7 +1329,7 @@ fn clamp_docs_scroll_keyboard(app: &mut App) {
                 let q = app.docs.search_query.clone();
                 docs_body_line_count(80, &q, app.docs.type_filter).saturating_sub(vp)
             }
-            p => free_page_line_count(p, app.docs.lang)
+            p => free_page_line_count(p, app)
                     .saturating_sub(viewport_h.saturating_sub(2)),
         };
         if app.docs.scroll > total { app.docs.scroll = total; }
@@ -1682,6 +1785,10 @@ fn parse_results_snapshot(text: &str) -> Result<CacheResultsSnapshot, String> {
 }
 
 fn apply_imem_search(app: &mut App) {
+    if let Some((_, addrs)) = app.source_line_addrs(&app.run.imem_search_query) {
+        app.scroll_imem_to_addr(addrs[0]);
+        return;
+    }
     let q = app.run.imem_search_query.trim().to_lowercase();
     if q.is_empty() { return; }
     let mut matches: Vec<u32> = app.run.labels.iter()
@@ -1735,6 +1842,62 @@ fn refresh_path_completions(input: &mut PathInput) {
     input.completion_sel = 0;
 }
 
+/// Write the assembled program to `path`: an ELF32 executable when the name ends in
+/// `.elf`, an assembly listing for `.lst`, otherwise the `FALC` container.
+pub(super) fn export_binary(app: &mut App, path: &std::path::Path) {
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("lst")) {
+        match app.main_listing() {
+            Ok(lst) => match std::fs::write(path, lst) {
+                Ok(()) => app.console.push_line(format!("Exported listing to {}", path.display())),
+                Err(e) => app.console.push_error(format!("Cannot export: {e}")),
+            },
+            Err(e) => app.console.push_error(format!("Cannot export: assemble error at {e}")),
+        }
+        return;
+    }
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("elf")) {
+        // Re-assembled at the usual executable address rather than the editor's base.
+        let base = crate::falcon::program::ELF_TEXT_BASE;
+        match app.assemble_main_at(base) {
+            Ok(p) => match std::fs::write(path, crate::falcon::program::write_elf(&p, base, base)) {
+                Ok(()) => app.console.push_line(format!("Exported ELF to {}", path.display())),
+                Err(e) => app.console.push_error(format!("Cannot export: {e}")),
+            },
+            Err(e) => app.console.push_error(format!("Cannot export: assemble error at {e}")),
+        }
+        return;
+    }
+    // Use cached result when available; otherwise re-assemble.
+    let (words, data, bss_size) = match (
+        app.editor.last_ok_text.as_ref(),
+        app.editor.last_ok_data.as_ref(),
+        app.editor.last_ok_bss_size,
+    ) {
+        (Some(t), Some(d), bss) => (t.clone(), d.clone(), bss.unwrap_or(0)),
+        _ => match app.assemble_main() {
+            Ok(p) => (p.text, p.data, p.bss_size),
+            Err(e) => {
+                app.console.push_error(format!("Cannot export: assemble error at {e}"));
+                return;
+            }
+        },
+    };
+    // FALC format: "FALC" + text_size(u32LE) + data_size(u32LE) + bss_size(u32LE)
+    //              + text_bytes + data_bytes
+    // BSS is NOT stored — loader zero-initialises it at runtime.
+    let text_bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let text_size = text_bytes.len() as u32;
+    let data_size = data.len() as u32;
+    let mut bytes: Vec<u8> = Vec::with_capacity(16 + text_bytes.len() + data.len());
+    bytes.extend_from_slice(b"FALC");
+    bytes.extend_from_slice(&text_size.to_le_bytes());
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    bytes.extend_from_slice(&bss_size.to_le_bytes());
+    bytes.extend_from_slice(&text_bytes);
+    bytes.extend_from_slice(&data);
+    let _ = std::fs::write(path, bytes);
+}
+
 pub(super) fn open_path_input(app: &mut App, action: PathInputAction) {
     app.path_input.action = action;
     app.path_input.open = true;
@@ -1748,18 +1911,15 @@ fn dispatch_path_input(app: &mut App, action: PathInputAction, path: std::path::
     match action {
         PathInputAction::OpenFas => {
             if let Ok(content) = std::fs::read_to_string(&path) {
-                app.editor.buf.lines = content.lines().map(|s| s.to_string()).collect();
-                app.editor.buf.cursor_row = 0;
-                app.editor.buf.cursor_col = 0;
-                app.assemble_and_load();
+                app.open_source(path, &content);
             }
         }
         PathInputAction::SaveFas => {
-            let _ = std::fs::write(&path, app.editor.buf.text());
+            app.save_source(path);
         }
         PathInputAction::OpenBin => {
             if let Ok(bytes) = std::fs::read(&path) {
-                app.load_binary(&bytes);
+                app.load_binary(&bytes, &path.file_name().unwrap_or_default().to_string_lossy());
                 use crate::ui::view::disasm::disasm_word;
                 let lines: Vec<String> = if let Some(ref words) = app.editor.last_ok_text {
                     words.iter().map(|&w| disasm_word(w)).collect()
@@ -1776,31 +1936,7 @@ fn dispatch_path_input(app: &mut App, action: PathInputAction, path: std::path::
             }
         }
         PathInputAction::SaveBin => {
-            let (words, data, bss_size) = match (
-                app.editor.last_ok_text.as_ref(),
-                app.editor.last_ok_data.as_ref(),
-                app.editor.last_ok_bss_size,
-            ) {
-                (Some(t), Some(d), bss) => (t.clone(), d.clone(), bss.unwrap_or(0)),
-                _ => match crate::falcon::asm::assemble(&app.editor.buf.text(), app.run.base_pc) {
-                    Ok(p) => (p.text, p.data, p.bss_size),
-                    Err(e) => {
-                        app.console.push_error(format!("Cannot export: assemble error at line {}: {}", e.line + 1, e.msg));
-                        return;
-                    }
-                },
-            };
-            let text_bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
-            let text_size = text_bytes.len() as u32;
-            let data_size = data.len() as u32;
-            let mut bytes: Vec<u8> = Vec::with_capacity(16 + text_bytes.len() + data.len());
-            bytes.extend_from_slice(b"FALC");
-            bytes.extend_from_slice(&text_size.to_le_bytes());
-            bytes.extend_from_slice(&data_size.to_le_bytes());
-            bytes.extend_from_slice(&bss_size.to_le_bytes());
-            bytes.extend_from_slice(&text_bytes);
-            bytes.extend_from_slice(&data);
-            let _ = std::fs::write(&path, bytes);
+            export_binary(app, &path);
         }
         PathInputAction::OpenFcache => {
             match std::fs::read_to_string(&path) {
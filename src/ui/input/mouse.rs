@@ -31,6 +31,11 @@ pub fn handle_mouse(app: &mut App, me: MouseEvent, area: Rect) {
         return;
     }
 
+    // The guest file browser is keyboard-driven; keep clicks off the panels behind it
+    if (app.run.files_open || app.run.syscalls_open) && matches!(app.tab, Tab::Run) {
+        return;
+    }
+
     // Hover tabs — derived from Tab::all() so new tabs are automatically supported
     app.hover_tab = None;
     app.hover_run_button = None;
@@ -371,27 +376,7 @@ fn apply_run_button(app: &mut App, btn: RunButton) {
             }
         }
         RunButton::Region => {
-            match app.run.mem_region {
-                MemRegion::Data | MemRegion::Custom => {
-                    let sp = app.run.cpu.x[2];
-                    app.run.mem_view_addr = sp & !(app.run.mem_view_bytes - 1);
-                    app.run.mem_region = MemRegion::Stack;
-                }
-                MemRegion::Stack => {
-                    app.run.mem_region = MemRegion::Access;
-                }
-                MemRegion::Access => {
-                    let hb = app.run.cpu.heap_break;
-                    app.run.mem_view_addr = hb & !(app.run.mem_view_bytes - 1);
-                    app.run.mem_region = MemRegion::Heap;
-                }
-                MemRegion::Heap => {
-                    app.run.mem_view_addr = app.run.data_base;
-                    app.run.mem_region = MemRegion::Data;
-                }
-            }
-            app.run.show_registers = false;
-            app.run.show_dyn = false;
+            app.cycle_mem_region();
         }
         RunButton::Speed => {
             app.run.speed = app.run.speed.cycle();
@@ -510,6 +495,7 @@ fn run_status_hit(app: &App, status: Rect, col: u16) -> Option<RunButton> {
         MemRegion::Stack => "STACK",
         MemRegion::Access => "R/W",
         MemRegion::Heap => "HEAP",
+        MemRegion::Mmap(_) => "MMAP",
     };
     let run_text = if app.run.is_running { "RUN" } else { "PAUSE" };
 
@@ -827,7 +813,7 @@ fn clamp_docs_scroll(app: &mut App, area: Rect) {
             // header(2) consumed by render_free_page
             let viewport_h = docs_area.height.saturating_sub(2) as usize;
             if viewport_h == 0 { app.docs.scroll = 0; return; }
-            free_page_line_count(p, app.docs.lang).saturating_sub(viewport_h)
+            free_page_line_count(p, app).saturating_sub(viewport_h)
         }
     };
     if app.docs.scroll > max_start {
@@ -865,8 +851,8 @@ fn handle_editor_status_click(app: &mut App, me: MouseEvent, status_area: Rect)
             .add_filter("All Files", &["*"])
             .pick_file()
         {
-            if let Ok(bytes) = std::fs::read(path) {
-                app.load_binary(&bytes);
+            if let Ok(bytes) = std::fs::read(&path) {
+                app.load_binary(&bytes, &path.file_name().unwrap_or_default().to_string_lossy());
                 // Build editor disassembly from the already-decoded text words (ELF text
                 // segment or FALC/flat text section), not from raw file bytes.
                 use crate::ui::view::disasm::disasm_word;
@@ -893,11 +879,8 @@ fn handle_editor_status_click(app: &mut App, me: MouseEvent, status_area: Rect)
             .add_filter("Falcon ASM", &["fas", "asm"])
             .pick_file()
         {
-            if let Ok(content) = std::fs::read_to_string(path) {
-                app.editor.buf.lines = content.lines().map(|s| s.to_string()).collect();
-                app.editor.buf.cursor_row = 0;
-                app.editor.buf.cursor_col = 0;
-                app.assemble_and_load();
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                app.open_source(path, &content);
             }
         } else {
             super::keyboard::open_path_input(app, PathInputAction::OpenFas);
@@ -907,50 +890,27 @@ fn handle_editor_status_click(app: &mut App, me: MouseEvent, status_area: Rect)
     if col >= ebin_start && col < ebin_end {
         if let Some(path) = OSFileDialog::new()
             .add_filter("Binary", &["bin"])
+            .add_filter("ELF executable", &["elf"])
+            .add_filter("Listing", &["lst"])
             .set_file_name("program.bin")
             .save_file()
         {
-            // Use cached result when available; otherwise re-assemble.
-            let (words, data, bss_size) = match (
-                app.editor.last_ok_text.as_ref(),
-                app.editor.last_ok_data.as_ref(),
-                app.editor.last_ok_bss_size,
-            ) {
-                (Some(t), Some(d), bss) => (t.clone(), d.clone(), bss.unwrap_or(0)),
-                _ => match crate::falcon::asm::assemble(&app.editor.buf.text(), app.run.base_pc) {
-                    Ok(p) => (p.text, p.data, p.bss_size),
-                    Err(e) => {
-                        app.console.push_error(format!("Cannot export: assemble error at line {}: {}", e.line + 1, e.msg));
-                        return;
-                    }
-                },
-            };
-            // FALC format: "FALC" + text_size(u32LE) + data_size(u32LE) + bss_size(u32LE)
-            //              + text_bytes + data_bytes
-            // BSS is NOT stored — loader zero-initialises it at runtime.
-            let text_bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
-            let text_size = text_bytes.len() as u32;
-            let data_size = data.len() as u32;
-            let mut bytes: Vec<u8> = Vec::with_capacity(16 + text_bytes.len() + data.len());
-            bytes.extend_from_slice(b"FALC");
-            bytes.extend_from_slice(&text_size.to_le_bytes());
-            bytes.extend_from_slice(&data_size.to_le_bytes());
-            bytes.extend_from_slice(&bss_size.to_le_bytes());
-            bytes.extend_from_slice(&text_bytes);
-            bytes.extend_from_slice(&data);
-            let _ = std::fs::write(path, bytes);
+            super::keyboard::export_binary(app, &path);
         } else {
             super::keyboard::open_path_input(app, PathInputAction::SaveBin);
         }
         return;
     }
     if col >= ecode_start && col < ecode_end {
+        if app.save_shown_include() {
+            return;
+        }
         if let Some(path) = OSFileDialog::new()
             .add_filter("Falcon ASM", &["fas", "asm"])
             .set_file_name("program.fas")
             .save_file()
         {
-            let _ = std::fs::write(path, app.editor.buf.text());
+            app.save_source(path);
         } else {
             super::keyboard::open_path_input(app, PathInputAction::SaveFas);
         }
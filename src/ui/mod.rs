@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 mod app;
+mod gdb;
 pub mod editor;
 mod input;
 pub mod theme;
@@ -197,7 +197,7 @@ fn fmt3f(m: &str, rd: u8, rs1: u8, rs2: u8) -> String {
 fn fmt4f(m: &str, rd: u8, rs1: u8, rs2: u8, rs3: u8) -> String {
     format!("{m:<9} {}, {}, {}, {}", freg_name(rd), freg_name(rs1), freg_name(rs2), freg_name(rs3))
 }
-fn freg_name(i: u8) -> &'static str {
+pub fn freg_name(i: u8) -> &'static str {
     match i {
         0  => "ft0",  1  => "ft1",  2  => "ft2",  3  => "ft3",
         4  => "ft4",  5  => "ft5",  6  => "ft6",  7  => "ft7",
@@ -211,7 +211,7 @@ fn freg_name(i: u8) -> &'static str {
         _  => "f?",
     }
 }
-fn reg_name(i: u8) -> &'static str {
+pub fn reg_name(i: u8) -> &'static str {
     match i {
         0 => "zero",
         1 => "ra",
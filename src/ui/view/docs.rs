@@ -56,12 +56,12 @@ const SHOW_EXP_MIN_W: usize = 95;
 // ── Instruction table data ─────────────────────────────────────────────────────
 
 #[derive(Clone, Copy)]
-struct DocRow {
-    ty:       &'static str,
-    mnemonic: &'static str,
-    operands: &'static str,
-    desc:     &'static str,
-    expands:  &'static str,
+pub(crate) struct DocRow {
+    pub(crate) ty:       &'static str,
+    pub(crate) mnemonic: &'static str,
+    pub(crate) operands: &'static str,
+    pub(crate) desc:     &'static str,
+    pub(crate) expands:  &'static str,
 }
 
 macro_rules! row {
@@ -227,8 +227,34 @@ const DOCS: &[DocRow] = &[
     row!("Dir", ".align",   "n",            "Align PC to 2^n byte boundary"),
     row!("Dir", ".globl",   "sym",          "Mark symbol as global / exported"),
     row!("Dir", ".equ",     "sym, val",     "Define symbolic constant (equate)"),
+    row!("Dir", ".include", "\"file\"",     "Assemble another source file here"),
+    row!("Dir", ".macro",   "name a, b=1",  "Start macro; body uses \\a, \\@ (unique id)"),
+    row!("Dir", ".endm",    "",             "End of .macro body"),
+    row!("Dir", ".if",      "expr",         "Assemble block if expr != 0 (.ifeq/.ifne/.ifgt/...)"),
+    row!("Dir", ".ifdef",   "sym",          "Assemble block if sym is already defined"),
+    row!("Dir", ".ifndef",  "sym",          "Assemble block if sym is not yet defined"),
+    row!("Dir", ".else",    "",             "Alternative branch of .if (.elseif expr too)"),
+    row!("Dir", ".endif",   "",             "End of .if block"),
+    row!("Dir", ".rept",    "count",        "Repeat block count times"),
+    row!("Dir", ".irp",     "sym, a, b",    "Repeat block once per value, \\sym = value"),
+    row!("Dir", ".endr",    "",             "End of .rept / .irp block"),
+    row!("Dir", "%hi",      "(sym)",        "Upper 20 bits of sym, for lui (pairs with %lo)"),
+    row!("Dir", "%lo",      "(sym)",        "Low 12 bits of sym, for addi / lw %lo(sym)(rs)"),
+    row!("Dir", "%pcrel_hi","(sym)",        "PC-relative upper 20 bits of sym, for auipc"),
+    row!("Dir", "%pcrel_lo","(label)",      "Low 12 bits for the auipc at label"),
 ];
 
+/// All reference rows (instructions, pseudos, directives); also used by the language server.
+pub(crate) fn doc_rows() -> &'static [DocRow] {
+    DOCS
+}
+
+/// Reference row for one mnemonic or directive. Rows like `bez/beqz` match either spelling.
+pub(crate) fn lookup_doc(mnemonic: &str) -> Option<&'static DocRow> {
+    let m = mnemonic.to_ascii_lowercase();
+    DOCS.iter().find(|r| r.mnemonic.split('/').any(|alias| alias == m))
+}
+
 // ── Filtering ──────────────────────────────────────────────────────────────────
 
 fn ty_bit(ty: &str) -> u16 {
@@ -521,7 +547,7 @@ pub(super) fn render_docs(f: &mut Frame, area: Rect, app: &App) {
     match app.docs.page {
         DocsPage::InstrRef   => render_instr_ref(f, area, app),
         DocsPage::Syscalls   => render_free_page(f, area, app, syscall_lines(app.docs.lang)),
-        DocsPage::MemoryMap  => render_free_page(f, area, app, memory_map_lines(app.docs.lang)),
+        DocsPage::MemoryMap  => render_free_page(f, area, app, memory_map_page(app)),
     }
 }
 
@@ -720,11 +746,11 @@ fn render_free_page(f: &mut Frame, area: Rect, app: &App, lines: Vec<Line<'stati
 }
 
 /// Total scrollable line count for free-form pages (used by clamp helpers).
-pub(crate) fn free_page_line_count(page: DocsPage, lang: DocsLang) -> usize {
+pub(crate) fn free_page_line_count(page: DocsPage, app: &App) -> usize {
     match page {
         DocsPage::InstrRef   => 0,
-        DocsPage::Syscalls   => syscall_lines(lang).len(),
-        DocsPage::MemoryMap  => memory_map_lines(lang).len(),
+        DocsPage::Syscalls   => syscall_lines(app.docs.lang).len(),
+        DocsPage::MemoryMap  => memory_map_page(app).len(),
     }
 }
 
@@ -796,13 +822,33 @@ fn syscall_lines_en() -> Vec<Line<'static>> {
         blank(),
         thead(),
         tsep(),
-        trow("63",  "read",         "fd=a0, buf=a1, n=a2",   "bytes read",  "fd=0 (stdin) only; blocks until line ready"),
-        trow("64",  "write",        "fd=a0, buf=a1, n=a2",   "bytes written","fd=1 (stdout) or 2 (stderr)"),
+        trow("35",  "unlinkat",     "dir=a0, path=a1, fl=a2","0",           "removes a guest file (dir = -100, AT_FDCWD)"),
+        trow("56",  "openat",       "dir=a0, path=a1, fl=a2","fd",          "opens a guest file; O_CREAT/O_TRUNC/O_APPEND"),
+        trow("57",  "close",        "fd=a0",                 "0",           "frees the descriptor"),
+        trow("62",  "lseek",        "fd=a0, off=a1, wh=a2",  "offset",      "whence 0=set 1=cur 2=end"),
+        trow("63",  "read",         "fd=a0, buf=a1, n=a2",   "bytes read",  "fd=0 (stdin) blocks until line ready; or a file"),
+        trow("64",  "write",        "fd=a0, buf=a1, n=a2",   "bytes written","fd=1 (stdout), 2 (stderr) or a file"),
+        trow("80",  "fstat",        "fd=a0, stat=a1",        "0",           "128-byte RISC-V struct stat (st_size at +48)"),
         trow("93",  "exit",         "code=a0",               "—",           "halts execution; sets exit code"),
         trow("94",  "exit_group",   "code=a0",               "—",           "alias of exit (93)"),
+        trow("129", "kill",         "pid=a0, sig=a1",        "—",           "pid 1: stops with exit code 128+sig (abort)"),
+        trow("101", "nanosleep",    "req=a0, rem=a1",        "0",           "moves the clock forward; never waits"),
+        trow("113", "clock_gettime","clk=a0, ts=a1",         "0",           "i32 tv_sec, i32 tv_nsec (403: both i64)"),
+        trow("169", "gettimeofday", "tv=a0, tz=a1",          "0",           "i64 tv_sec, i32 tv_usec"),
+        trow("172", "getpid",       "—",                     "1",           "the program is pid 1"),
+        trow("214", "brk",          "addr=a0",               "break",       "0 queries; stops at mmap regions and the stack"),
+        trow("215", "munmap",       "addr=a0, len=a1",       "0",           "frees mmap pages, splitting regions"),
+        trow("222", "mmap",         "addr, len, prot, flags","addr",        "MAP_ANONYMOUS only; zeroed pages below the stack"),
+        trow("226", "mprotect",     "addr=a0, len=a1, pr=a2","0",           "records permissions (not enforced)"),
         trow("278", "getrandom",    "buf=a0, len=a1, flags=a2","len",        "fills buf with cryptographic random bytes"),
         blank(),
         note("Supported getrandom flags: GRND_NONBLOCK (0x1), GRND_RANDOM (0x2)."),
+        note("Files live in a sandboxed in-memory filesystem: F on the Run tab browses it, raven run --fs <dir> fills it."),
+        note("Time is virtual: cycles run at 10 MHz (--clock <freq>) plus sleeps; --host-time uses the host clock."),
+        note("newlib also gets fstatat (79), faccessat (48), clock_gettime64 (403), open (1024), unlink (1026), access (1033), stat (1038)."),
+        note("T on the Run tab traces every ecall with its arguments and result; raven run --strace <file> saves it."),
+        note("B on the Run tab (or --syscalls rars) switches to RARS numbering: 1 PrintInt, 4 PrintString, 5 ReadInt, 9 Sbrk, 10 Exit..."),
+        note("Semihosting: slli x0,x0,0x1f; ebreak; srai x0,x0,7 runs op a0 (0x04 WRITE0, 0x06 READ, 0x15 GET_CMDLINE, 0x18 EXIT...) with a1."),
         blank(),
 
         // ── RAVEN extensions ──
@@ -848,13 +894,33 @@ fn syscall_lines_ptbr() -> Vec<Line<'static>> {
         blank(),
         thead(),
         tsep(),
-        trow("63",  "read",         "fd=a0, buf=a1, n=a2",   "bytes lidos", "fd=0 (stdin); bloqueia até linha disponível"),
-        trow("64",  "write",        "fd=a0, buf=a1, n=a2",   "bytes escritos","fd=1 (stdout) ou 2 (stderr)"),
+        trow("35",  "unlinkat",     "dir=a0, path=a1, fl=a2","0",           "remove um arquivo (dir = -100, AT_FDCWD)"),
+        trow("56",  "openat",       "dir=a0, path=a1, fl=a2","fd",          "abre um arquivo; O_CREAT/O_TRUNC/O_APPEND"),
+        trow("57",  "close",        "fd=a0",                 "0",           "libera o descritor"),
+        trow("62",  "lseek",        "fd=a0, off=a1, wh=a2",  "deslocamento","whence 0=set 1=cur 2=end"),
+        trow("63",  "read",         "fd=a0, buf=a1, n=a2",   "bytes lidos", "fd=0 (stdin) bloqueia até linha disponível; ou um arquivo"),
+        trow("64",  "write",        "fd=a0, buf=a1, n=a2",   "bytes escritos","fd=1 (stdout), 2 (stderr) ou um arquivo"),
+        trow("80",  "fstat",        "fd=a0, stat=a1",        "0",           "struct stat RISC-V de 128 bytes (st_size em +48)"),
         trow("93",  "exit",         "code=a0",               "—",           "encerra execução; define código de saída"),
         trow("94",  "exit_group",   "code=a0",               "—",           "alias de exit (93)"),
+        trow("129", "kill",         "pid=a0, sig=a1",        "—",           "pid 1: encerra com código 128+sig (abort)"),
+        trow("101", "nanosleep",    "req=a0, rem=a1",        "0",           "adianta o relógio; nunca espera"),
+        trow("113", "clock_gettime","clk=a0, ts=a1",         "0",           "i32 tv_sec, i32 tv_nsec (403: ambos i64)"),
+        trow("169", "gettimeofday", "tv=a0, tz=a1",          "0",           "i64 tv_sec, i32 tv_usec"),
+        trow("172", "getpid",       "—",                     "1",           "o programa é o pid 1"),
+        trow("214", "brk",          "addr=a0",               "break",       "0 consulta; para nas regiões de mmap e na pilha"),
+        trow("215", "munmap",       "addr=a0, len=a1",       "0",           "libera páginas de mmap, dividindo regiões"),
+        trow("222", "mmap",         "addr, len, prot, flags","addr",        "só MAP_ANONYMOUS; páginas zeradas abaixo da pilha"),
+        trow("226", "mprotect",     "addr=a0, len=a1, pr=a2","0",           "registra permissões (não impostas)"),
         trow("278", "getrandom",    "buf=a0, len=a1, flags=a2","len",        "preenche buf com bytes aleatórios criptográficos"),
         blank(),
         note("Flags aceitas em getrandom: GRND_NONBLOCK (0x1), GRND_RANDOM (0x2)."),
+        note("Arquivos ficam num sistema de arquivos em memória: F na aba Run o navega, raven run --fs <dir> o preenche."),
+        note("O tempo é virtual: ciclos a 10 MHz (--clock <freq>) mais as pausas; --host-time usa o relógio do host."),
+        note("A newlib também usa fstatat (79), faccessat (48), clock_gettime64 (403), open (1024), unlink (1026), access (1033), stat (1038)."),
+        note("T na aba Run rastreia cada ecall com argumentos e resultado; raven run --strace <arquivo> o salva."),
+        note("B na aba Run (ou --syscalls rars) troca para a numeração do RARS: 1 PrintInt, 4 PrintString, 5 ReadInt, 9 Sbrk, 10 Exit..."),
+        note("Semihosting: slli x0,x0,0x1f; ebreak; srai x0,x0,7 executa a operação a0 (0x04 WRITE0, 0x06 READ, 0x15 GET_CMDLINE, 0x18 EXIT...) com a1."),
         blank(),
 
         // ── RAVEN extensions ──
@@ -889,6 +955,54 @@ fn syscall_lines_ptbr() -> Vec<Line<'static>> {
 
 // ── Memory map content ────────────────────────────────────────────────────────
 
+/// The reference text, preceded by the loaded program's break, mappings and stack reserve.
+fn memory_map_page(app: &App) -> Vec<Line<'static>> {
+    let lang = app.docs.lang;
+    let mut lines = memory_map_lines(lang);
+    let pt = lang == DocsLang::PtBr;
+    let cpu = &app.run.cpu;
+    let maps = &cpu.mmap;
+    let row = |key: &str, range: String, what: String| {
+        Line::from(vec![
+            Span::styled(format!("  {key:<8}"), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{range:<25}"), Style::default().fg(Color::Rgb(180, 180, 200))),
+            Span::styled(what, Style::default().fg(Color::White)),
+        ])
+    };
+    let mut live = vec![
+        h2(if pt { "Nesta execução" } else { "This run" }),
+        blank(),
+        row(
+            "brk",
+            format!("0x{:08x}", cpu.heap_break),
+            if pt {
+                format!("fim do heap; brk pode subir até 0x{:08x}", maps.brk_limit())
+            } else {
+                format!("heap end; brk may grow it up to 0x{:08x}", maps.brk_limit())
+            },
+        ),
+    ];
+    for m in maps.regions() {
+        live.push(row("mmap", format!("0x{:08x}-0x{:08x}", m.start, m.end()), format!("{}  {} KiB", m.prot_str(), m.len / 1024)));
+    }
+    live.push(row(
+        if pt { "pilha" } else { "stack" },
+        format!("0x{:08x}-0x{:08x}", maps.stack_floor(), app.run.mem_size as u64),
+        if pt { format!("reservada; sp = 0x{:08x}", cpu.x[2]) } else { format!("reserved; sp = 0x{:08x}", cpu.x[2]) },
+    ));
+    if maps.regions().is_empty() {
+        live.push(note(if pt {
+            "Nenhum mapeamento ainda: mmap (222) os cria de cima para baixo, abaixo da pilha."
+        } else {
+            "No mappings yet: mmap (222) hands them out top-down, below the stack."
+        }));
+    }
+    live.push(blank());
+    // After the title and the RAM note
+    lines.splice(4..4, live);
+    lines
+}
+
 fn memory_map_lines(lang: DocsLang) -> Vec<Line<'static>> {
     match lang {
         DocsLang::En  => memory_map_lines_en(),
@@ -909,11 +1023,13 @@ fn memory_map_lines_en() -> Vec<Line<'static>> {
         mono("  0x00000000  ┌─────────────────────┐"),
         mono("              │  .text  (code)       │  ← instructions start at base_pc (default 0x0)"),
         mono("              ├─────────────────────┤"),
-        mono("  0x00001000  │  .data              │  ← initialized data  (data_base = base_pc + 0x1000)"),
+        mono("  0x00001000  │  .data              │  ← initialized data  (data_base: next page after .text)"),
         mono("              │  .bss               │  ← zero-initialized; grows up after .data"),
         mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤"),
-        mono("              │   (free space)       │  ← no allocator; read/write freely with lw/sw"),
-        mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤"),
+        mono("              │  heap  (brk ↑)      │  ← program break: starts after .bss, moved up by brk (214)"),
+        mono("              │   (free space)       │"),
+        mono("              │  mmap  (↓)          │  ← anonymous mmap (222) regions, handed out top-down"),
+        mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤  ← stack floor: 1/16 of RAM is kept for the stack (16 KiB-1 MiB)"),
         mono("  0x0001FFFF  │  stack  (grows ↓)   │  ← sp = 0x20000 (one past end); push: sp-=4, sw rs,0(sp)"),
         mono("              └─────────────────────┘"),
         blank(),
@@ -930,17 +1046,20 @@ fn memory_map_lines_en() -> Vec<Line<'static>> {
         h2("Key addresses"),
         blank(),
         kv("base_pc",    "Start of .text. Configurable; default 0x00000000."),
-        kv("data_base",  "Start of .data / .bss = base_pc + 0x1000."),
+        kv("data_base",  "Start of .data / .bss: first page boundary after .text, at least base_pc + 0x1000."),
         kv("sp (initial)","0x00020000 — one past end of RAM (RISC-V ABI). First push writes to 0x1FFFC."),
         blank(),
 
         // ── Free space note ──
-        h2("Free space — no heap allocator"),
+        h2("Free space — brk and mmap"),
         blank(),
-        raw("  The region between bss_end and the stack is ordinary RAM with no"),
-        raw("  management. You can use it directly with sw/lw if you know the address."),
-        raw("  There is no malloc/free — RAVEN has a flat, fixed 128 KB address space"),
-        raw("  with no pagination or memory protection."),
+        raw("  The region between bss_end and the stack is ordinary RAM. brk (214) moves"),
+        raw("  the program break up through it — malloc in newlib does this — and"),
+        raw("  mmap (222) with MAP_ANONYMOUS carves page-aligned regions from its top;"),
+        raw("  munmap (215) returns them and mprotect (226) records new permissions."),
+        raw("  brk fails once it would reach a mapping or the stack reserve. There is"),
+        raw("  no MMU: permissions are shown but not enforced, and sw/lw still reach"),
+        raw("  any address."),
         blank(),
         note("Tip: use .bss labels to reserve named buffers without wasting binary space."),
         blank(),
@@ -992,11 +1111,13 @@ fn memory_map_lines_ptbr() -> Vec<Line<'static>> {
         mono("  0x00000000  ┌─────────────────────┐"),
         mono("              │  .text  (código)     │  ← instruções começam em base_pc (padrão 0x0)"),
         mono("              ├─────────────────────┤"),
-        mono("  0x00001000  │  .data              │  ← dados inicializados  (data_base = base_pc + 0x1000)"),
+        mono("  0x00001000  │  .data              │  ← dados inicializados  (data_base: próxima página após o .text)"),
         mono("              │  .bss               │  ← inicializada com zeros; cresce após .data"),
         mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤"),
-        mono("              │   (espaço livre)     │  ← sem alocador; leitura/escrita livre com lw/sw"),
-        mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤"),
+        mono("              │  heap  (brk ↑)      │  ← program break: começa após o .bss, sobe com brk (214)"),
+        mono("              │   (espaço livre)     │"),
+        mono("              │  mmap  (↓)          │  ← regiões de mmap (222) anônimo, entregues de cima para baixo"),
+        mono("              ├ ─ ─ ─ ─ ─ ─ ─ ─ ─ ┤  ← piso da pilha: 1/16 da RAM fica para a pilha (16 KiB-1 MiB)"),
         mono("  0x0001FFFF  │  pilha  (cresce ↓)  │  ← sp = 0x20000 (um além do fim); push: sp-=4, sw rs,0(sp)"),
         mono("              └─────────────────────┘"),
         blank(),
@@ -1013,17 +1134,20 @@ fn memory_map_lines_ptbr() -> Vec<Line<'static>> {
         h2("Endereços importantes"),
         blank(),
         kv("base_pc",    "Início do .text. Configurável; padrão 0x00000000."),
-        kv("data_base",  "Início do .data / .bss = base_pc + 0x1000."),
+        kv("data_base",  "Início do .data / .bss: primeira fronteira de página após o .text, no mínimo base_pc + 0x1000."),
         kv("sp (inicial)","0x00020000 — um além do fim da RAM (ABI RISC-V). Primeiro push escreve em 0x1FFFC."),
         blank(),
 
         // ── Espaço livre ──
-        h2("Espaço livre — sem alocador de heap"),
+        h2("Espaço livre — brk e mmap"),
         blank(),
-        raw("  A região entre o fim do .bss e a pilha é RAM comum, sem gerenciamento."),
-        raw("  Você pode usá-la diretamente com sw/lw se souber o endereço."),
-        raw("  Não existe malloc/free — o RAVEN tem um espaço de endereçamento"),
-        raw("  plano e fixo de 128 KB, sem paginação nem proteção de memória."),
+        raw("  A região entre o fim do .bss e a pilha é RAM comum. brk (214) sobe o"),
+        raw("  program break por ela — é o que o malloc da newlib faz — e mmap (222)"),
+        raw("  com MAP_ANONYMOUS recorta regiões alinhadas a página do topo dela;"),
+        raw("  munmap (215) as devolve e mprotect (226) registra novas permissões."),
+        raw("  brk falha quando alcançaria um mapeamento ou a reserva da pilha. Não há"),
+        raw("  MMU: as permissões são exibidas mas não impostas, e sw/lw ainda"),
+        raw("  alcançam qualquer endereço."),
         blank(),
         note("Dica: use labels no .bss para reservar buffers nomeados sem desperdiçar espaço no binário."),
         blank(),
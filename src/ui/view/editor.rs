@@ -5,6 +5,9 @@ use std::cmp::min;
 use std::collections::HashSet;
 
 use super::{App, Editor};
+use crate::ui::app::file_name;
+use crate::falcon::asm::Severity;
+use crate::falcon::coverage::Coverage;
 
 pub(super) fn render_editor_status(f: &mut Frame, area: Rect, app: &App) {
     let compile_span = if app.editor.last_ok_elf_bytes.is_some() {
@@ -26,11 +29,12 @@ pub(super) fn render_editor_status(f: &mut Frame, area: Rect, app: &App) {
     let ln = app.editor.buf.cursor_row + 1;
     let col = app.editor.buf.cursor_col + 1;
     let hints_label = if app.editor.show_addr_hints { " [addr]" } else { "" };
+    let cov_label = if app.editor.show_coverage { " [cov]" } else { "" };
     let build = Line::from(vec![
         Span::raw("Build status: "),
         compile_span,
         Span::styled(
-            format!("  Ln {ln}, Col {col}{hints_label}"),
+            format!("  Ln {ln}, Col {col}{hints_label}{cov_label}"),
             Style::default().fg(Color::DarkGray),
         ),
     ]);
@@ -157,6 +161,24 @@ pub(super) fn render_editor(f: &mut Frame, area: Rect, app: &App) {
     let show_hints = app.editor.show_addr_hints;
     let hint_w: usize = if show_hints { 11 } else { 0 }; // "0x00000000 " = 11 chars
 
+    // Coverage markers replace the gutter separator, so the gutter width is unchanged.
+    let coverage = if app.editor.show_coverage && app.editor.last_ok_elf_bytes.is_none() {
+        app.editor.last_ok_text.as_ref().map(|text| {
+            Coverage::compute(
+                &app.editor.line_to_addr,
+                &app.editor.other_file_addrs,
+                text,
+                app.run.base_pc,
+                &app.run.exec_counts,
+                &app.run.branch_counts,
+            )
+        })
+    } else {
+        None
+    };
+    let cov_hits = coverage.as_ref().map(|c| c.line_hits()).unwrap_or_default();
+    let cov_partial = coverage.as_ref().map(|c| c.partial_branch_lines()).unwrap_or_default();
+
     // Compute highlight_word: the identifier under the cursor, if it's a known label
     let highlight_word: Option<String> = {
         let row = app.editor.buf.cursor_row;
@@ -203,13 +225,16 @@ pub(super) fn render_editor(f: &mut Frame, area: Rect, app: &App) {
             apply_label_highlight(&mut line, line_str, hw);
         }
 
-        if Some(i) == app.editor.diag_line {
-            let err_style = Style::default()
-                .fg(Color::Red)
+        // Underline each problem's span on this line; errors last so they win on overlap
+        let shown = app.editor.shown_file();
+        let mut line_diags: Vec<_> =
+            app.editor.diagnostics.iter().filter(|d| d.line == i && d.file.as_deref() == shown).collect();
+        line_diags.sort_by_key(|d| std::cmp::Reverse(d.severity));
+        for d in line_diags {
+            let style = Style::default()
+                .fg(severity_color(d.severity))
                 .add_modifier(Modifier::UNDERLINED);
-            for span in &mut line.spans {
-                span.style = span.style.patch(err_style);
-            }
+            overlay_range(&mut line, d.cols.start, d.cols.end.max(d.cols.start + 1), style);
         }
 
         let mut spans = Vec::new();
@@ -229,12 +254,18 @@ pub(super) fn render_editor(f: &mut Frame, area: Rect, app: &App) {
             format!("{:>width$}", i + 1, width = num_width),
             Style::default().fg(Color::DarkGray),
         ));
-        let marker_style = if Some(i) == app.editor.diag_line {
-            Style::default().fg(Color::Red)
+        let worst = app.editor.diagnostics.iter().filter(|d| d.line == i && d.file.as_deref() == shown).map(|d| d.severity).min();
+        let (marker, marker_style) = if let Some(sev) = worst {
+            (severity_marker(sev), Style::default().fg(severity_color(sev)))
         } else {
-            Style::default().fg(Color::DarkGray)
+            match cov_hits.get(&i) {
+                Some(0) => (" ○ ", Style::default().fg(Color::Red)),
+                Some(_) if cov_partial.contains(&i) => (" ◐ ", Style::default().fg(Color::Yellow)),
+                Some(_) => (" ● ", Style::default().fg(Color::Green)),
+                None => (" │ ", Style::default().fg(Color::DarkGray)),
+            }
         };
-        spans.push(Span::styled(" │ ", marker_style));
+        spans.push(Span::styled(marker, marker_style));
         spans.extend(line.spans);
 
         if i == app.editor.buf.cursor_row {
@@ -261,6 +292,10 @@ pub(super) fn render_editor(f: &mut Frame, area: Rect, app: &App) {
         .border_style(Style::default().fg(Color::DarkGray))
         .border_type(BorderType::Rounded)
         .title("Editor (Risc-v ASM)");
+    if let Some(file) = app.editor.shown_file() {
+        let note = format!(" {} (included, Alt+← = back) ", file_name(file));
+        block = block.title(Span::styled(note, Style::default().fg(Color::Cyan)));
+    }
     if let Some(ok) = app.editor.last_compile_ok {
         let (txt, color) = if ok {
             ("[OK]", Color::Green)
@@ -1153,3 +1188,68 @@ fn is_reg_token(tok: &str) -> bool {
             | "a7"
     )
 }
+
+fn severity_color(sev: Severity) -> Color {
+    match sev {
+        Severity::Error => Color::Red,
+        Severity::Warning => Color::Yellow,
+    }
+}
+
+fn severity_marker(sev: Severity) -> &'static str {
+    match sev {
+        Severity::Error => " ✖ ",
+        Severity::Warning => " ▲ ",
+    }
+}
+
+/// Problems panel: one row per diagnostic from the last assembly, under the editor.
+pub(super) fn render_problems(f: &mut Frame, area: Rect, app: &App) {
+    let cursor_row = app.editor.buf.cursor_row;
+    let inner_h = area.height.saturating_sub(2) as usize;
+    let diags = &app.editor.diagnostics;
+    let shown = app.editor.shown_file();
+    // Keep the problem at (or after) the cursor line in view.
+    let focus = diags.iter().position(|d| d.file.as_deref() == shown && d.line >= cursor_row).unwrap_or(0);
+    let skip = focus.saturating_sub(inner_h.saturating_sub(1));
+    let rows: Vec<Line> = diags
+        .iter()
+        .enumerate()
+        .skip(skip)
+        .take(inner_h)
+        .map(|(n, d)| {
+            let style = if n == focus && d.line == cursor_row && d.file.as_deref() == shown {
+                Style::default().bg(Color::Rgb(40, 40, 55))
+            } else {
+                Style::default()
+            };
+            let kind = match d.severity {
+                Severity::Error => "error  ",
+                Severity::Warning => "warning",
+            };
+            let code = d.code.map(|c| format!("  [{c}]")).unwrap_or_default();
+            Line::from(vec![
+                Span::styled(format!("{}{kind} ", severity_marker(d.severity)), Style::default().fg(severity_color(d.severity))),
+                Span::styled(
+                    match &d.file {
+                        // Problems in other files (includes, or the main program) name the file
+                        Some(file) if d.file.as_deref() != shown => format!("{} {}:{}  ", file_name(file), d.line + 1, d.cols.start + 1),
+                        None if shown.is_some() => format!("main {}:{}  ", d.line + 1, d.cols.start + 1),
+                        _ => format!("Ln {}, Col {}  ", d.line + 1, d.cols.start + 1),
+                    },
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(d.msg.clone()),
+                Span::styled(code, Style::default().fg(Color::DarkGray)),
+            ])
+            .style(style)
+        })
+        .collect();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .border_type(BorderType::Rounded)
+        .title(format!("Problems ({})", diags.len()))
+        .title(Line::styled("F8 = next", Style::default().fg(Color::DarkGray)).right_aligned());
+    f.render_widget(Paragraph::new(rows).block(block), area);
+}
@@ -0,0 +1,87 @@
+use ratatui::Frame;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph};
+
+use crate::ui::theme;
+use crate::ui::app::App;
+
+/// The guest's files as the running program sees them, with a preview of the
+/// selected one.
+pub fn render_files(f: &mut Frame, area: Rect, app: &App) {
+    let popup_w = (area.width * 3 / 4).max(60).min(area.width.saturating_sub(4));
+    let popup_h = (area.height * 3 / 4).max(12).min(area.height.saturating_sub(2));
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(popup_w) / 2,
+        area.y + area.height.saturating_sub(popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+    f.render_widget(Clear, popup);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::ACCENT))
+        .border_type(BorderType::Rounded)
+        .title(Span::styled(" Guest Files ", Style::default().fg(theme::ACCENT).bold()));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    let fs = &app.run.cpu.fs;
+    let files: Vec<(&str, &[u8])> = fs.files().collect();
+    let selected = app.run.files_selected.min(files.len().saturating_sub(1));
+
+    let items: Vec<ListItem> = if files.is_empty() {
+        vec![ListItem::new(Span::styled(
+            "  No files. Press i to import one, or start raven with --fs <dir>.",
+            Style::default().fg(theme::IDLE),
+        ))]
+    } else {
+        // Keep the selection on screen
+        let height = chunks[0].height as usize;
+        let skip = (selected + 1).saturating_sub(height);
+        files.iter().enumerate().skip(skip).take(height).map(|(i, &(name, data))| {
+            let fds = fs.fds_of(name);
+            let open = if fds.is_empty() {
+                String::new()
+            } else {
+                format!("  open as fd {}", fds.iter().map(u32::to_string).collect::<Vec<_>>().join(", "))
+            };
+            let style = if i == selected {
+                Style::default().fg(theme::HOVER_FG).bg(theme::HOVER_BG)
+            } else {
+                Style::default().fg(theme::TEXT)
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("  {name:<32} {:>10} B", data.len()), style),
+                Span::styled(open, Style::default().fg(theme::RUNNING)),
+            ]))
+        }).collect()
+    };
+    f.render_widget(List::new(items), chunks[0]);
+
+    // Preview: text as is, control bytes as '.'
+    if let Some(&(_, data)) = files.get(selected) {
+        let height = chunks[1].height.saturating_sub(1) as usize;
+        let text = String::from_utf8_lossy(data);
+        let lines: Vec<Line> = text.lines().take(height).map(|l| {
+            let shown: String = l.chars().map(|c| if c.is_control() && c != '\t' { '.' } else { c }).collect();
+            Line::styled(format!("  {shown}"), Style::default().fg(theme::LABEL))
+        }).collect();
+        let preview = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::TOP).border_style(Style::default().fg(theme::BORDER)));
+        f.render_widget(preview, chunks[1]);
+    }
+
+    f.render_widget(
+        Paragraph::new(Line::styled(
+            "  ↑/↓=Select  i=Import  x=Export  d=Delete  Esc=Close   (restart resets to the imported files)",
+            Style::default().fg(theme::IDLE),
+        )),
+        chunks[2],
+    );
+}
@@ -16,13 +16,17 @@ pub mod disasm;
 mod cache;
 mod splash;
 mod path_input_overlay;
+mod files_overlay;
+mod syscalls_overlay;
 
 use docs::render_docs;
-use editor::{render_editor, render_editor_status};
+use editor::{render_editor, render_editor_status, render_problems};
 use run::render_run;
 use cache::render_cache;
 use splash::render_splash;
 use path_input_overlay::render_path_input;
+use files_overlay::render_files;
+use syscalls_overlay::render_syscalls;
 
 pub fn ui(f: &mut Frame, app: &App) {
     // Splash screen takes over the full frame
@@ -90,12 +94,20 @@ pub fn ui(f: &mut Frame, app: &App) {
 
     match app.tab {
         Tab::Editor => {
+            // Problems panel appears under the editor only while there are problems.
+            let problems_h = match app.editor.diagnostics.len() {
+                0 => 0,
+                n => (n as u16 + 2).min(8),
+            };
             let editor_chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Length(5), Constraint::Min(3)])
+                .constraints([Constraint::Length(5), Constraint::Min(3), Constraint::Length(problems_h)])
                 .split(chunks[1]);
             render_editor_status(f, editor_chunks[0], app);
             render_editor(f, editor_chunks[1], app);
+            if problems_h > 0 {
+                render_problems(f, editor_chunks[2], app);
+            }
         }
         Tab::Run => render_run(f, chunks[1], app),
         Tab::Cache => render_cache(f, chunks[1], app),
@@ -111,7 +123,7 @@ pub fn ui(f: &mut Frame, app: &App) {
             )
         }
         Tab::Run => (
-            "s=Step  r=Run  p=Pause  R=Restart  f=Speed  v=Sidebar  k=Region  Ctrl+F=Jump RAM  Ctrl+G=Label  [?]=Help".to_string(),
+            "s=Step  S=Line  r=Run  p=Pause  R=Restart  f=Speed  v=Sidebar  k=Region  Ctrl+F=Jump RAM  Ctrl+G=Label  [?]=Help".to_string(),
             Style::default().fg(theme::LABEL),
         ),
         Tab::Cache => {
@@ -146,6 +158,14 @@ pub fn ui(f: &mut Frame, app: &App) {
         render_elf_prompt(f, size, app);
     }
 
+    if app.run.files_open && matches!(app.tab, Tab::Run) {
+        render_files(f, size, app);
+    }
+
+    if app.run.syscalls_open && matches!(app.tab, Tab::Run) {
+        render_syscalls(f, size, app);
+    }
+
     render_path_input(f, size, app);
 }
 
@@ -317,17 +337,22 @@ fn help_pages(tab: Tab) -> Vec<Vec<HelpEntry>> {
         Tab::Run => vec![
             vec![
                 ("[s]",            "step one instruction"),
+                ("[S]",            "step one source line (ELF built with -g)"),
                 ("[r]",            "run / stop execution"),
                 ("[p]",            "pause"),
                 ("[R]",            "restart from beginning"),
+                ("[A]",            "set program arguments / NAME=value env, restart"),
+                ("[F]",            "guest files: browse, import, export, delete"),
+                ("[T]",            "syscall trace: every ecall, type to filter"),
+                ("[B]",            "cycle syscall ABI: Linux → RAVEN → RARS"),
                 ("[f]",            "cycle execution speed (1x → 2x → 4x → 8x → GO)"),
                 ("[v]",            "cycle sidebar: RAM → REGS → Dyn"),
-                ("[k]",            "cycle RAM region: DATA → STACK → R/W → HEAP"),
+                ("[k]",            "cycle RAM region: DATA → STACK → R/W → HEAP → MMAP…"),
                 ("[Tab] REGS",     "toggle integer / float register bank"),
                 ("[F9]",           "toggle breakpoint at hovered / PC"),
                 ("",               ""),
                 ("[Ctrl+F]",       "jump RAM view to address (type hex, live)"),
-                ("[Ctrl+G]",       "jump instruction view to label (type name, live)"),
+                ("[Ctrl+G]",       "jump to label or file:line (Enter toggles bp)"),
                 ("[t]",            "toggle instruction trace panel"),
                 ("[e]",            "toggle execution count display (×N)"),
                 ("[y]",            "toggle instruction type badge ([R],[I]…)"),
@@ -365,6 +390,10 @@ fn help_pages(tab: Tab) -> Vec<Vec<HelpEntry>> {
                 ("[Ctrl+G]",       "goto line number"),
                 ("[Ctrl+O]",       "import file"),
                 ("[Ctrl+S]",       "export / save file"),
+                ("[F3]",           "toggle coverage gutter (● hit  ◐ branch  ○ never run)"),
+                ("[F8]",           "jump to next problem"),
+                ("[F12]",          "go to definition / open .include file"),
+                ("[Alt+←]",        "back from an included file"),
                 ("",               ""),
                 ("[Ctrl+A]",       "select all"),
                 ("[Ctrl+C]",       "copy selection"),
@@ -378,7 +407,7 @@ fn help_pages(tab: Tab) -> Vec<Vec<HelpEntry>> {
                 ("[r]",            "reset statistics"),
                 ("[p]",            "pause / resume execution"),
                 ("[v]",            "cycle sidebar view: RAM → REGS → Dyn"),
-                ("[k]",            "cycle RAM region: DATA → STACK → R/W → HEAP"),
+                ("[k]",            "cycle RAM region: DATA → STACK → R/W → HEAP → MMAP…"),
                 ("[f]",            "cycle speed: 1x → 2x → 4x → 8x → GO"),
                 ("[e]",            "toggle exec count display"),
                 ("[y]",            "toggle instruction type badge"),
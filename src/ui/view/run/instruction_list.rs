@@ -2,6 +2,7 @@ use ratatui::Frame;
 use ratatui::prelude::*;
 use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, Paragraph};
 
+use crate::falcon::program::function_at;
 use crate::ui::theme;
 use super::App;
 use super::instruction_details::disasm_word;
@@ -11,8 +12,9 @@ pub(super) fn render_instruction_memory(f: &mut Frame, area: Rect, app: &App) {
     let block = instruction_block(app);
     let inner = block.inner(area);
 
-    // Reserve 1 line at the top for the label search bar when open
-    let (search_area, list_area) = if app.run.imem_search_open && inner.height > 2 {
+    // Reserve 1 line at the top for the label search bar or the arguments prompt
+    let bar_open = app.run.imem_search_open || app.run.args_prompt.is_some();
+    let (search_area, list_area) = if bar_open && inner.height > 2 {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(1), Constraint::Min(1)])
@@ -31,7 +33,27 @@ pub(super) fn render_instruction_memory(f: &mut Frame, area: Rect, app: &App) {
     render_instruction_drag_arrow(f, area, app);
 
     if let Some(bar) = search_area {
-        render_imem_search_bar(f, bar, app);
+        match &app.run.args_prompt {
+            Some(line) => render_args_prompt(f, bar, line),
+            None => render_imem_search_bar(f, bar, app),
+        }
+    }
+}
+
+fn render_args_prompt(f: &mut Frame, area: Rect, line: &str) {
+    let bg = Color::Rgb(20, 22, 40);
+    let bar = Line::from(vec![
+        Span::styled(" Args: ", Style::default().fg(theme::ACCENT).bg(bg).bold()),
+        Span::styled(line.to_string(), Style::default().fg(theme::LABEL_Y).bg(bg)),
+        Span::styled("  Enter=restart with them  Esc=cancel", Style::default().fg(theme::IDLE).bg(bg)),
+    ]);
+    f.render_widget(Paragraph::new(bar).style(Style::default().bg(bg)), area);
+
+    let prefix = " Args: ".len() as u16;
+    let cx = (area.x + prefix + line.chars().count() as u16)
+        .min(area.x + area.width.saturating_sub(1));
+    if area.height > 0 {
+        f.set_cursor_position((cx, area.y));
     }
 }
 
@@ -40,6 +62,7 @@ fn render_imem_search_bar(f: &mut Frame, area: Rect, app: &App) {
     let q = &app.run.imem_search_query;
 
     let q_lower = q.to_lowercase();
+    let source_line = app.source_line_addrs(q);
     let match_count = if q.is_empty() { 0usize } else {
         app.run.labels.values()
             .filter(|labels| labels.iter().any(|l| l.to_lowercase().contains(&q_lower)))
@@ -48,6 +71,11 @@ fn render_imem_search_bar(f: &mut Frame, area: Rect, app: &App) {
 
     let result_span = if q.is_empty() {
         Span::styled("", Style::default().bg(bg))
+    } else if let Some((place, addrs)) = &source_line {
+        Span::styled(
+            format!("  →  {place} at 0x{:08X}  Enter=toggle breakpoint", addrs[0]),
+            Style::default().fg(theme::RUNNING).bg(bg),
+        )
     } else if match_count > 0 {
         Span::styled(
             format!("  →  {match_count} match{}", if match_count == 1 { "" } else { "es" }),
@@ -84,22 +112,34 @@ fn instruction_block(app: &App) -> Block<'static> {
         Style::default().fg(theme::BORDER)
     };
 
+    // ELF images name the function (from symbol sizes) and, with DWARF line
+    // info, the source line at the PC
+    let pc = app.run.cpu.pc;
+    let func = function_at(&app.run.elf_symbols, pc).map(|f| format!("{}+0x{:x}", f.name, pc - f.addr));
+    let place = app.run.debug.as_ref().and_then(|d| d.describe(pc));
+    let title = match (func, place) {
+        (Some(func), Some(place)) => format!("Instruction Memory — {func} ({place})"),
+        (Some(at), None) | (None, Some(at)) => format!("Instruction Memory — {at}"),
+        (None, None) => "Instruction Memory".to_string(),
+    };
     Block::default()
         .borders(Borders::ALL)
         .border_style(border_style)
         .border_type(BorderType::Rounded)
-        .title("Instruction Memory")
+        .title(title)
 }
 
 fn instruction_items(inner: Rect, app: &App) -> Vec<ListItem<'static>> {
     // imem_scroll is now in visual rows; compute the starting address + how many
     // header rows (block_comment/labels) to skip at the first block.
-    let (mut addr, mut skip) = app.imem_addr_skip_for_scroll();
+    let (addr, mut skip) = app.imem_addr_skip_for_scroll();
     let lines = inner.height as u32;
     let mut items = Vec::new();
     let mut remaining = lines;
 
-    while remaining > 0 && imem_address_in_range(app, addr) {
+    let mut next = imem_address_in_range(app, addr).then_some(addr);
+    while let Some(addr) = next {
+        if remaining == 0 { break; }
         // Block comment separator
         if let Some(bc) = app.run.block_comments.get(&addr) {
             if skip > 0 {
@@ -124,16 +164,19 @@ fn instruction_items(inner: Rect, app: &App) -> Vec<ListItem<'static>> {
                 let is_hover = app.run.hover_imem_addr == Some(addr);
                 let lbl_style = Style::default().fg(theme::LABEL_Y)
                     .patch(if is_hover { Style::default().bg(HOVER_BG) } else { Style::default() });
-                items.push(ListItem::new(Line::from(vec![
-                    Span::styled(format!("{name}:"), lbl_style),
-                ])));
+                let mut spans = vec![Span::styled(format!("{name}:"), lbl_style)];
+                // ELF functions and objects show their extent from the symbol table
+                if let Some(sym) = app.run.elf_symbols.iter().find(|s| s.addr == addr && s.name == *name && s.size > 0) {
+                    spans.push(Span::styled(format!("  # {} bytes", sym.size), Style::default().fg(theme::COMMENT)));
+                }
+                items.push(ListItem::new(Line::from(spans)));
                 remaining -= 1;
             }
         }
         if remaining == 0 { break; }
         items.push(instruction_item(app, addr));
         remaining -= 1;
-        addr = addr.wrapping_add(4);
+        next = app.imem_next(addr);
     }
     items
 }
@@ -296,6 +296,17 @@ fn memory_block(app: &App) -> Block<'static> {
                 let hb = app.run.cpu.heap_break;
                 format!("Memory [Heap]  HB=0x{hb:08x}")
             }
+            MemRegion::Mmap(i) => match app.run.cpu.mmap.regions().get(i) {
+                Some(m) => format!(
+                    "Memory [Mmap {}/{}]  0x{:08x}-0x{:08x} {}",
+                    i + 1,
+                    app.run.cpu.mmap.regions().len(),
+                    m.start,
+                    m.end(),
+                    m.prot_str()
+                ),
+                None => "Memory [Mmap] unmapped".to_string(),
+            },
             _ => "Memory".to_string(),
         }
     };
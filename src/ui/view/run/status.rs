@@ -31,9 +31,30 @@ fn cycle_line(app: &App) -> Line<'static> {
         Span::styled(format!("CPI:{cpi:.2}"), Style::default().fg(theme::METRIC_CPI)),
         Span::raw("  "),
         Span::styled(format!("Instrs:{instr}"), Style::default().fg(theme::LABEL)),
+        Span::raw("  "),
+        Span::styled(time_text(app), Style::default().fg(theme::LABEL)),
+        Span::raw("  "),
+        Span::styled(format!("ABI:{}", app.run.cpu.abi.label()), Style::default().fg(theme::LABEL)),
     ])
 }
 
+/// The guest's clock: elapsed virtual time, or just the source when it is the host's.
+fn time_text(app: &App) -> String {
+    let clock = &app.run.cpu.clock;
+    if clock.host {
+        return format!("Time:{}", clock.describe());
+    }
+    let ns = clock.now_ns(app.run.mem.total_program_cycles());
+    let t = if ns >= 1_000_000_000 {
+        format!("{:.3}s", ns as f64 / 1e9)
+    } else if ns >= 1_000_000 {
+        format!("{:.3}ms", ns as f64 / 1e6)
+    } else {
+        format!("{:.3}us", ns as f64 / 1e3)
+    };
+    format!("Time:{t} @{}", crate::falcon::clock::format_hz(clock.hz).replace(' ', ""))
+}
+
 fn status_spans(app: &App) -> Vec<Span<'static>> {
     let mut spans = Vec::new();
 
@@ -129,6 +150,7 @@ fn region_text(app: &App) -> &'static str {
         MemRegion::Stack => "STACK",
         MemRegion::Access => "R/W",
         MemRegion::Heap => "HEAP",
+        MemRegion::Mmap(_) => "MMAP",
     }
 }
 
@@ -0,0 +1,100 @@
+use ratatui::Frame;
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph};
+
+use crate::falcon::strace::{Outcome, SyscallRecord};
+use crate::ui::theme;
+use crate::ui::app::App;
+
+/// Whether `r` passes the panel's filter: a case-insensitive substring of the
+/// traced line. A leading `!` keeps only calls that failed.
+fn matches_filter(r: &SyscallRecord, filter: &str) -> bool {
+    let (errors_only, text) = match filter.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, filter),
+    };
+    if errors_only && !r.is_error() {
+        return false;
+    }
+    let text = text.trim().to_lowercase();
+    text.is_empty() || r.line().to_lowercase().contains(&text)
+}
+
+/// Every `ecall` of the run, newest at the bottom, as strace shows them.
+pub fn render_syscalls(f: &mut Frame, area: Rect, app: &App) {
+    let popup_w = (area.width * 7 / 8).max(60).min(area.width.saturating_sub(4));
+    let popup_h = (area.height * 3 / 4).max(12).min(area.height.saturating_sub(2));
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(popup_w) / 2,
+        area.y + area.height.saturating_sub(popup_h) / 2,
+        popup_w,
+        popup_h,
+    );
+    f.render_widget(Clear, popup);
+
+    let trace = &app.run.cpu.trace;
+    let shown: Vec<&SyscallRecord> =
+        trace.records.iter().filter(|r| matches_filter(r, &app.run.syscalls_filter)).collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme::ACCENT))
+        .border_type(BorderType::Rounded)
+        .title(Span::styled(
+            format!(" Syscalls  {}/{} ", shown.len(), trace.records.len()),
+            Style::default().fg(theme::ACCENT).bold(),
+        ));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("  Filter: ", Style::default().fg(theme::LABEL)),
+            Span::styled(format!("{}▏", app.run.syscalls_filter), Style::default().fg(theme::TEXT)),
+        ])),
+        chunks[0],
+    );
+
+    let height = chunks[1].height as usize;
+    let items: Vec<ListItem> = if shown.is_empty() {
+        let msg = if trace.records.is_empty() { "  No syscalls yet." } else { "  No syscalls match the filter." };
+        vec![ListItem::new(Span::styled(msg, Style::default().fg(theme::IDLE)))]
+    } else {
+        let scroll = app.run.syscalls_scroll.min(shown.len().saturating_sub(height));
+        let end = shown.len() - scroll;
+        let start = end.saturating_sub(height);
+        shown[start..end].iter().map(|r| {
+            let style = match r.outcome {
+                Outcome::Waiting => Style::default().fg(theme::PAUSED),
+                _ if r.is_error() => Style::default().fg(theme::DANGER),
+                _ => Style::default().fg(theme::TEXT),
+            };
+            let lbl = app.run.labels.get(&r.pc)
+                .and_then(|v| v.first())
+                .map(|s| format!(" <{s}>"))
+                .unwrap_or_default();
+            let blocked = if r.blocked && r.outcome != Outcome::Waiting { "  [blocked on input]" } else { "" };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("  0x{:08x}{lbl} ", r.pc), Style::default().fg(theme::LABEL)),
+                Span::styled(format!("[{}] ", r.number), Style::default().fg(theme::IDLE)),
+                Span::styled(r.call(), style),
+                Span::styled(blocked, Style::default().fg(theme::PAUSED)),
+            ]))
+        }).collect()
+    };
+    f.render_widget(List::new(items), chunks[1]);
+
+    let dropped = if trace.dropped > 0 { format!("   ({} older calls dropped)", trace.dropped) } else { String::new() };
+    f.render_widget(
+        Paragraph::new(Line::styled(
+            format!("  Type=Filter (!=errors only)  ↑/↓/PgUp/PgDn=Scroll  End=Newest  Esc=Close{dropped}"),
+            Style::default().fg(theme::IDLE),
+        )),
+        chunks[2],
+    );
+}